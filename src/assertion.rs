@@ -0,0 +1,124 @@
+use colored::Colorize;
+
+/// Lines of source shown on either side of a failing assert.
+const CONTEXT: usize = 2;
+
+/// Location of an `assert()` that fired, parsed out of a test's stderr.
+pub struct AssertFailure {
+	pub file: std::path::PathBuf,
+	pub line: usize,
+	pub message: String,
+}
+
+/// Parses the assert message printed by the common C runtimes.
+///
+/// * glibc: `prog: file.c:12: main: Assertion `x' failed.`
+/// * musl: `Assertion failed: x (file.c: main: 12)`
+/// * macOS: `Assertion failed: (x), function main, file file.c, line 12.`
+pub fn parse(stderr: &str) -> Option<AssertFailure> {
+	stderr.lines().find_map(|line| {
+		let line = line.trim();
+
+		if let Some(idx) = line.find("Assertion `") {
+			let (location, _func) = line[..idx]
+				.trim_end()
+				.trim_end_matches(':')
+				.rsplit_once(": ")?;
+			let (file, lineno) = location.rsplit_once(':')?;
+			let file = file
+				.split_once(": ")
+				.map(|(_prog, file)| file)
+				.unwrap_or(file);
+
+			return Some(AssertFailure {
+				file: file.into(),
+				line: lineno.trim().parse().ok()?,
+				message: line[idx..].to_owned(),
+			});
+		}
+
+		let rest = line.strip_prefix("Assertion failed: ")?;
+
+		if let Some((expr, location)) = rest.rsplit_once(", file ") {
+			let (file, lineno) = location.rsplit_once(", line ")?;
+			let expr = expr.rsplit_once(", function ").map(|p| p.0).unwrap_or(expr);
+
+			return Some(AssertFailure {
+				file: file.into(),
+				line: lineno.trim_end_matches('.').parse().ok()?,
+				message: format!("Assertion failed: {expr}"),
+			});
+		}
+
+		let (expr, location) = rest.rsplit_once(" (")?;
+		let mut parts = location.trim_end_matches(')').split(": ");
+		let file = parts.next()?;
+		let lineno = parts.last()?;
+
+		Some(AssertFailure {
+			file: file.into(),
+			line: lineno.parse().ok()?,
+			message: format!("Assertion failed: {expr}"),
+		})
+	})
+}
+
+/// Finds the source file an assert refers to.
+/// The compiler records whatever path it was given, so try the usual roots.
+fn resolve(
+	proj: &crate::Project,
+	test: &std::path::Path,
+	file: &std::path::Path,
+) -> Option<std::path::PathBuf> {
+	if file.is_absolute() {
+		return file.is_file().then(|| file.to_owned());
+	}
+
+	[proj.path().to_owned(), proj.tests()]
+		.into_iter()
+		.chain(proj.src())
+		.chain(test.parent().map(|p| p.to_owned()))
+		.map(|root| root.join(file))
+	.find(|p| p.is_file())
+}
+
+/// Renders a failed test's stderr as the assert message with surrounding source.
+/// Returns [None] if the failure wasn't an assert, or the source can't be found.
+pub fn render(proj: &crate::Project, test: &std::path::Path, stderr: &str) -> Option<String> {
+	let failure = parse(stderr)?;
+	let file = resolve(proj, test, &failure.file)?;
+	let source = std::fs::read_to_string(&file).ok()?;
+
+	let lines = source.lines().collect::<Vec<_>>();
+	if failure.line == 0 || failure.line > lines.len() {
+		return None;
+	}
+
+	let first = failure.line.saturating_sub(CONTEXT).max(1);
+	let last = (failure.line + CONTEXT).min(lines.len());
+	let width = last.to_string().len();
+
+	let shown = file.strip_prefix(proj.path()).unwrap_or(&file);
+
+	let mut out = format!(
+		"{}\n{:>width$}{} {}:{}\n",
+		failure.message.bold(),
+		"",
+		"-->".blue(),
+		shown.display(),
+		failure.line
+	);
+
+	for n in first..=last {
+		let gutter = format!("{n:>width$} |").blue();
+		let text = lines[n - 1];
+
+		if n == failure.line {
+			out.push_str(&format!("{gutter} {}\n", text.red().bold()));
+		} else {
+			out.push_str(&format!("{gutter} {text}\n"));
+		}
+	}
+
+	Some(out)
+}
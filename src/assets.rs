@@ -0,0 +1,248 @@
+/// Helper files cpkg hands to the tools it runs, by file name.
+/// They're written to disk on first use, and a project can override any of them
+/// by placing a file of the same name under .cpkg/assets.
+const BUILTIN: &[(&str, &str)] = &[
+	("Doxyfile", include_str!("assets/Doxyfile")),
+	("cpkg_test.h", include_str!("assets/cpkg_test.h")),
+	("repl.h", include_str!("assets/repl.h")),
+];
+
+/// Folder in a project holding overrides for built-in assets
+const OVERRIDES: &str = ".cpkg/assets";
+
+/// File beside materialized assets recording the hash of each,
+/// so copies written by another version of cpkg get refreshed
+const MANIFEST: &str = ".manifest";
+
+/// Where built-in assets are materialized, in the project's target folder,
+/// or the user's cache outside of a project.
+pub fn dir(proj: Option<&crate::Project>) -> Option<std::path::PathBuf> {
+	match proj {
+		Some(proj) => Some(proj.target().join(".cpkg").join("assets")),
+		None => cache_dir().map(|d| d.join("assets")),
+	}
+}
+
+/// cpkg's folder in the user's cache, shared by every project.
+pub fn cache_dir() -> Option<std::path::PathBuf> {
+	#[cfg(target_os = "windows")]
+	let base = std::env::var_os("LOCALAPPDATA").map(std::path::PathBuf::from);
+
+	#[cfg(not(target_os = "windows"))]
+	let base = std::env::var_os("XDG_CACHE_HOME")
+		.map(std::path::PathBuf::from)
+		.or_else(|| std::env::var_os("HOME").map(|h| std::path::Path::new(&h).join(".cache")));
+
+	base.map(|b| b.join("cpkg"))
+}
+
+/// Path to an asset, preferring the project's override.
+/// The built-in copy is written out if it's missing or stale.
+pub fn locate(proj: Option<&crate::Project>, name: &str) -> anyhow::Result<std::path::PathBuf> {
+	if let Some(proj) = proj {
+		let custom = proj.path().join(OVERRIDES).join(name);
+
+		if custom.is_file() {
+			return Ok(custom);
+		}
+	}
+
+	let Some((_, contents)) = BUILTIN.iter().find(|(n, _)| *n == name) else {
+		anyhow::bail!("No built-in asset named {name}.");
+	};
+
+	let Some(dir) = dir(proj) else {
+		anyhow::bail!("Couldn't find a folder to write cpkg's assets to.");
+	};
+
+	let path = dir.join(name);
+	let hash = format!("{:016x}", hash(contents));
+
+	let mut manifest = read_manifest(&dir);
+	if path.is_file() && manifest.get(name) == Some(&hash) {
+		return Ok(path);
+	}
+
+	std::fs::create_dir_all(&dir)?;
+	std::fs::write(&path, contents)?;
+
+	manifest.insert(name.to_owned(), hash);
+
+	let manifest = manifest
+		.iter()
+		.map(|(name, hash)| format!("{name} {hash}\n"))
+		.collect::<String>();
+
+	std::fs::write(dir.join(MANIFEST), manifest)?;
+
+	Ok(path)
+}
+
+/// Contents of an asset, see [locate].
+pub fn read(proj: Option<&crate::Project>, name: &str) -> anyhow::Result<String> {
+	let path = locate(proj, name)?;
+
+	std::fs::read_to_string(&path)
+		.map_err(|e| anyhow::anyhow!("Failed to read asset {}: {e}", path.display()))
+}
+
+/// Removes the project's materialized assets, returning whether there were any.
+/// Overrides under .cpkg/assets are left alone.
+pub fn clean(proj: &crate::Project) -> anyhow::Result<bool> {
+	let Some(dir) = dir(Some(proj)) else {
+		return Ok(false);
+	};
+
+	if !dir.exists() {
+		return Ok(false);
+	}
+
+	std::fs::remove_dir_all(dir)?;
+
+	Ok(true)
+}
+
+fn read_manifest(dir: &std::path::Path) -> std::collections::BTreeMap<String, String> {
+	std::fs::read_to_string(dir.join(MANIFEST))
+		.unwrap_or_default()
+		.lines()
+		.filter_map(|l| l.split_once(' '))
+		.map(|(name, hash)| (name.to_owned(), hash.to_owned()))
+		.collect()
+}
+
+/// FNV-1a, which unlike std's hasher gives the same result across builds of cpkg.
+pub fn hash(contents: impl AsRef<[u8]>) -> u64 {
+	use std::hash::Hasher;
+
+	let mut hasher = StableHasher::default();
+	hasher.write(contents.as_ref());
+	hasher.finish()
+}
+
+/// [hash] for contents fed in pieces, like a file's path and then its contents.
+/// Only `write` is stable, as std's `Hash` impls can change how they feed it.
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+	fn default() -> Self {
+		Self(0xcbf29ce484222325)
+	}
+}
+
+impl std::hash::Hasher for StableHasher {
+	fn write(&mut self, bytes: &[u8]) {
+		for b in bytes {
+			self.0 = (self.0 ^ *b as u64).wrapping_mul(0x100000001b3);
+		}
+	}
+
+	fn finish(&self) -> u64 {
+		self.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::project::testing::project;
+
+	fn builtin(name: &str) -> &'static str {
+		BUILTIN.iter().find(|(n, _)| *n == name).unwrap().1
+	}
+
+	#[test]
+	fn hash_is_fnv1a() {
+		assert_eq!(hash(""), 0xcbf29ce484222325);
+		assert_eq!(hash("a"), 0xaf63dc4c8601ec8c);
+		assert_eq!(hash("foobar"), 0x85944171f73967e8);
+	}
+
+	#[test]
+	fn materializes_into_target() {
+		let temp = project("", &[]);
+		let proj = crate::Project::open(temp.path()).unwrap();
+
+		let path = locate(Some(&proj), "repl.h").unwrap();
+
+		assert_eq!(path, proj.target().join(".cpkg/assets/repl.h"));
+		assert_eq!(std::fs::read_to_string(&path).unwrap(), builtin("repl.h"));
+		assert_eq!(
+			read_manifest(path.parent().unwrap()).get("repl.h"),
+			Some(&format!("{:016x}", hash(builtin("repl.h"))))
+		);
+
+		assert!(locate(Some(&proj), "missing.h").is_err());
+	}
+
+	#[test]
+	fn refreshes_stale_copies() {
+		let temp = project("", &[]);
+		let proj = crate::Project::open(temp.path()).unwrap();
+
+		let path = locate(Some(&proj), "cpkg_test.h").unwrap();
+		let dir = path.parent().unwrap();
+
+		/* As if written by another version of cpkg */
+		std::fs::write(&path, "/* old */").unwrap();
+		std::fs::write(dir.join(MANIFEST), "cpkg_test.h 0000000000000000\n").unwrap();
+
+		assert_eq!(
+			read(Some(&proj), "cpkg_test.h").unwrap(),
+			builtin("cpkg_test.h")
+		);
+
+		/* Deleted copies are written again, without losing other entries */
+		locate(Some(&proj), "repl.h").unwrap();
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(
+			read(Some(&proj), "cpkg_test.h").unwrap(),
+			builtin("cpkg_test.h")
+		);
+		assert_eq!(
+			read_manifest(dir).keys().collect::<Vec<_>>(),
+			["cpkg_test.h", "repl.h"]
+		);
+	}
+
+	#[test]
+	fn prefers_overrides() {
+		let temp = project("", &[]);
+		let proj = crate::Project::open(temp.path()).unwrap();
+
+		let overrides = temp.path().join(OVERRIDES);
+		std::fs::create_dir_all(&overrides).unwrap();
+		std::fs::write(overrides.join("Doxyfile"), "PROJECT_NAME = custom\n").unwrap();
+
+		assert_eq!(
+			locate(Some(&proj), "Doxyfile").unwrap(),
+			overrides.join("Doxyfile")
+		);
+		assert_eq!(
+			read(Some(&proj), "Doxyfile").unwrap(),
+			"PROJECT_NAME = custom\n"
+		);
+		assert!(
+			!dir(Some(&proj)).unwrap().exists(),
+			"nothing is materialized"
+		);
+	}
+
+	#[test]
+	fn clean_keeps_overrides() {
+		let temp = project("", &[]);
+		let proj = crate::Project::open(temp.path()).unwrap();
+
+		assert!(!clean(&proj).unwrap());
+
+		let overrides = temp.path().join(OVERRIDES);
+		std::fs::create_dir_all(&overrides).unwrap();
+		std::fs::write(overrides.join("Doxyfile"), "").unwrap();
+		locate(Some(&proj), "repl.h").unwrap();
+
+		assert!(clean(&proj).unwrap());
+		assert!(!dir(Some(&proj)).unwrap().exists());
+		assert!(overrides.join("Doxyfile").is_file());
+	}
+}
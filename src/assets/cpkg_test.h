@@ -0,0 +1,24 @@
+/*
+	Harness for tests with several cases in one file, used by including <cpkg_test.h>.
+	Define cases with TEST(name) { ... } and check conditions with CHECK(cond).
+	cpkg generates the main function, which runs each case and reports it on its own.
+	Override by placing a cpkg_test.h under .cpkg/assets/ in your project.
+*/
+#ifndef CPKG_TEST_H
+#define CPKG_TEST_H
+
+/* Defined by the generated main */
+void cpkg_test_fail(const char *file, int line, const char *cond);
+
+#define TEST(name) void cpkg_test_##name(void)
+
+/* Fails the current case and stops it, so it can only be used directly inside of a TEST */
+#define CHECK(cond) \
+	do { \
+		if (!(cond)) { \
+			cpkg_test_fail(__FILE__, __LINE__, #cond); \
+			return; \
+		} \
+	} while (0)
+
+#endif
@@ -0,0 +1,14 @@
+/*
+	Prelude of every cpkg repl program.
+	__cpkg_fmt picks the printf format for an expression's value.
+	Override by placing a repl.h under .cpkg/assets/ in your project.
+*/
+#include <stdio.h>
+
+#define __cpkg_fmt(x) _Generic((x), \
+	_Bool: "%d\n", char: "'%c'\n", signed char: "%hhd\n", unsigned char: "%hhu\n", \
+	short: "%hd\n", unsigned short: "%hu\n", int: "%d\n", unsigned int: "%u\n", \
+	long: "%ld\n", unsigned long: "%lu\n", long long: "%lld\n", unsigned long long: "%llu\n", \
+	float: "%g\n", double: "%g\n", long double: "%Lg\n", \
+	char *: "\"%s\"\n", const char *: "\"%s\"\n", \
+	default: "%p\n")
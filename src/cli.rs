@@ -7,6 +7,26 @@ use clap::{Parser, Subcommand};
 pub struct Cli {
 	#[command(subcommand)]
 	pub command: Commands,
+
+	/// Prints every external command before running it.
+	#[arg(short, long, global = true, conflicts_with = "quiet")]
+	pub verbose: bool,
+
+	/// Only prints errors and warnings.
+	#[arg(short, long, global = true)]
+	pub quiet: bool,
+
+	/// Ignores the CC, CFLAGS and LDFLAGS environment variables.
+	#[arg(long, global = true)]
+	pub ignore_env: bool,
+
+	/// Fails instead of falling back to another backend when a `default` in cpkg.toml isn't installed, for CI.
+	#[arg(long, global = true)]
+	pub strict_backend: bool,
+
+	/// Whether to color cpkg's output, and the compiler's and formatter's it passes on.
+	#[arg(long, global = true, value_enum, default_value_t)]
+	pub color: crate::log::Color,
 }
 
 #[derive(Subcommand)]
@@ -15,36 +35,254 @@ pub enum Commands {
 	New {
 		/// Name of folder to create new project inside of.
 		name: String,
+
+		/// Package name, if it should differ from the folder's.
+		#[arg(long = "name", value_name = "NAME")]
+		package: Option<String>,
+
+		/// Skips creating a git repository.
+		#[arg(long)]
+		no_git: bool,
+
+		/// Defines a template variable, usable as {{key}} in scaffolded files.
+		#[arg(long = "define", value_name = "KEY=VALUE", value_parser = crate::template::parse_define)]
+		defines: Vec<(String, String)>,
+
+		/// Directory, git url, or name under [templates] in the user config to scaffold from.
+		#[arg(long)]
+		template: Option<String>,
+
+		/// License written to LICENSE. Asked for unless set here or as `license` in the user config.
+		#[arg(long, value_enum)]
+		license: Option<crate::license::License>,
+
+		#[command(flatten)]
+		metadata: MetadataArgs,
+	},
+	#[command(about = "Initializes a template project at the cwd.")]
+	Init {
+		/// Package name, if it should differ from the folder's.
+		#[arg(long)]
+		name: Option<String>,
+
+		/// Skips creating a git repository.
+		#[arg(long)]
+		no_git: bool,
+
+		/// Defines a template variable, usable as {{key}} in scaffolded files.
+		#[arg(long = "define", value_name = "KEY=VALUE", value_parser = crate::template::parse_define)]
+		defines: Vec<(String, String)>,
+
+		/// Directory, git url, or name under [templates] in the user config to scaffold from.
+		#[arg(long)]
+		template: Option<String>,
+
+		/// License written to LICENSE. Asked for unless set here or as `license` in the user config.
+		#[arg(long, value_enum)]
+		license: Option<crate::license::License>,
+
+		#[command(flatten)]
+		metadata: MetadataArgs,
+	},
+
+	#[command(
+		about = "Creates a cpkg.toml for an existing Makefile project at the cwd.\n\x1b[31m"
+	)]
+	Import {
+		/// Prints the generated cpkg.toml instead of writing it.
+		#[arg(long)]
+		dry_run: bool,
 	},
-	#[command(about = "Initializes a template project at the cwd.\n\x1b[31m")]
-	Init,
 
 	#[command(
 		about = "Builds the project to the target directory using gcc or clang, if available.\x1b[31m"
 	)]
 	Build {
+		/// Workspace member to build, instead of every member when at the root.
+		#[arg(short, long)]
+		package: Option<String>,
+
 		#[arg(long)]
 		bin: Option<String>,
+
+		/// Builds every target: binaries, the library, examples and test binaries, without running them.
+		#[arg(long, conflicts_with = "bin")]
+		all_targets: bool,
+
+		/// Prints what the build would do, without doing it.
+		#[arg(long)]
+		plan: bool,
+
+		/// Prints the plan as JSON.
+		#[arg(long, requires = "plan")]
+		json: bool,
+
+		/// Prints diagnostics and artifacts as JSON, one object per line, for editors and CI.
+		#[arg(long, value_enum, default_value = "human", conflicts_with = "plan")]
+		message_format: MessageFormat,
+
+		/// Builds for another platform, a name under [target] in cpkg.toml, wasm, or a target triple.
+		#[arg(long)]
+		target: Option<String>,
+
+		/// Prints the compiler's errors untouched, instead of deduplicating and tidying them.
+		#[arg(long)]
+		raw_errors: bool,
+
+		#[command(flatten)]
+		profile: ProfileArgs,
+
+		/// Reports how long each compile, link and archive took, also writing it to target/timings.json.
+		/// With =verbose, includes where the compiler spent its time, for gcc and clang.
+		#[arg(long, value_enum, value_name = "MODE", num_args = 0..=1, require_equals = true, default_missing_value = "summary", conflicts_with_all = ["plan", "emit"])]
+		timings: Option<crate::Timings>,
+
+		/// Only compiles one file as far as an object, assembly, preprocessed source or LLVM IR, writing it to target/emit.
+		#[arg(long, value_enum, conflicts_with_all = ["plan", "all_targets", "bin"])]
+		emit: Option<crate::compiler::Emit>,
+
+		/// File to emit, main.c by default.
+		#[arg(requires = "emit")]
+		file: Option<std::path::PathBuf>,
 	},
 
 	#[command(
 		about = "Runs the project's main file, a standalone c file or a cpkg.toml script.\x1b[31m"
 	)]
 	Run {
+		/// Script under [scripts] or C file to run. Use --script or --file when a name could be either.
 		path: Option<String>,
 
+		/// More C files to compile along with a standalone file.
+		files: Vec<std::path::PathBuf>,
+
+		/// Runs a script from cpkg.toml, even if a file has the same name.
+		#[arg(long, conflicts_with_all = ["path", "file", "bin"])]
+		script: Option<String>,
+
+		/// Compiles and runs a standalone C file, even if a script has the same name.
+		#[arg(long, conflicts_with = "bin")]
+		file: Option<std::path::PathBuf>,
+
+		/// Workspace member to run.
+		#[arg(short, long)]
+		package: Option<String>,
+
 		#[arg(long)]
 		bin: Option<String>,
+
+		/// Builds for another platform, a name under [target] in cpkg.toml, wasm, or a target triple.
+		#[arg(long)]
+		target: Option<String>,
+
+		/// Runs the binary through this program, overriding the configured runner, e.g. node for wasm.
+		#[arg(long)]
+		runner: Option<String>,
+
+		/// Prints the compiler's errors untouched, instead of deduplicating and tidying them.
+		#[arg(long)]
+		raw_errors: bool,
+
+		/// Compiles everything again, even if the binary is up to date.
+		#[arg(long)]
+		force_rebuild: bool,
+
+		#[command(flatten)]
+		profile: ProfileArgs,
+
+		/// Builds with debug info and no optimizations, then starts the binary under gdb or lldb.
+		#[arg(long, conflicts_with_all = ["target", "runner", "script"])]
+		debug: bool,
+
+		/// Arguments for the program, after `--`.
+		#[arg(last = true)]
+		args: Vec<String>,
 	},
 
 	#[command(about = "Runs the project's test suite.\n\x1b[33m")]
 	Test {
+		/// Only runs tests whose path contains this, or harness cases whose name does.
+		filter: Option<String>,
+
+		/// Prints what each test outputs as it runs, prefixed with its name.
 		#[arg(short, long)]
 		print: bool,
+
+		/// Workspace member to test, instead of every member when at the root.
+		/// There's no short form, as -p is --print.
+		#[arg(long)]
+		package: Option<String>,
+
+		/// Recompiles every test, even those whose sources haven't changed.
+		#[arg(long)]
+		no_cache: bool,
+
+		/// Also builds the default targets like `cpkg build`, so errors in main.c, which tests leave out, fail the run too.
+		#[arg(long)]
+		all: bool,
+
+		#[command(flatten)]
+		profile: ProfileArgs,
+
+		/// Prints diagnostics and test results as JSON, one object per line, for editors and CI.
+		#[arg(long, value_enum, default_value = "human", conflicts_with = "print")]
+		message_format: MessageFormat,
+
+		/// Builds for another platform, a name under [target] in cpkg.toml, wasm, or a target triple.
+		#[arg(long)]
+		target: Option<String>,
+
+		/// Prints the compiler's errors untouched, instead of deduplicating and tidying them.
+		#[arg(long)]
+		raw_errors: bool,
+
+		/// Starts the one test the filter matches under gdb or lldb, built with debug info and no optimizations.
+		#[arg(long, requires = "filter", conflicts_with_all = ["print", "all", "target"])]
+		debug: bool,
 	},
 
 	#[command(about = "Removes compiled programs from the project.\x1b[33m")]
-	Clean,
+	Clean {
+		/// Only removes vendored dependencies no longer declared in cpkg.toml.
+		#[arg(long)]
+		deps: bool,
+
+		/// Only removes the output of the given binary.
+		#[arg(long, conflicts_with_all = ["deps", "all_targets"])]
+		bin: Option<String>,
+
+		/// Removes the outputs of every target, keeping vendored dependencies and docs.
+		#[arg(long, conflicts_with = "deps")]
+		all_targets: bool,
+
+		/// Only removes helper files cpkg wrote to target/.cpkg/assets, so they're rewritten on next use.
+		#[arg(long, conflicts_with_all = ["deps", "bin", "all_targets"])]
+		assets: bool,
+
+		/// Only removes compiled tests.
+		#[arg(long, conflicts_with_all = ["deps", "bin", "all_targets", "assets"])]
+		tests: bool,
+
+		/// Only removes generated documentation.
+		#[arg(long, conflicts_with_all = ["deps", "bin", "all_targets", "assets"])]
+		doc: bool,
+
+		/// Only removes vendored dependencies, which need to be installed again.
+		#[arg(long, conflicts_with_all = ["deps", "bin", "all_targets", "assets"])]
+		vendor: bool,
+
+		/// Only removes files cpkg wrote to the project root, like compile_flags.txt, that weren't edited since.
+		#[arg(long, conflicts_with_all = ["deps", "bin", "all_targets", "assets", "tests", "doc", "vendor"])]
+		generated: bool,
+
+		/// Removes the whole target directory, vendored dependencies included.
+		#[arg(long, conflicts_with_all = ["deps", "bin", "all_targets", "assets", "tests", "doc", "vendor", "generated"])]
+		all: bool,
+
+		/// Only removes what an interrupted command left broken, like half-cloned dependencies or unfinished binaries.
+		#[arg(long, conflicts_with_all = ["deps", "bin", "all_targets", "assets", "tests", "doc", "vendor", "generated", "all"])]
+		broken: bool,
+	},
 
 	#[command(
 		about = "Generates documentation for the project using doxygen, if available.\x1b[33m"
@@ -52,10 +290,26 @@ pub enum Commands {
 	Doc {
 		#[arg(short, long)]
 		open: bool,
+
+		/// Serves the documentation over HTTP on localhost, optionally at a given port.
+		#[arg(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "8000")]
+		serve: Option<u16>,
+
+		/// Regenerates documentation when sources change while serving.
+		#[arg(long, requires = "serve")]
+		watch: bool,
 	},
 
 	#[command(about = "Formats the project's code using clang-format, if available.\x1b[33m", aliases = &["fmt"])]
-	Format,
+	Format {
+		/// Fails if any file isn't formatted, instead of formatting it.
+		#[arg(long)]
+		check: bool,
+
+		/// Only looks at sources staged in git, as the pre-commit hook does.
+		#[arg(long)]
+		staged: bool,
+	},
 
 	#[command(about = "Generates a project file for use with other build managers.\n\x1b[36m")]
 	Generate {
@@ -63,8 +317,19 @@ pub enum Commands {
 		kind: GenerateCommand,
 	},
 
+	#[command(about = "Checks the project for common problems without building it.\x1b[33m")]
+	Validate {
+		/// Applies fixes for problems that can be fixed mechanically.
+		#[arg(long)]
+		fix: bool,
+	},
+
+	#[command(about = "Explains a code reported by cpkg validate.\n\x1b[33m")]
+	Explain { code: String },
+
 	#[command(about = "Adds a dependency to cpkg.toml.\x1b[36m")]
 	Add {
+		/// Name of the dependency. Without --git, --path or --lib, it's looked up in the package index.
 		name: String,
 
 		/// Adds the dependency as a git dependency.
@@ -74,23 +339,454 @@ pub enum Commands {
 		/// Adds the dependency, as a local file path to symlink.
 		#[arg(long)]
 		path: Option<std::path::PathBuf>,
+
+		/// Adds a prebuilt library to link, like z for the system's zlib.
+		#[arg(long, conflicts_with_all = ["git", "path"])]
+		lib: Option<String>,
+
+		/// Folder the library is in, when the linker wouldn't find it on its own.
+		#[arg(long, requires = "lib")]
+		search: Option<std::path::PathBuf>,
+
+		/// Folder the library's headers are in.
+		#[arg(long, requires = "lib")]
+		headers: Option<std::path::PathBuf>,
+
+		/// Only edits cpkg.toml, leaving the dependency for the next cpkg install.
+		#[arg(long)]
+		no_install: bool,
+
+		/// Adds a dependency found in the package index without asking first.
+		#[arg(short, long)]
+		yes: bool,
 	},
 
+	#[command(about = "Lists libraries in the package index, which cpkg add can add by name.\x1b[36m")]
+	Search { term: String },
+
 	#[command(about = "Removes a dependency from cpkg.toml and deletes it.\x1b[36m")]
 	Remove { name: String },
 
-	#[command(about = "Installs dependencies from cpkg project.\n\x1b[34m")]
-	Install,
+	#[command(about = "Manages vendored dependencies and how they're included.\x1b[36m")]
+	Deps {
+		#[command(subcommand)]
+		command: DepsCommand,
+	},
+
+	#[command(about = "Prints or bumps the package version in cpkg.toml.\x1b[36m")]
+	Version {
+		#[command(subcommand)]
+		command: Option<VersionCommand>,
+	},
+
+	#[command(about = "Installs dependencies from cpkg project.\x1b[34m")]
+	Install {
+		/// Removes vendored dependencies no longer declared in cpkg.toml.
+		#[arg(long)]
+		prune: bool,
+
+		/// Does nothing, as broken vendor entries are always re-linked or re-cloned now. Kept so scripts using it still work.
+		#[arg(long, hide = true)]
+		repair: bool,
+
+		/// Runs the build commands of dependencies again, even if they've already built.
+		#[arg(long)]
+		force: bool,
+	},
+
+	#[command(about = "Shows the dependencies of the package, or of every member at the root of a workspace.\x1b[34m")]
+	Tree,
+
+	#[command(about = "Copies installed dependencies into a vendor folder to commit, for offline builds.\x1b[34m")]
+	Vendor {
+		/// Checks the snapshot matches cpkg.toml and cpkg.lock instead of making one.
+		#[arg(long)]
+		check: bool,
+	},
+
+	#[command(about = "Builds a release binary and copies it onto your PATH.\x1b[34m")]
+	InstallBin {
+		#[arg(long)]
+		bin: Option<String>,
+
+		/// Installs into <ROOT>/bin, defaults to ~/.local (%USERPROFILE%\.cpkg on Windows).
+		#[arg(long)]
+		root: Option<std::path::PathBuf>,
+
+		/// Overwrites an existing file at the destination.
+		#[arg(long)]
+		force: bool,
+	},
+
+	#[command(about = "Removes a binary installed with install-bin.\n\x1b[34m")]
+	Uninstall {
+		/// Name of the binary, defaults to the one the project builds.
+		name: Option<String>,
+
+		#[arg(long)]
+		root: Option<std::path::PathBuf>,
+	},
 
 	#[command(about = "Creates a REPL with gcc or clang, if available.\x1b[34m")]
-	Repl,
+	Repl {
+		/// Ignores the project in the cwd, if any.
+		#[arg(long)]
+		no_project: bool,
+	},
+
+	#[command(about = "Compiles arbitrary files with the located compiler, outside of a project.\x1b[34m")]
+	Cc {
+		/// C files to compile.
+		#[arg(required = true)]
+		files: Vec<std::path::PathBuf>,
+
+		/// Output executable, defaults to the name of the first file.
+		#[arg(short)]
+		output: Option<std::path::PathBuf>,
+
+		/// Flags passed verbatim to the compiler, after `--`.
+		#[arg(last = true)]
+		flags: Vec<String>,
+	},
+
+	#[command(about = "Compiles a single file without linking, with the project's flags and includes if in one.\x1b[34m")]
+	Compile {
+		/// Source to compile.
+		file: std::path::PathBuf,
+
+		/// Where to write the output, beside the file by default, like foo.o for foo.c.
+		#[arg(short, long)]
+		output: Option<std::path::PathBuf>,
+
+		/// Compiles to an object file, which is the default.
+		#[arg(long, group = "kind")]
+		object: bool,
+
+		/// Compiles to assembly.
+		#[arg(long, group = "kind")]
+		asm: bool,
+
+		/// Only runs the preprocessor.
+		#[arg(long, group = "kind")]
+		preprocess: bool,
+
+		#[command(flatten)]
+		profile: ProfileArgs,
+	},
+
+	#[command(about = "Builds the project and archives the executable for distribution.\x1b[34m")]
+	Dist {
+		#[command(flatten)]
+		archive: ArchiveArgs,
+	},
+
+	#[command(about = "Archives the project's sources into a package that builds with or without cpkg.\x1b[34m")]
+	Package {
+		/// Skips building the package in a temporary folder to check nothing is missing from it.
+		#[arg(long)]
+		no_verify: bool,
+	},
+
+	#[command(about = "Summarizes the project, the backends it would use and its dependencies.\x1b[35m")]
+	Info {
+		/// Prints the summary as JSON, for editors and other tools.
+		#[arg(long)]
+		json: bool,
+	},
+
+	#[command(about = "Reports which compilers and tools cpkg can find, and which it would use.\x1b[35m")]
+	Doctor,
 
 	#[command(about = "Updates to the latest version of cpkg.\n\x1b[35m")]
-	Upgrade,
+	Upgrade {
+		/// Only reports whether a newer version exists, along with its release notes.
+		#[arg(long)]
+		check: bool,
+
+		/// Installs a specific version instead, like 0.4.2. Can go back to an older one.
+		#[arg(long, conflicts_with_all = ["check", "prerelease"])]
+		version: Option<semver::Version>,
+
+		/// Also considers pre-releases, like 0.13.0-rc.1.
+		#[arg(long)]
+		prerelease: bool,
+
+		/// Replaces the binary without asking first.
+		#[arg(short, long)]
+		yes: bool,
+	},
+
+	#[command(about = "Prints a shell completion script to stdout.\x1b[35m")]
+	Completions { shell: clap_complete::Shell },
+
+	/// Lists names for dynamic shell completion.
+	#[command(name = "__complete", hide = true)]
+	Complete { kind: CompleteKind },
+}
+
+#[derive(Subcommand)]
+pub enum DepsCommand {
+	#[command(about = "Rewrites flat includes of dependency headers to the <dep/header.h> form")]
+	MigrateIncludes {
+		/// Only rewrites these files, leaving the rest of the project to shims.
+		files: Vec<std::path::PathBuf>,
+
+		/// Prints the changes as a diff instead of making them.
+		#[arg(long)]
+		dry_run: bool,
+
+		/// Writes shim headers to target/vendor that forward old includes, with a deprecation warning.
+		#[arg(long)]
+		shims: bool,
+
+		/// Deletes previously written shim headers.
+		#[arg(long, conflicts_with_all = ["files", "dry_run", "shims"])]
+		remove_shims: bool,
+	},
+}
+
+#[derive(Subcommand)]
+pub enum VersionCommand {
+	#[command(about = "Increments part of the version, resetting the parts after it")]
+	Bump { part: VersionPart },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum VersionPart {
+	Major,
+	Minor,
+	Patch,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MessageFormat {
+	Human,
+	Json,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum CiProvider {
+	/// GitHub Actions, testing on Linux, macOS and Windows
+	Github,
+	/// GitLab CI, testing on Linux
+	Gitlab,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum CompleteKind {
+	Scripts,
+	Deps,
+}
+
+/// Options shared by every command that builds with a profile of the user's choosing.
+#[derive(clap::Args)]
+pub struct ProfileArgs {
+	/// Builds with optimizations into target/release, the same as --profile release.
+	#[arg(long, conflicts_with = "profile")]
+	pub release: bool,
+
+	/// Profile to build with, debug by default.
+	#[arg(long, value_enum)]
+	pub profile: Option<crate::Profile>,
+}
+
+impl ProfileArgs {
+	pub fn get(&self) -> crate::Profile {
+		if self.release {
+			crate::Profile::Release
+		} else {
+			self.profile.unwrap_or_default()
+		}
+	}
+}
+
+/// Details recorded under [package] by the commands creating a project.
+#[derive(clap::Args)]
+pub struct MetadataArgs {
+	/// One line summary of the package.
+	#[arg(long)]
+	pub description: Option<String>,
+
+	/// Author of the package, which can be given several times. Defaults to `author` in the user config, or your name from git.
+	#[arg(long = "author", value_name = "AUTHOR")]
+	pub authors: Vec<String>,
+
+	/// Url of the package's source repository.
+	#[arg(long)]
+	pub repository: Option<String>,
+
+	/// Url of the package's website.
+	#[arg(long)]
+	pub homepage: Option<String>,
+
+	/// Word describing the package, which can be given several times.
+	#[arg(long = "keyword", value_name = "KEYWORD")]
+	pub keywords: Vec<String>,
+}
+
+/// Options shared by every command that writes an archive.
+#[derive(clap::Args)]
+pub struct ArchiveArgs {
+	/// Archive format, inferred from --output if not given.
+	#[arg(long)]
+	pub format: Option<crate::archive::Format>,
+
+	/// Where to write the archive.
+	#[arg(short, long)]
+	pub output: Option<std::path::PathBuf>,
+
+	/// Zeroes modification times so identical inputs produce identical archives.
+	#[arg(long)]
+	pub reproducible: bool,
+}
+
+impl ArchiveArgs {
+	/// Resolves the format and output path, defaulting to `<dir>/<stem>.tar.gz`.
+	pub fn resolve(
+		&self,
+		dir: &std::path::Path,
+		stem: &str,
+	) -> anyhow::Result<(crate::archive::Format, std::path::PathBuf)> {
+		let inferred = self.output.as_deref().and_then(crate::archive::Format::infer);
+
+		let format = match (self.format, inferred, &self.output) {
+			(Some(format), _, _) | (None, Some(format), _) => format,
+			(None, None, Some(output)) => anyhow::bail!(
+				"Couldn't infer archive format from {}, pass --format.",
+				output.display()
+			),
+			(None, None, None) => crate::archive::Format::TarGz,
+		};
+
+		let output = self
+			.output
+			.clone()
+			.unwrap_or_else(|| dir.join(format!("{stem}.{}", format.extension())));
+
+		Ok((format, output))
+	}
 }
 
 #[derive(Subcommand)]
 pub enum GenerateCommand {
 	#[command(about = "Creates a Makefile in the project directory")]
 	Make,
+
+	#[command(about = "Creates a build.ninja in the project directory")]
+	Ninja,
+
+	#[command(about = "Creates a pkg-config file for the library in the target directory")]
+	Pc {
+		/// Install prefix the library and headers will live under.
+		#[arg(long, default_value = "/usr/local")]
+		prefix: String,
+	},
+
+	#[command(about = "Creates a CI pipeline that builds, checks and tests the project")]
+	Ci {
+		provider: CiProvider,
+
+		/// Overwrites an existing pipeline.
+		#[arg(long)]
+		force: bool,
+	},
+
+	#[command(about = "Creates a .gitignore for build output in the project directory")]
+	Gitignore {
+		/// Overwrites an existing .gitignore.
+		#[arg(long)]
+		force: bool,
+	},
+
+	#[command(about = "Creates a .clang-format from a preset in the project directory")]
+	ClangFormat {
+		#[arg(long, value_enum, default_value = "llvm")]
+		style: crate::format::Style,
+
+		/// Overwrites an existing .clang-format.
+		#[arg(long)]
+		force: bool,
+	},
+
+	#[command(about = "Creates a git pre-commit hook that checks staged files are formatted")]
+	Hooks {
+		/// Uninstalls the hook, putting back any hook it replaced.
+		#[arg(long)]
+		remove: bool,
+	},
+
+	#[command(about = "Creates an .editorconfig matching a .clang-format preset in the project directory")]
+	Editorconfig {
+		#[arg(long, value_enum, default_value = "llvm")]
+		style: crate::format::Style,
+
+		/// Overwrites an existing .editorconfig.
+		#[arg(long)]
+		force: bool,
+	},
+}
+
+/// Hooks appended to the generated completion scripts, which fill in
+/// dependency names for `remove` and script names for `run` from `cpkg __complete`.
+const BASH_HOOK: &str = r#"
+_cpkg_dynamic() {
+    if [[ ${COMP_CWORD} -eq 2 ]]; then
+        case "${COMP_WORDS[1]}" in
+            remove)
+                COMPREPLY=( $(compgen -W "$(cpkg __complete deps 2>/dev/null)" -- "${COMP_WORDS[2]}") )
+                return 0
+                ;;
+            run)
+                COMPREPLY=( $(compgen -W "$(cpkg __complete scripts 2>/dev/null)" -- "${COMP_WORDS[2]}") )
+                if [[ ${#COMPREPLY[@]} -gt 0 ]]; then
+                    return 0
+                fi
+                ;;
+        esac
+    fi
+
+    _cpkg "$@"
+}
+
+complete -F _cpkg_dynamic -o bashdefault -o default cpkg
+"#;
+
+const ZSH_HOOK: &str = r#"
+_cpkg_dynamic() {
+    if (( CURRENT == 3 )); then
+        local -a names
+        case "$words[2]" in
+            remove)
+                names=(${(f)"$(cpkg __complete deps 2>/dev/null)"})
+                _describe -t dependencies 'dependency' names
+                return
+                ;;
+            run)
+                names=(${(f)"$(cpkg __complete scripts 2>/dev/null)"})
+                _describe -t scripts 'script' names
+                _files
+                return
+                ;;
+        esac
+    fi
+
+    _cpkg "$@"
+}
+
+compdef _cpkg_dynamic cpkg
+"#;
+
+const FISH_HOOK: &str = r#"
+complete -c cpkg -n "__fish_cpkg_using_subcommand remove" -f -a "(cpkg __complete deps 2>/dev/null)" -d 'Dependency'
+complete -c cpkg -n "__fish_cpkg_using_subcommand run" -a "(cpkg __complete scripts 2>/dev/null)" -d 'Script'
+"#;
+
+/// Extra completion code for shells that support looking up names at completion time.
+pub fn dynamic_completions(shell: clap_complete::Shell) -> Option<&'static str> {
+	match shell {
+		clap_complete::Shell::Bash => Some(BASH_HOOK),
+		clap_complete::Shell::Zsh => Some(ZSH_HOOK),
+		clap_complete::Shell::Fish => Some(FISH_HOOK),
+		_ => None,
+	}
 }
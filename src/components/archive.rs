@@ -0,0 +1,358 @@
+use std::io::Write;
+
+/// Archive formats cpkg can write outputs as.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+	#[value(name = "tar.gz", alias = "tgz")]
+	TarGz,
+	#[value(name = "tar.zst")]
+	TarZst,
+	#[value(name = "zip")]
+	Zip,
+}
+
+impl Format {
+	pub fn extension(&self) -> &'static str {
+		match self {
+			Self::TarGz => "tar.gz",
+			Self::TarZst => "tar.zst",
+			Self::Zip => "zip",
+		}
+	}
+
+	/// Guesses the format from an output path's extension.
+	pub fn infer(path: &std::path::Path) -> Option<Self> {
+		let name = path.file_name()?.to_string_lossy().to_lowercase();
+
+		if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+			Some(Self::TarGz)
+		} else if name.ends_with(".tar.zst") {
+			Some(Self::TarZst)
+		} else if name.ends_with(".zip") {
+			Some(Self::Zip)
+		} else {
+			None
+		}
+	}
+
+	fn create(&self, out: std::fs::File, reproducible: bool) -> anyhow::Result<Box<dyn Archive>> {
+		Ok(match self {
+			Self::TarGz => Box::new(TarGz {
+				builder: tar::Builder::new(flate2::write::GzEncoder::new(
+					out,
+					flate2::Compression::default(),
+				)),
+				reproducible,
+			}),
+			Self::TarZst => Box::new(TarZst {
+				builder: tar::Builder::new(zstd::Encoder::new(out, 0)?),
+				reproducible,
+			}),
+			Self::Zip => Box::new(Zip {
+				writer: zip::ZipWriter::new(out),
+				reproducible,
+			}),
+		})
+	}
+}
+
+/// A file going into an archive.
+pub struct Entry {
+	/// Path inside of the archive, always using `/`
+	pub name: String,
+	/// File on disk to read from
+	pub source: std::path::PathBuf,
+}
+
+/// Writer for a single archive format.
+pub trait Archive {
+	fn add(&mut self, entry: &Entry) -> anyhow::Result<()>;
+	fn finish(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+/// Writes entries to an archive at `out`, sorted so the output is stable.
+/// Under reproducible mode, modification times are zeroed too.
+pub fn write(
+	format: Format,
+	out: &std::path::Path,
+	mut entries: Vec<Entry>,
+	reproducible: bool,
+) -> anyhow::Result<()> {
+	entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+	if let Some(parent) = out.parent() {
+		std::fs::create_dir_all(parent)?;
+	}
+
+	let mut archive = format.create(std::fs::File::create(out)?, reproducible)?;
+
+	for entry in &entries {
+		archive.add(entry)?;
+	}
+
+	archive.finish()
+}
+
+#[cfg(unix)]
+fn mode(meta: &std::fs::Metadata) -> u32 {
+	use std::os::unix::fs::PermissionsExt;
+	meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode(meta: &std::fs::Metadata) -> u32 {
+	if meta.permissions().readonly() {
+		0o444
+	} else {
+		0o644
+	}
+}
+
+fn mtime(meta: &std::fs::Metadata, reproducible: bool) -> u64 {
+	if reproducible {
+		return 0;
+	}
+
+	meta.modified()
+		.ok()
+		.and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+		.map(|d| d.as_secs())
+		.unwrap_or(0)
+}
+
+fn append_tar<W: Write>(
+	builder: &mut tar::Builder<W>,
+	entry: &Entry,
+	reproducible: bool,
+) -> anyhow::Result<()> {
+	let file = std::fs::File::open(&entry.source)?;
+	let meta = file.metadata()?;
+
+	let mut header = tar::Header::new_gnu();
+	header.set_size(meta.len());
+	header.set_mode(mode(&meta));
+	header.set_mtime(mtime(&meta, reproducible));
+	header.set_uid(0);
+	header.set_gid(0);
+
+	builder.append_data(&mut header, &entry.name, file)?;
+
+	Ok(())
+}
+
+struct TarGz {
+	builder: tar::Builder<flate2::write::GzEncoder<std::fs::File>>,
+	reproducible: bool,
+}
+
+impl Archive for TarGz {
+	fn add(&mut self, entry: &Entry) -> anyhow::Result<()> {
+		append_tar(&mut self.builder, entry, self.reproducible)
+	}
+
+	fn finish(self: Box<Self>) -> anyhow::Result<()> {
+		self.builder.into_inner()?.finish()?;
+		Ok(())
+	}
+}
+
+struct TarZst {
+	builder: tar::Builder<zstd::Encoder<'static, std::fs::File>>,
+	reproducible: bool,
+}
+
+impl Archive for TarZst {
+	fn add(&mut self, entry: &Entry) -> anyhow::Result<()> {
+		append_tar(&mut self.builder, entry, self.reproducible)
+	}
+
+	fn finish(self: Box<Self>) -> anyhow::Result<()> {
+		self.builder.into_inner()?.finish()?;
+		Ok(())
+	}
+}
+
+struct Zip {
+	writer: zip::ZipWriter<std::fs::File>,
+	reproducible: bool,
+}
+
+impl Archive for Zip {
+	fn add(&mut self, entry: &Entry) -> anyhow::Result<()> {
+		let meta = std::fs::metadata(&entry.source)?;
+
+		let mut options = zip::write::SimpleFileOptions::default()
+			.compression_method(zip::CompressionMethod::Deflated)
+			.unix_permissions(mode(&meta));
+
+		if self.reproducible {
+			/* Zip can't go earlier than 1980 */
+			options = options.last_modified_time(zip::DateTime::default());
+		}
+
+		self.writer.start_file(entry.name.as_str(), options)?;
+		std::io::copy(&mut std::fs::File::open(&entry.source)?, &mut self.writer)?;
+
+		Ok(())
+	}
+
+	fn finish(self: Box<Self>) -> anyhow::Result<()> {
+		self.writer.finish()?.flush()?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Read;
+
+	/// Name, contents and mode of each file in an archive or picked for one, sorted by name.
+	type Tree = Vec<(String, Vec<u8>, u32)>;
+
+	fn source() -> (tempfile::TempDir, Vec<Entry>, Tree) {
+		let dir = tempfile::tempdir().unwrap();
+
+		let files: &[(&str, &[u8], u32)] = &[
+			("pkg/bin/tool", b"#!/bin/sh\necho hi\n", 0o755),
+			("pkg/README.md", b"# pkg\n", 0o644),
+			("pkg/include/pkg.h", b"int pkg(void);\n", 0o644),
+			("pkg/empty", b"", 0o600),
+		];
+
+		let mut entries = vec![];
+		let mut tree = vec![];
+
+		for (name, contents, file_mode) in files {
+			let path = dir.path().join(name);
+			std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+			std::fs::write(&path, contents).unwrap();
+
+			#[cfg(unix)]
+			{
+				use std::os::unix::fs::PermissionsExt;
+				std::fs::set_permissions(&path, std::fs::Permissions::from_mode(*file_mode))
+					.unwrap();
+			}
+
+			let meta_mode = mode(&std::fs::metadata(&path).unwrap());
+			assert!(cfg!(not(unix)) || meta_mode == *file_mode);
+
+			entries.push(Entry {
+				name: name.to_string(),
+				source: path,
+			});
+			tree.push((name.to_string(), contents.to_vec(), meta_mode));
+		}
+
+		tree.sort();
+		(dir, entries, tree)
+	}
+
+	fn read_tar(reader: impl Read) -> Tree {
+		let mut archive = tar::Archive::new(reader);
+		let mut tree = vec![];
+
+		for entry in archive.entries().unwrap() {
+			let mut entry = entry.unwrap();
+			let name = entry.path().unwrap().to_string_lossy().into_owned();
+			let entry_mode = entry.header().mode().unwrap();
+
+			let mut contents = vec![];
+			entry.read_to_end(&mut contents).unwrap();
+			tree.push((name, contents, entry_mode));
+		}
+
+		tree
+	}
+
+	fn read(format: Format, path: &std::path::Path) -> Tree {
+		let file = std::fs::File::open(path).unwrap();
+
+		match format {
+			Format::TarGz => read_tar(flate2::read::GzDecoder::new(file)),
+			Format::TarZst => read_tar(zstd::Decoder::new(file).unwrap()),
+			Format::Zip => {
+				let mut archive = zip::ZipArchive::new(file).unwrap();
+
+				(0..archive.len())
+					.map(|i| {
+						let mut entry = archive.by_index(i).unwrap();
+						let mut contents = vec![];
+						entry.read_to_end(&mut contents).unwrap();
+						(
+							entry.name().to_owned(),
+							contents,
+							entry.unix_mode().unwrap() & 0o7777,
+						)
+					})
+					.collect()
+			}
+		}
+	}
+
+	const FORMATS: [Format; 3] = [Format::TarGz, Format::TarZst, Format::Zip];
+
+	#[test]
+	fn round_trips_every_format() {
+		for format in FORMATS {
+			let (_dir, entries, tree) = source();
+			let out = tempfile::tempdir().unwrap();
+			let path = out.path().join(format!("pkg.{}", format.extension()));
+
+			write(format, &path, entries, false).unwrap();
+
+			/* Entries are written sorted, so the listing matches as is */
+			assert_eq!(read(format, &path), tree, "{}", format.extension());
+		}
+	}
+
+	#[test]
+	fn reproducible_archives_are_identical() {
+		for format in FORMATS {
+			let out = tempfile::tempdir().unwrap();
+			let first = out.path().join(format!("first.{}", format.extension()));
+			let second = out.path().join(format!("second.{}", format.extension()));
+
+			let (_dir, mut entries, _) = source();
+			write(format, &first, entries, true).unwrap();
+
+			/* Written again from files with other modification times, picked in another order */
+			let (_dir, entries_again, _) = source();
+			entries = entries_again;
+			entries.reverse();
+
+			for entry in &entries {
+				let file = std::fs::File::options()
+					.write(true)
+					.open(&entry.source)
+					.unwrap();
+				file.set_modified(
+					std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000),
+				)
+				.unwrap();
+			}
+
+			write(format, &second, entries, true).unwrap();
+
+			assert_eq!(
+				std::fs::read(&first).unwrap(),
+				std::fs::read(&second).unwrap(),
+				"{}",
+				format.extension()
+			);
+		}
+	}
+
+	#[test]
+	fn infers_format_from_name() {
+		let infer = |name: &str| Format::infer(std::path::Path::new(name));
+
+		assert!(infer("out/pkg-0.1.0.tar.gz") == Some(Format::TarGz));
+		assert!(infer("PKG.TGZ") == Some(Format::TarGz));
+		assert!(infer("pkg.tar.zst") == Some(Format::TarZst));
+		assert!(infer("pkg.zip") == Some(Format::Zip));
+		assert!(infer("pkg.tar").is_none());
+		assert!(infer("pkg").is_none());
+	}
+}
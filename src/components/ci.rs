@@ -0,0 +1,324 @@
+/// Repository cpkg is released from, which generated pipelines download it from.
+const REPO: &str = "DvvCz/cpkg";
+
+/// zig release installed by pipelines for projects that default to zig cc.
+const ZIG_VERSION: &str = "0.13.0";
+
+/// What a generated CI pipeline needs to know about the project.
+pub struct CiData {
+	/// Compiler the project defaults to under [compiler], if any
+	pub compiler: Option<String>,
+	/// Formatter the project uses, when it has a [formatter] section
+	pub formatter: Option<String>,
+	/// Folder dependencies are installed to, which is cached between runs
+	pub vendor: String,
+}
+
+impl CiData {
+	pub fn new(proj: &crate::Project) -> Self {
+		let config = proj.config();
+
+		Self {
+			compiler: config.compiler.as_ref().and_then(|c| c.default.clone()),
+			formatter: config.formatter.as_ref().map(|f| {
+				f.default
+					.clone()
+					.unwrap_or_else(|| "clang-format".to_owned())
+			}),
+			vendor: proj
+				.vendor()
+				.strip_prefix(proj.path())
+				.unwrap_or(&proj.vendor())
+				.to_string_lossy()
+				.replace('\\', "/"),
+		}
+	}
+}
+
+pub trait CiGenerator {
+	/// Where the pipeline is written, relative to the project root.
+	fn file_name(&self) -> &'static str;
+
+	fn generate(&self, data: &CiData) -> anyhow::Result<String>;
+}
+
+/// Debian package providing a compiler or formatter, for tools that aren't preinstalled.
+fn apt_package(tool: &str) -> Option<&'static str> {
+	match tool {
+		"clang" => Some("clang"),
+		"tcc" => Some("tcc"),
+		"clang-format" => Some("clang-format"),
+		"uncrustify" => Some("uncrustify"),
+		_ => None,
+	}
+}
+
+pub struct GitHub;
+
+impl GitHub {
+	/// Runners the project's compiler can run on.
+	fn runners(compiler: Option<&str>) -> &'static str {
+		match compiler {
+			Some("msvc") => "[windows-latest]",
+			Some("tcc") => "[ubuntu-latest]",
+			Some("cosmocc") => "[ubuntu-latest, macos-latest]",
+			_ => "[ubuntu-latest, macos-latest, windows-latest]",
+		}
+	}
+
+	/// Steps installing the project's compiler, where runners don't already have it.
+	fn compiler_steps(compiler: Option<&str>) -> String {
+		match compiler {
+			/* Windows runners come with a gcc, but an msys2 one is kept up to date */
+			Some("gcc") => indoc::indoc! {r#"
+				- name: Install gcc (Windows)
+				  if: runner.os == 'Windows'
+				  id: msys2
+				  uses: msys2/setup-msys2@v2
+				  with:
+				    msystem: UCRT64
+				    install: mingw-w64-ucrt-x86_64-gcc
+
+				- name: Add gcc to PATH (Windows)
+				  if: runner.os == 'Windows'
+				  run: echo "${{ steps.msys2.outputs.msys2-location }}\ucrt64\bin" >> $env:GITHUB_PATH
+			"#}
+			.to_owned(),
+
+			Some("msvc") => indoc::indoc! {"
+				- name: Set up msvc
+				  uses: ilammy/msvc-dev-cmd@v1
+			"}
+			.to_owned(),
+
+			Some("tcc") => indoc::indoc! {"
+				- name: Install tcc
+				  run: sudo apt-get install -y tcc
+			"}
+			.to_owned(),
+
+			Some("cosmocc") => indoc::indoc! {r#"
+				- name: Install cosmocc
+				  run: |
+				    curl -fsSLO https://cosmo.zip/pub/cosmocc/cosmocc.zip
+				    unzip -q -d "$HOME/cosmocc" cosmocc.zip
+				    echo "$HOME/cosmocc/bin" >> "$GITHUB_PATH"
+			"#}
+			.to_owned(),
+
+			Some("zig") => indoc::formatdoc! {"
+				- name: Install zig
+				  uses: mlugg/setup-zig@v1
+				  with:
+				    version: {ZIG_VERSION}
+			"},
+
+			/* gcc or clang are on every runner already */
+			_ => String::new(),
+		}
+	}
+
+	fn formatter_steps(formatter: &str) -> String {
+		let install = apt_package(formatter)
+			.map(|p| {
+				indoc::formatdoc! {"
+					- name: Install {formatter}
+					  if: runner.os == 'Linux'
+					  run: sudo apt-get install -y {p}
+
+				"}
+			})
+			.unwrap_or_default();
+
+		/* Formatting doesn't depend on the platform, so one runner checking it is enough */
+		install
+			+ indoc::indoc! {"
+				- name: Check formatting
+				  if: runner.os == 'Linux'
+				  run: cpkg format --check
+			"}
+	}
+}
+
+impl CiGenerator for GitHub {
+	fn file_name(&self) -> &'static str {
+		".github/workflows/ci.yml"
+	}
+
+	fn generate(&self, data: &CiData) -> anyhow::Result<String> {
+		let compiler = data.compiler.as_deref();
+		let mut steps = vec![];
+
+		steps.push("- uses: actions/checkout@v4\n".to_owned());
+		steps.push(Self::compiler_steps(compiler));
+
+		/* Release binaries are only built for Linux and Windows, so macOS builds from source */
+		steps.push(indoc::formatdoc! {r#"
+			- name: Install cpkg (Linux)
+			  if: runner.os == 'Linux'
+			  env:
+			    GH_TOKEN: ${{{{ github.token }}}}
+			  run: |
+			    mkdir -p "$HOME/.local/bin"
+			    gh release download --repo {REPO} --pattern 'cpkg-*-x86_64-unknown-linux-gnu' --output "$HOME/.local/bin/cpkg"
+			    chmod +x "$HOME/.local/bin/cpkg"
+			    echo "$HOME/.local/bin" >> "$GITHUB_PATH"
+
+			- name: Install cpkg (Windows)
+			  if: runner.os == 'Windows'
+			  env:
+			    GH_TOKEN: ${{{{ github.token }}}}
+			  run: |
+			    New-Item -ItemType Directory -Force "$env:USERPROFILE\.cpkg\bin" | Out-Null
+			    gh release download --repo {REPO} --pattern 'cpkg-*-x86_64-pc-windows-msvc.exe' --output "$env:USERPROFILE\.cpkg\bin\cpkg.exe"
+			    echo "$env:USERPROFILE\.cpkg\bin" >> $env:GITHUB_PATH
+
+			- name: Install cpkg (macOS)
+			  if: runner.os == 'macOS'
+			  run: cargo install --git https://github.com/{REPO} --locked
+		"#});
+
+		steps.push(indoc::formatdoc! {"
+			- name: Cache dependencies
+			  uses: actions/cache@v4
+			  with:
+			    path: {vendor}
+			    key: vendor-${{{{ runner.os }}}}-${{{{ hashFiles('cpkg.toml') }}}}
+
+			- run: cpkg install
+
+			- run: cpkg build
+		",
+			vendor = data.vendor,
+		});
+
+		if let Some(formatter) = &data.formatter {
+			steps.push(Self::formatter_steps(formatter));
+		}
+
+		steps.push("- run: cpkg test\n".to_owned());
+
+		let steps = steps
+			.iter()
+			.filter(|s| !s.is_empty())
+			.map(|s| indent(s, 6))
+			.collect::<Vec<_>>()
+			.join("\n");
+
+		Ok(indoc::formatdoc! {"
+			name: CI
+
+			on:
+			  push:
+			  pull_request:
+
+			jobs:
+			  test:
+			    strategy:
+			      fail-fast: false
+			      matrix:
+			        os: {runners}
+
+			    runs-on: ${{{{ matrix.os }}}}
+			    steps:
+			{steps}",
+			runners = Self::runners(compiler),
+		})
+	}
+}
+
+pub struct GitLab;
+
+impl CiGenerator for GitLab {
+	fn file_name(&self) -> &'static str {
+		".gitlab-ci.yml"
+	}
+
+	fn generate(&self, data: &CiData) -> anyhow::Result<String> {
+		let mut setup = vec![];
+		let mut packages = vec![];
+
+		match data.compiler.as_deref() {
+			Some("msvc") => anyhow::bail!(
+				"GitLab pipelines are generated for Linux runners, which can't run msvc. Use gcc or clang as the default compiler."
+			),
+			Some("cosmocc") => setup.extend([
+				"curl -fsSLO https://cosmo.zip/pub/cosmocc/cosmocc.zip".to_owned(),
+				"unzip -q -d /opt/cosmocc cosmocc.zip".to_owned(),
+				"export PATH=/opt/cosmocc/bin:$PATH".to_owned(),
+			]),
+			Some("zig") => setup.extend([
+				format!("curl -fsSL https://ziglang.org/download/{ZIG_VERSION}/zig-linux-x86_64-{ZIG_VERSION}.tar.xz | tar -xJ -C /opt"),
+				format!("export PATH=/opt/zig-linux-x86_64-{ZIG_VERSION}:$PATH"),
+			]),
+			Some(compiler) => packages.extend(apt_package(compiler)),
+			None => {}
+		}
+
+		if let Some(formatter) = &data.formatter {
+			packages.extend(apt_package(formatter));
+		}
+
+		if !packages.is_empty() {
+			setup.insert(
+				0,
+				format!("apt-get update && apt-get install -y {}", packages.join(" ")),
+			);
+		}
+
+		setup.push(format!(
+			"curl -fsSL -o /usr/local/bin/cpkg \"$(curl -fsSL https://api.github.com/repos/{REPO}/releases/latest | grep -o 'https://[^\"]*x86_64-unknown-linux-gnu')\""
+		));
+		setup.push("chmod +x /usr/local/bin/cpkg".to_owned());
+
+		let mut script = vec!["cpkg install", "cpkg build"];
+
+		if data.formatter.is_some() {
+			script.push("cpkg format --check");
+		}
+
+		script.push("cpkg test");
+
+		let list = |items: &[String]| {
+			items
+				.iter()
+				.map(|i| format!("  - {i}"))
+				.collect::<Vec<_>>()
+				.join("\n")
+		};
+
+		Ok(indoc::formatdoc! {"
+			image: gcc:latest
+
+			cache:
+			  key:
+			    files:
+			      - cpkg.toml
+			  paths:
+			    - {vendor}
+
+			before_script:
+			{setup}
+
+			test:
+			  script:
+			{script}",
+			setup = list(&setup),
+			vendor = data.vendor,
+			script = indent(&list(&script.iter().map(|s| s.to_string()).collect::<Vec<_>>()), 2),
+		})
+	}
+}
+
+/// Indents every non-empty line, for nesting a chunk of yaml.
+fn indent(text: &str, by: usize) -> String {
+	text.lines()
+		.map(|l| {
+			if l.is_empty() {
+				"\n".to_owned()
+			} else {
+				format!("{}{l}\n", " ".repeat(by))
+			}
+		})
+		.collect()
+}
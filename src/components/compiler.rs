@@ -1,108 +1,1103 @@
+use colored::Colorize;
+
 pub trait Compiler {
+	/// Creates the command [Self::compile] would run, without running it, as shown by plans and `--verbose`.
+	fn command(
+		&self,
+		files: &[std::path::PathBuf],
+		deps: &[&std::path::Path],
+		to: &std::path::Path,
+		flags: &[String],
+	) -> std::process::Command;
+
+	/// Compiles `files` to `to`, handing `output` each line the compiler prints as it prints it.
+	/// Everything printed is kept too, for the [CompileError] if it fails.
 	fn compile(
 		&self,
 		files: &[std::path::PathBuf],
 		deps: &[&std::path::Path],
 		to: &std::path::Path,
 		flags: &[String],
+		output: &mut dyn FnMut(&str),
 	) -> anyhow::Result<()>;
 
-	fn makefile(&self, proj: &crate::Project) -> String;
+	/// Executable invoked by this backend.
+	fn bin(&self) -> &str;
+
+	/// Command line that invokes the compiler, for exported build files.
+	fn driver(&self) -> Vec<String> {
+		vec![self.bin().to_owned()]
+	}
+
+	/// Whether the backend takes gcc-style arguments, which exported build files assume.
+	fn gcc_style(&self) -> bool {
+		true
+	}
+
+	/// Creates a command that compiles and runs a single file in one step,
+	/// for backends that can skip writing a binary.
+	fn run(&self, _file: &std::path::Path, _flags: &[String]) -> Option<std::process::Command> {
+		None
+	}
+
+	/// Counterpart of the backend compiling C++, like g++ for gcc, for C++ sources and linking anything with them.
+	fn cxx(&self) -> anyhow::Result<Box<dyn Compiler>> {
+		anyhow::bail!("{} can't compile C++, set CXX to a compiler that can.", self.bin());
+	}
+
+	/// Flag making the compiler print where its time went, for `cpkg build --timings=verbose`.
+	fn time_report(&self) -> Option<&'static str> {
+		None
+	}
+
+	/// Flag making the compiler color its diagnostics, even though cpkg captures them.
+	fn color_flag(&self) -> Option<&'static str> {
+		None
+	}
+
+	/// Whether the compiler writes files beside the output named after it, like emcc's .js or cl's .pdb,
+	/// which compiling to a temporary name and moving it into place would break.
+	fn writes_beside(&self) -> bool {
+		false
+	}
+
+	/// Picks out the diagnostics from what the compiler printed when it failed.
+	fn diagnostics(&self, output: &str) -> Vec<Diagnostic> {
+		parse_gcc(output)
+	}
+
+	/// Compiles a single file only as far as `kind`, writing what the compiler produced to `to`.
+	fn emit(
+		&self,
+		_file: &std::path::Path,
+		_deps: &[&std::path::Path],
+		_to: &std::path::Path,
+		_flags: &[String],
+		kind: Emit,
+	) -> anyhow::Result<()> {
+		anyhow::bail!("{} can't emit {}.", self.bin(), kind.describe());
+	}
+}
+
+/// Intermediate output `cpkg build --emit` and `cpkg compile` stop compiling at.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Emit {
+	/// Object file, compiled but not linked
+	Object,
+	Asm,
+	Preprocessed,
+	/// LLVM IR, which only clang based compilers produce
+	Ir,
+}
+
+impl Emit {
+	pub fn extension(&self) -> &'static str {
+		match self {
+			Self::Object => "o",
+			Self::Asm => "s",
+			Self::Preprocessed => "i",
+			Self::Ir => "ll",
+		}
+	}
+
+	pub fn describe(&self) -> &'static str {
+		match self {
+			Self::Object => "an object file",
+			Self::Asm => "assembly",
+			Self::Preprocessed => "preprocessed source",
+			Self::Ir => "LLVM IR",
+		}
+	}
+
+	/// gcc-style flags stopping the compile at this output.
+	fn flags(&self) -> &'static [&'static str] {
+		match self {
+			Self::Object => &["-c"],
+			Self::Asm => &["-S"],
+			Self::Preprocessed => &["-E"],
+			Self::Ir => &["-S", "-emit-llvm"],
+		}
+	}
+}
+
+/// Hint given when several files define main, as happens building with every binary included.
+const MAIN_HINT: &str = "did you mean to run with --bin?";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+	Error,
+	Warning,
+	Note,
+}
+
+/// A message from the compiler about a specific place in a source file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+	pub file: std::path::PathBuf,
+	pub line: usize,
+	pub column: Option<usize>,
+	pub severity: Severity,
+	pub message: String,
+}
+
+/// Parses `file:line:col: severity: message` lines, as printed by gcc, clang, tcc and zig.
+/// Lines that aren't diagnostics, like source excerpts and linker output, are skipped.
+pub fn parse_gcc(output: &str) -> Vec<Diagnostic> {
+	const SEVERITIES: &[(&str, Severity)] = &[
+		(": fatal error: ", Severity::Error),
+		(": error: ", Severity::Error),
+		(": warning: ", Severity::Warning),
+		(": note: ", Severity::Note),
+	];
+
+	output
+		.lines()
+		.filter_map(|text| {
+			let (idx, marker, severity) = SEVERITIES
+				.iter()
+				.filter_map(|(marker, severity)| Some((text.find(marker)?, *marker, *severity)))
+				.min_by_key(|(idx, ..)| *idx)?;
+
+			/* Paths are split from the right, so drive letters on windows don't get in the way */
+			let (rest, last) = text[..idx].rsplit_once(':')?;
+			let last = last.parse().ok()?;
+
+			let (file, line, column) = match rest.rsplit_once(':') {
+				Some((file, line)) if line.parse::<usize>().is_ok() => {
+					(file, line.parse().ok()?, Some(last))
+				}
+				_ => (rest, last, None),
+			};
+
+			Some(Diagnostic {
+				file: file.into(),
+				line,
+				column,
+				severity,
+				message: text[idx + marker.len()..].to_owned(),
+			})
+		})
+		.collect()
+}
+
+/// A compile that failed, keeping what the compiler printed along with what could be parsed out of it.
+#[derive(Debug)]
+pub struct CompileError {
+	pub output: String,
+	pub diagnostics: Vec<Diagnostic>,
+	/// Suggestion from cpkg on what went wrong
+	pub hint: Option<&'static str>,
+}
+
+impl CompileError {
+	/// Colors the compiler added are kept for showing `output` as is, but left out when parsing it.
+	pub fn new(backend: &dyn Compiler, output: String, hint: Option<&'static str>) -> Self {
+		Self {
+			diagnostics: backend.diagnostics(&strip_colors(&output)),
+			output,
+			hint,
+		}
+	}
+}
+
+/// Prints a line the compiler printed as it goes, unless `--quiet`.
+/// Warnings show up while the build is still running, rather than all at once at the end.
+pub fn echo(line: &str) {
+	if crate::log::handled() || crate::log::level() >= crate::log::Level::Normal {
+		crate::log::output(crate::log::Stream::Stderr, line);
+	}
+}
+
+/// Runs a compiler, handing `output` each line it prints to stdout or stderr as they come,
+/// while capturing both like [std::process::Command::output].
+fn stream(mut cmd: std::process::Command, output: &mut dyn FnMut(&str)) -> std::io::Result<std::process::Output> {
+	cmd.stdin(std::process::Stdio::null())
+		.stdout(std::process::Stdio::piped())
+		.stderr(std::process::Stdio::piped());
+
+	let mut child = cmd.spawn()?;
+	let pipes = [
+		child.stdout.take().map(|p| Box::new(p) as Box<dyn std::io::Read + Send>),
+		child.stderr.take().map(|p| Box::new(p) as Box<dyn std::io::Read + Send>),
+	];
+
+	let mut captured = [vec![], vec![]];
+
+	std::thread::scope(|s| {
+		let (tx, rx) = std::sync::mpsc::channel();
+
+		/* Each pipe is read on its own thread, so one filling up can't stall the compiler */
+		for (index, pipe) in pipes.into_iter().enumerate() {
+			let tx = tx.clone();
+
+			s.spawn(move || {
+				let mut reader = std::io::BufReader::new(pipe?);
+
+				loop {
+					let mut line = vec![];
+
+					match std::io::BufRead::read_until(&mut reader, b'\n', &mut line) {
+						Ok(0) | Err(_) => break,
+						Ok(_) => {
+							let _ = tx.send((index, line));
+						}
+					}
+				}
+
+				Some(())
+			});
+		}
+
+		drop(tx);
+
+		for (index, line) in rx {
+			output(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']));
+			captured[index].extend(line);
+		}
+	});
+
+	let [stdout, stderr] = captured;
+
+	Ok(std::process::Output {
+		status: child.wait()?,
+		stdout,
+		stderr,
+	})
+}
+
+/// Removes the ANSI escape sequences terminals color text with.
+pub fn strip_colors(text: &str) -> String {
+	let mut out = String::with_capacity(text.len());
+	let mut chars = text.chars();
+
+	while let Some(c) = chars.next() {
+		if c == '\x1b' {
+			/* A CSI sequence like \x1b[01;31m runs until its final byte, a letter */
+			if chars.next() == Some('[') {
+				for c in chars.by_ref() {
+					if c.is_ascii_alphabetic() {
+						break;
+					}
+				}
+			}
+
+			continue;
+		}
+
+		out.push(c);
+	}
+
+	out
+}
+
+impl std::fmt::Display for CompileError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.hint {
+			Some(hint) => write!(f, "{}\n(cpkg: {hint})", self.output),
+			None => write!(f, "{}", self.output),
+		}
+	}
+}
+
+impl std::error::Error for CompileError {}
+
+/// Several failed compiles from one build, reported together, see [report].
+#[derive(Debug)]
+pub struct CompileErrors {
+	pub failures: Vec<CompileError>,
+	/// Project root that paths are shown relative to
+	pub root: std::path::PathBuf,
+}
+
+impl std::fmt::Display for CompileErrors {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"Failed to compile:\n{}",
+			report(&self.failures, &self.root).trim_end()
+		)
+	}
+}
+
+impl std::error::Error for CompileErrors {}
+
+/// Renders the diagnostics of several failed compiles together, showing each only once,
+/// with paths relative to `root`.
+/// Output with no errors that could be parsed, like from the linker, is shown as is.
+pub fn report(failures: &[CompileError], root: &std::path::Path) -> String {
+	let mut seen = std::collections::HashSet::new();
+	let mut raw = std::collections::HashSet::new();
+	let mut hints = vec![];
+	let mut out = String::new();
+
+	for failure in failures {
+		let parsed = failure
+			.diagnostics
+			.iter()
+			.any(|d| d.severity == Severity::Error);
+
+		if !parsed && raw.insert(failure.output.trim()) {
+			out.push_str(failure.output.trim_end());
+			out.push('\n');
+		}
+
+		for diagnostic in failure.diagnostics.iter().filter(|d| seen.insert(*d)) {
+			let file = diagnostic
+				.file
+				.strip_prefix(root)
+				.unwrap_or(&diagnostic.file);
+
+			let location = match diagnostic.column {
+				Some(column) => format!("{}:{}:{column}", file.display(), diagnostic.line),
+				None => format!("{}:{}", file.display(), diagnostic.line),
+			};
+
+			let severity = match diagnostic.severity {
+				Severity::Error => "error:".bright_red().bold(),
+				Severity::Warning => "warning:".yellow().bold(),
+				Severity::Note => "note:".cyan().bold(),
+			};
+
+			out.push_str(&format!(
+				"{}: {severity} {}\n",
+				location.bold(),
+				diagnostic.message
+			));
+		}
+
+		if let Some(hint) = failure.hint.filter(|h| !hints.contains(h)) {
+			hints.push(hint);
+		}
+	}
+
+	for hint in hints {
+		out.push_str(&format!("(cpkg: {hint})\n"));
+	}
+
+	out
 }
 
 pub struct Gcc {
-	bin: &'static str,
+	bin: String,
+	/// Arguments before the compiler's own, like `cc` for `zig cc` or `gcc` for `ccache gcc`
+	args: Vec<String>,
+}
+
+impl Gcc {
+	fn locate(bin: &str) -> Option<Box<dyn Compiler>> {
+		if !super::probe::exists(bin) {
+			return None;
+		}
+
+		Some(Box::new(Self {
+			bin: bin.to_owned(),
+			args: vec![],
+		}))
+	}
 }
 
 impl Compiler for Gcc {
-	fn makefile(&self, proj: &crate::Project) -> String {
-		let cc = self.bin;
+	fn cxx(&self) -> anyhow::Result<Box<dyn Compiler>> {
+		/* The last word names the compiler, so `ccache gcc` becomes `ccache g++` and `zig cc` becomes `zig c++` */
+		let mut driver = self.driver();
+		let last = driver.last_mut().expect("driver has the executable");
 
-		let name = proj.name();
-		let flags = proj.build_flags(self as &dyn Compiler).join(" ");
-		let bin = proj.build_out(None).display().to_string();
+		let Some(cxx) = cxx_name(last) else {
+			anyhow::bail!("No C++ compiler goes with {}, set CXX to one.", self.bin);
+		};
+
+		/* Wrappers are left to report a missing compiler themselves */
+		if self.args.is_empty() && !super::probe::exists(&cxx) {
+			return Err(super::probe::LocateError::NotFound {
+				tool: super::probe::Tool::Compiler,
+				bin: cxx,
+				from: format!("{} for the C++ sources", self.bin),
+			}
+			.into());
+		}
 
-		indoc::formatdoc! {"
-			CC = {cc}
+		*last = cxx;
 
-			{name}: $(wildcard src/*)
-				$(CC) $(wildcard src/*.c) -o {bin} {flags}
-		"}
+		Ok(Box::new(Self {
+			bin: driver.remove(0),
+			args: driver,
+		}))
 	}
 
-	fn compile(
+	fn time_report(&self) -> Option<&'static str> {
+		Some("-ftime-report")
+	}
+
+	fn color_flag(&self) -> Option<&'static str> {
+		Some("-fdiagnostics-color=always")
+	}
+
+	fn writes_beside(&self) -> bool {
+		/* cosmocc writes a .dbg and .elf for each architecture */
+		std::path::Path::new(&self.bin)
+			.file_stem()
+			.is_some_and(|s| ["emcc", "em++", "cosmocc", "cosmoc++"].iter().any(|b| s == *b))
+	}
+
+	fn bin(&self) -> &str {
+		&self.bin
+	}
+
+	fn driver(&self) -> Vec<String> {
+		std::iter::once(&self.bin)
+			.chain(&self.args)
+			.cloned()
+			.collect()
+	}
+
+	fn command(
 		&self,
 		files: &[std::path::PathBuf],
 		deps: &[&std::path::Path],
 		to: &std::path::Path,
 		flags: &[String],
-	) -> anyhow::Result<()> {
+	) -> std::process::Command {
 		let mut cmd = std::process::Command::new(&self.bin);
 
-		cmd.args(files).arg("-o").arg(to).args(flags);
+		cmd.args(&self.args).args(files).arg("-o").arg(to).args(flags);
 
 		for dep in deps {
 			// Include dependency folder
 			cmd.arg("-I").arg(dep);
 		}
 
-		let e = cmd.output()?;
+		cmd
+	}
+
+	fn compile(
+		&self,
+		files: &[std::path::PathBuf],
+		deps: &[&std::path::Path],
+		to: &std::path::Path,
+		flags: &[String],
+		output: &mut dyn FnMut(&str),
+	) -> anyhow::Result<()> {
+		let cmd = self.command(files, deps, to, flags);
+		crate::log::command(&cmd);
+
+		let e = stream(cmd, output)?;
 
 		if !e.status.success() {
-			let msg = String::from_utf8_lossy(&e.stderr);
-			if msg.find("multiple definition of `main").is_some() {
-				/* todo: should be backend agnostic, moved upward */
-				anyhow::bail!("{msg}\n(cpkg: did you mean to run with --bin?)");
+			let msg = String::from_utf8_lossy(&e.stderr).into_owned();
+
+			/* todo: should be backend agnostic, moved upward */
+			let hint = strip_colors(&msg)
+				.contains("multiple definition of `main")
+				.then_some(MAIN_HINT);
+
+			return Err(CompileError::new(self, msg, hint).into());
+		}
+
+		Ok(())
+	}
+
+	fn emit(
+		&self,
+		file: &std::path::Path,
+		deps: &[&std::path::Path],
+		to: &std::path::Path,
+		flags: &[String],
+		kind: Emit,
+	) -> anyhow::Result<()> {
+		/* clang, zig cc and emcc all sit on top of clang, so can output its IR */
+		let is_clang = std::path::Path::new(&self.bin)
+			.file_stem()
+			.is_some_and(|s| s.to_string_lossy().contains("clang") || s == "zig" || s == "emcc");
+
+		if kind == Emit::Ir && !is_clang {
+			anyhow::bail!(
+				"{} can't emit LLVM IR, only clang can. Set CC=clang, or default = \"clang\" under [compiler] in cpkg.toml.",
+				self.bin
+			);
+		}
+
+		let mut flags = flags.to_vec();
+		flags.extend(kind.flags().iter().map(|f| f.to_string()));
+
+		/* What's emitted is the output, and errors are reported once it fails */
+		self.compile(&[file.to_owned()], deps, to, &flags, &mut |_| {})
+	}
+}
+
+/// TinyCC, which compiles fast enough to make the repl and scripts feel instant.
+/// It takes gcc-style arguments, minus a few it rejects.
+pub struct Tcc {
+	inner: Gcc,
+}
+
+impl Tcc {
+	fn locate() -> Option<Box<dyn Compiler>> {
+		if !super::probe::exists("tcc") {
+			return None;
+		}
+
+		Some(Box::new(Self {
+			inner: Gcc {
+				bin: "tcc".to_owned(),
+				args: vec![],
+			},
+		}))
+	}
+
+	/// Drops flags tcc errors on, which cpkg or a project may pass for gcc and clang.
+	fn filter(flags: &[String]) -> Vec<String> {
+		flags
+			.iter()
+			.filter(|f| !f.starts_with("-fdiagnostics-color") && *f != "-zmuldefs")
+			.cloned()
+			.collect()
+	}
+}
+
+impl Compiler for Tcc {
+	fn bin(&self) -> &str {
+		self.inner.bin()
+	}
+
+	fn command(
+		&self,
+		files: &[std::path::PathBuf],
+		deps: &[&std::path::Path],
+		to: &std::path::Path,
+		flags: &[String],
+	) -> std::process::Command {
+		self.inner.command(files, deps, to, &Self::filter(flags))
+	}
+
+	fn compile(
+		&self,
+		files: &[std::path::PathBuf],
+		deps: &[&std::path::Path],
+		to: &std::path::Path,
+		flags: &[String],
+		output: &mut dyn FnMut(&str),
+	) -> anyhow::Result<()> {
+		self.inner.compile(files, deps, to, &Self::filter(flags), output)
+	}
+
+	fn run(&self, file: &std::path::Path, flags: &[String]) -> Option<std::process::Command> {
+		let mut cmd = std::process::Command::new(self.bin());
+		cmd.args(Self::filter(flags)).arg("-run").arg(file);
+
+		Some(cmd)
+	}
+
+	fn emit(
+		&self,
+		file: &std::path::Path,
+		deps: &[&std::path::Path],
+		to: &std::path::Path,
+		flags: &[String],
+		kind: Emit,
+	) -> anyhow::Result<()> {
+		/* tcc goes straight to machine code, there's no assembly in between to show */
+		if !matches!(kind, Emit::Preprocessed | Emit::Object) {
+			anyhow::bail!(
+				"tcc can't emit {}, only preprocessed source and object files. Use gcc or clang instead.",
+				kind.describe()
+			);
+		}
+
+		self.inner.emit(file, deps, to, &Self::filter(flags), kind)
+	}
+}
+
+/// Microsoft's compiler, cl.exe, which takes its own style of arguments.
+/// Projects and cpkg itself write flags gcc-style, so the common ones are translated.
+pub struct Msvc {
+	bin: std::path::PathBuf,
+	/// Environment from vcvars, when not ran from a developer prompt
+	env: Vec<(String, String)>,
+}
+
+impl Msvc {
+	/// Finds cl.exe on PATH when ran from a developer prompt, or through vswhere otherwise.
+	fn locate() -> Option<Box<dyn Compiler>> {
+		if let Some(bin) = super::probe::which("cl") {
+			return Some(Box::new(Self { bin, env: vec![] }));
+		}
+
+		let env = Self::vcvars()?;
+		let path = env
+			.iter()
+			.find(|(k, _)| k.eq_ignore_ascii_case("PATH"))
+			.map(|(_, v)| v.clone())?;
+
+		let bin = which::which_in("cl", Some(path), ".").ok()?;
+
+		Some(Box::new(Self { bin, env }))
+	}
+
+	/// Loads the environment of the latest Visual Studio with C++ tools, found through vswhere.
+	/// Without it, cl.exe can't find the standard headers or libraries.
+	#[cfg(target_os = "windows")]
+	fn vcvars() -> Option<Vec<(String, String)>> {
+		use std::os::windows::process::CommandExt;
+
+		let program_files = std::env::var_os("ProgramFiles(x86)")?;
+		let vswhere = std::path::Path::new(&program_files)
+			.join("Microsoft Visual Studio")
+			.join("Installer")
+			.join("vswhere.exe");
+
+		let out = std::process::Command::new(vswhere)
+			.args(["-latest", "-products", "*"])
+			.args(["-requires", "Microsoft.VisualStudio.Component.VC.Tools.x86.x64"])
+			.args(["-property", "installationPath"])
+			.output()
+			.ok()?;
+
+		let install = String::from_utf8(out.stdout).ok()?;
+		let install = install.trim();
+
+		if install.is_empty() {
+			return None;
+		}
+
+		let vcvars = std::path::Path::new(install).join(r"VC\Auxiliary\Build\vcvars64.bat");
+
+		/* cmd has its own quoting rules, which the default escaping gets wrong */
+		let out = std::process::Command::new("cmd")
+			.raw_arg(format!("/c \"call \"{}\" >nul && set\"", vcvars.display()))
+			.output()
+			.ok()?;
+
+		if !out.status.success() {
+			return None;
+		}
+
+		let env = String::from_utf8_lossy(&out.stdout)
+			.lines()
+			.filter_map(|l| l.split_once('='))
+			.map(|(k, v)| (k.to_owned(), v.to_owned()))
+			.collect();
+
+		Some(env)
+	}
+
+	#[cfg(not(target_os = "windows"))]
+	fn vcvars() -> Option<Vec<(String, String)>> {
+		None
+	}
+
+	/// Translates a gcc-style flag, returning it and whether it's for the linker.
+	/// Flags with no equivalent are dropped, and anything unrecognized passes through as is.
+	fn translate(flag: &str) -> Option<(String, bool)> {
+		let compile = |f: &str| Some((f.to_owned(), false));
+		let link = |f: String| Some((f, true));
+
+		match flag {
+			/* Lets the first of several mains win, like -zmuldefs does */
+			"-zmuldefs" => link("/FORCE:MULTIPLE".to_owned()),
+			"-c" => compile("/c"),
+			"-fsyntax-only" => compile("/Zs"),
+			"-w" => compile("/w"),
+			"-Wall" => compile("/W4"),
+			/* /W4 already covers what -Wextra adds */
+			"-Wextra" => None,
+			"-Werror" => compile("/WX"),
+			"-g" => compile("/Zi"),
+			"-O0" => compile("/Od"),
+			"-O1" | "-Os" => compile("/O1"),
+			"-O2" | "-O3" => compile("/O2"),
+			"-std=c11" => compile("/std:c11"),
+			"-std=c17" => compile("/std:c17"),
+			"-std=c2x" => compile("/std:clatest"),
+			"-std=c++14" => compile("/std:c++14"),
+			"-std=c++17" => compile("/std:c++17"),
+			"-std=c++20" => compile("/std:c++20"),
+			"-std=c++2b" => compile("/std:c++latest"),
+			/* The math library is part of the C runtime */
+			"-lm" => None,
+			_ => {
+				if let Some(lib) = flag.strip_prefix("-l") {
+					link(format!("{lib}.lib"))
+				} else if let Some(dir) = flag.strip_prefix("-L") {
+					link(format!("/LIBPATH:{dir}"))
+				} else if flag.starts_with("-D") || flag.starts_with("-U") || flag.starts_with("-I") {
+					compile(&flag.replacen('-', "/", 1))
+				} else if ["-f", "-W", "-m", "-std=", "-pedantic"]
+					.iter()
+					.any(|p| flag.starts_with(p))
+				{
+					None
+				} else {
+					compile(flag)
+				}
+			}
+		}
+	}
+}
+
+impl Compiler for Msvc {
+	fn bin(&self) -> &str {
+		self.bin.to_str().unwrap_or("cl")
+	}
+
+	fn cxx(&self) -> anyhow::Result<Box<dyn Compiler>> {
+		/* cl compiles C++ too, going by the extension */
+		Ok(Box::new(Self {
+			bin: self.bin.clone(),
+			env: self.env.clone(),
+		}))
+	}
+
+	fn gcc_style(&self) -> bool {
+		false
+	}
+
+	fn writes_beside(&self) -> bool {
+		true
+	}
+
+	fn diagnostics(&self, _output: &str) -> Vec<Diagnostic> {
+		/* todo: parse cl's `file(line,col): error C0000: message`, until then its output is shown as is */
+		vec![]
+	}
+
+	fn command(
+		&self,
+		files: &[std::path::PathBuf],
+		deps: &[&std::path::Path],
+		to: &std::path::Path,
+		flags: &[String],
+	) -> std::process::Command {
+		let mut cmd = std::process::Command::new(&self.bin);
+		cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+
+		cmd.arg("/nologo").args(files);
+
+		for dep in deps {
+			cmd.arg("/I").arg(dep);
+		}
+
+		let mut link = vec![];
+
+		for (flag, is_link) in flags.iter().filter_map(|f| Self::translate(f)) {
+			if is_link {
+				link.push(flag);
 			} else {
-				anyhow::bail!("{msg}");
+				cmd.arg(flag);
 			}
 		}
 
+		if flags.iter().any(|f| f == "-c") {
+			cmd.arg(format!("/Fo:{}", to.display()));
+		} else {
+			cmd.arg(format!("/Fe:{}", to.display()));
+		}
+
+		/* Everything after /link goes to the linker, so it has to come last */
+		if !link.is_empty() {
+			cmd.arg("/link").args(link);
+		}
+
+		cmd
+	}
+
+	fn compile(
+		&self,
+		files: &[std::path::PathBuf],
+		deps: &[&std::path::Path],
+		to: &std::path::Path,
+		flags: &[String],
+		output: &mut dyn FnMut(&str),
+	) -> anyhow::Result<()> {
+		let cmd = self.command(files, deps, to, flags);
+		crate::log::command(&cmd);
+
+		let e = stream(cmd, output)?;
+
+		if !e.status.success() {
+			/* cl reports errors on stdout */
+			let msg = format!(
+				"{}{}",
+				String::from_utf8_lossy(&e.stdout),
+				String::from_utf8_lossy(&e.stderr)
+			);
+
+			let hint = (msg.contains("LNK2005") && msg.contains("main")).then_some(MAIN_HINT);
+
+			return Err(CompileError::new(self, msg, hint).into());
+		}
+
+		Ok(())
+	}
+
+	fn emit(
+		&self,
+		file: &std::path::Path,
+		deps: &[&std::path::Path],
+		to: &std::path::Path,
+		flags: &[String],
+		kind: Emit,
+	) -> anyhow::Result<()> {
+		let mut cmd = std::process::Command::new(&self.bin);
+		cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+
+		cmd.arg("/nologo").arg(file);
+
+		for dep in deps {
+			cmd.arg("/I").arg(dep);
+		}
+
+		cmd.args(
+			flags
+				.iter()
+				.filter_map(|f| Self::translate(f))
+				.filter(|(_, is_link)| !is_link)
+				.map(|(f, _)| f),
+		);
+
+		match kind {
+			Emit::Object => {
+				cmd.arg("/c").arg(format!("/Fo:{}", to.display()));
+			}
+			Emit::Asm => {
+				/* cl writes the object file alongside the listing, so it's kept out of the way */
+				cmd.arg("/c")
+					.arg("/FA")
+					.arg(format!("/Fa:{}", to.display()))
+					.arg(format!("/Fo:{}", to.with_extension("obj").display()));
+			}
+			Emit::Preprocessed => {
+				cmd.arg("/P").arg(format!("/Fi:{}", to.display()));
+			}
+			Emit::Ir => anyhow::bail!(
+				"msvc can't emit LLVM IR, only clang can. Set CC=clang, or default = \"clang\" under [compiler] in cpkg.toml."
+			),
+		}
+
+		crate::log::command(&cmd);
+		let e = cmd.output()?;
+
+		if !e.status.success() {
+			let msg = format!(
+				"{}{}",
+				String::from_utf8_lossy(&e.stdout),
+				String::from_utf8_lossy(&e.stderr)
+			);
+
+			return Err(CompileError::new(self, msg, None).into());
+		}
+
 		Ok(())
 	}
 }
 
-const SUPPORTED: &[(&'static str, fn() -> Box<dyn Compiler>)] = &[
-	("gcc", || Box::new(Gcc { bin: "gcc" })),
-	("clang", || Box::new(Gcc { bin: "clang" })),
-	("cosmocc", || Box::new(Gcc { bin: "cosmocc" })),
+type Locator = fn() -> Option<Box<dyn Compiler>>;
+
+const SUPPORTED: &[(&str, Locator)] = &[
+	("gcc", || Gcc::locate("gcc")),
+	("clang", || Gcc::locate("clang")),
+	("cosmocc", || Gcc::locate("cosmocc")),
+	("msvc", Msvc::locate),
+	("tcc", Tcc::locate),
+	/* zig cc bundles clang along with sysroots for cross compiling */
+	("zig", || {
+		if !super::probe::exists("zig") {
+			return None;
+		}
+
+		Some(Box::new(Gcc {
+			bin: "zig".to_owned(),
+			args: vec!["cc".to_owned()],
+		}))
+	}),
 ];
 
+/// Whether cpkg has a backend with the given name.
+pub fn is_supported(name: &str) -> bool {
+	SUPPORTED.iter().any(|(bin, _)| *bin == name)
+}
+
+/// Names of every compiler backend, in the order they're probed.
+pub fn supported() -> impl Iterator<Item = &'static str> {
+	SUPPORTED.iter().map(|(name, _)| *name)
+}
+
+/// Executable a backend is found by, which only differs for msvc.
+pub fn program(name: &str) -> &str {
+	match name {
+		"msvc" => "cl",
+		_ => name,
+	}
+}
+
+/// Uses a specific compiler executable, assumed to take gcc-style arguments.
+/// This is how cross compilers like arm-none-eabi-gcc are used.
+pub fn from_bin(bin: &str) -> anyhow::Result<Box<dyn Compiler>> {
+	if !super::probe::exists(bin) {
+		return Err(super::probe::LocateError::NotFound {
+			tool: super::probe::Tool::Compiler,
+			bin: bin.to_owned(),
+			from: "the target's compiler".to_owned(),
+		}
+		.into());
+	}
+
+	Ok(Box::new(Gcc {
+		bin: bin.to_owned(),
+		args: vec![],
+	}))
+}
+
+/// Set by `--ignore-env`, for when reproducible builds matter more than convention.
+static IGNORE_ENV: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Stops CC, CFLAGS and LDFLAGS from being read for the rest of this invocation.
+pub fn ignore_env() {
+	IGNORE_ENV.store(true, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Value of a conventional variable like CC, unless it's empty or `--ignore-env` was passed.
+fn env_var(name: &str) -> Option<String> {
+	if IGNORE_ENV.load(std::sync::atomic::Ordering::Relaxed) {
+		return None;
+	}
+
+	std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Flags from a variable like CFLAGS, split the way a shell would.
+pub fn env_flags(name: &str) -> Vec<String> {
+	env_var(name)
+		.map(|v| {
+			/* Unbalanced quotes, fall back to plain words rather than failing the build */
+			shlex::split(&v).unwrap_or_else(|| v.split_whitespace().map(str::to_owned).collect())
+		})
+		.unwrap_or_default()
+}
+
+/// Compiler named by a variable like CC, e.g. `clang`, `/opt/gcc/bin/gcc` or `ccache gcc`.
+fn from_env(var: &str) -> Option<anyhow::Result<Box<dyn Compiler>>> {
+	let cc = env_var(var)?;
+	let mut words = shlex::split(&cc)
+		.unwrap_or_else(|| cc.split_whitespace().map(str::to_owned).collect())
+		.into_iter();
+
+	let bin = words.next()?;
+	let args = words.collect::<Vec<_>>();
+
+	if !super::probe::exists(&bin) {
+		return Some(Err(super::probe::LocateError::NotFound {
+			tool: super::probe::Tool::Compiler,
+			bin,
+			from: var.to_owned(),
+		}
+		.into()));
+	}
+
+	let stem = std::path::Path::new(&bin).file_stem().and_then(|s| s.to_str());
+
+	if stem == Some("cl") && args.is_empty() {
+		return Some(Ok(Box::new(Msvc {
+			bin: super::probe::which(&bin)?,
+			env: vec![],
+		})));
+	}
+
+	Some(Ok(Box::new(Gcc { bin, args })))
+}
+
 /// Tries to find an available C compiler backend.
-/// Currently only supports gcc -> clang.
+/// Uses the cross target's compiler, then CC, then the project's default, then probes gcc -> clang -> cosmocc -> msvc -> tcc -> zig.
 pub fn try_locate(proj: Option<&crate::Project>) -> anyhow::Result<Box<dyn Compiler>> {
-	let default = proj
-		.map(|p| {
-			p.config()
-				.compiler
-				.as_ref()
-				.map(|f| f.default.as_ref())
-				.flatten()
-		})
-		.flatten();
-
-	let backends = if let Some(d) = default {
-		match d.as_ref() {
-			"clang" | "gcc" | "cosmocc" => {
-				let mut c = SUPPORTED.to_vec();
-				let target = c.iter().position(|e| e.0 == d).unwrap();
-				c.swap(0, target);
-				std::borrow::Cow::Owned(c)
-			}
+	let explicit = proj
+		.and_then(|p| p.cross())
+		.and_then(|c| c.config.compiler.as_deref());
 
-			_ => {
-				anyhow::bail!("Unrecognized default compiler: {d}");
-			}
+	if let Some(bin) = explicit {
+		return from_bin(bin);
+	}
+
+	if let Some(backend) = from_env("CC") {
+		return backend;
+	}
+
+	let config = proj.and_then(|p| p.config().compiler.as_ref());
+	let default = config.and_then(|c| c.default.as_deref());
+	let tool = super::probe::Tool::Compiler;
+
+	for (name, locate) in super::probe::preferred(tool, SUPPORTED, default)?.as_ref() {
+		if let Some(backend) = locate() {
+			super::probe::check_fallback(tool, default, name, config.is_some_and(|c| c.strict))?;
+			return Ok(backend);
 		}
-	} else {
-		std::borrow::Cow::Borrowed(SUPPORTED)
-	};
+	}
+
+	Err(super::probe::LocateError::NotInstalled {
+		tool: super::probe::Tool::Compiler,
+		probed: supported().map(program).map(str::to_owned).collect(),
+	}
+	.into())
+}
+
+/// Finds a backend for quick, throwaway compiles like repl lines and single file scripts.
+/// Prefers tcc when it's installed, since it compiles many times faster than the rest.
+/// A compiler set through CC still wins, though.
+pub fn try_locate_fast(proj: Option<&crate::Project>) -> anyhow::Result<Box<dyn Compiler>> {
+	if env_var("CC").is_some() {
+		return try_locate(proj);
+	}
+
+	match Tcc::locate() {
+		Some(tcc) => Ok(tcc),
+		None => try_locate(proj),
+	}
+}
+
+/// C compilers and their C++ counterparts, which keep any cross prefix or version suffix around the name.
+const CXX: &[(&str, &str)] = &[
+	("gcc", "g++"),
+	("clang", "clang++"),
+	("cosmocc", "cosmoc++"),
+	("emcc", "em++"),
+	("cc", "c++"),
+];
+
+/// C++ counterpart of a C compiler, like g++ for gcc, arm-none-eabi-g++ for arm-none-eabi-gcc or clang++-17 for clang-17.
+fn cxx_name(bin: &str) -> Option<String> {
+	let path = std::path::Path::new(bin);
+	let name = path.file_name()?.to_str()?;
 
-	for (bin, make) in backends.as_ref() {
-		if which::which(bin).is_ok() {
-			return Ok(make());
+	let renamed = CXX.iter().find_map(|(c, cxx)| {
+		if name == *c {
+			Some(cxx.to_string())
+		} else if let Some(prefix) = name.strip_suffix(c).filter(|p| p.ends_with('-')) {
+			Some(format!("{prefix}{cxx}"))
+		} else {
+			let version = name.strip_prefix(c).filter(|v| v.starts_with('-'))?;
+			Some(format!("{cxx}{version}"))
 		}
+	})?;
+
+	Some(path.with_file_name(renamed).to_string_lossy().into_owned())
+}
+
+/// Finds the compiler for C++ sources: CXX if it's set, otherwise the counterpart of `backend`.
+pub fn locate_cxx(backend: &dyn Compiler) -> anyhow::Result<Box<dyn Compiler>> {
+	match from_env("CXX") {
+		Some(cxx) => cxx,
+		None => backend.cxx(),
 	}
+}
+
+/// Whether a backend is a C++ compiler like g++, rather than one for C.
+/// cl compiles both, and counts as one for C.
+pub fn is_cxx(backend: &dyn Compiler) -> bool {
+	backend
+		.driver()
+		.last()
+		.is_some_and(|word| word.ends_with("++") || word.contains("++-"))
+}
 
-	Err(anyhow::anyhow!("Couldn't find a compiler backend."))
+/// Whether a backend is zig cc or zig c++, which cross compile given a target triple.
+pub fn is_zig(backend: &dyn Compiler) -> bool {
+	matches!(backend.driver().as_slice(), [zig, cmd] if zig == "zig" && (cmd == "cc" || cmd == "c++"))
 }
@@ -0,0 +1,73 @@
+pub trait Debugger {
+	/// Command starting `program` under the debugger, passing it `args` as they are.
+	fn command(&self, program: &std::path::Path, args: &[String]) -> std::process::Command;
+}
+
+pub struct Gdb;
+
+impl Debugger for Gdb {
+	fn command(&self, program: &std::path::Path, args: &[String]) -> std::process::Command {
+		let mut cmd = std::process::Command::new("gdb");
+		cmd.arg("--args").arg(program).args(args);
+		cmd
+	}
+}
+
+pub struct Lldb;
+
+impl Debugger for Lldb {
+	fn command(&self, program: &std::path::Path, args: &[String]) -> std::process::Command {
+		let mut cmd = std::process::Command::new("lldb");
+		cmd.arg("--").arg(program).args(args);
+		cmd
+	}
+}
+
+type Constructor = fn() -> Box<dyn Debugger>;
+
+const SUPPORTED: &[(&str, Constructor)] = &[
+	("gdb", || Box::new(Gdb)),
+	("lldb", || Box::new(Lldb)),
+];
+
+/// Names of every debugger cpkg supports, in the order they're probed.
+pub fn supported() -> impl Iterator<Item = &'static str> {
+	SUPPORTED.iter().map(|(bin, _)| *bin)
+}
+
+/// Supported debuggers in the order they're probed, with the project's default first.
+fn preferred(proj: Option<&crate::Project>) -> anyhow::Result<std::borrow::Cow<'static, [(&'static str, Constructor)]>> {
+	let default = proj.and_then(|p| p.config().debugger.as_ref())
+		.and_then(|c| c.default.as_deref());
+	Ok(super::probe::preferred(super::probe::Tool::Debugger, SUPPORTED, default)?)
+}
+
+/// Name of the debugger try_locate would pick, if any is installed.
+pub fn selected(proj: Option<&crate::Project>) -> anyhow::Result<Option<&'static str>> {
+	Ok(preferred(proj)?
+		.iter()
+		.map(|(bin, _)| *bin)
+		.find(|bin| super::probe::exists(bin)))
+}
+
+/// Tries to find an available debugger.
+/// Probes gdb -> lldb, unless the project, if there is one, picks a default.
+pub fn try_locate(proj: Option<&crate::Project>) -> anyhow::Result<Box<dyn Debugger>> {
+	let config = proj.and_then(|p| p.config().debugger.as_ref());
+
+	for (bin, make) in preferred(proj)?.as_ref() {
+		if super::probe::exists(bin) {
+			let default = config.and_then(|c| c.default.as_deref());
+			let strict = config.is_some_and(|c| c.strict);
+
+			super::probe::check_fallback(super::probe::Tool::Debugger, default, bin, strict)?;
+			return Ok(make());
+		}
+	}
+
+	Err(super::probe::LocateError::NotInstalled {
+		tool: super::probe::Tool::Debugger,
+		probed: supported().map(str::to_owned).collect(),
+	}
+	.into())
+}
@@ -1,26 +1,160 @@
 pub trait Docgen {
-	fn generate(&self, src: &std::path::Path, to: &std::path::Path) -> anyhow::Result<()>;
+	fn generate(&self, proj: &crate::Project, to: &std::path::Path) -> anyhow::Result<()>;
 	fn open(&self, to: &std::path::Path) -> anyhow::Result<()>;
 }
 
+/// Main page a project can write itself, used instead of the generated one and never written to.
+const USER_MAINPAGE: &str = "docs/mainpage.md";
+
+/// Landing page tying the documentation to the project: its name, version, description, details, README and dependencies.
+fn mainpage(proj: &crate::Project) -> anyhow::Result<String> {
+	let package = &proj.config().package;
+	let mut page = format!("# {} {}\n\n", proj.name(), proj.version());
+
+	if let Some(ref description) = package.description {
+		page.push_str(&format!("{description}\n\n"));
+	}
+
+	let details = [
+		("Authors", package.authors.join(", ")),
+		("License", package.license.clone().unwrap_or_default()),
+		("Repository", package.repository.as_ref().map(|r| format!("<{r}>")).unwrap_or_default()),
+		("Homepage", package.homepage.as_ref().map(|h| format!("<{h}>")).unwrap_or_default()),
+		("Keywords", package.keywords.join(", ")),
+	];
+
+	let details = details
+		.iter()
+		.filter(|(_, value)| !value.is_empty())
+		.map(|(label, value)| format!("- **{label}:** {value}\n"))
+		.collect::<String>();
+
+	if !details.is_empty() {
+		page.push_str(&details);
+		page.push('\n');
+	}
+
+	let readme = std::fs::read_dir(proj.path())?
+		.flatten()
+		.map(|e| e.path())
+		.filter(|p| p.is_file())
+		.find(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("README")));
+
+	if let Some(readme) = readme {
+		page.push_str(std::fs::read_to_string(readme)?.trim_end());
+		page.push_str("\n\n");
+	}
+
+	let deps = proj.config().dependencies.entries.iter().collect::<Vec<_>>();
+
+	if !deps.is_empty() {
+		page.push_str("## Dependencies\n\n");
+
+		for (name, dep) in deps {
+			let line = match dep {
+				crate::ConfigDependency::Git { git, .. } => format!("- [{name}]({git})\n"),
+				crate::ConfigDependency::Path { path, .. } => format!("- {name}, from `{}`\n", path.display()),
+				crate::ConfigDependency::Lib { lib, .. } => format!("- {name}, linked as `-l{lib}`\n"),
+			};
+
+			page.push_str(&line);
+		}
+	}
+
+	Ok(page)
+}
+
+/// Writes the main page to `to` as `file`, copying the project's own if it has one.
+/// It's rewritten every time, so it's never out of date.
+fn write_mainpage(proj: &crate::Project, to: &std::path::Path, file: &str) -> anyhow::Result<std::path::PathBuf> {
+	let user = proj.path().join(USER_MAINPAGE);
+
+	let contents = if user.is_file() {
+		std::fs::read_to_string(user)?
+	} else {
+		mainpage(proj)?
+	};
+
+	std::fs::create_dir_all(to)?;
+
+	let page = to.join(file);
+	std::fs::write(&page, contents)?;
+
+	Ok(page)
+}
+
 pub struct Doxygen;
 
 impl Docgen for Doxygen {
-	fn generate(&self, _src: &std::path::Path, to: &std::path::Path) -> anyhow::Result<()> {
+	fn generate(&self, proj: &crate::Project, to: &std::path::Path) -> anyhow::Result<()> {
 		let config = to.join("Doxyfile");
+		let output = to.display();
 
-		#[rustfmt::skip]
-		std::fs::write(
-			&config,
-			indoc::formatdoc! {"
-				INPUT=../../src
-				OUTPUT_DIRECTORY=.
-			"}
-		)?;
+		let user = proj
+			.config()
+			.docgen
+			.as_ref()
+			.and_then(|d| d.doxygen.as_ref())
+			.map(|d| proj.path().join(&d.doxyfile));
 
-		let out = std::process::Command::new("doxygen")
-			.current_dir(to)
-			.output()?;
+		let base = if let Some(user) = user {
+			if !user.is_file() {
+				anyhow::bail!("Configured Doxyfile {} does not exist.", user.display());
+			}
+
+			user
+		} else {
+			crate::assets::locate(Some(proj), "Doxyfile")?
+		};
+
+		/* Use the base config, but keep output where cpkg expects it */
+		let mut contents = indoc::formatdoc! {r#"
+			@INCLUDE = "{}"
+			OUTPUT_DIRECTORY = "{output}"
+		"#, base.display()};
+
+		/* A Doxyfile picking its own main page keeps it */
+		let picks_mainpage = std::fs::read_to_string(&base)?
+			.lines()
+			.any(|l| l.trim_start().starts_with("USE_MDFILE_AS_MAINPAGE"));
+
+		if !picks_mainpage {
+			let user = proj.path().join(USER_MAINPAGE);
+
+			let page = if user.is_file() {
+				user
+			} else {
+				write_mainpage(proj, to, "mainpage.md")?
+			};
+
+			contents.push_str(&indoc::formatdoc! {r#"
+				INPUT += "{page}"
+				USE_MDFILE_AS_MAINPAGE = "{page}"
+			"#, page = page.display()});
+		}
+
+		std::fs::write(&config, contents)?;
+
+		let quoted = proj
+			.src()
+			.iter()
+			.map(|dir| format!("\"{}\"", dir.display()))
+			.collect::<Vec<_>>()
+			.join(" ");
+
+		/* Relative paths in a user's Doxyfile are relative to the project root */
+		let mut cmd = std::process::Command::new("doxygen");
+		cmd.arg(&config)
+			.env("CPKG_PKG_NAME", proj.name())
+			.env("CPKG_PKG_VERSION", proj.version().to_string())
+			.env("CPKG_PKG_DESCRIPTION", proj.config().package.description.as_deref().unwrap_or_default())
+			/* CPKG_SRC is kept for Doxyfiles from before there could be several source folders */
+			.env("CPKG_SRC", &proj.src()[0])
+			.env("CPKG_SRC_DIRS", quoted)
+			.current_dir(proj.path());
+
+		crate::log::command(&cmd);
+		let out = cmd.output()?;
 
 		if !out.status.success() {
 			anyhow::bail!(
@@ -41,14 +175,24 @@ impl Docgen for Doxygen {
 pub struct Cldoc;
 
 impl Docgen for Cldoc {
-	fn generate(&self, src: &std::path::Path, to: &std::path::Path) -> anyhow::Result<()> {
-		let out = std::process::Command::new("cldoc")
-			.arg("generate")
+	fn generate(&self, proj: &crate::Project, to: &std::path::Path) -> anyhow::Result<()> {
+		let src = proj.src();
+
+		/* cldoc merges markdown from a folder into the docs, where index.md is the main page */
+		let pages = to.join("pages");
+		write_mainpage(proj, &pages, "index.md")?;
+
+		let mut cmd = std::process::Command::new("cldoc");
+		cmd.arg("generate")
 			.arg("--")
 			.arg("--output")
 			.arg(to)
-			.arg(src)
-			.output()?;
+			.arg("--merge")
+			.arg(&pages)
+			.args(&src);
+
+		crate::log::command(&cmd);
+		let out = cmd.output()?;
 
 		if !out.status.success() {
 			anyhow::bail!(
@@ -66,14 +210,14 @@ impl Docgen for Cldoc {
 }
 
 #[cfg(target_os = "linux")]
-fn start_program(p: &std::path::Path) -> anyhow::Result<()> {
+pub fn start_program(p: &std::path::Path) -> anyhow::Result<()> {
 	std::process::Command::new("xdg-open").arg(p).output()?;
 
 	Ok(())
 }
 
 #[cfg(target_os = "windows")]
-fn start_program(p: &std::path::Path) -> anyhow::Result<()> {
+pub fn start_program(p: &std::path::Path) -> anyhow::Result<()> {
 	// TODO: Test on windows
 	std::process::Command::new("cmd")
 		.arg("-C")
@@ -85,42 +229,50 @@ fn start_program(p: &std::path::Path) -> anyhow::Result<()> {
 	Ok(())
 }
 
-const SUPPORTED: &[(&'static str, fn() -> Box<dyn Docgen>)] = &[
+type Constructor = fn() -> Box<dyn Docgen>;
+
+const SUPPORTED: &[(&str, Constructor)] = &[
 	("doxygen", || Box::new(Doxygen)),
 	("cldoc", || Box::new(Cldoc)),
 ];
 
-/// Tries to find an available C compiler backend.
-/// Currently only supports gcc -> clang.
+/// Names of every doc generator cpkg supports, in the order they're probed.
+pub fn supported() -> impl Iterator<Item = &'static str> {
+	SUPPORTED.iter().map(|(bin, _)| *bin)
+}
+
+/// Supported doc generators in the order they're probed, with the project's default first.
+fn preferred(proj: &crate::Project) -> anyhow::Result<std::borrow::Cow<'static, [(&'static str, Constructor)]>> {
+	let default = proj.config().docgen.as_ref().and_then(|c| c.default.as_deref());
+	Ok(super::probe::preferred(super::probe::Tool::Docgen, SUPPORTED, default)?)
+}
+
+/// Name of the doc generator try_locate would pick, if any is installed.
+pub fn selected(proj: &crate::Project) -> anyhow::Result<Option<&'static str>> {
+	Ok(preferred(proj)?
+		.iter()
+		.map(|(bin, _)| *bin)
+		.find(|bin| super::probe::exists(bin)))
+}
+
+/// Tries to find an available doc generator.
+/// Probes doxygen -> cldoc, unless the project picks a default.
 pub fn try_locate(proj: &crate::Project) -> anyhow::Result<Box<dyn Docgen>> {
-	let default = proj.config()
-		.docgen
-		.as_ref()
-		.map(|f| f.default.as_ref())
-		.flatten();
-
-	let backends = if let Some(d) = default {
-		match d.as_ref() {
-			"doxygen" | "cldoc" => {
-				let mut c = SUPPORTED.to_vec();
-				let target = c.iter().position(|e| e.0 == d).unwrap();
-				c.swap(0, target);
-				std::borrow::Cow::Owned(c)
-			},
-
-			_ => {
-				anyhow::bail!("Unrecognized default doc generator: {d}");
-			}
-		}
-	} else {
-		std::borrow::Cow::Borrowed(SUPPORTED)
-	};
+	let config = proj.config().docgen.as_ref();
+
+	for (bin, make) in preferred(proj)?.as_ref() {
+		if super::probe::exists(bin) {
+			let default = config.and_then(|c| c.default.as_deref());
+			let strict = config.is_some_and(|c| c.strict);
 
-	for (bin, make) in backends.as_ref() {
-		if which::which(bin).is_ok() {
+			super::probe::check_fallback(super::probe::Tool::Docgen, default, bin, strict)?;
 			return Ok(make());
 		}
 	}
 
-	Err(anyhow::anyhow!("Couldn't find a docgen backend"))
+	Err(super::probe::LocateError::NotInstalled {
+		tool: super::probe::Tool::Docgen,
+		probed: supported().map(str::to_owned).collect(),
+	}
+	.into())
 }
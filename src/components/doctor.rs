@@ -0,0 +1,139 @@
+/// Programs cpkg shells out to outside of a backend, reported so missing ones are noticed early.
+const AUXILIARY: &[&str] = &["git", "valgrind", "pkg-config"];
+
+/// A program `cpkg doctor` looked for.
+pub struct Tool {
+	/// Which part of cpkg uses it, e.g. "compiler"
+	pub component: &'static str,
+	pub name: String,
+	pub path: Option<std::path::PathBuf>,
+	pub version: Option<String>,
+	/// Whether its component would currently pick it, given the project's config
+	pub selected: bool,
+}
+
+/// Everything `cpkg doctor` found.
+pub struct Report {
+	pub tools: Vec<Tool>,
+	/// Problems picking a backend, like an unrecognized default in cpkg.toml
+	pub warnings: Vec<String>,
+}
+
+/// Probes every supported backend and auxiliary tool.
+/// Pass the project at the cwd, if any, so its configured defaults and cross target are respected.
+pub fn probe(proj: Option<&crate::Project>) -> Report {
+	let mut warnings = vec![];
+	let mut tools = vec![];
+
+	let compiler = match super::compiler::try_locate(proj) {
+		Ok(backend) => Some((std::path::PathBuf::from(backend.bin()), backend.driver().join(" "))),
+		Err(e) => {
+			warnings.push(e.to_string());
+			None
+		}
+	};
+
+	/* Compare by file stem, since msvc found through vswhere reports a full path to cl.exe */
+	let stem = |path: &std::path::Path| path.file_stem().map(|s| s.to_owned());
+	let compiler_stem = compiler.as_ref().and_then(|(bin, _)| stem(bin));
+
+	for name in super::compiler::supported() {
+		let program = super::compiler::program(name);
+		let selected = compiler_stem.as_deref() == Some(std::ffi::OsStr::new(program));
+
+		let path = super::probe::which(program).or_else(|| {
+			/* cl.exe only ends up on PATH inside of a developer prompt */
+			compiler.clone().map(|(bin, _)| bin).filter(|_| selected)
+		});
+
+		tools.push(Tool {
+			component: "compiler",
+			name: name.to_owned(),
+			path,
+			version: None,
+			selected,
+		});
+	}
+
+	/* A cross target's compiler or one from CC, like arm-none-eabi-gcc, isn't one of the regular backends */
+	if let Some((bin, driver)) = compiler.filter(|_| !tools.iter().any(|t| t.selected)) {
+		tools.push(Tool {
+			component: "compiler",
+			name: driver,
+			path: super::probe::which(&bin.to_string_lossy()),
+			version: None,
+			selected: true,
+		});
+	}
+
+	let (formatter, docgen) = match proj {
+		Some(proj) => (
+			super::format::selected(proj).unwrap_or_else(|e| {
+				warnings.push(e.to_string());
+				None
+			}),
+			super::docgen::selected(proj).unwrap_or_else(|e| {
+				warnings.push(e.to_string());
+				None
+			}),
+		),
+		/* Formatting and docs only happen inside of a project */
+		None => (None, None),
+	};
+
+	for name in super::format::supported() {
+		tools.push(Tool {
+			component: "formatter",
+			name: name.to_owned(),
+			path: super::probe::which(name),
+			version: None,
+			selected: formatter == Some(name),
+		});
+	}
+
+	for name in super::docgen::supported() {
+		tools.push(Tool {
+			component: "docgen",
+			name: name.to_owned(),
+			path: super::probe::which(name),
+			version: None,
+			selected: docgen == Some(name),
+		});
+	}
+
+	let debugger = super::debugger::selected(proj).unwrap_or_else(|e| {
+		warnings.push(e.to_string());
+		None
+	});
+
+	for name in super::debugger::supported() {
+		tools.push(Tool {
+			component: "debugger",
+			name: name.to_owned(),
+			path: super::probe::which(name),
+			version: None,
+			selected: debugger == Some(name),
+		});
+	}
+
+	for name in AUXILIARY {
+		tools.push(Tool {
+			component: "tool",
+			name: (*name).to_owned(),
+			path: super::probe::which(name),
+			version: None,
+			selected: false,
+		});
+	}
+
+	/* Querying versions means running every tool, so do it all at once */
+	std::thread::scope(|s| {
+		for tool in &mut tools {
+			if let Some(path) = tool.path.clone() {
+				s.spawn(move || tool.version = super::probe::version(&path));
+			}
+		}
+	});
+
+	Report { tools, warnings }
+}
@@ -0,0 +1,462 @@
+/// Everything an exported build file needs to reproduce `cpkg build`.
+/// Paths are relative to the project root, so the files can be committed.
+pub struct BuildData {
+	/// Command invoking the compiler, e.g. ["zig", "cc"]
+	pub cc: Vec<String>,
+	/// Command invoking the C++ compiler, which also links, when the project has C++ sources or is set to C++
+	pub cxx: Option<Vec<String>>,
+	pub sources: Vec<std::path::PathBuf>,
+	/// Sources besides the entrypoint, which tests link against
+	pub lib_sources: Vec<std::path::PathBuf>,
+	pub includes: Vec<std::path::PathBuf>,
+	pub tests: Vec<std::path::PathBuf>,
+	/// Include paths for tests, which can also include headers beside them
+	pub test_includes: Vec<std::path::PathBuf>,
+	/// Flags besides the structured ones in options
+	pub flags: Vec<String>,
+	/// Flags for C++ sources, which pick a C++ standard instead of a C one
+	pub cxx_flags: Vec<String>,
+	/// Libraries, defines and search folders from cpkg.toml
+	pub options: crate::BuildOptions,
+	/// Final executable
+	pub output: std::path::PathBuf,
+	/// Extension executables get, like "exe" when building for Windows
+	pub exe_extension: Option<String>,
+	/// Directory for intermediate object files
+	pub objects: std::path::PathBuf,
+	pub target: std::path::PathBuf,
+}
+
+impl BuildData {
+	pub fn new(proj: &crate::Project, backend: &dyn crate::compiler::Compiler) -> anyhow::Result<Self> {
+		let rel = |p: std::path::PathBuf| match p.strip_prefix(proj.path()) {
+			Ok(r) => r.to_owned(),
+			Err(_) => p,
+		};
+
+		let options = proj.build_options();
+		let sources = proj.c_files(&proj.src()).chain(proj.test_files()).collect::<Vec<_>>();
+		let cxx = proj.cxx_backend(backend, &sources)?;
+
+		/* Include dirs from cpkg.toml get a variable of their own, so they're left out here */
+		let includes = proj
+			.include_paths(&proj.src())
+			.into_iter()
+			.filter(|p| !options.include_dirs.contains(p))
+			.collect::<Vec<_>>();
+
+		Ok(Self {
+			cc: backend.driver(),
+			cxx: cxx.as_ref().map(|c| c.driver()),
+			sources: proj.c_files(&proj.src()).map(rel).collect(),
+			lib_sources: proj.lib_files(&proj.src()).map(rel).collect(),
+			test_includes: std::iter::once(proj.tests())
+				.chain(includes.iter().cloned())
+				.map(rel)
+				.collect(),
+			includes: includes.into_iter().map(rel).collect(),
+			tests: proj.test_files().map(rel).collect(),
+			flags: proj.raw_flags(backend),
+			cxx_flags: cxx.as_ref().map(|c| proj.raw_flags(c.as_ref())).unwrap_or_default(),
+			options: crate::BuildOptions {
+				include_dirs: options.include_dirs.into_iter().map(rel).collect(),
+				lib_dirs: options.lib_dirs.into_iter().map(rel).collect(),
+				..options
+			},
+			output: rel(proj.build_out(None)),
+			exe_extension: proj.exe_extension().map(str::to_owned),
+			objects: rel(proj.target().join("obj")),
+			target: rel(proj.target()),
+		})
+	}
+
+	/// Object file a source compiles to.
+	pub fn object(&self, source: &std::path::Path) -> std::path::PathBuf {
+		crate::project::object_path(&self.objects, source)
+	}
+
+	/// Executable a test compiles to.
+	pub fn test_output(&self, test: &std::path::Path) -> std::path::PathBuf {
+		crate::project::with_exe_extension(
+			self.target.join("test").join(test).with_extension(""),
+			self.exe_extension.as_deref(),
+		)
+	}
+}
+
+fn defines(options: &crate::BuildOptions) -> Vec<String> {
+	options
+		.defines
+		.iter()
+		.map(|(name, value)| crate::BuildOptions::define(name, value))
+		.collect()
+}
+
+fn libs(options: &crate::BuildOptions) -> Vec<String> {
+	options.libs.iter().map(|l| format!("-l{l}")).collect()
+}
+
+fn quote_flags(flags: &[String]) -> String {
+	flags
+		.iter()
+		.map(|f| crate::quote::build_file(f))
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+/// A build system that cpkg can export a project to.
+pub trait BuildfileGenerator {
+	/// Name of the file written to the project root.
+	fn file_name(&self) -> &'static str;
+
+	fn generate(&self, data: &BuildData) -> String;
+}
+
+pub struct Make;
+
+impl BuildfileGenerator for Make {
+	fn file_name(&self) -> &'static str {
+		"Makefile"
+	}
+
+	fn generate(&self, data: &BuildData) -> String {
+		let cc = quote_flags(&data.cc);
+		let flags = quote_flags(&data.flags);
+		let cflags = Self::cflags(&data.flags, &data.includes);
+		let test_cflags = Self::cflags(&data.flags, &data.test_includes);
+
+		let defines = quote_flags(&defines(&data.options));
+		let include_dirs = Self::dirs("-I", &data.options.include_dirs);
+		let lib_dirs = Self::dirs("-L", &data.options.lib_dirs);
+		let libs = quote_flags(&libs(&data.options));
+
+		let objects = Self::list(data.sources.iter().map(|s| data.object(s)));
+		let lib_objects = Self::list(data.lib_sources.iter().map(|s| data.object(s)));
+		let tests = Self::list(data.tests.iter().map(|t| data.test_output(t)));
+
+		let output = Self::target(&data.output);
+
+		/* make hands $@ to the shell without its escapes, so recipes spell out quoted paths instead */
+		let output_word = Self::word(&data.output);
+
+		/* C++ sources are compiled with CXX, which then links everything so the C++ runtime comes along */
+		let (cxx, linker) = match data.cxx {
+			Some(ref cxx) => (
+				indoc::formatdoc! {"
+					CXX = {}
+					CXXFLAGS = {}
+					TEST_CXXFLAGS = {}
+					",
+					quote_flags(cxx),
+					Self::cflags(&data.cxx_flags, &data.includes),
+					Self::cflags(&data.cxx_flags, &data.test_includes),
+				},
+				"$(CXX)",
+			),
+			None => (String::new(), "$(CC)"),
+		};
+
+		/* Only POSIX make features, so it works with BSD make and the like too */
+		let mut out = indoc::formatdoc! {"
+			CC = {cc}
+			DEFINES = {defines}
+			INCLUDE_DIRS = {include_dirs}
+			LIB_DIRS = {lib_dirs}
+			LIBS = {libs}
+
+			CFLAGS = {cflags}
+			TEST_CFLAGS = {test_cflags}
+			{cxx}LDFLAGS = {flags} $(LIB_DIRS)
+
+			OBJECTS = {objects}
+			LIB_OBJECTS = {lib_objects}
+			TESTS = {tests}
+
+			.PHONY: all test clean
+
+			all: {output}
+
+			{output}: $(OBJECTS)
+				{linker} $(OBJECTS) -o {output_word} $(LDFLAGS) $(LIBS)
+		"};
+
+		for source in &data.sources {
+			let compile = match crate::project::is_cxx_source(source) {
+				true => "$(CXX) $(CXXFLAGS)",
+				false => "$(CC) $(CFLAGS)",
+			};
+
+			let object = data.object(source);
+
+			out.push_str(&indoc::formatdoc! {"
+
+				{}: {}
+					@mkdir -p {}
+					{compile} -c {} -o {}
+				",
+				Self::target(&object),
+				Self::target(source),
+				Self::word(object.parent().unwrap_or(std::path::Path::new("."))),
+				Self::word(source),
+				Self::word(&object),
+			});
+		}
+
+		/* Tests have their own main, so they only link with the library's objects */
+		for test in &data.tests {
+			let output = data.test_output(test);
+			let bin = Self::word(&output);
+
+			/* Linking with CXX would compile a C test as C++, so the test gets an object of its own first */
+			let build = match (&data.cxx, crate::project::is_cxx_source(test)) {
+				(None, _) => format!("$(CC) {} $(LIB_OBJECTS) -o {bin} $(TEST_CFLAGS) $(LIB_DIRS) $(LIBS)", Self::word(test)),
+				(Some(_), true) => format!("$(CXX) {} $(LIB_OBJECTS) -o {bin} $(TEST_CXXFLAGS) $(LIB_DIRS) $(LIBS)", Self::word(test)),
+				(Some(_), false) => {
+					let mut object = output.clone().into_os_string();
+					object.push(".o");
+
+					let object = Self::word(std::path::Path::new(&object));
+					format!(
+						"$(CC) $(TEST_CFLAGS) -c {} -o {object}\n\t$(CXX) {object} $(LIB_OBJECTS) -o {bin} $(LDFLAGS) $(LIBS)",
+						Self::word(test)
+					)
+				}
+			};
+
+			out.push_str(&indoc::formatdoc! {"
+
+				{}: {} $(LIB_OBJECTS)
+					@mkdir -p {}
+					{build}
+				",
+				Self::target(&output),
+				Self::target(test),
+				Self::word(output.parent().unwrap_or(std::path::Path::new("."))),
+			});
+		}
+
+		out.push_str("\ntest: $(TESTS)\n");
+
+		for test in &data.tests {
+			let bin = std::path::Path::new(".").join(data.test_output(test));
+			out.push_str(&format!("\t{}\n", Self::word(&bin)));
+		}
+
+		out.push_str(&format!("\nclean:\n\trm -rf {}\n", Self::word(&data.target)));
+
+		out
+	}
+}
+
+impl Make {
+	fn target(p: &std::path::Path) -> String {
+		crate::quote::make_target(&p.display().to_string())
+	}
+
+	fn word(p: &std::path::Path) -> String {
+		crate::quote::build_file(&p.display().to_string())
+	}
+
+	fn list(paths: impl Iterator<Item = std::path::PathBuf>) -> String {
+		paths.map(|p| Self::target(&p)).collect::<Vec<_>>().join(" ")
+	}
+
+	fn cflags(flags: &[String], includes: &[std::path::PathBuf]) -> String {
+		flags
+			.iter()
+			.map(|f| crate::quote::build_file(f))
+			.chain(["$(DEFINES)".to_owned(), "$(INCLUDE_DIRS)".to_owned()])
+			.chain(includes.iter().map(|i| format!("-I {}", Self::word(i))))
+			.collect::<Vec<_>>()
+			.join(" ")
+	}
+
+	fn dirs(flag: &str, dirs: &[std::path::PathBuf]) -> String {
+		dirs.iter()
+			.map(|d| format!("{flag} {}", Self::word(d)))
+			.collect::<Vec<_>>()
+			.join(" ")
+	}
+}
+
+pub struct Ninja;
+
+impl Ninja {
+	fn escape(p: &std::path::Path) -> String {
+		p.display()
+			.to_string()
+			.replace('$', "$$")
+			.replace(' ', "$ ")
+			.replace(':', "$:")
+	}
+}
+
+impl BuildfileGenerator for Ninja {
+	fn file_name(&self) -> &'static str {
+		"build.ninja"
+	}
+
+	fn generate(&self, data: &BuildData) -> String {
+		let cc = quote_flags(&data.cc);
+
+		let flags = quote_flags(&data.flags);
+
+		let dirs = |flag: &str, dirs: &[std::path::PathBuf]| {
+			dirs.iter()
+				.map(|d| format!("{flag} {}", crate::quote::build_file(&d.display().to_string())))
+				.collect::<Vec<_>>()
+		};
+
+		let defines = quote_flags(&defines(&data.options));
+		let include_dirs = dirs("-I", &data.options.include_dirs).join(" ");
+		let lib_dirs = dirs("-L", &data.options.lib_dirs).join(" ");
+		let libs = quote_flags(&libs(&data.options));
+
+		let cflags = |flags: &[String]| {
+			flags
+				.iter()
+				.map(|f| crate::quote::build_file(f))
+				.chain(["$defines".to_owned(), "$include_dirs".to_owned()])
+				.chain(dirs("-I", &data.includes))
+				.collect::<Vec<_>>()
+				.join(" ")
+		};
+
+		/* C++ sources are compiled with cxx, which then links everything so the C++ runtime comes along */
+		#[rustfmt::skip]
+		let (cxx, linker) = match data.cxx {
+			Some(ref cxx) => (
+				indoc::formatdoc! {"
+					cxx = {}
+					cxxflags = {}
+
+					rule cxx
+					  command = $cxx -MD -MF $out.d $cxxflags -c $in -o $out
+					  depfile = $out.d
+					  deps = gcc
+					  description = CXX $out
+
+					",
+					quote_flags(cxx),
+					cflags(&data.cxx_flags),
+				},
+				"$cxx",
+			),
+			None => (String::new(), "$cc"),
+		};
+
+		let cflags = cflags(&data.flags);
+
+		#[rustfmt::skip]
+		let mut out = indoc::formatdoc! {"
+			cc = {cc}
+			defines = {defines}
+			include_dirs = {include_dirs}
+			lib_dirs = {lib_dirs}
+			libs = {libs}
+
+			cflags = {cflags}
+			ldflags = {flags} $lib_dirs $libs
+
+			rule cc
+			  command = $cc -MD -MF $out.d $cflags -c $in -o $out
+			  depfile = $out.d
+			  deps = gcc
+			  description = CC $out
+
+			rule link
+			  command = {linker} $in -o $out $ldflags
+			  description = LINK $out
+
+			{cxx}"};
+
+		let mut objects = vec![];
+
+		for source in &data.sources {
+			let object = Self::escape(&data.object(source));
+			let rule = if crate::project::is_cxx_source(source) { "cxx" } else { "cc" };
+
+			out.push_str(&format!("build {object}: {rule} {}\n", Self::escape(source)));
+			objects.push(object);
+		}
+
+		let output = Self::escape(&data.output);
+
+		out.push_str(&format!("\nbuild {output}: link {}\n", objects.join(" ")));
+		out.push_str(&format!("\ndefault {output}\n"));
+
+		out
+	}
+}
+
+/// Contents of a pkg-config file for the project's static library, installed under `prefix`.
+pub fn pkg_config(proj: &crate::Project, prefix: &str) -> String {
+	let name = proj.name();
+	let version = proj.version();
+	let description = proj
+		.config()
+		.package
+		.description
+		.as_deref()
+		.unwrap_or(name);
+
+	let mut out = indoc::formatdoc! {"
+		prefix={prefix}
+		exec_prefix=${{prefix}}
+		libdir=${{exec_prefix}}/lib
+		includedir=${{prefix}}/include
+
+		Name: {name}
+		Description: {description}
+		Version: {version}
+		Cflags: -I${{includedir}}
+		Libs: -L${{libdir}} -l{name}
+	"};
+
+	if let Some(ref license) = proj.config().package.license {
+		out.push_str(&format!("License: {license}\n"));
+	}
+
+	let package = &proj.config().package;
+
+	if let Some(url) = package.homepage.as_ref().or(package.repository.as_ref()) {
+		out.push_str(&format!("URL: {url}\n"));
+	}
+
+	/* A static library doesn't carry the libraries it needs, so users link them too */
+	let libs = libs(&proj.build_options());
+
+	if !libs.is_empty() {
+		out.push_str(&format!("Libs.private: {}\n", libs.join(" ")));
+	}
+
+	out
+}
+
+/// Contents of a .gitignore for a cpkg project.
+pub const GITIGNORE: &str = indoc::indoc! {"
+	/target
+	compile_commands.json
+	*.o
+"};
+
+/// Contents of an .editorconfig matching a .clang-format preset.
+pub fn editorconfig(style: super::format::Style) -> String {
+	indoc::formatdoc! {"
+		root = true
+
+		[*]
+		charset = utf-8
+		end_of_line = lf
+		insert_final_newline = true
+		trim_trailing_whitespace = true
+
+		[*.{{c,h}}]
+		indent_style = space
+		indent_size = {indent}
+		max_line_length = {columns}
+	",
+		indent = style.indent_width(),
+		columns = style.column_limit(),
+	}
+}
@@ -1,26 +1,102 @@
 pub trait Format {
-	fn format(&self, proj: &crate::Project) -> anyhow::Result<()>;
+	/// Formats the given sources of the project in place, or with `check`, fails if any would change.
+	fn format(&self, proj: &crate::Project, paths: &[std::path::PathBuf], check: bool) -> anyhow::Result<()>;
+}
+
+/// Turns the formatter's output into an error, telling unformatted files apart from it failing.
+fn failure(output: &std::process::Output, check: bool) -> anyhow::Error {
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	let stderr = String::from_utf8_lossy(&output.stderr);
+
+	if check {
+		anyhow::anyhow!(
+			"Some files aren't formatted, run cpkg format to fix them.\n{}{}",
+			stdout,
+			stderr
+		)
+	} else {
+		anyhow::anyhow!("Failed to format files. {}", stderr)
+	}
+}
+
+/// Presets `cpkg generate clang-format` bases a .clang-format on.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Style {
+	Llvm,
+	Google,
+	Gnu,
+}
+
+impl Style {
+	/// Name clang-format knows the preset by.
+	fn name(self) -> &'static str {
+		match self {
+			Self::Llvm => "LLVM",
+			Self::Google => "Google",
+			Self::Gnu => "GNU",
+		}
+	}
+
+	/// Spaces per indent, which every preset uses over tabs.
+	pub fn indent_width(self) -> usize {
+		2
+	}
+
+	pub fn column_limit(self) -> usize {
+		match self {
+			Self::Llvm | Self::Google => 80,
+			Self::Gnu => 79,
+		}
+	}
+}
+
+/// Contents of a .clang-format for a preset, spelling out what .editorconfig needs to agree with.
+pub fn clang_format_config(style: Style) -> String {
+	indoc::formatdoc! {"
+		---
+		BasedOnStyle: {name}
+		IndentWidth: {indent}
+		ColumnLimit: {columns}
+		UseTab: Never
+		...
+	",
+		name = style.name(),
+		indent = style.indent_width(),
+		columns = style.column_limit(),
+	}
 }
 
 pub struct ClangFormat;
 
 impl Format for ClangFormat {
-	fn format(&self, proj: &crate::Project) -> anyhow::Result<()> {
-		let paths = proj.src_files()
-			.collect::<Vec<_>>();
+	fn format(&self, proj: &crate::Project, paths: &[std::path::PathBuf], check: bool) -> anyhow::Result<()> {
+		let mut cmd = std::process::Command::new("clang-format");
+
+		/* Say so outright, rather than relying on clang-format finding it from each file */
+		if proj.path().join(".clang-format").exists() {
+			cmd.arg("-style=file");
+		}
 
-		let cmd = std::process::Command::new("clang-format")
-			.args(paths)
-			.arg("-i") // Format in place (edit files)
-			.output()?;
+		if check {
+			cmd.args(["--dry-run", "-Werror"]);
+
+			/* Its diagnostics are captured, so only colored when asked to be */
+			if crate::log::color() {
+				cmd.arg("--fcolor-diagnostics");
+			}
+		} else {
+			cmd.arg("-i"); // Format in place (edit files)
+		}
+
+		cmd.args(paths);
+
+		crate::log::command(&cmd);
+		let cmd = cmd.output()?;
 
 		if cmd.status.success() {
 			Ok(())
 		} else {
-			Err(anyhow::anyhow!(
-				"Failed to format files. {}",
-				String::from_utf8_lossy(&cmd.stderr)
-			))
+			Err(failure(&cmd, check))
 		}
 	}
 }
@@ -28,10 +104,7 @@ impl Format for ClangFormat {
 pub struct Uncrustify;
 
 impl Format for Uncrustify {
-	fn format(&self, proj: &crate::Project) -> anyhow::Result<()> {
-		let paths = proj.src_files()
-			.collect::<Vec<_>>();
-
+	fn format(&self, proj: &crate::Project, paths: &[std::path::PathBuf], check: bool) -> anyhow::Result<()> {
 		let mut cmd = std::process::Command::new("uncrustify");
 
 		if let Some(ref f) = proj.config().formatter {
@@ -42,57 +115,63 @@ impl Format for Uncrustify {
 			}
 		}
 
-		let cmd = cmd
-			.args(paths)
-			.arg("--no-backup")
-			.output()?;
+		cmd.args(paths).arg(if check { "--check" } else { "--no-backup" });
+
+		crate::log::command(&cmd);
+		let cmd = cmd.output()?;
 
 		if cmd.status.success() {
 			Ok(())
 		} else {
-			Err(anyhow::anyhow!(
-				"Failed to format files. {}",
-				String::from_utf8_lossy(&cmd.stderr)
-			))
+			Err(failure(&cmd, check))
 		}
 	}
 }
 
-const SUPPORTED: &[(&'static str, fn() -> Box<dyn Format>)] = &[
+type Constructor = fn() -> Box<dyn Format>;
+
+const SUPPORTED: &[(&str, Constructor)] = &[
 	( "clang-format", || Box::new(ClangFormat) ),
 	( "uncrustify", || Box::new(Uncrustify) )
 ];
 
+/// Names of every formatter cpkg supports, in the order they're probed.
+pub fn supported() -> impl Iterator<Item = &'static str> {
+	SUPPORTED.iter().map(|(bin, _)| *bin)
+}
+
+/// Supported formatters in the order they're probed, with the project's default first.
+fn preferred(proj: &crate::Project) -> anyhow::Result<std::borrow::Cow<'static, [(&'static str, Constructor)]>> {
+	let default = proj.config().formatter.as_ref().and_then(|c| c.default.as_deref());
+	Ok(super::probe::preferred(super::probe::Tool::Formatter, SUPPORTED, default)?)
+}
+
+/// Name of the formatter try_locate would pick, if any is installed.
+pub fn selected(proj: &crate::Project) -> anyhow::Result<Option<&'static str>> {
+	Ok(preferred(proj)?
+		.iter()
+		.map(|(bin, _)| *bin)
+		.find(|bin| super::probe::exists(bin)))
+}
+
 /// Tries to find an available C formatter
-/// Currently only supports clang-format.
+/// Currently supports clang-format and uncrustify.
 pub fn try_locate(proj: &crate::Project) -> anyhow::Result<Box<dyn Format>> {
-	let default = proj.config().formatter
-		.as_ref()
-		.map(|f| f.default.as_ref())
-		.flatten();
-
-	let backends = if let Some(d) = default {
-		match d.as_ref() {
-			"clang-format" | "uncrustify" => {
-				let mut c = SUPPORTED.to_vec();
-				let target = c.iter().position(|e| e.0 == d).unwrap();
-				c.swap(0, target);
-				std::borrow::Cow::Owned(c)
-			},
-
-			_ => {
-				anyhow::bail!("Unrecognized default formatter: {d}");
-			}
-		}
-	} else {
-		std::borrow::Cow::Borrowed(SUPPORTED)
-	};
+	let config = proj.config().formatter.as_ref();
 
-	for (bin, make) in backends.as_ref() {
-		if which::which(bin).is_ok() {
+	for (bin, make) in preferred(proj)?.as_ref() {
+		if super::probe::exists(bin) {
+			let default = config.and_then(|c| c.default.as_deref());
+			let strict = config.is_some_and(|c| c.strict);
+
+			super::probe::check_fallback(super::probe::Tool::Formatter, default, bin, strict)?;
 			return Ok(make());
 		}
 	}
 
-	Err(anyhow::anyhow!("Couldn't find a formatting backend"))
+	Err(super::probe::LocateError::NotInstalled {
+		tool: super::probe::Tool::Formatter,
+		probed: supported().map(str::to_owned).collect(),
+	}
+	.into())
 }
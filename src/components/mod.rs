@@ -1,3 +1,9 @@
+pub mod archive;
+pub mod ci;
 pub mod compiler;
+pub mod debugger;
+pub mod doctor;
 pub mod docgen;
+pub mod export;
 pub mod format;
+pub mod probe;
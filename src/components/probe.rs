@@ -0,0 +1,214 @@
+use colored::Colorize;
+
+/// Where each program looked up so far was found, if at all.
+/// PATH doesn't change during a run, so every backend lookup after the first is free.
+static FOUND: std::sync::OnceLock<
+	std::sync::Mutex<std::collections::HashMap<String, Option<std::path::PathBuf>>>,
+> = std::sync::OnceLock::new();
+
+/// Finds a program on PATH, remembering the answer for the rest of this invocation.
+pub fn which(bin: &str) -> Option<std::path::PathBuf> {
+	let mut found = FOUND
+		.get_or_init(Default::default)
+		.lock()
+		.unwrap_or_else(std::sync::PoisonError::into_inner);
+
+	found
+		.entry(bin.to_owned())
+		.or_insert_with(|| which::which(bin).ok())
+		.clone()
+}
+
+/// Whether a program is on PATH.
+pub fn exists(bin: &str) -> bool {
+	which(bin).is_some()
+}
+
+/// First line a program prints for `--version`, for reports like `cpkg doctor`.
+/// Some tools print it to stderr instead, like cl which has no such flag and prints a banner.
+pub fn version(bin: &std::path::Path) -> Option<String> {
+	let out = std::process::Command::new(bin)
+		.arg("--version")
+		.stdin(std::process::Stdio::null())
+		.output()
+		.ok()?;
+
+	let first_line = |o: &[u8]| {
+		String::from_utf8_lossy(o)
+			.lines()
+			.map(str::trim)
+			.find(|l| !l.is_empty())
+			.map(str::to_owned)
+	};
+
+	first_line(&out.stdout).or_else(|| first_line(&out.stderr))
+}
+
+/// Set by `--strict-backend`, so CI notices a configured backend going missing.
+static STRICT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Makes every configured `default` required for the rest of this invocation, as if each section set `strict = true`.
+pub fn strict_backends() {
+	STRICT.store(true, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// `supported` in the order backends are probed, with the configured `default` first.
+pub fn preferred<T: Clone>(
+	tool: Tool,
+	supported: &'static [(&'static str, T)],
+	default: Option<&str>,
+) -> Result<std::borrow::Cow<'static, [(&'static str, T)]>, LocateError> {
+	let Some(default) = default else {
+		return Ok(std::borrow::Cow::Borrowed(supported));
+	};
+
+	let Some(target) = supported.iter().position(|(name, _)| *name == default) else {
+		return Err(LocateError::Unrecognized {
+			tool,
+			name: default.to_owned(),
+		});
+	};
+
+	let mut backends = supported.to_vec();
+	backends.swap(0, target);
+
+	Ok(std::borrow::Cow::Owned(backends))
+}
+
+/// Checks that `found`, the first backend installed, is the configured `default`.
+/// If it isn't, that's warned about once per invocation, or is an error when `strict` or `--strict-backend` is set.
+pub fn check_fallback(tool: Tool, default: Option<&str>, found: &str, strict: bool) -> Result<(), LocateError> {
+	static WARNED: std::sync::OnceLock<std::sync::Mutex<std::collections::HashSet<Tool>>> = std::sync::OnceLock::new();
+
+	let Some(default) = default.filter(|d| *d != found) else {
+		return Ok(());
+	};
+
+	if strict || STRICT.load(std::sync::atomic::Ordering::Relaxed) {
+		return Err(LocateError::NoFallback {
+			tool,
+			default: default.to_owned(),
+			found: found.to_owned(),
+		});
+	}
+
+	let first = WARNED
+		.get_or_init(Default::default)
+		.lock()
+		.unwrap_or_else(std::sync::PoisonError::into_inner)
+		.insert(tool);
+
+	/* Flags meant for one backend can break another, so this shouldn't go unnoticed */
+	if first {
+		crate::log::warn(format!(
+			"The default {tool} under [{}] in cpkg.toml is {}, but it isn't installed, so {} is used instead.",
+			tool.section(),
+			default.bold(),
+			found.bold()
+		));
+	}
+
+	Ok(())
+}
+
+/// Kind of tool cpkg looks for on PATH, for telling the user how to get one.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Tool {
+	Compiler,
+	Formatter,
+	Docgen,
+	Debugger,
+}
+
+impl Tool {
+	/// Section of cpkg.toml whose `default` picks between the backends.
+	pub fn section(self) -> &'static str {
+		match self {
+			Self::Compiler => "compiler",
+			Self::Formatter => "formatter",
+			Self::Docgen => "docgen",
+			Self::Debugger => "debugger",
+		}
+	}
+
+	/// Package to install for it, by package manager.
+	fn package(self, manager: &str) -> &'static str {
+		match (self, manager) {
+			(Self::Compiler, "choco") => "mingw",
+			(Self::Compiler, _) => "gcc",
+			(Self::Formatter, "pacman") => "clang",
+			(Self::Formatter, "dnf") => "clang-tools-extra",
+			(Self::Formatter, "choco") => "llvm",
+			(Self::Formatter, _) => "clang-format",
+			(Self::Docgen, "choco") => "doxygen.install",
+			(Self::Docgen, _) => "doxygen",
+			(Self::Debugger, _) => "gdb",
+		}
+	}
+
+	/// Command installing one with the platform's package manager, if there's one cpkg knows of.
+	pub fn install_hint(self) -> Option<String> {
+		/* Apple's clang and lldb come with the command line tools, and are what most people want */
+		if cfg!(target_os = "macos") && matches!(self, Self::Compiler | Self::Debugger) {
+			return Some("xcode-select --install".to_owned());
+		}
+
+		const MANAGERS: &[(&str, &str)] = &[
+			("apt", "sudo apt install"),
+			("pacman", "sudo pacman -S"),
+			("dnf", "sudo dnf install"),
+			("brew", "brew install"),
+			("choco", "choco install"),
+		];
+
+		MANAGERS
+			.iter()
+			.find(|(bin, _)| exists(bin))
+			.map(|(bin, install)| format!("{install} {}", self.package(bin)))
+	}
+}
+
+impl std::fmt::Display for Tool {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Compiler => write!(f, "compiler"),
+			Self::Formatter => write!(f, "formatter"),
+			Self::Docgen => write!(f, "doc generator"),
+			Self::Debugger => write!(f, "debugger"),
+		}
+	}
+}
+
+/// Why no backend could be used for a tool, as opposed to one failing while it ran.
+#[derive(Debug)]
+pub enum LocateError {
+	/// None of the programs probed for are on PATH
+	NotInstalled { tool: Tool, probed: Vec<String> },
+	/// A program asked for specifically isn't on PATH, `from` saying where it was asked for
+	NotFound { tool: Tool, bin: String, from: String },
+	/// `default` in cpkg.toml names a backend cpkg doesn't know
+	Unrecognized { tool: Tool, name: String },
+	/// `default` in cpkg.toml names a backend that isn't installed, and falling back to `found` isn't allowed
+	NoFallback { tool: Tool, default: String, found: String },
+}
+
+impl std::fmt::Display for LocateError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::NotInstalled { tool, probed } => {
+				write!(f, "Couldn't find a {tool}. Looked for {} on PATH.", probed.join(", "))
+			}
+			Self::NotFound { tool, bin, from } => {
+				write!(f, "Couldn't find {tool} {bin} from {from}, is it installed and on your PATH?")
+			}
+			Self::Unrecognized { tool, name } => {
+				write!(f, "Unrecognized default {tool}: {name}")
+			}
+			Self::NoFallback { tool, default, found } => {
+				write!(f, "The default {tool} {default} isn't installed, and falling back to {found} isn't allowed.")
+			}
+		}
+	}
+}
+
+impl std::error::Error for LocateError {}
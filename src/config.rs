@@ -1,30 +1,214 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 nestify::nest! {
 	#[derive(serde::Serialize, serde::Deserialize)]*
 	pub struct Config {
 		pub package: pub struct ConfigPackage {
 			pub name: String,
+			/// Semantic version of the package, e.g. "0.1.0"
+			#[serde(default = "default_version", deserialize_with = "deserialize_version")]
+			pub version: semver::Version,
+			/// One line summary of the package, used by generated pkg-config files and documentation
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub description: Option<String>,
+			/// SPDX identifier of the package's license, e.g. "MIT"
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub license: Option<String>,
+			/// People who wrote the package, e.g. ["Jane Doe <jane@example.com>"]. A single string is taken as one author
+			#[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "deserialize_list")]
+			pub authors: Vec<String>,
+			/// Url of the package's source repository
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub repository: Option<String>,
+			/// Url of the package's website, when it isn't the repository
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub homepage: Option<String>,
+			/// Words describing what the package is about, for finding it
+			#[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "deserialize_list")]
+			pub keywords: Vec<String>,
 			/// Optional location to output the target binary
-			pub bin: Option<std::path::PathBuf>
+			pub bin: Option<std::path::PathBuf>,
+			/// Source with the package's main(), when it isn't src/main.c or the only one defining it
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub entry: Option<std::path::PathBuf>,
+			/// Folders holding the sources, instead of src
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub src: Option<Vec<std::path::PathBuf>>,
+			/// Folder holding the tests, instead of tests
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub tests: Option<std::path::PathBuf>,
+			/// Folder builds output to, instead of target
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub target: Option<std::path::PathBuf>,
+			/// Language the package is written in. C++ is also used when any source ends in .cpp, .cc or .cxx
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub language: Option<#[derive(Clone, Copy, PartialEq, Eq)] pub enum ConfigLanguage {
+				#[serde(rename = "c")]
+				C,
+				/// Compiles the C++ sources with the C++ counterpart of the compiler, which links everything
+				#[serde(rename = "c++")]
+				Cxx,
+			}>,
+			/// What the package is made of, when it isn't a regular C project
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub kind: Option<#[derive(Clone, Copy, PartialEq, Eq)] #[serde(rename_all = "kebab-case")] pub enum ConfigKind {
+				/// Only headers, which builds check compile on their own instead of compiling anything
+				HeaderOnly,
+			}>,
+			/// What a bare `cpkg build` produces, e.g. ["bin:app", "lib"]
+			#[serde(rename = "default-targets")]
+			pub default_targets: Option<Vec<String>>,
+			/// Gitignore style patterns left out of `cpkg package`, on top of .gitignore
+			#[serde(default, skip_serializing_if = "Vec::is_empty")]
+			pub exclude: Vec<String>,
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub metadata: Option<pub struct ConfigMetadata {
+				/// Extra defines in the generated cpkg_config.h. Strings are quoted, numbers and booleans aren't
+				#[serde(default)]
+				pub defines: std::collections::BTreeMap<String, toml::Value>,
+			}>
 		},
 
 		#[serde(default)]
-		pub dependencies: HashMap<String, #[serde(untagged)] pub enum ConfigDependency {
-			Path {
-				path: std::path::PathBuf,
-			},
-			Git {
-				git: String
-			}
-		}>,
+		pub dependencies: #[derive(Default)] pub struct ConfigDependencies {
+			/// Remove vendored dependencies that are no longer declared when installing
+			#[serde(rename = "auto-prune", default, skip_serializing_if = "std::ops::Not::not")]
+			pub auto_prune: bool,
+
+			/// Committed copy of every dependency made by `cpkg vendor`, which builds look in before target/vendor
+			#[serde(rename = "vendor-dir", default, skip_serializing_if = "Option::is_none")]
+			pub vendor_dir: Option<std::path::PathBuf>,
+
+			#[serde(flatten)]
+			pub entries: BTreeMap<String, #[serde(untagged)] pub enum ConfigDependency {
+				Path {
+					path: std::path::PathBuf,
+					#[serde(default, skip_serializing_if = "Option::is_none")]
+					include: Option<#[derive(Clone, Copy, PartialEq, Eq)] #[serde(rename_all = "lowercase")] pub enum ConfigInclude {
+						/// Headers are included as <dep/header.h>
+						Namespaced,
+						/// The dependency's folder is searched too, so its headers can be included as <header.h>
+						Flat,
+					}>,
+					/// Command building the dependency inside of its vendored folder, e.g. "make libfoo.a"
+					#[serde(default, skip_serializing_if = "Option::is_none")]
+					build: Option<String>,
+					/// Files the build command makes that get linked, relative to the dependency
+					#[serde(default, skip_serializing_if = "Vec::is_empty")]
+					artifacts: Vec<std::path::PathBuf>,
+					/// Header target/include/<dep>.h includes, relative to the dependency, when it has more than one at the top level
+					#[serde(default, skip_serializing_if = "Option::is_none")]
+					entry: Option<std::path::PathBuf>,
+				},
+				Git {
+					git: String,
+					#[serde(default, skip_serializing_if = "Option::is_none")]
+					include: Option<ConfigInclude>,
+					#[serde(default, skip_serializing_if = "Option::is_none")]
+					build: Option<String>,
+					#[serde(default, skip_serializing_if = "Vec::is_empty")]
+					artifacts: Vec<std::path::PathBuf>,
+					#[serde(default, skip_serializing_if = "Option::is_none")]
+					entry: Option<std::path::PathBuf>,
+				},
+				/// Library that's already built, on the system or in the project, linked as `-l<lib>`
+				Lib {
+					lib: String,
+					/// Folder the library is in, when the linker wouldn't find it on its own
+					#[serde(default, skip_serializing_if = "Option::is_none")]
+					search: Option<std::path::PathBuf>,
+					/// Folder the library's headers are in
+					#[serde(default, skip_serializing_if = "Option::is_none")]
+					headers: Option<std::path::PathBuf>,
+				}
+			}>
+		},
 
 		#[serde(default)]
-		pub scripts: HashMap<String, String>,
+		pub scripts: #[derive(Default)] pub struct ConfigScripts {
+			/// Shell scripts run with, defaulting to sh, or cmd on Windows
+			#[serde(default, skip_serializing_if = "Option::is_none")]
+			pub shell: Option<#[derive(Clone, Copy, PartialEq, Eq)] #[serde(rename_all = "lowercase")] pub enum ConfigShell {
+				Sh,
+				Cmd,
+				Powershell,
+			}>,
 
-		pub compiler: Option<pub struct ConfigCompiler {
+			#[serde(flatten)]
+			pub entries: BTreeMap<String, String>,
+		},
+
+		/// Cross-compilation targets, selected with `--target <name>`
+		#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+		pub target: BTreeMap<String, #[derive(Clone, Default)] pub struct ConfigTarget {
+			/// Compiler executable to use instead of probing, e.g. "arm-none-eabi-gcc"
+			pub compiler: Option<String>,
+			pub flags: Option<Vec<String>>,
+			/// Flags only passed when linking
+			pub ldflags: Option<Vec<String>>,
+			/// Program that runs binaries built for this target, e.g. "qemu-arm"
+			pub runner: Option<String>,
+			/// Extension of built executables, e.g. "html", which emscripten defaults to
+			pub extension: Option<String>,
+			/// Target triple for compilers that cross compile on their own, passed to zig as `-target`
+			pub triple: Option<String>,
+		}>,
+
+		pub compiler: Option<#[derive(Default)] pub struct ConfigCompiler {
 			pub default: Option<String>,
+			/// Fails when `default` isn't installed, instead of falling back to another compiler, e.g. for CI
+			#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+			pub strict: bool,
 			pub flags: Option<Vec<String>>,
+			/// Version of C to compile as
+			pub standard: Option<#[derive(Clone, Copy)] #[serde(rename_all = "lowercase")] pub enum ConfigStandard {
+				C89,
+				C99,
+				C11,
+				C17,
+				C23,
+			}>,
+			/// Version of C++ to compile the C++ sources as
+			#[serde(rename = "cxx-standard", default, skip_serializing_if = "Option::is_none")]
+			pub cxx_standard: Option<#[derive(Clone, Copy)] pub enum ConfigCxxStandard {
+				#[serde(rename = "c++11")]
+				Cxx11,
+				#[serde(rename = "c++14")]
+				Cxx14,
+				#[serde(rename = "c++17")]
+				Cxx17,
+				#[serde(rename = "c++20")]
+				Cxx20,
+				#[serde(rename = "c++23")]
+				Cxx23,
+			}>,
+			/// Which warnings to enable, and whether they're treated as errors
+			pub warnings: Option<#[derive(Clone, Copy)] #[serde(rename_all = "lowercase")] pub enum ConfigWarnings {
+				None,
+				All,
+				Extra,
+				Error,
+			}>,
+			/// Libraries to link against, e.g. ["m", "pthread"]
+			pub libs: Option<Vec<String>>,
+			/// Folders searched for libraries, relative to the project
+			#[serde(rename = "lib-dirs", alias = "lib_dirs")]
+			pub lib_dirs: Option<Vec<std::path::PathBuf>>,
+			/// Folders searched for headers besides src and the vendored dependencies
+			#[serde(rename = "include-dirs", alias = "include_dirs")]
+			pub include_dirs: Option<Vec<std::path::PathBuf>>,
+			/// Preprocessor defines. An empty value defines the name without one
+			pub defines: Option<std::collections::BTreeMap<String, String>>,
+			/// Flags for the sources matching a pattern relative to the project, like "src/legacy/*.c"
+			#[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+			pub overrides: std::collections::BTreeMap<String, #[derive(Clone, PartialEq)] pub struct ConfigOverride {
+				/// Flags passed after the project's
+				#[serde(default, skip_serializing_if = "Vec::is_empty")]
+				pub flags: Vec<String>,
+				/// Flags of the project's to leave out, like "-Werror"
+				#[serde(default, skip_serializing_if = "Vec::is_empty")]
+				pub remove: Vec<String>,
+			}>,
 
 			pub gcc: Option<pub struct ConfigGcc {
 				pub flags: Option<Vec<String>>,
@@ -32,11 +216,23 @@ nestify::nest! {
 
 			pub clang: Option<pub struct ConfigClang {
 				pub flags: Option<Vec<String>>,
+			}>,
+
+			pub emcc: Option<pub struct ConfigEmcc {
+				pub flags: Option<Vec<String>>,
+				/// Emscripten settings, passed as `-s`, e.g. "ALLOW_MEMORY_GROWTH=1"
+				pub settings: Option<Vec<String>>,
 			}>
 		}>,
 
 		pub formatter: Option<pub struct ConfigFormatter {
 			pub default: Option<String>,
+			/// Fails when `default` isn't installed, instead of falling back to another formatter
+			#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+			pub strict: bool,
+			/// Gitignore style patterns left out of `cpkg format`, e.g. ["src/generated/**"]
+			#[serde(default, skip_serializing_if = "Vec::is_empty")]
+			pub exclude: Vec<String>,
 
 			pub clang_format: Option<pub struct ConfigClangFormat {
 				/* nada */
@@ -46,12 +242,263 @@ nestify::nest! {
 			}>
 		}>,
 
+		/// How `cpkg test` runs test binaries
+		pub test: Option<pub struct ConfigTest {
+			/// Program binaries run through, e.g. "qemu-arm -L /usr/arm-linux-gnueabi". Also used by `cpkg run`
+			pub runner: Option<String>,
+			/// Seconds a test may run before it's killed and fails
+			pub timeout: Option<f64>,
+		}>,
+
+		pub net: Option<pub struct ConfigNet {
+			/// How many times to try fetching a dependency, defaults to 3
+			pub attempts: Option<u32>,
+			/// Milliseconds to wait before the first retry, doubling after each
+			pub backoff: Option<u64>,
+		}>,
+
 		pub docgen: Option<pub struct ConfigDocgen {
 			pub default: Option<String>,
+			/// Fails when `default` isn't installed, instead of falling back to another doc generator
+			#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+			pub strict: bool,
 
 			pub doxygen: Option<pub struct ConfigDoxygen {
 				pub doxyfile: std::path::PathBuf
 			}>,
+		}>,
+
+		pub debugger: Option<pub struct ConfigDebugger {
+			/// gdb or lldb, picked over the other when both are installed
+			pub default: Option<String>,
+			/// Fails when `default` isn't installed, instead of falling back to another debugger
+			#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+			pub strict: bool,
 		}>
 	}
 }
+
+/// Keys each table of cpkg.toml accepts, for suggesting a fix for unknown ones. `*` stands for any name.
+/// Keep in sync with the structs above.
+pub const KEYS: &[(&str, &[&str])] = &[
+	("", &["package", "dependencies", "scripts", "target", "compiler", "formatter", "test", "net", "docgen", "debugger"]),
+	(
+		"package",
+		&[
+			"name", "version", "description", "license", "authors", "repository", "homepage", "keywords", "bin", "entry", "src", "tests", "target", "language", "kind", "default-targets",
+			"exclude",
+			"metadata",
+		],
+	),
+	("package.metadata", &["defines"]),
+	("dependencies", &["auto-prune", "vendor-dir"]),
+	("scripts", &["shell"]),
+	("target.*", &["compiler", "flags", "ldflags", "runner", "extension", "triple"]),
+	(
+		"compiler",
+		&[
+			"default", "strict", "flags", "standard", "cxx-standard", "warnings", "libs", "lib-dirs", "include-dirs", "defines", "overrides",
+			"gcc", "clang", "emcc",
+		],
+	),
+	("compiler.overrides.*", &["flags", "remove"]),
+	("compiler.gcc", &["flags"]),
+	("compiler.clang", &["flags"]),
+	("compiler.emcc", &["flags", "settings"]),
+	("formatter", &["default", "strict", "exclude", "clang_format", "uncrustify"]),
+	("formatter.uncrustify", &["config"]),
+	("test", &["runner", "timeout"]),
+	("net", &["attempts", "backoff"]),
+	("docgen", &["default", "strict", "doxygen"]),
+	("docgen.doxygen", &["doxyfile"]),
+	("debugger", &["default", "strict"]),
+];
+
+fn default_version() -> semver::Version {
+	semver::Version::new(0, 1, 0)
+}
+
+/// Parses the package version, pointing out what's wrong with it rather than failing generically.
+fn deserialize_version<'de, D: serde::Deserializer<'de>>(d: D) -> Result<semver::Version, D::Error> {
+	let version = <String as serde::Deserialize>::deserialize(d)?;
+
+	version.parse().map_err(|e| {
+		serde::de::Error::custom(format!(
+			"invalid package version '{version}': {e}. Expected a semantic version like 1.2.3"
+		))
+	})
+}
+
+/// Parses a list of strings, taking a lone string as a list of one rather than failing on it.
+fn deserialize_list<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<String>, D::Error> {
+	#[derive(serde::Deserialize)]
+	#[serde(untagged)]
+	enum List {
+		One(String),
+		Many(Vec<String>),
+	}
+
+	Ok(match <List as serde::Deserialize>::deserialize(d)? {
+		List::One(one) => vec![one],
+		List::Many(many) => many,
+	})
+}
+
+impl std::fmt::Display for ConfigDependency {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Path { path, .. } => write!(f, "{}", path.display()),
+			Self::Git { git, .. } => write!(f, "{git}"),
+			Self::Lib { lib, .. } => write!(f, "-l{lib}"),
+		}
+	}
+}
+
+impl ConfigDependency {
+	/// How the dependency's headers are included, namespaced under its name unless it opts out.
+	pub fn include(&self) -> ConfigInclude {
+		match self {
+			Self::Path { include, .. } | Self::Git { include, .. } => {
+				include.unwrap_or(ConfigInclude::Namespaced)
+			}
+			/* Its headers are wherever `headers` says, which builds search on their own */
+			Self::Lib { .. } => ConfigInclude::Namespaced,
+		}
+	}
+
+	/// Whether the dependency is installed to target/vendor, which prebuilt libraries aren't.
+	pub fn is_vendored(&self) -> bool {
+		!matches!(self, Self::Lib { .. })
+	}
+
+	/// Command `cpkg install` builds the dependency with, for libraries that aren't cpkg projects.
+	pub fn build(&self) -> Option<&str> {
+		match self {
+			Self::Path { build, .. } | Self::Git { build, .. } => build.as_deref(),
+			Self::Lib { .. } => None,
+		}
+	}
+
+	/// Files the build command makes that are linked into the project.
+	pub fn artifacts(&self) -> &[std::path::PathBuf] {
+		match self {
+			Self::Path { artifacts, .. } | Self::Git { artifacts, .. } => artifacts,
+			Self::Lib { .. } => &[],
+		}
+	}
+
+	/// Header the dependency declares it's included through, see [crate::Project::write_shims].
+	pub fn entry(&self) -> Option<&std::path::Path> {
+		match self {
+			Self::Path { entry, .. } | Self::Git { entry, .. } => entry.as_deref(),
+			Self::Lib { .. } => None,
+		}
+	}
+}
+
+impl ConfigStandard {
+	/// gcc-style flag selecting the standard, which other backends translate.
+	pub fn flag(self) -> &'static str {
+		match self {
+			Self::C89 => "-std=c89",
+			Self::C99 => "-std=c99",
+			Self::C11 => "-std=c11",
+			Self::C17 => "-std=c17",
+			/* Spelled the old way, so compilers from before C23 was finalized accept it */
+			Self::C23 => "-std=c2x",
+		}
+	}
+}
+
+impl ConfigCxxStandard {
+	/// gcc-style flag selecting the standard, which other backends translate.
+	pub fn flag(self) -> &'static str {
+		match self {
+			Self::Cxx11 => "-std=c++11",
+			Self::Cxx14 => "-std=c++14",
+			Self::Cxx17 => "-std=c++17",
+			Self::Cxx20 => "-std=c++20",
+			/* Like C23, spelled the old way for compilers from before it was finalized */
+			Self::Cxx23 => "-std=c++2b",
+		}
+	}
+}
+
+impl ConfigWarnings {
+	/// gcc-style flags enabling the warnings, which other backends translate.
+	pub fn flags(self) -> &'static [&'static str] {
+		match self {
+			Self::None => &["-w"],
+			Self::All => &["-Wall"],
+			Self::Extra => &["-Wall", "-Wextra"],
+			Self::Error => &["-Wall", "-Wextra", "-Werror"],
+		}
+	}
+}
+
+/// cpkg.toml at the root of a workspace, which holds packages rather than being one.
+#[derive(serde::Deserialize)]
+pub struct WorkspaceConfig {
+	pub workspace: ConfigWorkspace,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ConfigWorkspace {
+	/// Folders of the packages in the workspace, relative to its root
+	pub members: Vec<std::path::PathBuf>,
+}
+
+/// User-level configuration, shared by every project.
+/// Lives at `~/.config/cpkg/config.toml` (or `%APPDATA%\cpkg\config.toml` on Windows).
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct GlobalConfig {
+	pub compiler: Option<ConfigCompiler>,
+
+	/// Named templates for `cpkg new --template`, as paths or git urls
+	#[serde(default)]
+	pub templates: BTreeMap<String, String>,
+
+	/// Name on the copyright line of licenses written by `cpkg new`, instead of git's user.name
+	pub author: Option<String>,
+
+	/// License `cpkg new` writes without asking, like "mit" or "none"
+	pub license: Option<crate::license::License>,
+}
+
+impl GlobalConfig {
+	pub fn dir() -> Option<std::path::PathBuf> {
+		#[cfg(target_os = "windows")]
+		let base = std::env::var_os("APPDATA").map(std::path::PathBuf::from);
+
+		#[cfg(not(target_os = "windows"))]
+		let base = std::env::var_os("XDG_CONFIG_HOME")
+			.map(std::path::PathBuf::from)
+			.or_else(|| std::env::var_os("HOME").map(|h| std::path::Path::new(&h).join(".config")));
+
+		base.map(|b| b.join("cpkg"))
+	}
+
+	pub fn path() -> Option<std::path::PathBuf> {
+		Self::dir().map(|d| d.join("config.toml"))
+	}
+
+	/// Loads the user's config, or an empty one if it doesn't exist.
+	pub fn load() -> anyhow::Result<Self> {
+		match Self::path() {
+			Some(path) if path.is_file() => {
+				let config = std::fs::read_to_string(&path)?;
+				toml::from_str(&config)
+					.map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", path.display()))
+			}
+			_ => Ok(Self::default()),
+		}
+	}
+
+	/// Flags passed to every compiler invocation outside of a project.
+	pub fn compiler_flags(&self) -> &[String] {
+		self.compiler
+			.as_ref()
+			.and_then(|c| c.flags.as_deref())
+			.unwrap_or_default()
+	}
+}
@@ -0,0 +1,110 @@
+/// Record of the files cpkg wrote to the project root for other tools, like compile_flags.txt for clangd,
+/// with a hash of what was written. It's what tells them apart from files written or edited by hand,
+/// which `cpkg clean --generated` never removes. Relative to the target folder.
+const LEDGER: &str = ".cpkg/generated";
+
+/// First line of generated build files, which keeps them recognizable after the target folder is removed.
+pub const MARKER: &str = "# Generated by cpkg generate, delete this line to keep cpkg from removing it";
+
+/// Build files cpkg generate writes with [MARKER], which are recognized without being recorded.
+const MARKED: &[&str] = &["Makefile", "build.ninja"];
+
+/// Folder the ledger's paths are relative to, so members of a workspace sharing a target folder don't collide.
+fn root<'a>(proj: &'a crate::Project) -> &'a std::path::Path {
+	proj.workspace().map_or(proj.path(), |ws| ws.root())
+}
+
+fn key(proj: &crate::Project, file: &str) -> String {
+	let path = proj.path().join(file);
+
+	path.strip_prefix(root(proj))
+		.unwrap_or(&path)
+		.to_string_lossy()
+		.replace('\\', "/")
+}
+
+fn read_ledger(proj: &crate::Project) -> std::collections::BTreeMap<String, String> {
+	std::fs::read_to_string(proj.target().join(LEDGER))
+		.unwrap_or_default()
+		.lines()
+		.filter_map(|l| l.rsplit_once(' '))
+		.map(|(file, hash)| (file.to_owned(), hash.to_owned()))
+		.collect()
+}
+
+fn save_ledger(
+	proj: &crate::Project,
+	ledger: &std::collections::BTreeMap<String, String>,
+) -> anyhow::Result<()> {
+	let path = proj.target().join(LEDGER);
+
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent)?;
+	}
+
+	let contents = ledger
+		.iter()
+		.map(|(file, hash)| format!("{file} {hash}\n"))
+		.collect::<String>();
+
+	std::fs::write(path, contents)?;
+	Ok(())
+}
+
+fn hash(contents: &str) -> String {
+	format!("{:016x}", crate::assets::hash(contents))
+}
+
+/// Writes `file` to the project root, recording it as generated.
+pub fn write(proj: &crate::Project, file: &str, contents: &str) -> anyhow::Result<()> {
+	std::fs::write(proj.path().join(file), contents)?;
+
+	let mut ledger = read_ledger(proj);
+	ledger.insert(key(proj, file), hash(contents));
+
+	save_ledger(proj, &ledger)
+}
+
+/// Whether `file` in the project root is as cpkg generated it, so it's safe to overwrite or remove.
+pub fn is_generated(proj: &crate::Project, file: &str) -> bool {
+	let Ok(contents) = std::fs::read_to_string(proj.path().join(file)) else {
+		return false;
+	};
+
+	(MARKED.contains(&file) && contents.starts_with(MARKER))
+		|| read_ledger(proj).get(&key(proj, file)) == Some(&hash(&contents))
+}
+
+/// Removes the project's generated files that are still as cpkg wrote them, returning their names.
+pub fn clean(proj: &crate::Project) -> anyhow::Result<Vec<String>> {
+	let mut ledger = read_ledger(proj);
+
+	let recorded = ledger.keys().filter_map(|k| {
+		root(proj)
+			.join(k)
+			.strip_prefix(proj.path())
+			.ok()?
+			.to_str()
+			.map(str::to_owned)
+	});
+
+	let candidates = recorded
+		.chain(MARKED.iter().map(|f| f.to_string()))
+		.collect::<std::collections::BTreeSet<_>>();
+
+	let mut removed = vec![];
+
+	for file in candidates {
+		if is_generated(proj, &file) {
+			std::fs::remove_file(proj.path().join(&file))?;
+			ledger.remove(&key(proj, &file));
+			removed.push(file);
+		}
+	}
+
+	if proj.target().join(LEDGER).exists() {
+		save_ledger(proj, &ledger)?;
+	}
+
+	Ok(removed)
+}
@@ -0,0 +1,146 @@
+/// Header a test includes to opt into the harness, see assets/cpkg_test.h.
+pub const HEADER: &str = "cpkg_test.h";
+
+/// Prefix of the lines the generated main prints about each case, to tell them apart from the test's own output.
+const PREFIX: &str = "cpkg-test: ";
+
+/// Names of the `TEST(name)` cases in a test, or [None] if it doesn't use the harness.
+pub fn cases(source: &str) -> Option<Vec<String>> {
+	let uses_harness = crate::includes::scan(source)
+		.iter()
+		.any(|i| i.path == HEADER);
+
+	if !uses_harness {
+		return None;
+	}
+
+	let cases = source
+		.lines()
+		.filter_map(|line| line.trim_start().strip_prefix("TEST("))
+		.filter_map(|rest| rest.split_once(')'))
+		.map(|(name, _)| name.trim())
+		.filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+		.map(str::to_owned)
+		.collect();
+
+	Some(cases)
+}
+
+/// What a `// cpkg: ...` comment on the first line of a test says about running it.
+pub enum Marker {
+	/// `should-fail`, for a test documenting a known bug, which is expected to exit non-zero
+	ShouldFail,
+	/// `skip`, optionally followed by why, for a test that's compiled but not run
+	Skip(Option<String>),
+	/// Anything else after `cpkg:`, likely a typo
+	Unknown(String),
+}
+
+/// Reads the marker on the first line of a test, or [None] if it doesn't have one.
+pub fn marker(source: &str) -> Option<Marker> {
+	let comment = source.lines().next()?.trim().strip_prefix("//")?;
+	let rest = comment.trim_start().strip_prefix("cpkg:")?.trim();
+	let (kind, reason) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+	match kind {
+		"should-fail" => Some(Marker::ShouldFail),
+		"skip" => Some(Marker::Skip(
+			Some(reason.trim()).filter(|r| !r.is_empty()).map(str::to_owned),
+		)),
+		_ => Some(Marker::Unknown(rest.to_owned())),
+	}
+}
+
+/// Source of the main function running a test's cases.
+/// Given case names as arguments, it only runs those.
+pub fn main(cases: &[String]) -> String {
+	let declarations = cases
+		.iter()
+		.map(|c| format!("void cpkg_test_{c}(void);\n"))
+		.collect::<String>();
+
+	let table = cases
+		.iter()
+		.map(|c| format!("\t{{ \"{c}\", cpkg_test_{c} }},\n"))
+		.collect::<String>();
+
+	indoc::formatdoc! {r#"
+		/* Generated by cpkg to run the cases of a test using {HEADER} */
+		#include <stdio.h>
+		#include <string.h>
+
+		static const char *cpkg_test_current;
+		static int cpkg_test_failed;
+
+		void cpkg_test_fail(const char *file, int line, const char *cond) {{
+			cpkg_test_failed = 1;
+			printf("{PREFIX}fail %s %s:%d: %s: Assertion `%s' failed.\n", cpkg_test_current, file, line, cpkg_test_current, cond);
+			fflush(stdout);
+		}}
+
+		{declarations}
+		static const struct {{
+			const char *name;
+			void (*run)(void);
+		}} cpkg_test_cases[] = {{
+		{table}	{{ NULL, NULL }},
+		}};
+
+		int main(int argc, char **argv) {{
+			int failed = 0;
+
+			for (int i = 0; cpkg_test_cases[i].name; i++) {{
+				int wanted = argc < 2;
+
+				for (int a = 1; a < argc; a++) {{
+					wanted |= strcmp(argv[a], cpkg_test_cases[i].name) == 0;
+				}}
+
+				if (!wanted) {{
+					continue;
+				}}
+
+				cpkg_test_current = cpkg_test_cases[i].name;
+				cpkg_test_failed = 0;
+
+				printf("{PREFIX}start %s\n", cpkg_test_current);
+				fflush(stdout);
+
+				cpkg_test_cases[i].run();
+
+				if (!cpkg_test_failed) {{
+					printf("{PREFIX}pass %s\n", cpkg_test_current);
+					fflush(stdout);
+				}}
+
+				failed |= cpkg_test_failed;
+			}}
+
+			return failed;
+		}}
+	"#}
+}
+
+/// What the generated main reported about a case.
+pub enum Event {
+	Start(String),
+	Pass(String),
+	/// Case name, and the failed check in the form of a glibc assert message
+	Fail(String, String),
+}
+
+/// Reads a harness line the generated main printed, or [None] if the test printed it itself.
+pub fn parse(line: &str) -> Option<Event> {
+	let rest = line.strip_prefix(PREFIX)?;
+	let (kind, rest) = rest.split_once(' ')?;
+
+	match kind {
+		"start" => Some(Event::Start(rest.to_owned())),
+		"pass" => Some(Event::Pass(rest.to_owned())),
+		"fail" => {
+			let (name, message) = rest.split_once(' ')?;
+			Some(Event::Fail(name.to_owned(), message.to_owned()))
+		}
+		_ => None,
+	}
+}
@@ -0,0 +1,139 @@
+/// First line after the shebang of a hook cpkg generated, to tell it apart from one a user wrote.
+const MARKER: &str = "# Generated by cpkg generate hooks";
+
+/// Name a hook that was already there is moved to, so the generated one can run it first.
+const CHAINED: &str = "pre-commit.local";
+
+/// Runs git in a folder, returning what it printed, trimmed.
+fn git(dir: &std::path::Path, args: &[&str]) -> anyhow::Result<String> {
+	let mut cmd = std::process::Command::new("git");
+	cmd.arg("-C").arg(dir).args(args);
+
+	crate::log::command(&cmd);
+	let out = cmd.output()?;
+
+	if !out.status.success() {
+		anyhow::bail!(
+			"{} isn't inside of a git repository, run git init first.",
+			dir.display()
+		);
+	}
+
+	Ok(String::from_utf8_lossy(&out.stdout).trim().to_owned())
+}
+
+/// Folder git runs hooks from, which honors core.hooksPath.
+fn hooks_dir(dir: &std::path::Path) -> anyhow::Result<std::path::PathBuf> {
+	Ok(dir.join(git(dir, &["rev-parse", "--git-path", "hooks"])?))
+}
+
+/// Files staged for the next commit that still exist, as canonical paths.
+pub fn staged(dir: &std::path::Path) -> anyhow::Result<Vec<std::path::PathBuf>> {
+	let root = std::path::PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?);
+	let names = git(dir, &["diff", "--cached", "--name-only", "--diff-filter=ACMR"])?;
+
+	Ok(names
+		.lines()
+		.filter_map(|name| root.join(name).canonicalize().ok())
+		.collect())
+}
+
+fn script(proj: &crate::Project) -> anyhow::Result<String> {
+	let root = std::path::PathBuf::from(git(proj.path(), &["rev-parse", "--show-toplevel"])?);
+	let rel = proj
+		.path()
+		.canonicalize()?
+		.strip_prefix(root.canonicalize()?)?
+		.to_string_lossy()
+		.replace('\\', "/");
+
+	Ok(indoc::formatdoc! {r#"
+		#!/bin/sh
+		{MARKER}, remove it with cpkg generate hooks --remove.
+		# A hook that was here before is kept as {CHAINED}, and runs first.
+
+		hooks="$(dirname "$0")"
+
+		if [ -x "$hooks/{CHAINED}" ]; then
+			"$hooks/{CHAINED}" "$@" || exit $?
+		fi
+
+		cd "$(git rev-parse --show-toplevel)/{rel}" || exit 1
+
+		if ! cpkg format --staged --check; then
+			echo "cpkg: staged files aren't formatted. Run cpkg format, stage the changes and commit again." >&2
+			echo "cpkg: to commit anyway, pass --no-verify to git commit." >&2
+			exit 1
+		fi
+	"#})
+}
+
+fn generated(hook: &std::path::Path) -> bool {
+	std::fs::read_to_string(hook).is_ok_and(|s| s.contains(MARKER))
+}
+
+/// Writes a pre-commit hook checking staged sources are formatted, returning where it went.
+/// A hook that's already there is kept and chained, and regenerating just rewrites cpkg's own.
+pub fn install(proj: &crate::Project) -> anyhow::Result<std::path::PathBuf> {
+	if proj.config().formatter.is_none() && crate::format::selected(proj)?.is_none() {
+		anyhow::bail!(
+			"No formatter is configured or installed, so the hook would have nothing to check. Add a [formatter] section to cpkg.toml, or install {}.",
+			crate::format::supported().collect::<Vec<_>>().join(" or ")
+		);
+	}
+
+	let dir = hooks_dir(proj.path())?;
+	std::fs::create_dir_all(&dir)?;
+
+	let hook = dir.join("pre-commit");
+	let chained = dir.join(CHAINED);
+
+	if hook.exists() && !generated(&hook) {
+		if chained.exists() {
+			anyhow::bail!(
+				"Both {} and {} exist, so the current hook can't be kept. Merge them first.",
+				hook.display(),
+				chained.display()
+			);
+		}
+
+		std::fs::rename(&hook, &chained)?;
+	}
+
+	std::fs::write(&hook, script(proj)?)?;
+
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+	}
+
+	Ok(hook)
+}
+
+/// Deletes the generated pre-commit hook, putting back the hook it chained to, if any.
+pub fn remove(proj: &crate::Project) -> anyhow::Result<()> {
+	let dir = hooks_dir(proj.path())?;
+	let hook = dir.join("pre-commit");
+
+	if !hook.exists() {
+		anyhow::bail!("There's no pre-commit hook to remove.");
+	}
+
+	if !generated(&hook) {
+		anyhow::bail!(
+			"{} wasn't generated by cpkg, so it was left alone.",
+			hook.display()
+		);
+	}
+
+	std::fs::remove_file(&hook)?;
+
+	let chained = dir.join(CHAINED);
+
+	if chained.exists() {
+		std::fs::rename(chained, hook)?;
+	}
+
+	Ok(())
+}
@@ -0,0 +1,867 @@
+/* Best-effort translation of an existing Makefile project into a cpkg.toml */
+
+/// Variables naming the compiler.
+const CC_VARS: &[&str] = &["CC"];
+
+/// Variables holding compile and link flags, in the order they're applied.
+const FLAG_VARS: &[&str] = &["CPPFLAGS", "CFLAGS", "LDFLAGS", "LDLIBS", "LIBS"];
+
+/// Variables listing the sources to build.
+const SOURCE_VARS: &[&str] = &["SRC", "SRCS", "SOURCES", "C_SOURCES", "CSRC"];
+
+/// Variables listing objects, which usually mirror the sources.
+const OBJECT_VARS: &[&str] = &["OBJ", "OBJS", "OBJECTS"];
+
+/// Variables naming the output binary.
+const NAME_VARS: &[&str] = &[
+	"TARGET", "BIN", "PROG", "PROGRAM", "EXEC", "EXE", "NAME", "OUT",
+];
+
+/// Variables holding the project's version.
+const VERSION_VARS: &[&str] = &["VERSION", "PROJECT_VERSION"];
+
+/// Variables that only matter to make itself, so aren't worth reporting.
+const IGNORED_VARS: &[&str] = &[
+	"RM",
+	"MKDIR",
+	"MKDIR_P",
+	"CP",
+	"MV",
+	"INSTALL",
+	"PREFIX",
+	"DESTDIR",
+	"BINDIR",
+	"LIBDIR",
+	"BUILD",
+	"BUILD_DIR",
+	"BUILDDIR",
+	"OBJDIR",
+	"OBJ_DIR",
+	"SRCDIR",
+	"SRC_DIR",
+	"SHELL",
+	"DEPS",
+	".DEFAULT_GOAL",
+];
+
+/// Rules cpkg has its own commands for.
+const BUILTIN_RULES: &[&str] = &[
+	"all",
+	"clean",
+	"distclean",
+	"install",
+	"uninstall",
+	"test",
+	"check",
+	"run",
+	"default",
+];
+
+/// Folders never searched for sources.
+const SKIPPED_DIRS: &[&str] = &[".git", "target", "build", "vendor", "node_modules"];
+
+/// What `cpkg import` found out about an existing project.
+pub struct Import {
+	pub config: crate::Config,
+	/// Anything that couldn't be translated, written as comments in the config
+	pub todos: Vec<String>,
+}
+
+impl Import {
+	/// Inspects the project at `dir` without touching anything inside of it.
+	pub fn inspect(dir: &std::path::Path) -> anyhow::Result<Self> {
+		let name = dir
+			.file_name()
+			.map(|n| n.to_string_lossy().into_owned())
+			.unwrap_or_else(|| "project".to_owned());
+
+		let mut import = Self {
+			config: crate::Config {
+				package: crate::ConfigPackage {
+					name,
+					version: semver::Version::new(0, 1, 0),
+					description: None,
+					license: None,
+					authors: vec![],
+					repository: None,
+					homepage: None,
+					keywords: vec![],
+					bin: None,
+					entry: None,
+					src: None,
+					tests: None,
+					target: None,
+					language: None,
+					kind: None,
+					default_targets: None,
+					exclude: vec![],
+					metadata: None,
+				},
+
+				dependencies: Default::default(),
+				scripts: Default::default(),
+				target: Default::default(),
+
+				compiler: None,
+				formatter: None,
+				test: None,
+				net: None,
+				docgen: None,
+				debugger: None,
+			},
+			todos: vec![],
+		};
+
+		let makefile = ["GNUmakefile", "makefile", "Makefile"]
+			.iter()
+			.map(|f| dir.join(f))
+			.find(|p| p.is_file());
+
+		let listed = match makefile {
+			Some(ref path) => {
+				let makefile = Makefile::parse(&std::fs::read_to_string(path)?, dir);
+				import.apply_makefile(&makefile)
+			}
+			None => {
+				import
+					.todos
+					.push("No Makefile found, only the source layout was inspected".to_owned());
+				vec![]
+			}
+		};
+
+		import.inspect_layout(dir, &listed);
+
+		Ok(import)
+	}
+
+	/// Takes whatever maps onto cpkg.toml from a Makefile, returning the sources it lists.
+	fn apply_makefile(&mut self, makefile: &Makefile) -> Vec<std::path::PathBuf> {
+		let mut used = std::collections::HashSet::new();
+
+		for var in CC_VARS {
+			let Some(cc) = makefile.get(var) else {
+				continue;
+			};
+
+			used.insert(*var);
+
+			let bin = cc.split_whitespace().next().unwrap_or_default();
+			let bin = std::path::Path::new(bin)
+				.file_name()
+				.map(|b| b.to_string_lossy().into_owned())
+				.unwrap_or_default();
+
+			/* Versioned compilers like gcc-13 still map onto their backend */
+			let backend = bin.split('-').next().unwrap_or_default();
+			let backend = match backend.trim_end_matches(".exe") {
+				"cl" => "msvc",
+				backend => backend,
+			};
+
+			if crate::compiler::is_supported(backend) {
+				self.config
+					.compiler
+					.get_or_insert_with(empty_compiler)
+					.default = Some(backend.to_owned());
+			} else if bin != "cc" && !bin.is_empty() {
+				self.todos
+					.push(format!("CC = {cc} isn't a compiler cpkg supports"));
+			}
+		}
+
+		let mut flags = vec![];
+		for var in FLAG_VARS {
+			if let Some(value) = makefile.get(var) {
+				used.insert(*var);
+				flags.extend(value.split_whitespace().map(str::to_owned));
+			}
+		}
+
+		if !flags.is_empty() {
+			self.config
+				.compiler
+				.get_or_insert_with(empty_compiler)
+				.flags = Some(flags);
+		}
+
+		for var in NAME_VARS {
+			if let Some(name) = makefile.get(var) {
+				used.insert(*var);
+
+				let name = std::path::Path::new(name.trim())
+					.file_stem()
+					.map(|n| n.to_string_lossy().into_owned());
+
+				if let Some(name) = name.filter(|n| !n.is_empty() && !n.contains('$')) {
+					self.config.package.name = name;
+					break;
+				}
+			}
+		}
+
+		for var in VERSION_VARS {
+			if let Some(version) = makefile.get(var) {
+				used.insert(*var);
+
+				match version.trim().parse() {
+					Ok(version) => self.config.package.version = version,
+					Err(_) => self.todos.push(format!(
+						"{var} = {version} isn't a semantic version like 1.2.3, so the version was left at {}",
+						self.config.package.version
+					)),
+				}
+
+				break;
+			}
+		}
+
+		let mut sources = vec![];
+
+		for var in SOURCE_VARS {
+			if let Some(value) = makefile.get(var) {
+				used.insert(*var);
+				sources.extend(
+					value
+						.split_whitespace()
+						.filter(|s| s.ends_with(".c"))
+						.map(|s| std::path::PathBuf::from(s.trim_start_matches("./"))),
+				);
+			}
+		}
+
+		if sources.is_empty() {
+			for var in OBJECT_VARS {
+				if let Some(value) = makefile.get(var) {
+					used.insert(*var);
+					sources.extend(value.split_whitespace().filter(|s| s.ends_with(".o")).map(
+						|s| std::path::Path::new(s.trim_start_matches("./")).with_extension("c"),
+					));
+				}
+			}
+		} else {
+			used.extend(OBJECT_VARS);
+		}
+
+		for (name, value) in &makefile.vars {
+			if !used.contains(name.as_str()) && !IGNORED_VARS.contains(&name.as_str()) {
+				self.todos.push(format!(
+					"Makefile variable {name} = {value} wasn't translated"
+				));
+			}
+		}
+
+		for rule in &makefile.rules {
+			if BUILTIN_RULES.contains(&rule.target.as_str())
+				|| rule.target.starts_with('.')
+				|| rule.target.contains(['%', '$', '/'])
+				|| rule.target.ends_with(".o")
+				|| rule.target == self.config.package.name
+				|| rule
+					.recipe
+					.iter()
+					.any(|l| l.contains("$(CC)") || l.contains("${CC}"))
+			{
+				/* Compiling and linking is what cpkg does itself */
+				continue;
+			}
+
+			/* Recipes using make variables or automatic variables can't be ran by a shell as-is */
+			if !rule.recipe.is_empty() && !rule.recipe.iter().any(|l| l.contains('$')) {
+				self.config
+					.scripts
+					.entries
+					.insert(rule.target.clone(), rule.recipe.join(" && "));
+			} else {
+				self.todos.push(format!(
+					"Makefile rule '{}' wasn't translated, consider adding it to [scripts]",
+					rule.target
+				));
+			}
+		}
+
+		makefile.unsupported.iter().for_each(|line| {
+			self.todos
+				.push(format!("Couldn't understand Makefile line: {line}"));
+		});
+
+		sources
+	}
+
+	/// Compares where the sources and tests are against what cpkg expects.
+	fn inspect_layout(&mut self, dir: &std::path::Path, listed: &[std::path::PathBuf]) {
+		let files = walkdir::WalkDir::new(dir)
+			.into_iter()
+			.filter_entry(|e| {
+				e.depth() == 0 || !SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref())
+			})
+			.flatten()
+			.filter(|e| e.file_type().is_file())
+			.filter(|e| e.path().extension().is_some_and(|ext| ext == "c"))
+			.filter_map(|e| e.path().strip_prefix(dir).ok().map(|p| p.to_owned()))
+			.collect::<Vec<_>>();
+
+		let (tests, sources): (Vec<_>, Vec<_>) = files.into_iter().partition(|f| is_test(f));
+
+		if sources.is_empty() {
+			self.todos.push("No C sources were found".to_owned());
+		}
+
+		let outside = sources
+			.iter()
+			.filter(|f| !f.starts_with("src"))
+			.map(|f| f.display().to_string())
+			.collect::<Vec<_>>();
+
+		if !outside.is_empty() {
+			self.todos.push(format!(
+				"cpkg builds every .c file under src/, move these into it: {}",
+				outside.join(", ")
+			));
+		}
+
+		let in_src = sources.iter().filter(|f| f.starts_with("src")).count();
+		let unlisted = sources
+			.iter()
+			.filter(|f| f.starts_with("src") && !listed.is_empty() && !listed.contains(f))
+			.map(|f| f.display().to_string())
+			.collect::<Vec<_>>();
+
+		if !unlisted.is_empty() {
+			self.todos.push(format!(
+				"The Makefile only builds {} of {in_src} sources under src/, cpkg would also build: {}",
+				in_src - unlisted.len(),
+				unlisted.join(", ")
+			));
+		}
+
+		let mains = sources
+			.iter()
+			.filter(|f| f.starts_with("src"))
+			.filter(|f| std::fs::read_to_string(dir.join(f)).is_ok_and(|s| crate::project::defines_main(&s)))
+			.collect::<Vec<_>>();
+
+		match mains.as_slice() {
+			[] => {}
+			[main] if main.file_name().is_some_and(|n| n == "main.c") => {}
+			[main] if main.parent() == Some(std::path::Path::new("src")) => {
+				let stem = main.file_stem().unwrap().to_string_lossy();
+				self.config.package.default_targets = Some(vec![format!("bin:{stem}")]);
+			}
+			_ => self.todos.push(format!(
+				"Found main() in {}, set [package] entry to the one to build, the others can be built with --bin",
+				mains
+					.iter()
+					.map(|m| m.display().to_string())
+					.collect::<Vec<_>>()
+					.join(", ")
+			)),
+		}
+
+		let misplaced = tests
+			.iter()
+			.filter(|f| !f.starts_with("tests") && !f.to_string_lossy().ends_with(".test.c"))
+			.map(|f| f.display().to_string())
+			.collect::<Vec<_>>();
+
+		if !misplaced.is_empty() {
+			self.todos.push(format!(
+				"cpkg runs tests from tests/ and src/**/*.test.c, move or rename these: {}",
+				misplaced.join(", ")
+			));
+		}
+	}
+
+	/// The config as it'd be written to cpkg.toml, with anything untranslated as TODO comments.
+	pub fn render(&self) -> anyhow::Result<String> {
+		let mut out = toml::to_string(&self.config)?;
+
+		if !self.todos.is_empty() {
+			out.push_str(
+				"\n# Generated by `cpkg import`, these couldn't be translated automatically:\n",
+			);
+
+			for todo in &self.todos {
+				out.push_str(&format!("# TODO: {todo}\n"));
+			}
+		}
+
+		Ok(out)
+	}
+}
+
+fn empty_compiler() -> crate::ConfigCompiler {
+	crate::ConfigCompiler {
+		default: None,
+		strict: false,
+		flags: None,
+		standard: None,
+		cxx_standard: None,
+		warnings: None,
+		libs: None,
+		lib_dirs: None,
+		include_dirs: None,
+		defines: None,
+		overrides: Default::default(),
+		gcc: None,
+		clang: None,
+		emcc: None,
+	}
+}
+
+fn is_test(path: &std::path::Path) -> bool {
+	let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+	path.iter().any(|p| p == "test" || p == "tests")
+		|| name.ends_with(".test.c")
+		|| name.ends_with("_test.c")
+		|| name.starts_with("test_")
+}
+
+/// A rule from a Makefile, with its recipe lines.
+struct Rule {
+	target: String,
+	recipe: Vec<String>,
+}
+
+/// The subset of a Makefile cpkg understands: variable assignments and rules.
+struct Makefile {
+	/// Folder the Makefile is in, which wildcards are relative to
+	root: std::path::PathBuf,
+	vars: std::collections::BTreeMap<String, String>,
+	rules: Vec<Rule>,
+	/// Directives like `include` or `ifeq` which aren't evaluated
+	unsupported: Vec<String>,
+}
+
+impl Makefile {
+	fn parse(text: &str, root: &std::path::Path) -> Self {
+		let mut makefile = Self {
+			root: root.to_owned(),
+			vars: Default::default(),
+			rules: vec![],
+			unsupported: vec![],
+		};
+
+		/* Join continued lines first */
+		let text = text.replace("\\\r\n", " ").replace("\\\n", " ");
+
+		/* Conditionals aren't evaluated, so everything inside of them is reported instead */
+		let mut conditional = 0;
+
+		for line in text.lines() {
+			if let Some(recipe) = line.strip_prefix('\t') {
+				if let Some(rule) = makefile.rules.last_mut() {
+					let recipe = recipe.trim().trim_start_matches(['@', '-']);
+
+					if !recipe.is_empty() {
+						rule.recipe.push(recipe.to_owned());
+					}
+				}
+
+				continue;
+			}
+
+			let line = line.split_once('#').map(|p| p.0).unwrap_or(line).trim();
+			if line.is_empty() {
+				continue;
+			}
+
+			let directive = line.split_whitespace().next().unwrap_or_default();
+
+			if matches!(directive, "ifeq" | "ifneq" | "ifdef" | "ifndef") {
+				conditional += 1;
+			}
+
+			if conditional > 0 {
+				if directive == "endif" {
+					conditional -= 1;
+				}
+
+				makefile.unsupported.push(line.to_owned());
+				continue;
+			}
+
+			if matches!(
+				directive,
+				"include" | "-include" | "sinclude" | "define" | "endef" | "export" | "vpath"
+			) {
+				makefile.unsupported.push(line.to_owned());
+				continue;
+			}
+
+			if let Some((name, op, value)) = assignment(line) {
+				let value = value.trim().to_owned();
+
+				match op {
+					"+=" => {
+						let entry = makefile.vars.entry(name.to_owned()).or_default();
+						if !entry.is_empty() {
+							entry.push(' ');
+						}
+						entry.push_str(&value);
+					}
+					"?=" => {
+						makefile.vars.entry(name.to_owned()).or_insert(value);
+					}
+					_ => {
+						makefile.vars.insert(name.to_owned(), value);
+					}
+				}
+
+				continue;
+			}
+
+			if let Some((targets, _prereqs)) = line.split_once(':') {
+				for target in targets.split_whitespace() {
+					makefile.rules.push(Rule {
+						target: target.to_owned(),
+						recipe: vec![],
+					});
+				}
+
+				continue;
+			}
+
+			makefile.unsupported.push(line.to_owned());
+		}
+
+		makefile
+	}
+
+	/// Value of a variable with references to other variables expanded.
+	fn get(&self, name: &str) -> Option<String> {
+		self.vars.get(name).map(|v| self.expand(v, 0))
+	}
+
+	fn expand(&self, value: &str, depth: usize) -> String {
+		/* Guards against variables referring to themselves */
+		if depth > 16 {
+			return value.to_owned();
+		}
+
+		let mut out = String::new();
+		let mut rest = value;
+
+		while let Some(start) = rest.find('$') {
+			out.push_str(&rest[..start]);
+
+			let after = &rest[start + 1..];
+			let close = match after.chars().next() {
+				Some('(') => ')',
+				Some('{') => '}',
+				_ => {
+					out.push('$');
+					rest = after;
+					continue;
+				}
+			};
+
+			let Some(end) = after.find(close) else {
+				out.push_str(&rest[start..]);
+				rest = "";
+				break;
+			};
+
+			let inner = self.expand(&after[1..end], depth + 1);
+			out.push_str(&self.call(&inner, depth));
+			rest = &after[end + 1..];
+		}
+
+		out.push_str(rest);
+		out
+	}
+
+	/// Evaluates the inside of a `$(...)`, either a variable or one of the few functions supported.
+	fn call(&self, inner: &str, depth: usize) -> String {
+		if let Some(pattern) = inner.strip_prefix("wildcard ") {
+			return pattern
+				.split_whitespace()
+				.flat_map(|p| glob_files(&self.root, p))
+				.collect::<Vec<_>>()
+				.join(" ");
+		}
+
+		if let Some(args) = inner.strip_prefix("patsubst ") {
+			let mut args = args.splitn(3, ',');
+			if let (Some(from), Some(to), Some(text)) = (args.next(), args.next(), args.next()) {
+				return text
+					.split_whitespace()
+					.map(|word| patsubst(from.trim(), to.trim(), word))
+					.collect::<Vec<_>>()
+					.join(" ");
+			}
+		}
+
+		/* Substitution references, like $(SRC:.c=.o) */
+		if let Some((var, subst)) = inner.split_once(':') {
+			if let Some((from, to)) = subst.split_once('=') {
+				if let Some(value) = self.vars.get(var) {
+					return self
+						.expand(value, depth + 1)
+						.split_whitespace()
+						.map(|w| match w.strip_suffix(from) {
+							Some(stem) => format!("{stem}{to}"),
+							None => w.to_owned(),
+						})
+						.collect::<Vec<_>>()
+						.join(" ");
+				}
+			}
+		}
+
+		match self.vars.get(inner) {
+			Some(value) => self.expand(value, depth + 1),
+			/* Unknown, or a function we don't evaluate */
+			None => format!("$({inner})"),
+		}
+	}
+}
+
+/// Splits `NAME op value` for each of make's assignment operators.
+fn assignment(line: &str) -> Option<(&str, &str, &str)> {
+	for op in ["::=", ":=", "+=", "?=", "!=", "="] {
+		if let Some((name, value)) = line.split_once(op) {
+			let name = name.trim();
+
+			/* A rule like `a: b=c` has its colon before the assignment */
+			if name.is_empty() || name.contains([':', ' ', '\t', '$']) {
+				continue;
+			}
+
+			return Some((name, op, value));
+		}
+	}
+
+	None
+}
+
+fn patsubst(from: &str, to: &str, word: &str) -> String {
+	match from.split_once('%') {
+		Some((prefix, suffix)) => match word
+			.strip_prefix(prefix)
+			.and_then(|w| w.strip_suffix(suffix))
+		{
+			Some(stem) => to.replacen('%', stem, 1),
+			None => word.to_owned(),
+		},
+		None if word == from => to.to_owned(),
+		None => word.to_owned(),
+	}
+}
+
+/// Expands a `$(wildcard)` pattern with a single `*` in its file name.
+fn glob_files(root: &std::path::Path, pattern: &str) -> Vec<String> {
+	let path = std::path::Path::new(pattern);
+	let dir = path.parent().filter(|p| !p.as_os_str().is_empty());
+	let name = path
+		.file_name()
+		.map(|n| n.to_string_lossy().into_owned())
+		.unwrap_or_default();
+
+	let Some((prefix, suffix)) = name.split_once('*') else {
+		return vec![pattern.to_owned()];
+	};
+
+	let Ok(entries) = std::fs::read_dir(root.join(dir.unwrap_or(std::path::Path::new("")))) else {
+		return vec![];
+	};
+
+	let mut files = entries
+		.flatten()
+		.map(|e| e.file_name().to_string_lossy().into_owned())
+		.filter(|n| n.starts_with(prefix) && n.ends_with(suffix))
+		.map(|n| match dir {
+			Some(dir) => dir.join(n).to_string_lossy().into_owned(),
+			None => n,
+		})
+		.collect::<Vec<_>>();
+
+	files.sort();
+	files
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn fixture(name: &str) -> std::path::PathBuf {
+		std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+			.join("tests/fixtures/import")
+			.join(name)
+	}
+
+	/// Every file under `dir` with its contents, to check nothing was touched.
+	fn snapshot(dir: &std::path::Path) -> Vec<(std::path::PathBuf, Vec<u8>)> {
+		walkdir::WalkDir::new(dir)
+			.sort_by_file_name()
+			.into_iter()
+			.flatten()
+			.filter(|e| e.file_type().is_file())
+			.map(|e| (e.path().to_owned(), std::fs::read(e.path()).unwrap()))
+			.collect()
+	}
+
+	fn inspect(name: &str) -> Import {
+		let dir = fixture(name);
+		let before = snapshot(&dir);
+
+		let import = Import::inspect(&dir).unwrap();
+
+		assert_eq!(before, snapshot(&dir), "{name} was left untouched");
+		import
+	}
+
+	fn has_todo(import: &Import, needle: &str) -> bool {
+		import.todos.iter().any(|t| t.contains(needle))
+	}
+
+	#[test]
+	fn simple_makefile() {
+		let import = inspect("simple");
+		let compiler = import.config.compiler.as_ref().unwrap();
+
+		assert_eq!(import.config.package.name, "hello");
+		assert_eq!(compiler.default.as_deref(), Some("gcc"));
+		assert_eq!(
+			compiler.flags.as_deref(),
+			Some(&["-Wall".to_owned(), "-O2".to_owned()][..])
+		);
+		assert_eq!(import.config.package.default_targets, None);
+		assert!(import.config.scripts.entries.is_empty());
+		assert!(import.todos.is_empty(), "{:?}", import.todos);
+
+		assert!(!import.render().unwrap().contains("TODO"));
+	}
+
+	#[test]
+	fn makefile_with_variables() {
+		let import = inspect("vars");
+		let compiler = import.config.compiler.as_ref().unwrap();
+
+		assert_eq!(import.config.package.name, "calc");
+		assert_eq!(import.config.package.version, semver::Version::new(1, 2, 0));
+		assert_eq!(compiler.default.as_deref(), Some("clang"));
+		assert_eq!(
+			compiler.flags.as_deref().unwrap(),
+			["-Iinclude", "-std=c11", "-g", "-lm", "-lpthread"]
+		);
+		assert_eq!(
+			import.config.package.default_targets,
+			Some(vec!["bin:calc".to_owned()])
+		);
+
+		/* Plain recipes become scripts, ones using make variables are left as TODOs */
+		assert_eq!(
+			import
+				.config
+				.scripts
+				.entries
+				.get("docs")
+				.map(String::as_str),
+			Some("doxygen Doxyfile")
+		);
+		assert!(!import.config.scripts.entries.contains_key("release"));
+		assert!(has_todo(&import, "rule 'release'"));
+		assert!(has_todo(&import, "WARN = -Wextra"));
+
+		/* Both sources were found through $(wildcard), and the test is where cpkg looks */
+		assert!(!has_todo(&import, "only builds"));
+		assert!(!has_todo(&import, "move"));
+		assert_eq!(import.todos.len(), 2, "{:?}", import.todos);
+	}
+
+	#[test]
+	fn complex_makefile() {
+		let import = inspect("complex");
+		let compiler = import.config.compiler.as_ref().unwrap();
+
+		assert_eq!(import.config.package.name, "tool");
+		assert_eq!(
+			compiler.default.as_deref(),
+			Some("clang"),
+			"versioned compilers map onto their backend"
+		);
+		assert_eq!(
+			compiler.flags.as_deref().unwrap(),
+			["-O2", "-DNDEBUG"],
+			"conditionals aren't applied"
+		);
+		assert_eq!(
+			import
+				.config
+				.scripts
+				.entries
+				.get("lint")
+				.map(String::as_str),
+			Some("cppcheck src")
+		);
+
+		assert!(has_todo(&import, "VERSION = 2.0 isn't a semantic version"));
+		assert_eq!(import.config.package.version, semver::Version::new(0, 1, 0));
+
+		for line in [
+			"include config.mk",
+			"ifeq ($(DEBUG),1)",
+			"CFLAGS += -g",
+			"endif",
+			"export PATH",
+		] {
+			assert!(
+				has_todo(
+					&import,
+					&format!("Couldn't understand Makefile line: {line}")
+				),
+				"{line}"
+			);
+		}
+
+		assert!(has_todo(&import, "move these into it: tools/gen.c"));
+		assert!(has_todo(
+			&import,
+			"only builds 2 of 3 sources under src/, cpkg would also build: src/extra.c"
+		));
+		assert!(has_todo(
+			&import,
+			"move or rename these: helpers/test_parse.c"
+		));
+	}
+
+	#[test]
+	fn render_keeps_todos_as_comments() {
+		let import = inspect("complex");
+		let rendered = import.render().unwrap();
+
+		let todos = rendered
+			.lines()
+			.filter(|l| l.starts_with("# TODO: "))
+			.count();
+		assert_eq!(todos, import.todos.len());
+
+		let config = toml::from_str::<crate::Config>(&rendered).expect("rendered config parses");
+		assert_eq!(config.package.name, "tool");
+		assert_eq!(
+			config.package.default_targets,
+			Some(vec!["bin:tool".to_owned()])
+		);
+	}
+
+	#[test]
+	fn no_makefile() {
+		let temp = tempfile::tempdir().unwrap();
+		std::fs::create_dir(temp.path().join("src")).unwrap();
+		std::fs::write(
+			temp.path().join("src/main.c"),
+			"int main(void) { return 0; }\n",
+		)
+		.unwrap();
+
+		let import = Import::inspect(temp.path()).unwrap();
+
+		assert!(import.config.compiler.is_none());
+		assert_eq!(
+			import.todos,
+			["No Makefile found, only the source layout was inspected"]
+		);
+	}
+}
@@ -0,0 +1,564 @@
+/// File inside of the vendor folder listing shim headers written by [write_shims]
+const SHIM_LEDGER: &str = ".cpkg-shims";
+
+/// An `#include` directive found in a source file.
+pub struct Include {
+	/// Line number, starting at 1
+	pub line: usize,
+	pub path: String,
+	/// Whether it's written as `<path>` rather than `"path"`
+	pub angled: bool,
+	/// Byte range of the path and its delimiters within the line
+	pub span: std::ops::Range<usize>,
+}
+
+/// Finds the include directives in C source, skipping any inside comments.
+/// Includes under `#if` are all returned, as which branch is taken isn't known until compiling.
+pub fn scan(text: &str) -> Vec<Include> {
+	let mut includes = vec![];
+	let mut in_comment = false;
+
+	for (n, line) in text.lines().enumerate() {
+		let code = mask_comments(line, &mut in_comment);
+
+		if let Some((angled, span)) = parse_directive(&code) {
+			includes.push(Include {
+				line: n + 1,
+				path: line[span.start + 1..span.end - 1].to_owned(),
+				angled,
+				span,
+			});
+		}
+	}
+
+	includes
+}
+
+/// Replaces comments in a line with spaces, keeping byte offsets intact.
+/// `in_comment` carries an unterminated block comment over to the next line.
+fn mask_comments(line: &str, in_comment: &mut bool) -> String {
+	let bytes = line.as_bytes();
+	let mut out = bytes.to_vec();
+	let mut quote = None;
+	let mut i = 0;
+
+	while i < bytes.len() {
+		if *in_comment {
+			if bytes[i..].starts_with(b"*/") {
+				out[i..i + 2].fill(b' ');
+				*in_comment = false;
+				i += 2;
+			} else {
+				out[i] = b' ';
+				i += 1;
+			}
+
+			continue;
+		}
+
+		match quote {
+			Some(_) if bytes[i] == b'\\' => i += 2,
+			Some(q) => {
+				if bytes[i] == q {
+					quote = None;
+				}
+
+				i += 1;
+			}
+			None if bytes[i..].starts_with(b"//") => {
+				out[i..].fill(b' ');
+				break;
+			}
+			None if bytes[i..].starts_with(b"/*") => {
+				out[i..i + 2].fill(b' ');
+				*in_comment = true;
+				i += 2;
+			}
+			None => {
+				if bytes[i] == b'"' || bytes[i] == b'\'' {
+					quote = Some(bytes[i]);
+				}
+
+				i += 1;
+			}
+		}
+	}
+
+	/* Only whole characters are ever masked, so this stays valid */
+	String::from_utf8(out).unwrap_or_default()
+}
+
+/// Parses `# include "path"` or `# include <path>`, returning whether it's angled and where the path is.
+fn parse_directive(code: &str) -> Option<(bool, std::ops::Range<usize>)> {
+	let rest = code.trim_start().strip_prefix('#')?.trim_start();
+	let after = rest.strip_prefix("include")?.trim_start();
+
+	/* Also rules out #include_next and macro includes */
+	let (close, angled) = match after.chars().next()? {
+		'"' => ('"', false),
+		'<' => ('>', true),
+		_ => return None,
+	};
+
+	let start = code.len() - after.len();
+	let end = start + 1 + after[1..].find(close)? + 1;
+
+	Some((angled, start..end))
+}
+
+/// Finds the file an include refers to, looking beside the including file first for quoted includes.
+pub fn resolve(
+	include: &Include,
+	from: &std::path::Path,
+	search: &[std::path::PathBuf],
+) -> Option<std::path::PathBuf> {
+	let local = (!include.angled)
+		.then(|| from.parent().map(|p| p.join(&include.path)))
+		.flatten();
+
+	local
+		.into_iter()
+		.chain(search.iter().map(|dir| dir.join(&include.path)))
+		.find(|p| p.is_file())
+}
+
+/// An include of a dependency header written for the old flat layout,
+/// where each dependency's folder was on the include path itself.
+pub struct Rewrite {
+	pub file: std::path::PathBuf,
+	pub line: usize,
+	pub dep: String,
+	/// Header path, relative to the dependency
+	pub header: String,
+	pub before: String,
+	pub after: String,
+}
+
+/// Everything `cpkg deps migrate-includes` would change.
+pub struct Migration {
+	pub rewrites: Vec<Rewrite>,
+	/// Includes matching headers of several dependencies, which are left alone
+	pub ambiguous: Vec<(std::path::PathBuf, usize, Vec<String>)>,
+}
+
+impl Migration {
+	/// Finds flat includes across the project's sources, headers, tests and examples.
+	pub fn find(proj: &crate::Project) -> anyhow::Result<Self> {
+		let vendor = proj.vendor();
+		let shims = written_shims(&vendor);
+
+		let mut deps = proj
+			.config()
+			.dependencies
+			.entries
+			.iter()
+			.filter(|(_, dep)| dep.is_vendored())
+			.map(|(name, _)| name.clone())
+			.collect::<Vec<_>>();
+		deps.sort();
+
+		let search = proj.src().into_iter().chain([vendor.clone()]).collect::<Vec<_>>();
+
+		let files = proj
+			.src_files()
+			.chain(
+				walkdir::WalkDir::new(proj.tests())
+					.into_iter()
+					.flatten()
+					.map(|e| e.into_path()),
+			)
+			.chain(proj.example_files())
+			.filter(|f| f.extension().is_some_and(|e| e == "c" || e == "h"));
+
+		let mut migration = Self {
+			rewrites: vec![],
+			ambiguous: vec![],
+		};
+
+		for file in files {
+			let contents = std::fs::read_to_string(&file)?;
+			let lines = contents.lines().collect::<Vec<_>>();
+
+			for include in scan(&contents) {
+				/* Shims make old includes resolve, but they still need migrating */
+				let resolved = resolve(&include, &file, &search)
+					.filter(|p| !shims.iter().any(|s| *p == vendor.join(s)));
+
+				if resolved.is_some() {
+					continue;
+				}
+
+				let owners = deps
+					.iter()
+					.filter(|dep| vendor.join(dep).join(&include.path).is_file())
+					.cloned()
+					.collect::<Vec<_>>();
+
+				match owners.as_slice() {
+					[] => (),
+					[dep] => {
+						let before = lines[include.line - 1];
+						let after = format!(
+							"{}<{dep}/{}>{}",
+							&before[..include.span.start],
+							include.path,
+							&before[include.span.end..]
+						);
+
+						migration.rewrites.push(Rewrite {
+							file: file.clone(),
+							line: include.line,
+							dep: dep.clone(),
+							header: include.path,
+							before: before.to_owned(),
+							after,
+						});
+					}
+					_ => migration
+						.ambiguous
+						.push((file.clone(), include.line, owners)),
+				}
+			}
+		}
+
+		Ok(migration)
+	}
+
+	/// Rewrites includes in place, in the files `selected` picks.
+	/// Returns how many includes were rewritten.
+	pub fn apply(&self, selected: impl Fn(&std::path::Path) -> bool) -> anyhow::Result<usize> {
+		let mut by_file = std::collections::BTreeMap::<&std::path::Path, Vec<&Rewrite>>::new();
+
+		for rewrite in self.rewrites.iter().filter(|r| selected(&r.file)) {
+			by_file.entry(&rewrite.file).or_default().push(rewrite);
+		}
+
+		let mut count = 0;
+
+		for (file, rewrites) in by_file {
+			let contents = std::fs::read_to_string(file)?;
+			let mut out = String::with_capacity(contents.len());
+
+			for (n, chunk) in contents.split_inclusive('\n').enumerate() {
+				match rewrites.iter().find(|r| r.line == n + 1) {
+					Some(rewrite) => {
+						/* Keep whichever line ending was there */
+						out.push_str(&rewrite.after);
+						out.push_str(&chunk[rewrite.before.len()..]);
+						count += 1;
+					}
+					None => out.push_str(chunk),
+				}
+			}
+
+			std::fs::write(file, out)?;
+		}
+
+		Ok(count)
+	}
+
+	/// Shim headers that keep flat includes in the files `selected` picks building,
+	/// as (header, dependency).
+	pub fn shims(
+		&self,
+		selected: impl Fn(&std::path::Path) -> bool,
+	) -> std::collections::BTreeMap<&str, &str> {
+		self.rewrites
+			.iter()
+			.filter(|r| selected(&r.file))
+			.map(|r| (r.header.as_str(), r.dep.as_str()))
+			.collect()
+	}
+}
+
+/// Shim headers previously written to the vendor folder, relative to it.
+pub fn written_shims(vendor: &std::path::Path) -> std::collections::BTreeSet<String> {
+	std::fs::read_to_string(vendor.join(SHIM_LEDGER))
+		.map(|l| l.lines().map(str::to_owned).collect())
+		.unwrap_or_default()
+}
+
+/// Whether a top level vendor entry is a shim or the shim ledger, rather than a dependency.
+pub fn is_shim_entry(vendor: &std::path::Path, name: &str) -> bool {
+	name == SHIM_LEDGER
+		|| written_shims(vendor).iter().any(|s| {
+			std::path::Path::new(s)
+				.components()
+				.next()
+				.is_some_and(|c| c.as_os_str() == name)
+		})
+}
+
+/// Writes headers to the vendor folder that forward old flat includes to the namespaced form,
+/// with a deprecation warning. Returns the headers written.
+pub fn write_shims(
+	proj: &crate::Project,
+	shims: std::collections::BTreeMap<&str, &str>,
+) -> anyhow::Result<Vec<String>> {
+	let vendor = proj.vendor();
+	let mut ledger = written_shims(&vendor);
+	let mut written = vec![];
+
+	for (header, dep) in shims {
+		let top = std::path::Path::new(header).components().next();
+		let clashes = top.is_some_and(|c| {
+			let name = c.as_os_str().to_string_lossy();
+			proj.config()
+				.dependencies
+				.entries
+				.contains_key(name.as_ref())
+		});
+
+		if clashes {
+			anyhow::bail!(
+				"Can't write a shim for \"{header}\", it would go inside a dependency's folder. Migrate its includes instead."
+			);
+		}
+
+		let shim = vendor.join(header);
+		if let Some(parent) = shim.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+
+		std::fs::write(
+			&shim,
+			indoc::formatdoc! {r#"
+				/* Generated by cpkg deps migrate-includes, remove with --remove-shims */
+				#warning "\"{header}\" is deprecated, include <{dep}/{header}> instead"
+				#include <{dep}/{header}>
+			"#},
+		)?;
+
+		ledger.insert(header.to_owned());
+		written.push(header.to_owned());
+	}
+
+	save_ledger(&vendor, &ledger)?;
+
+	Ok(written)
+}
+
+/// Deletes every shim header, returning how many there were.
+pub fn remove_shims(vendor: &std::path::Path) -> anyhow::Result<usize> {
+	let ledger = written_shims(vendor);
+
+	for header in &ledger {
+		let shim = vendor.join(header);
+		if shim.is_file() {
+			std::fs::remove_file(&shim)?;
+		}
+
+		/* Clean up folders made for nested headers, stopping at the first that isn't empty */
+		let mut dir = shim.parent();
+		while let Some(d) = dir.filter(|d| *d != vendor) {
+			if std::fs::remove_dir(d).is_err() {
+				break;
+			}
+
+			dir = d.parent();
+		}
+	}
+
+	save_ledger(vendor, &Default::default())?;
+
+	Ok(ledger.len())
+}
+
+fn save_ledger(
+	vendor: &std::path::Path,
+	ledger: &std::collections::BTreeSet<String>,
+) -> anyhow::Result<()> {
+	let path = vendor.join(SHIM_LEDGER);
+
+	if ledger.is_empty() {
+		if path.exists() {
+			std::fs::remove_file(path)?;
+		}
+
+		return Ok(());
+	}
+
+	std::fs::create_dir_all(vendor)?;
+
+	let contents = ledger.iter().map(|s| format!("{s}\n")).collect::<String>();
+	std::fs::write(path, contents)?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn paths(text: &str) -> Vec<(usize, String)> {
+		scan(text).into_iter().map(|i| (i.line, i.path)).collect()
+	}
+
+	#[test]
+	fn skips_includes_in_comments() {
+		let text = indoc::indoc! {r#"
+			// #include "line.h"
+			/* #include "block.h" */
+			/*
+			#include "multiline.h"
+			*/ #include "after.h"
+			#include "kept.h" /* #include "trailing.h" */
+			const char *s = "/* not a comment"; #include "string.h"
+			#include "next.h" // done
+		"#};
+
+		assert_eq!(
+			paths(text),
+			[
+				(5, "after.h".to_owned()),
+				(6, "kept.h".to_owned()),
+				(8, "next.h".to_owned())
+			]
+		);
+	}
+
+	#[test]
+	fn returns_every_conditional_branch() {
+		let text = indoc::indoc! {r#"
+			#ifdef _WIN32
+			#  include <windows.h>
+			#elif defined(__APPLE__)
+			  # include "mach.h"
+			#else
+			#include<unistd.h>
+			#endif
+		"#};
+
+		let includes = scan(text);
+
+		assert_eq!(
+			includes
+				.iter()
+				.map(|i| (i.path.as_str(), i.angled))
+				.collect::<Vec<_>>(),
+			[("windows.h", true), ("mach.h", false), ("unistd.h", true)]
+		);
+
+		/* Spans cover the delimiters, wherever the directive sits in the line */
+		let line = text.lines().nth(3).unwrap();
+		assert_eq!(&line[includes[1].span.clone()], "\"mach.h\"");
+	}
+
+	#[test]
+	fn ignores_other_directives() {
+		let text = indoc::indoc! {r#"
+			#include_next <stdio.h>
+			#define HEADER "config.h"
+			#include HEADER
+			#pragma once
+			#include "unterminated.h
+		"#};
+
+		assert!(scan(text).is_empty());
+	}
+
+	/// A project depending on `json` and `util`, with their headers already vendored.
+	fn app() -> tempfile::TempDir {
+		let main = indoc::indoc! {r#"
+			#include "json.h" // parser
+			// #include "json.h"
+			#include "local.h"
+			#ifdef USE_UTIL
+			#  include <util.h>
+			#endif
+			#include "common.h"
+			#include <stdio.h>
+		"#}
+		.replace('\n', "\r\n");
+
+		crate::project::testing::project(
+			indoc::indoc! {r#"
+
+				[dependencies]
+				json = { path = "../json" }
+				util = { path = "../util" }
+				math = { lib = "m" }
+			"#},
+			&[
+				("target/vendor/json/json.h", ""),
+				("target/vendor/json/common.h", ""),
+				("target/vendor/util/util.h", ""),
+				("target/vendor/util/common.h", ""),
+				("src/local.h", ""),
+				("src/main.c", &main),
+			],
+		)
+	}
+
+	#[test]
+	fn migrates_tricky_includes() {
+		let temp = app();
+		let proj = crate::Project::open(temp.path()).unwrap();
+
+		let migration = Migration::find(&proj).unwrap();
+
+		let rewrites = migration
+			.rewrites
+			.iter()
+			.map(|r| (r.line, r.dep.as_str(), r.after.as_str()))
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			rewrites,
+			[
+				(1, "json", "#include <json/json.h> // parser"),
+				(5, "util", "#  include <util/util.h>"),
+			]
+		);
+
+		assert_eq!(migration.ambiguous.len(), 1);
+		assert_eq!(migration.ambiguous[0].1, 7);
+		assert_eq!(migration.ambiguous[0].2, ["json", "util"]);
+
+		assert_eq!(migration.apply(|_| true).unwrap(), 2);
+
+		let main = std::fs::read_to_string(temp.path().join("src/main.c")).unwrap();
+		assert_eq!(
+			main,
+			"#include <json/json.h> // parser\r\n// #include \"json.h\"\r\n#include \"local.h\"\r\n#ifdef USE_UTIL\r\n#  include <util/util.h>\r\n#endif\r\n#include \"common.h\"\r\n#include <stdio.h>\r\n",
+			"only the rewritten includes change, line endings are kept"
+		);
+
+		assert!(
+			Migration::find(&proj).unwrap().rewrites.is_empty(),
+			"migrating again is a no-op"
+		);
+	}
+
+	#[test]
+	fn shims_still_need_migrating() {
+		let temp = app();
+		let proj = crate::Project::open(temp.path()).unwrap();
+		let vendor = proj.vendor();
+
+		let migration = Migration::find(&proj).unwrap();
+		let written = write_shims(&proj, migration.shims(|_| true)).unwrap();
+
+		assert_eq!(written, ["json.h", "util.h"]);
+		assert!(std::fs::read_to_string(vendor.join("json.h"))
+			.unwrap()
+			.contains("#include <json/json.h>"));
+		assert!(is_shim_entry(&vendor, "json.h") && !is_shim_entry(&vendor, "json"));
+
+		/* The shim resolves the old include, but it's reported all the same */
+		assert_eq!(Migration::find(&proj).unwrap().rewrites.len(), 2);
+
+		let clash = std::collections::BTreeMap::from([("util/extra.h", "json")]);
+		assert!(
+			write_shims(&proj, clash).is_err(),
+			"shims don't go inside a dependency"
+		);
+
+		assert_eq!(remove_shims(&vendor).unwrap(), 2);
+		assert!(!vendor.join("json.h").exists() && !vendor.join(SHIM_LEDGER).exists());
+		assert!(
+			vendor.join("json/json.h").is_file(),
+			"dependencies are left alone"
+		);
+	}
+}
@@ -0,0 +1,102 @@
+/// Curated list of common libraries and where they're developed, kept as index.toml in cpkg's repository.
+const URL: &str = "https://raw.githubusercontent.com/DvvCz/cpkg/master/index.toml";
+
+/// How long a downloaded copy of the index is used before fetching it again.
+const TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60 * 24);
+
+#[derive(serde::Deserialize)]
+pub struct Index {
+	#[serde(default)]
+	packages: std::collections::BTreeMap<String, Entry>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct Entry {
+	pub git: String,
+	#[serde(default)]
+	pub description: String,
+}
+
+impl Index {
+	/// Loads the index from the user's cache, fetching it again once it's older than a day.
+	/// When that fails, like when offline, an outdated copy is used with a warning.
+	pub fn load() -> anyhow::Result<Self> {
+		let cached = crate::assets::cache_dir().map(|d| d.join("index.toml"));
+
+		let fresh = cached
+			.as_ref()
+			.and_then(|p| p.metadata().ok()?.modified().ok()?.elapsed().ok())
+			.is_some_and(|age| age < TTL);
+
+		if fresh {
+			if let Some(index) = cached.as_deref().and_then(read) {
+				return Ok(index);
+			}
+		}
+
+		let contents = match fetch() {
+			Ok(contents) => contents,
+			Err(e) => match cached.as_deref().and_then(read) {
+				Some(index) => {
+					crate::log::warn(format!("{e}, using an outdated copy"));
+					return Ok(index);
+				}
+				None => return Err(e),
+			},
+		};
+
+		let index = toml::from_str(&contents)
+			.map_err(|e| anyhow::anyhow!("The package index is malformed: {e}"))?;
+
+		/* Only a cache, so failing to write it isn't worth failing over */
+		if let Some(path) = cached {
+			let _ = std::fs::create_dir_all(path.parent().unwrap());
+			let _ = std::fs::write(path, contents);
+		}
+
+		Ok(index)
+	}
+
+	/// Entry for `name`, ignoring case so `cJSON` finds `cjson`.
+	pub fn get(&self, name: &str) -> Option<(&str, &Entry)> {
+		self.packages
+			.iter()
+			.find(|(n, _)| n.eq_ignore_ascii_case(name))
+			.map(|(n, e)| (n.as_str(), e))
+	}
+
+	/// Entries whose name or description contains `term`, ignoring case.
+	pub fn search(&self, term: &str) -> Vec<(&str, &Entry)> {
+		let term = term.to_lowercase();
+
+		self.packages
+			.iter()
+			.filter(|(n, e)| {
+				n.to_lowercase().contains(&term) || e.description.to_lowercase().contains(&term)
+			})
+			.map(|(n, e)| (n.as_str(), e))
+			.collect()
+	}
+
+	/// Name closest to a misspelled one, if any is close enough to be what was meant.
+	pub fn closest(&self, name: &str) -> Option<&str> {
+		crate::validate::closest(
+			&name.to_lowercase(),
+			self.packages.keys().map(String::as_str),
+		)
+	}
+}
+
+fn read(path: &std::path::Path) -> Option<Index> {
+	toml::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+fn fetch() -> anyhow::Result<String> {
+	let mut body = vec![];
+
+	self_update::Download::from_url(URL)
+		.download_to(&mut body)
+		.map_err(|e| anyhow::anyhow!("Couldn't fetch the package index: {e}"))?;
+
+	Ok(String::from_utf8(body)?)
+}
@@ -0,0 +1,99 @@
+/// Summary of a project printed by `cpkg info`, and serialized as is with `--json`.
+#[derive(serde::Serialize)]
+pub struct Info {
+	pub name: String,
+	pub version: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub description: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub license: Option<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub authors: Vec<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub repository: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub homepage: Option<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub keywords: Vec<String>,
+	/// What a bare `cpkg build` produces, e.g. "bin:main" or "lib"
+	pub targets: Vec<String>,
+	pub sources: usize,
+	pub tests: usize,
+	pub compiler: Backend,
+	pub formatter: Backend,
+	pub docgen: Backend,
+	pub dependencies: Vec<Dependency>,
+	/// Bytes used by the target folder, vendored dependencies included
+	pub target_size: u64,
+}
+
+/// A kind of backend, as configured in cpkg.toml and as cpkg would pick it right now.
+#[derive(serde::Serialize)]
+pub struct Backend {
+	/// Default named in cpkg.toml, if any
+	pub configured: Option<String>,
+	/// What would be used, or [None] if nothing suitable is installed
+	pub selected: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct Dependency {
+	pub name: String,
+	/// Git url or path, as declared in cpkg.toml
+	pub source: String,
+	pub installed: bool,
+}
+
+/// Gathers everything about a project `cpkg info` shows.
+pub fn gather(proj: &crate::Project) -> anyhow::Result<Info> {
+	let config = proj.config();
+	let vendors = proj.vendor_dirs();
+
+	let dependencies = config
+		.dependencies
+		.entries
+		.iter()
+		.map(|(name, dep)| Dependency {
+			name: name.clone(),
+			source: dep.to_string(),
+			/* Path dependencies are symlinks, which may dangle if the folder they point to moved */
+			installed: match dep.is_vendored() {
+				true => vendors.iter().any(|v| v.join(name).exists()),
+				false => proj.check_lib(name, dep).is_ok(),
+			},
+		})
+		.collect::<Vec<_>>();
+
+	Ok(Info {
+		name: proj.name().to_owned(),
+		version: proj.version().to_string(),
+		description: config.package.description.clone(),
+		license: config.package.license.clone(),
+		authors: config.package.authors.clone(),
+		repository: config.package.repository.clone(),
+		homepage: config.package.homepage.clone(),
+		keywords: config.package.keywords.clone(),
+		targets: crate::target::defaults(proj)?
+			.iter()
+			.map(|t| t.to_string())
+			.collect(),
+		sources: proj.c_files(&proj.src()).count() + proj.bin_files(&proj.src()).count(),
+		tests: proj.test_files().count(),
+		compiler: Backend {
+			configured: config.compiler.as_ref().and_then(|c| c.default.clone()),
+			selected: crate::compiler::try_locate(Some(proj))
+				.ok()
+				.map(|b| b.driver().join(" ")),
+		},
+		formatter: Backend {
+			configured: config.formatter.as_ref().and_then(|f| f.default.clone()),
+			selected: crate::format::selected(proj).ok().flatten().map(str::to_owned),
+		},
+		docgen: Backend {
+			configured: config.docgen.as_ref().and_then(|d| d.default.clone()),
+			selected: crate::docgen::selected(proj).ok().flatten().map(str::to_owned),
+		},
+		dependencies,
+		target_size: crate::Project::dir_size(&proj.target()),
+	})
+}
@@ -0,0 +1,54 @@
+//! The project model behind the cpkg binary, for tools that want to open, build or test cpkg projects themselves.
+//!
+//! Start from [Project::open] or [Project::create], and find a compiler with [compiler::try_locate].
+//! Nothing here prints once [log::set_handler] is given somewhere to send what it would have.
+
+pub mod components;
+pub use components::*;
+
+pub mod config;
+pub use config::*;
+
+pub mod license;
+
+pub mod log;
+
+pub mod project;
+pub use project::*;
+
+pub mod target;
+
+pub mod template;
+
+pub mod validate;
+
+pub mod workspace;
+
+/* Used by the binary, but not meant for other tools */
+
+#[doc(hidden)]
+pub mod assets;
+
+#[doc(hidden)]
+pub mod generated;
+
+#[doc(hidden)]
+pub mod harness;
+
+#[doc(hidden)]
+pub mod includes;
+
+#[doc(hidden)]
+pub mod lockfile;
+
+#[doc(hidden)]
+pub mod net;
+
+#[doc(hidden)]
+pub mod progress;
+
+#[doc(hidden)]
+pub mod quote;
+
+#[doc(hidden)]
+pub mod scripts;
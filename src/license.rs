@@ -0,0 +1,63 @@
+/// License `cpkg new` can write for a project, with the text built into cpkg so it works offline.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum License {
+	#[value(name = "mit")]
+	#[serde(rename = "mit")]
+	Mit,
+	#[value(name = "apache-2.0")]
+	#[serde(rename = "apache-2.0")]
+	Apache2,
+	#[value(name = "gpl-3.0")]
+	#[serde(rename = "gpl-3.0")]
+	Gpl3,
+	/// Leaves out the LICENSE file
+	#[default]
+	#[value(name = "none")]
+	#[serde(rename = "none")]
+	None,
+}
+
+impl License {
+	/// Every choice, in the order they're offered.
+	pub const ALL: &'static [Self] = &[Self::Mit, Self::Apache2, Self::Gpl3, Self::None];
+
+	/// SPDX identifier recorded as `license` under [package], or [None] for no license.
+	pub fn spdx(self) -> Option<&'static str> {
+		match self {
+			Self::Mit => Some("MIT"),
+			Self::Apache2 => Some("Apache-2.0"),
+			Self::Gpl3 => Some("GPL-3.0-or-later"),
+			Self::None => None,
+		}
+	}
+
+	/// Text of the LICENSE file, with {{year}}, {{author}} and {{name}} placeholders.
+	pub fn text(self) -> Option<&'static str> {
+		match self {
+			Self::Mit => Some(include_str!("assets/licenses/MIT")),
+			Self::Apache2 => Some(include_str!("assets/licenses/Apache-2.0")),
+			Self::Gpl3 => Some(include_str!("assets/licenses/GPL-3.0")),
+			Self::None => None,
+		}
+	}
+}
+
+impl std::fmt::Display for License {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		use clap::ValueEnum;
+		write!(f, "{}", self.to_possible_value().unwrap().get_name())
+	}
+}
+
+impl std::str::FromStr for License {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> anyhow::Result<Self> {
+		<Self as clap::ValueEnum>::from_str(s, true).map_err(|_| {
+			anyhow::anyhow!(
+				"Unknown license '{s}', expected one of {}",
+				Self::ALL.iter().map(Self::to_string).collect::<Vec<_>>().join(", ")
+			)
+		})
+	}
+}
@@ -0,0 +1,86 @@
+/// Commits that git dependencies were installed at, so every later install checks out the same ones.
+/// Lives beside cpkg.toml as cpkg.lock, and is meant to be committed.
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct Lockfile {
+	#[serde(default)]
+	pub dependencies: std::collections::BTreeMap<String, Locked>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct Locked {
+	/// Url the commit was fetched from, so changing it in cpkg.toml drops the pin
+	pub git: String,
+	pub commit: String,
+}
+
+impl Lockfile {
+	pub const FILE: &'static str = "cpkg.lock";
+
+	/// Reads the lockfile in a folder, or an empty one if there isn't one yet.
+	pub fn load(dir: &std::path::Path) -> anyhow::Result<Self> {
+		let path = dir.join(Self::FILE);
+
+		match std::fs::read_to_string(&path) {
+			Ok(contents) => toml::from_str(&contents)
+				.map_err(|e| anyhow::anyhow!("Failed to parse {}: {e}", path.display())),
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	pub fn save(&self, dir: &std::path::Path) -> anyhow::Result<()> {
+		std::fs::write(dir.join(Self::FILE), toml::to_string(self)?)?;
+		Ok(())
+	}
+
+	/// Commit a dependency is pinned to, if it was locked from the same url.
+	pub fn pinned(&self, name: &str, git: &str) -> Option<&str> {
+		self.dependencies
+			.get(name)
+			.filter(|l| l.git == git)
+			.map(|l| l.commit.as_str())
+	}
+}
+
+/// Commit a git checkout is at.
+pub fn head(dir: &std::path::Path) -> anyhow::Result<String> {
+	let mut cmd = std::process::Command::new("git");
+	cmd.arg("-C").arg(dir).args(["rev-parse", "HEAD"]);
+
+	crate::log::command(&cmd);
+	let out = cmd.output()?;
+
+	if !out.status.success() {
+		anyhow::bail!(
+			"Couldn't read the commit of {}: {}",
+			dir.display(),
+			String::from_utf8_lossy(&out.stderr).trim()
+		);
+	}
+
+	Ok(String::from_utf8_lossy(&out.stdout).trim().to_owned())
+}
+
+/// Moves a git checkout to a pinned commit.
+pub fn checkout(dir: &std::path::Path, commit: &str) -> anyhow::Result<()> {
+	let mut cmd = std::process::Command::new("git");
+	cmd.arg("-C").arg(dir).args(["checkout", "--quiet", commit]);
+
+	crate::log::command(&cmd);
+	let out = cmd.output()?;
+
+	if !out.status.success() {
+		anyhow::bail!(
+			"Couldn't check out locked commit {commit} in {}: {}",
+			dir.display(),
+			String::from_utf8_lossy(&out.stderr).trim()
+		);
+	}
+
+	Ok(())
+}
+
+/// Abbreviated commit, for messages.
+pub fn short(commit: &str) -> &str {
+	&commit[..commit.len().min(7)]
+}
@@ -0,0 +1,187 @@
+use colored::Colorize;
+
+/// How much cpkg prints, set once from `--quiet` or `--verbose`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+	/// Only errors and warnings
+	Quiet,
+	Normal,
+	/// Also every external command before it runs
+	Verbose,
+}
+
+static LEVEL: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(Level::Normal as u8);
+
+pub fn set(level: Level) {
+	LEVEL.store(level as u8, std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn level() -> Level {
+	match LEVEL.load(std::sync::atomic::Ordering::Relaxed) {
+		0 => Level::Quiet,
+		1 => Level::Normal,
+		_ => Level::Verbose,
+	}
+}
+
+/// Whether output is colored, set once from `--color`.
+#[derive(Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Color {
+	/// When printing to a terminal, and NO_COLOR isn't set
+	#[default]
+	Auto,
+	Always,
+	Never,
+}
+
+static COLOR: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(Color::Auto as u8);
+
+/// Applies `--color` to cpkg's own output, and to the tools it runs through [color].
+pub fn set_color(color: Color) {
+	COLOR.store(color as u8, std::sync::atomic::Ordering::Relaxed);
+
+	match color {
+		Color::Auto => colored::control::unset_override(),
+		Color::Always => colored::control::set_override(true),
+		Color::Never => colored::control::set_override(false),
+	}
+}
+
+/// Whether tools whose output cpkg captures, like compilers, should be told to color it anyway.
+/// Their output ends up on stderr, so that's what `auto` goes by.
+pub fn color() -> bool {
+	use std::io::IsTerminal;
+
+	match COLOR.load(std::sync::atomic::Ordering::Relaxed) {
+		0 => std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+		1 => true,
+		_ => false,
+	}
+}
+
+/// Something cpkg reports as it works, which the binary prints, see [set_handler].
+pub enum Record<'a> {
+	/// Status, like a dependency being installed. Printed to stdout unless `--quiet`
+	Info(&'a str),
+	/// Something likely wrong that didn't stop the command. Always printed to stderr
+	Warning(&'a str),
+	/// Only worth knowing when looking into a problem. Printed to stderr with `--verbose`
+	Detail(&'a str),
+	/// Program and arguments of a command about to run. Printed to stderr with `--verbose`
+	Command(&'a [String]),
+	/// Line a compiler or test printed as it ran, and where it printed it
+	Output(Stream, &'a str),
+	/// Problem `cpkg validate` checks for, also found in cpkg.toml whenever a project is opened. Printed to stderr
+	Finding(&'a crate::validate::Finding),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stream {
+	Stdout,
+	Stderr,
+}
+
+type Handler = Box<dyn Fn(&Record) + Send + Sync>;
+
+static HANDLER: std::sync::OnceLock<Handler> = std::sync::OnceLock::new();
+
+/// Hands every [Record] to `handler` instead of printing it, for the rest of the process.
+/// The level doesn't apply, so it sees all of them. Messages are colored like the binary's unless [set_color] says never.
+/// Returns false if a handler was already set, in which case that one stays.
+pub fn set_handler(handler: impl Fn(&Record) + Send + Sync + 'static) -> bool {
+	HANDLER.set(Box::new(handler)).is_ok()
+}
+
+/// Whether a handler takes records, so nothing should be printed directly.
+pub fn handled() -> bool {
+	HANDLER.get().is_some()
+}
+
+/// Gives `record` to the handler, returning false if there isn't one and it's up to the caller to print it.
+pub(crate) fn dispatch(record: &Record) -> bool {
+	match HANDLER.get() {
+		Some(handler) => {
+			handler(record);
+			true
+		}
+		None => false,
+	}
+}
+
+/// Prints a status message to stdout, unless running with `--quiet`.
+#[macro_export]
+macro_rules! info {
+	($($arg:tt)*) => {
+		$crate::log::status(&format!($($arg)*))
+	};
+}
+
+pub use crate::info;
+
+/// Prints a status message to stdout, unless running with `--quiet`. Prefer [info!].
+pub fn status(message: &str) {
+	if dispatch(&Record::Info(message)) || level() < Level::Normal {
+		return;
+	}
+
+	crate::progress::interrupt();
+	println!("{message}");
+}
+
+/// Prints a warning to stderr, even with `--quiet`.
+pub fn warn(message: impl std::fmt::Display) {
+	let message = message.to_string();
+
+	if dispatch(&Record::Warning(&message)) {
+		return;
+	}
+
+	crate::progress::interrupt();
+	eprintln!("{} {message}", "warning:".yellow());
+}
+
+/// Prints something only worth knowing when looking into a problem, like a file that couldn't be read,
+/// to stderr when running with `--verbose`.
+pub fn detail(message: impl std::fmt::Display) {
+	let message = message.to_string();
+
+	if dispatch(&Record::Detail(&message)) || level() < Level::Verbose {
+		return;
+	}
+
+	crate::progress::interrupt();
+	eprintln!("{}", message.dimmed());
+}
+
+/// Prints a command to stderr before it runs, when running with `--verbose`.
+pub fn command(cmd: &std::process::Command) {
+	if !handled() && level() < Level::Verbose {
+		return;
+	}
+
+	let argv = std::iter::once(cmd.get_program())
+		.chain(cmd.get_args())
+		.map(|a| a.to_string_lossy().into_owned())
+		.collect::<Vec<_>>();
+
+	if dispatch(&Record::Command(&argv)) {
+		return;
+	}
+
+	crate::progress::interrupt();
+	eprintln!("{} {}", "$".dimmed(), crate::quote::command(&argv).dimmed());
+}
+
+/// Prints a line a compiler or test printed, to the same `stream`.
+pub fn output(stream: Stream, line: &str) {
+	if dispatch(&Record::Output(stream, line)) {
+		return;
+	}
+
+	crate::progress::interrupt();
+
+	match stream {
+		Stream::Stdout => println!("{line}"),
+		Stream::Stderr => eprintln!("{line}"),
+	}
+}
@@ -1,334 +1,2703 @@
 use colored::Colorize;
 
+use cpkg::*;
+
+mod assertion;
 mod cli;
 
-mod components;
-use components::*;
+mod hooks;
+
+mod import;
+
+mod index;
+
+mod info;
+
+mod messages;
+
+mod package;
+
+mod repl;
+
+mod serve;
+
+mod snapshot;
+
+mod table;
+
+mod upgrade;
+
+#[cfg(test)]
+#[path = "project/testing.rs"]
+mod testing;
+
+fn human_size(bytes: u64) -> String {
+	const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB"];
+
+	let mut size = bytes as f64;
+	let mut unit = 0;
+
+	while size >= 1024.0 && unit < UNITS.len() - 1 {
+		size /= 1024.0;
+		unit += 1;
+	}
+
+	if unit == 0 {
+		format!("{bytes} B")
+	} else {
+		format!("{size:.1} {}", UNITS[unit])
+	}
+}
+
+/// Prunes stale vendored dependencies, printing disk usage before and after.
+fn prune_deps(proj: &Project) -> anyhow::Result<()> {
+	let entries = proj.prune_deps()?;
+
+	let before = entries.iter().map(|e| e.size).sum::<u64>();
+	let after = entries
+		.iter()
+		.filter(|e| e.status != VendorStatus::Stale)
+		.map(|e| e.size)
+		.sum::<u64>();
+
+	log::info!("Vendor usage before pruning: {}", human_size(before).yellow());
+
+	let mut listing = table::Table::new(3)
+		.header(&["Dependency", "Size", "Status"])
+		.indent(2)
+		.align(1, table::Align::Right)
+		.shrink(0);
+
+	for entry in &entries {
+		let size = if entry.linked {
+			"symlink".to_owned()
+		} else {
+			human_size(entry.size)
+		};
+
+		let status = match entry.status {
+			VendorStatus::Declared => table::Cell::from(""),
+			VendorStatus::Stale => table::Cell::styled("(pruned)", |s| s.red()),
+			VendorStatus::Unknown => table::Cell::styled("(unknown, not removed)", |s| s.yellow()),
+		};
+
+		listing.row([
+			entry.name.as_str().into(),
+			table::Cell::styled(size, |s| s.dimmed()),
+			status,
+		]);
+	}
+
+	listing.print();
+
+	log::info!("Vendor usage after pruning: {}", human_size(after).yellow());
+
+	Ok(())
+}
+
+fn print_plan(proj: &Project, plan: &BuildPlan) {
+	let rel = |p: &std::path::Path| p.strip_prefix(proj.path()).unwrap_or(p).display().to_string();
+
+	println!("Build plan for {}:", proj.name().yellow());
+
+	if let Some(ref script) = plan.build_script {
+		println!(
+			"  {} {} (may change the source directory)",
+			"Run build script".cyan(),
+			rel(script)
+		);
+	}
+
+	for (path, _) in &plan.generated {
+		println!("  {} {}", "Generate".cyan(), rel(path));
+	}
+
+	for name in &plan.missing {
+		println!(
+			"  {} {name} (not installed, run {})",
+			"Missing dependency".red(),
+			"cpkg install".yellow()
+		);
+	}
+
+	/* Fresh outputs are still listed, so the plan shows everything a clean build would do */
+	let skipped = |fresh: bool| if fresh { " (fresh, skipped)".dimmed().to_string() } else { String::new() };
+
+	for invocation in &plan.invocations {
+		println!(
+			"  {} {}{}",
+			invocation.step().to_string().cyan(),
+			rel(&invocation.output),
+			skipped(invocation.fresh)
+		);
+
+		for source in &invocation.sources {
+			println!("    {}", rel(source));
+		}
+
+		println!("    {} {}", "$".dimmed(), quote::command(&invocation.argv).dimmed());
+	}
+
+	for archive in &plan.archives {
+		println!("  {} {}{}", "Archive".cyan(), rel(&archive.output), skipped(archive.fresh));
+		println!("    {} {}", "$".dimmed(), quote::command(&archive.argv).dimmed());
+	}
+}
+
+/// Installs the dependencies of the package in `dir`, and writes the shims including them.
+fn install_package(dir: &std::path::Path, prune: bool, force: bool) -> anyhow::Result<()> {
+	let proj = Project::open(dir)?;
+
+	if proj.snapshot().is_some() {
+		let problems = snapshot::problems(&proj);
+
+		if problems.is_empty() {
+			log::info!(
+				"Dependencies are vendored in {}, nothing to install.",
+				snapshot::DIR.yellow()
+			);
+			return Ok(());
+		}
+
+		log::warn(format!(
+			"The vendor snapshot is out of date, installing to target/vendor instead. Run {} to update it.",
+			"cpkg vendor".yellow()
+		));
+	}
+
+	let now = std::time::Instant::now();
+
+	proj.install_deps(force)?;
+	let shims = proj.write_shims()?;
+
+	log::info!(
+		"Installed {} dependencies in {} seconds.",
+		proj.config().dependencies.entries.len().to_string().yellow(),
+		now.elapsed().as_secs_f32().to_string().yellow()
+	);
+
+	let mut includes = table::Table::new(2).indent(2);
+
+	for (name, shim) in &shims {
+		match shim {
+			Shim::Written(header) => includes.row([
+				format!("#include <{name}.h>").into(),
+				table::Cell::styled(format!("(includes {header})"), |s| s.dimmed()),
+			]),
+			Shim::NoHeader => {}
+			Shim::Ambiguous(n) => log::warn(format!(
+				"{name} has {n} headers at its top level, so set {} on it in cpkg.toml to include it as <{name}.h>.",
+				"entry = \"<header>\"".yellow()
+			)),
+		}
+	}
+
+	if log::level() >= log::Level::Normal && shims.iter().any(|(_, s)| matches!(s, Shim::Written(_))) {
+		println!("Include dependencies with:");
+		includes.print();
+	}
+
+	if prune || proj.config().dependencies.auto_prune {
+		prune_deps(&proj)?;
+	}
+
+	Ok(())
+}
+
+/// Prints dependencies under `title` as a tree, with the commit each git dependency is locked to.
+/// With `members`, each is followed by the workspace members using it, and sources they disagree on are listed apart.
+fn print_tree(
+	title: &str,
+	lock_dir: &std::path::Path,
+	deps: &std::collections::BTreeMap<String, Vec<workspace::Requirement>>,
+	members: bool,
+) -> anyhow::Result<()> {
+	let lock = lockfile::Lockfile::load(lock_dir)?;
+
+	println!("{}", title.bold());
+
+	if deps.is_empty() {
+		println!("{}", "(no dependencies)".dimmed());
+		return Ok(());
+	}
+
+	let width = deps.keys().map(|n| n.chars().count()).max().unwrap_or(0);
+
+	let describe = |name: &str, source: &str, users: &[&str]| {
+		let pinned = lock
+			.pinned(name, source)
+			.map(|commit| format!(" @ {}", lockfile::short(commit)))
+			.unwrap_or_default();
+
+		let users = match members {
+			true => format!(" ({})", users.join(", ")).dimmed().to_string(),
+			false => String::new(),
+		};
+
+		format!("{}{}{users}", source.dimmed(), pinned.yellow())
+	};
+
+	for (i, (name, requirements)) in deps.iter().enumerate() {
+		let last = i + 1 == deps.len();
+		let (branch, rest) = if last { ("└──", "    ") } else { ("├──", "│   ") };
+
+		/* Members using the same source are listed together */
+		let mut sources = Vec::<(&str, Vec<&str>)>::new();
+		for r in requirements {
+			match sources.iter_mut().find(|(s, _)| *s == r.source) {
+				Some((_, users)) => users.push(&r.member),
+				None => sources.push((&r.source, vec![&r.member])),
+			}
+		}
+
+		if let [(source, users)] = sources.as_slice() {
+			println!("{branch} {name:<width$}  {}", describe(name, source, users));
+			continue;
+		}
+
+		println!("{branch} {name:<width$}  {}", "conflicting sources".red());
+
+		for (j, (source, users)) in sources.iter().enumerate() {
+			let branch = if j + 1 == sources.len() { "└──" } else { "├──" };
+			println!("{rest}{branch} {}", describe(name, source, users));
+		}
+	}
+
+	Ok(())
+}
+
+/// Opens the project in `cd`, building for a cross target if one was given.
+fn open_for<'a>(cd: &'a std::path::Path, target: Option<&str>) -> anyhow::Result<Project<'a>> {
+	let proj = Project::open(cd)?;
+
+	match target {
+		Some(target) => proj.with_cross(target),
+		None => Ok(proj),
+	}
+}
+
+/// Finishes a command running with `--message-format json`, given whether it succeeded.
+/// Compile errors are emitted as diagnostics, anything else is reported normally.
+fn emit_outcome(outcome: anyhow::Result<bool>) -> anyhow::Result<()> {
+	match outcome {
+		Ok(success) => {
+			messages::emit(&messages::Message::Finished { success });
+
+			if !success {
+				std::process::exit(1);
+			}
+
+			Ok(())
+		}
+		Err(e) => {
+			let compiling = messages::emit_error(&e);
+			messages::emit(&messages::Message::Finished { success: false });
+
+			if !compiling {
+				return Err(e);
+			}
+
+			std::process::exit(1);
+		}
+	}
+}
+
+/// Folders of the packages a command applies to: the one selected with --package,
+/// every member when at the root of a workspace, or otherwise the cwd.
+fn package_dirs(cd: &std::path::Path, package: Option<&str>) -> anyhow::Result<Vec<std::path::PathBuf>> {
+	let root = workspace::Workspace::open(cd)?;
+
+	match package {
+		Some(package) => {
+			let Some(ws) = root.or_else(|| workspace::Workspace::containing(cd)) else {
+				anyhow::bail!("--package can only be used inside of a workspace.");
+			};
+
+			Ok(vec![ws.member(package)?.to_owned()])
+		}
+		None => match root {
+			Some(ws) => Ok(ws.members().to_vec()),
+			None => Ok(vec![cd.to_owned()]),
+		},
+	}
+}
+
+/// Serves the folder of an emscripten page over HTTP and opens it in the browser,
+/// since browsers won't load its .wasm from a file url.
+fn serve_page(page: &std::path::Path) -> anyhow::Result<()> {
+	let listener = serve::bind(0)?;
+	let url = format!(
+		"http://{}/{}",
+		listener.local_addr()?,
+		page.file_name().unwrap().to_string_lossy()
+	);
+
+	let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+	let handler = stop.clone();
+	ctrlc::set_handler(move || handler.store(true, std::sync::atomic::Ordering::Relaxed))?;
+
+	let root = page.parent().unwrap().to_owned();
+	let server = {
+		let stop = stop.clone();
+		std::thread::spawn(move || serve::serve(listener, root, stop))
+	};
+
+	log::info!("Serving {}", url.yellow());
+	log::info!("Press Ctrl-C to stop.");
+
+	if let Err(e) = docgen::start_program(std::path::Path::new(&url)) {
+		log::warn(format!("Couldn't open a browser ({e}), visit the url above instead."));
+	}
+
+	server
+		.join()
+		.map_err(|_| anyhow::anyhow!("Server thread panicked"))??;
+
+	Ok(())
+}
+
+/// Folder install-bin copies binaries into, `<root>/bin`.
+fn install_dir(root: &Option<std::path::PathBuf>) -> anyhow::Result<std::path::PathBuf> {
+	if let Some(root) = root {
+		return Ok(root.join("bin"));
+	}
+
+	#[cfg(target_os = "windows")]
+	let root = std::env::var_os("USERPROFILE").map(|h| std::path::Path::new(&h).join(".cpkg"));
+
+	#[cfg(not(target_os = "windows"))]
+	let root = std::env::var_os("HOME").map(|h| std::path::Path::new(&h).join(".local"));
+
+	root.map(|r| r.join("bin"))
+		.ok_or_else(|| anyhow::anyhow!("Couldn't find your home directory, pass --root instead."))
+}
+
+/// Prints how long each step of a build took, slowest first, then the time spent on each kind of step.
+/// Also writes them to `file` as JSON, with paths relative to `root`.
+fn report_timings(
+	root: &std::path::Path,
+	timed: &[Timing],
+	total: std::time::Duration,
+	mode: Timings,
+	file: &std::path::Path,
+) -> anyhow::Result<()> {
+	let rel = |p: &std::path::Path| p.strip_prefix(root).unwrap_or(p).display().to_string();
+
+	let mut timed = timed.iter().collect::<Vec<_>>();
+	timed.sort_by_key(|t| std::cmp::Reverse(t.duration));
+
+	let steps = timed
+		.iter()
+		.map(|t| {
+			serde_json::json!({
+				"step": t.step,
+				"output": rel(&t.output),
+				"sources": t.sources.iter().map(|s| rel(s)).collect::<Vec<_>>(),
+				"seconds": t.duration.as_secs_f64(),
+				"report": t.report,
+			})
+		})
+		.collect::<Vec<_>>();
+
+	let json = serde_json::json!({
+		"total": total.as_secs_f64(),
+		"steps": steps,
+	});
+
+	std::fs::write(file, serde_json::to_string_pretty(&json)?)?;
+
+	if log::level() < log::Level::Normal {
+		return Ok(());
+	}
+
+	if timed.is_empty() {
+		println!("Nothing was compiled, so there are no timings.");
+		return Ok(());
+	}
+
+	let mut table = table::Table::new(3)
+		.header(&["Time", "Step", "Output"])
+		.align(0, table::Align::Right)
+		.shrink(2);
+
+	for t in &timed {
+		table.row([
+			format!("{:.2}s", t.duration.as_secs_f32()).into(),
+			table::Cell::styled(t.step.to_string(), |s| s.cyan()),
+			rel(&t.output).into(),
+		]);
+	}
+
+	println!();
+	table.print();
+
+	if mode == Timings::Verbose {
+		for t in timed.iter().filter(|t| t.report.as_deref().is_some_and(|r| !r.trim().is_empty())) {
+			println!();
+			println!("{}", rel(&t.output).bold());
+			println!("{}", t.report.as_deref().unwrap_or_default().trim_end().dimmed());
+		}
+	}
+
+	let mut by_step = std::collections::BTreeMap::new();
+
+	for t in &timed {
+		*by_step.entry(t.step.to_string()).or_insert(std::time::Duration::ZERO) += t.duration;
+	}
+
+	let by_step = by_step
+		.iter()
+		.map(|(step, time)| format!("{} {:.2}s", step.to_lowercase(), time.as_secs_f32()))
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	println!();
+	println!(
+		"{by_step}, {:.2}s in total. Written to {}.",
+		total.as_secs_f32(),
+		rel(file).yellow()
+	);
+
+	Ok(())
+}
+
+/// Writes a config file for another tool to the project root, leaving any existing one alone unless forced.
+fn generate_file(proj: &Project, file: &str, contents: &str, force: bool) -> anyhow::Result<()> {
+	let path = proj.path().join(file);
+
+	if path.exists() && !force {
+		anyhow::bail!("{file} already exists. Pass --force to overwrite it.");
+	}
+
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent)?;
+	}
+
+	std::fs::write(path, contents)?;
+	log::info!("Generated {file}.");
+
+	Ok(())
+}
+
+/// What `cpkg run` was asked to run.
+enum Runnable {
+	/// Script under [scripts] in cpkg.toml
+	Script(String),
+	/// Standalone C file, with more files to compile along with it
+	File(std::path::PathBuf, Vec<std::path::PathBuf>),
+	/// The project's own binary
+	Project,
+}
+
+/// Works out whether `cpkg run` was given a script or a file.
+/// A bare name that's both a script and an existing file is an error, rather than a guess.
+fn resolve_run(
+	proj: Option<&Project>,
+	path: &Option<String>,
+	files: &[std::path::PathBuf],
+	script: &Option<String>,
+	file: &Option<std::path::PathBuf>,
+) -> anyhow::Result<Runnable> {
+	let scripts = proj.map(|p| &p.config().scripts.entries);
+
+	if let Some(script) = script {
+		if !files.is_empty() {
+			anyhow::bail!("Extra files can only be compiled along with a file, not a script.");
+		}
+
+		let Some(scripts) = scripts else {
+			anyhow::bail!("Scripts are read from cpkg.toml, but there's no project here.");
+		};
+
+		if !scripts.contains_key(script) {
+			let known = scripts.keys().map(String::as_str).collect::<Vec<_>>();
+
+			if known.is_empty() {
+				anyhow::bail!("No script named '{script}', cpkg.toml has no scripts.");
+			}
+
+			anyhow::bail!(
+				"No script named '{script}' in cpkg.toml. Scripts are: {}",
+				known.join(", ")
+			);
+		}
+
+		return Ok(Runnable::Script(script.clone()));
+	}
+
+	if let Some(file) = file {
+		/* With --file, every positional argument is another file to compile along with it */
+		let extra = path.iter().map(std::path::PathBuf::from).chain(files.iter().cloned());
+		return Ok(Runnable::File(file.clone(), extra.collect()));
+	}
+
+	let Some(path) = path else {
+		return Ok(Runnable::Project);
+	};
+
+	let is_script = scripts.is_some_and(|s| s.contains_key(path));
+	let is_file = std::path::Path::new(path).exists();
+
+	if is_script && is_file {
+		anyhow::bail!(
+			"'{path}' is both a script in cpkg.toml and a file. Run `cpkg run --script {path}` for the script, or `cpkg run --file {path}` to compile the file."
+		);
+	}
+
+	if is_script {
+		if !files.is_empty() {
+			anyhow::bail!("Extra files can only be compiled along with a file, not a script.");
+		}
+
+		return Ok(Runnable::Script(path.clone()));
+	}
+
+	Ok(Runnable::File(path.into(), files.to_vec()))
+}
+
+fn build_script_check() -> bool {
+	println!("This project needs a build script to run. Accept? (y/n)");
+
+	let mut s = String::new();
+	std::io::stdin().read_line(&mut s).unwrap();
+
+	s.find("y").is_some()
+}
+
+/// License for a new project: the one passed, else the user config's, else asked for on a terminal.
+fn choose_license(flag: Option<license::License>) -> anyhow::Result<license::License> {
+	use std::io::IsTerminal;
+
+	if let Some(license) = flag {
+		return Ok(license);
+	}
+
+	if let Some(license) = GlobalConfig::load()?.license {
+		return Ok(license);
+	}
+
+	if !std::io::stdin().is_terminal() {
+		return Ok(license::License::None);
+	}
+
+	let choices = license::License::ALL
+		.iter()
+		.map(|l| l.to_string())
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	loop {
+		print!("License ({choices}) [none]: ");
+		std::io::Write::flush(&mut std::io::stdout())?;
+
+		let mut s = String::new();
+		std::io::stdin().read_line(&mut s)?;
+
+		if s.trim().is_empty() {
+			return Ok(license::License::None);
+		}
+
+		match s.trim().parse() {
+			Ok(license) => return Ok(license),
+			Err(e) => eprintln!("{e}"),
+		}
+	}
+}
+
+fn main() -> anyhow::Result<()> {
+	let args: cli::Cli = clap::Parser::parse();
+
+	if args.quiet {
+		log::set(log::Level::Quiet);
+	} else if args.verbose {
+		log::set(log::Level::Verbose);
+	}
+
+	if args.ignore_env {
+		compiler::ignore_env();
+	}
+
+	if args.strict_backend {
+		probe::strict_backends();
+	}
+
+	log::set_color(args.color);
+
+	let cd = std::env::current_dir()?;
+
+	run(&args, cd).map_err(|e| match e.downcast_ref::<probe::LocateError>() {
+		Some(missing) => anyhow::anyhow!(explain_missing(missing)),
+		None => e,
+	})
+}
+
+/// Spells out how to get a tool that couldn't be found, since there are no diagnostics to show for it.
+fn explain_missing(error: &probe::LocateError) -> String {
+	let mut out = error.to_string();
+
+	match error {
+		probe::LocateError::NotInstalled { tool, .. } => {
+			if let Some(install) = tool.install_hint() {
+				out.push_str(&format!("\nInstall one with: {}", install.bold()));
+			}
+
+			if *tool == probe::Tool::Compiler {
+				out.push_str("\nCompilers are picked from the --target's compiler, then CC, then `default` under [compiler] in cpkg.toml, then the first of those found.");
+			} else {
+				out.push_str(&format!(
+					"\n`default` under [{}] in cpkg.toml picks which one is used.",
+					tool.section()
+				));
+			}
+		}
+		probe::LocateError::NotFound { from, .. } if from == "CC" => {
+			out.push_str("\nPass --ignore-env to probe for one instead of using CC.");
+		}
+		probe::LocateError::NotFound { .. } => {}
+		probe::LocateError::Unrecognized { tool, .. } => {
+			out.push_str(&format!(
+				"\nSet `default` under [{}] in cpkg.toml to one of the backends listed by cpkg doctor.",
+				tool.section()
+			));
+		}
+		probe::LocateError::NoFallback { tool, .. } => {
+			out.push_str(&format!(
+				"\nInstall it, or allow falling back by leaving out --strict-backend and `strict` under [{}] in cpkg.toml.",
+				tool.section()
+			));
+		}
+	}
+
+	out
+}
+
+fn run(args: &cli::Cli, cd: std::path::PathBuf) -> anyhow::Result<()> {
+	match &args.command {
+		cli::Commands::New {
+			name,
+			package,
+			no_git,
+			defines,
+			template,
+			license,
+			metadata,
+		} => {
+			let options = InitOptions {
+				defines: defines.clone(),
+				template: template.clone(),
+				name: package.clone(),
+				no_git: *no_git,
+				license: choose_license(*license)?,
+				description: metadata.description.clone(),
+				authors: metadata.authors.clone(),
+				repository: metadata.repository.clone(),
+				homepage: metadata.homepage.clone(),
+				keywords: metadata.keywords.clone(),
+			};
+
+			Project::create(name.as_ref(), &options)?;
+		}
+
+		cli::Commands::Init {
+			name,
+			no_git,
+			defines,
+			template,
+			license,
+			metadata,
+		} => {
+			let options = InitOptions {
+				defines: defines.clone(),
+				template: template.clone(),
+				name: name.clone(),
+				no_git: *no_git,
+				license: choose_license(*license)?,
+				description: metadata.description.clone(),
+				authors: metadata.authors.clone(),
+				repository: metadata.repository.clone(),
+				homepage: metadata.homepage.clone(),
+				keywords: metadata.keywords.clone(),
+			};
+
+			Project::init(&cd, &options)?;
+		}
+
+		cli::Commands::Import { dry_run } => {
+			let config = cd.join("cpkg.toml");
+
+			if config.exists() {
+				anyhow::bail!("Cannot import a project that already has a cpkg.toml.");
+			}
+
+			let import = import::Import::inspect(&cd)?;
+			let rendered = import.render()?;
+
+			if *dry_run {
+				print!("{rendered}");
+				return Ok(());
+			}
+
+			/* Never overwrite anything, even if a cpkg.toml appeared in the meantime */
+			use std::io::Write;
+			std::fs::OpenOptions::new()
+				.write(true)
+				.create_new(true)
+				.open(&config)?
+				.write_all(rendered.as_bytes())?;
+
+			let gitignore = cd.join(".gitignore");
+			if !gitignore.exists() {
+				std::fs::write(gitignore, "/target\n")?;
+			}
+
+			log::info!("Created {}.", "cpkg.toml".yellow());
+
+			if !import.todos.is_empty() {
+				log::info!(
+					"{} item(s) couldn't be translated, see the TODO comments in cpkg.toml.",
+					import.todos.len().to_string().yellow()
+				);
+			}
+		}
+
+		cli::Commands::Test {
+			filter,
+			print,
+			package,
+			target,
+			raw_errors,
+			no_cache,
+			all,
+			profile,
+			message_format,
+			debug,
+		} => {
+			if *debug {
+				/* Like `cpkg run`, only one member can be started */
+				let dir = match package {
+					Some(package) => package_dirs(&cd, Some(package))?.remove(0),
+					None => cd.clone(),
+				};
+
+				let proj = Project::open(&dir)?
+					.with_raw_errors(*raw_errors)
+					.with_profile(profile.get())
+					.with_debug_info(true);
+
+				let debugger = debugger::try_locate(Some(&proj))?;
+				let backend = compiler::try_locate(Some(&proj))?;
+
+				let filter = filter.as_deref().unwrap_or_default();
+				let (binary, args) = proj.debug_test(backend.as_ref(), filter)?;
+
+				let mut cmd = debugger.command(&binary, &args);
+				log::command(&cmd);
+
+				let status = cmd.status()?;
+				std::process::exit(status.code().unwrap_or(1));
+			}
+
+			let dirs = package_dirs(&cd, package.as_deref())?;
+
+			let projects = dirs
+				.iter()
+				.map(|dir| {
+					open_for(dir, target.as_deref())
+						.map(|p| p.with_raw_errors(*raw_errors).with_profile(profile.get()))
+				})
+				.collect::<anyhow::Result<Vec<_>>>()?;
+
+			if projects.iter().any(|p| p.cross().is_some_and(|c| c.is_emscripten())) {
+				log::warn("Running tests isn't supported for emscripten targets yet, skipping them.");
+
+				return Ok(());
+			}
+
+			let now = std::time::Instant::now();
+
+			let mut results = vec![];
+			let mut builds = vec![];
+
+			let outcome = projects.iter().try_for_each(|proj| {
+				let backend = compiler::try_locate(Some(proj))?;
+
+				/* A failed build is reported alongside the tests rather than stopping them */
+				if *all {
+					let targets = target::defaults(proj)?
+						.into_iter()
+						.filter(|t| *t != target::Target::Tests)
+						.collect::<Vec<_>>();
+
+					let built = proj.build(backend.as_ref(), &targets, build_script_check);
+					builds.push((proj, built.map(|_| ())));
+				}
+
+				let ran = proj.run_tests(backend.as_ref(), *print, !*no_cache, filter.as_deref())?;
+
+				results.extend(ran.into_iter().map(|r| (proj, r)));
+				anyhow::Ok(())
+			});
+
+			if *message_format == cli::MessageFormat::Json {
+				return emit_outcome(outcome.and_then(|_| {
+					for (_, built) in &builds {
+						if let Err(e) = built {
+							if !messages::emit_error(e) {
+								anyhow::bail!("{e}");
+							}
+						}
+					}
+
+					for (_, result) in &results {
+						messages::emit(&messages::Message::TestResult {
+							path: &result.path,
+							case: result.case.as_deref(),
+							passed: !result.status.failed(),
+							status: result.status,
+							skip_reason: result.reason.as_deref(),
+							cached: result.cached,
+							duration: result.duration.as_secs_f64(),
+							stderr: result.stderr.as_deref(),
+							binary: result.binary.as_deref(),
+						});
+					}
+
+					Ok(results.iter().all(|(_, r)| !r.status.failed())
+						&& builds.iter().all(|(_, b)| b.is_ok()))
+				}));
+			}
+
+			outcome?;
+
+			/* Relative to the workspace root when testing several members, so they can be told apart */
+			let shown = |result: &TestResult| {
+				let path = result.path.strip_prefix(&cd).unwrap_or(&result.path).display();
+
+				match result.case {
+					Some(ref case) => format!("{path}::{case}"),
+					None => path.to_string(),
+				}
+			};
+
+			let built = |proj: &Project| {
+				if projects.len() > 1 {
+					format!("build of {}", proj.config().package.name)
+				} else {
+					"build".to_owned()
+				}
+			};
+
+			let mut results_table = table::Table::new(3).shrink(1);
+
+			for (proj, result) in &builds {
+				let status = if result.is_ok() {
+					table::Cell::styled(" PASSED ", |s| s.on_bright_green().white())
+				} else {
+					table::Cell::styled(" FAILED ", |s| s.on_bright_red().white())
+				};
+
+				results_table.row([status, built(proj).into(), "".into()]);
+			}
+
+			for (_, result) in &results {
+				let status = match result.status {
+					TestStatus::Passed => table::Cell::styled(" PASSED ", |s| s.on_bright_green().white()),
+					TestStatus::Failed => table::Cell::styled(" FAILED ", |s| s.on_bright_red().white()),
+					TestStatus::ExpectedFailure => table::Cell::styled(" XFAIL ", |s| s.on_yellow().black()),
+					TestStatus::Skipped => table::Cell::styled(" SKIPPED ", |s| s.on_bright_black().white()),
+					TestStatus::CompileError => table::Cell::styled(" ERROR ", |s| s.on_red().white()),
+				};
+
+				let note = if let Some(ref reason) = result.reason {
+					table::Cell::styled(reason.clone(), |s| s.dimmed())
+				} else if result.cached {
+					table::Cell::styled("cached", |s| s.dimmed())
+				} else {
+					"".into()
+				};
+
+				results_table.row([status, shown(result).into(), note]);
+			}
+
+			if log::level() >= log::Level::Normal {
+				results_table.print();
+			}
+
+			for (proj, result) in &builds {
+				if let Err(e) = result {
+					eprintln!();
+					eprintln!("{} {}", " FAILED ".on_bright_red().white(), built(proj));
+					eprintln!("{}", e.to_string().trim_end());
+				}
+			}
+
+			for (proj, result) in results.iter().filter(|(_, r)| r.status.failed()) {
+				let err = result.stderr.as_deref().unwrap_or_default();
+
+				eprintln!();
+
+				if result.status == TestStatus::CompileError {
+					eprintln!("{} {} failed to compile:", " ERROR ".on_red().white(), shown(result));
+					eprintln!("{}", err.trim_end());
+				} else if let Some(snippet) = assertion::render(proj, &result.path, err) {
+					eprintln!("{} {}", " FAILED ".on_bright_red().white(), shown(result));
+					eprintln!("{}", snippet.trim_end());
+
+					/* Already shown as it was printed under --print */
+					if log::level() >= log::Level::Verbose && !*print {
+						eprintln!("{}", "stderr:".dimmed());
+						eprintln!("{}", err.trim_end());
+					}
+				} else {
+					eprintln!(
+						"{} {}: {}",
+						" FAILED ".on_bright_red().white(),
+						shown(result),
+						err.trim_end()
+					);
+				}
+
+				/* As it'd be typed from here, so it can be pasted after gdb */
+				if let Some(ref binary) = result.binary {
+					let binary = binary.strip_prefix(&cd).unwrap_or(binary).display();
+
+					match result.case {
+						Some(ref case) => eprintln!("{} {binary}, given {case} to only run this case", "binary:".dimmed()),
+						None => eprintln!("{} {binary}", "binary:".dimmed()),
+					}
+				}
+			}
+
+			let failed = results.iter().filter(|(_, r)| r.status.failed()).collect::<Vec<_>>();
+			let failed_builds = builds.iter().filter(|(_, b)| b.is_err()).collect::<Vec<_>>();
+
+			if failed.len() + failed_builds.len() > 1 {
+				eprintln!();
+				eprintln!("{}", "failures:".bold());
+
+				for (proj, _) in &failed_builds {
+					eprintln!("    {}", built(proj));
+				}
+
+				for (_, result) in &failed {
+					eprintln!("    {}", shown(result));
+				}
+			}
+
+			let across = if projects.len() > 1 {
+				format!(" across {} packages", projects.len())
+			} else {
+				String::new()
+			};
+
+			let count = |status| results.iter().filter(|(_, r)| r.status == status).count();
+
+			/* Expected failures and skipped tests are only mentioned when there are some */
+			let mut tally = vec![];
+
+			for (status, label, color) in [
+				(TestStatus::ExpectedFailure, "xfail", colored::Color::Yellow),
+				(TestStatus::Skipped, "skipped", colored::Color::BrightBlack),
+			] {
+				if count(status) > 0 {
+					tally.push(format!("{} {label}", count(status)).color(color).to_string());
+				}
+			}
+
+			let tally = tally.iter().map(|t| format!(", {t}")).collect::<String>();
+
+			let uncompiled = if count(TestStatus::CompileError) > 0 {
+				format!(", {} failed to compile", count(TestStatus::CompileError).to_string().red())
+			} else {
+				String::new()
+			};
+
+			let build_failed = if failed_builds.is_empty() {
+				String::new()
+			} else {
+				format!(", {}", "build failed".red())
+			};
+
+			if results.is_empty() && failed_builds.is_empty() {
+				let looked = match projects.as_slice() {
+					[proj] => proj.test_locations(),
+					_ => "each package".to_owned(),
+				};
+
+				match filter {
+					Some(filter) => log::info!("No tests matched '{filter}'."),
+					None => log::info!("No tests found (looked in {looked})."),
+				}
+			} else if failed.is_empty() && failed_builds.is_empty() {
+				log::info!(
+					"Successfully {} {} tests{across} ({}) in {}s, {} passed{tally}.",
+					if builds.is_empty() { "ran" } else { "built and ran" },
+					results.len(),
+					profile.get(),
+					now.elapsed().as_secs_f32(),
+					count(TestStatus::Passed).to_string().green()
+				);
+			} else {
+				log::info!(
+					"Ran {} tests{across} ({}) in {}s, {} passed, {} failed{uncompiled}{tally}{build_failed}.",
+					results.len(),
+					profile.get(),
+					now.elapsed().as_secs_f32(),
+					count(TestStatus::Passed).to_string().green(),
+					count(TestStatus::Failed).to_string().red()
+				);
+
+				std::process::exit(1);
+			}
+		}
+
+		cli::Commands::Build {
+			package,
+			bin,
+			all_targets,
+			plan,
+			json,
+			message_format,
+			target,
+			raw_errors,
+			profile,
+			timings,
+			emit,
+			file,
+		} => {
+			if let Some(kind) = emit {
+				let dir = match package {
+					Some(package) => package_dirs(&cd, Some(package))?.remove(0),
+					None => cd.clone(),
+				};
+
+				let proj = open_for(&dir, target.as_deref())?
+					.with_raw_errors(*raw_errors)
+					.with_profile(profile.get());
+
+				let to = proj.emit(
+					compiler::try_locate(Some(&proj))?.as_ref(),
+					file.as_deref(),
+					*kind,
+					None,
+				)?;
+
+				log::info!(
+					"Emitted {} to {}",
+					kind.describe(),
+					to.strip_prefix(proj.path()).unwrap_or(&to).display()
+				);
+
+				return Ok(());
+			}
+
+			let dirs = package_dirs(&cd, package.as_deref())?;
+
+			if *json && dirs.len() > 1 {
+				anyhow::bail!("A JSON plan covers a single package, select one with -p/--package.");
+			}
+
+			let now = std::time::Instant::now();
+
+			let mut built = 0;
+			let mut outputs = vec![];
+			let mut timed = vec![];
+			let mut timings_file = None;
+
+			let outcome = dirs.iter().try_for_each(|dir| {
+				let proj = open_for(dir, target.as_deref())?
+					.with_raw_errors(*raw_errors)
+					.with_profile(profile.get())
+					.with_timings(*timings);
+
+				let targets = target::resolve(&proj, bin.as_deref(), *all_targets)?;
+
+				if *plan {
+					let backend = compiler::try_locate(Some(&proj))?;
+					let plan = proj.plan(backend.as_ref(), &targets, &proj.src())?;
+
+					if *json {
+						println!("{}", serde_json::to_string_pretty(&plan)?);
+					} else {
+						print_plan(&proj, &plan);
+					}
+
+					return Ok(());
+				}
+
+				let result = proj.build(
+					compiler::try_locate(Some(&proj))?.as_ref(),
+					&targets,
+					build_script_check,
+				);
+
+				/* Shared by a workspace's members, like the vendor folder */
+				timings_file = Some(proj.target().join("timings.json"));
+				timed.extend(proj.take_timings());
+
+				outputs.extend(result?);
+				built += targets.len();
+				anyhow::Ok(())
+			});
+
+			if *plan {
+				return outcome;
+			}
+
+			if *message_format == cli::MessageFormat::Json {
+				return emit_outcome(outcome.map(|_| {
+					for path in &outputs {
+						messages::emit(&messages::Message::Artifact { path });
+					}
+
+					true
+				}));
+			}
+
+			if let (Some(mode), Some(file)) = (timings, &timings_file) {
+				report_timings(&cd, &timed, now.elapsed(), *mode, file)?;
+			}
+
+			outcome?;
+
+			log::info!(
+				"Successfully built {built} target(s) ({}) in {}s",
+				profile.get(),
+				now.elapsed().as_secs_f32()
+			);
+		}
+
+		cli::Commands::Run {
+			path,
+			files,
+			script,
+			file,
+			package,
+			bin,
+			target,
+			runner,
+			raw_errors,
+			force_rebuild,
+			profile,
+			debug,
+			args,
+		} => {
+			/* Running every member at once makes no sense, so the root of a workspace errors unless given one */
+			let dir = match package {
+				Some(package) => package_dirs(&cd, Some(package))?.remove(0),
+				None => cd.clone(),
+			};
+
+			let proj = open_for(&dir, target.as_deref())
+				.map(|p| {
+					p.with_raw_errors(*raw_errors)
+						.with_profile(profile.get())
+						.with_force_rebuild(*force_rebuild)
+						.with_debug_info(*debug)
+				})
+				.map(|p| match runner {
+					Some(runner) => p.with_runner(runner.clone()),
+					None => p,
+				});
+
+			/* A cpkg.toml that fails to load explains a missing script better than there being no project */
+			let proj = match proj {
+				Err(e) if script.is_some() && dir.join("cpkg.toml").is_file() => return Err(e),
+				proj => proj,
+			};
+
+			let runnable = resolve_run(proj.as_ref().ok(), path, files, script, file)?;
+
+			if let Runnable::Script(script) = &runnable {
+				if let Ok(ref proj) = proj {
+					let c = proj.config();
+
+					if let Some(script) = c.scripts.entries.get(script) {
+						let mut script = scripts::command(proj, script)?;
+						log::command(&script.cmd);
+
+						let status = script.cmd.status()?;
+
+						/* Exiting skips destructors, so remove a script file first */
+						drop(script);
+
+						if !status.success() {
+							std::process::exit(status.code().unwrap_or(1));
+						}
+
+						return Ok(());
+					}
+				}
+
+				anyhow::bail!("No script named '{script}' in cpkg.toml.");
+			}
+
+			if let Runnable::File(script, files) = runnable {
+				if script.exists() {
+					let backend = compiler::try_locate_fast(None)?;
+
+					/* Headers beside the script, and the project's own when inside of one */
+					let mut includes = vec![script
+						.parent()
+						.filter(|p| !p.as_os_str().is_empty())
+						.unwrap_or(std::path::Path::new("."))
+						.to_owned()];
+
+					if let Ok(ref proj) = proj {
+						includes.extend(proj.src());
+						includes.push(proj.vendor());
+					}
+
+					if let Some(missing) = files.iter().find(|f| !f.exists()) {
+						anyhow::bail!("File not found: {}", missing.display());
+					}
+
+					let debugger = debug.then(|| debugger::try_locate(proj.as_ref().ok())).transpose()?;
+
+					/* Backends like tcc can run the file without writing a binary first */
+					let flags = includes
+						.iter()
+						.flat_map(|i| [std::ffi::OsStr::new("-I"), i.as_os_str()])
+						.chain(files.iter().map(|f| f.as_os_str()))
+						.chain(profile.get().flags().iter().map(std::ffi::OsStr::new))
+						.map(|f| f.to_string_lossy().into_owned())
+						.collect::<Vec<_>>();
+
+					/* A debugger needs a binary to start */
+					if let Some(mut cmd) = backend.run(&script, &flags).filter(|_| !*debug) {
+						cmd.args(args);
+						log::command(&cmd);
+
+						let status = cmd.status()?;
+						std::process::exit(status.code().unwrap_or(1));
+					}
+
+					let temp = tempfile::Builder::new()
+						.prefix("cpkg-repl")
+						.suffix(std::env::consts::EXE_SUFFIX)
+						.tempfile()?
+						.into_temp_path();
+
+					let sources = std::iter::once(script).chain(files.iter().cloned()).collect::<Vec<_>>();
+					let includes = includes.iter().map(|i| i.as_path()).collect::<Vec<_>>();
+
+					let mut flags = profile.get().flags().iter().map(|f| f.to_string()).collect::<Vec<_>>();
+
+					if *debug {
+						flags.extend(["-g".to_owned(), "-O0".to_owned()]);
+					}
+
+					backend.compile(&sources, &includes, &temp, &flags, &mut compiler::echo)?;
+
+					let mut cmd = if let Some(debugger) = debugger {
+						debugger.command(&temp, args)
+					} else {
+						let mut cmd = std::process::Command::new(&temp);
+						cmd.args(args);
+						cmd
+					};
+
+					log::command(&cmd);
+
+					let status = cmd.status()?;
+
+					/* Exiting skips destructors, so remove the binary first */
+					drop(temp);
+					std::process::exit(status.code().unwrap_or(1));
+				} else {
+					return Err(anyhow::anyhow!("File not found: {}", script.display()));
+				}
+			}
+
+			let proj = proj?;
+			let target = target::resolve_run(&proj, bin.as_deref())?;
+
+			proj.runner_argv()?;
+
+			/* Looked for before building, so a missing one doesn't wait on the build */
+			let debugger = debug.then(|| debugger::try_locate(Some(&proj))).transpose()?;
+
+			let out = proj.build(
+				compiler::try_locate(Some(&proj))?.as_ref(),
+				&[target],
+				build_script_check,
+			)?;
+
+			if proj.compiled() == 0 {
+				log::info!(
+					"Binary up to date, running {}",
+					out[0].strip_prefix(proj.path()).unwrap_or(&out[0]).display()
+				);
+			}
+
+			let page = proj
+				.cross()
+				.is_some_and(|c| c.is_emscripten() && proj.runner().is_none());
+
+			if page {
+				serve_page(&out[0])?;
+			} else {
+				let mut cmd = if let Some(debugger) = debugger {
+					debugger.command(&out[0], args)
+				} else {
+					let mut cmd = proj.run_command(&out[0])?;
+					cmd.args(args);
+					cmd
+				};
+
+				log::command(&cmd);
+
+				/* The exit code is the program's, through the runner if there is one */
+				let status = cmd.status()?;
+				if !status.success() {
+					std::process::exit(status.code().unwrap_or(1));
+				}
+			}
+		}
+
+		cli::Commands::Clean {
+			deps,
+			bin,
+			all_targets,
+			assets,
+			tests,
+			doc,
+			vendor,
+			generated,
+			all,
+			broken,
+		} => {
+			let proj = Project::open(&cd)?;
+
+			if *deps {
+				return prune_deps(&proj);
+			}
+
+			if *broken {
+				let removed = proj.clean_broken()?;
+
+				for path in &removed {
+					log::info!("Removed {}", path.strip_prefix(proj.path()).unwrap_or(path).display());
+				}
+
+				if removed.is_empty() {
+					log::info!("Nothing broken to remove.");
+				}
+
+				return Ok(());
+			}
+
+			if *generated {
+				let removed = generated::clean(&proj)?;
+
+				for file in &removed {
+					log::info!("Removed {file}");
+				}
+
+				if removed.is_empty() {
+					log::info!("No generated files to remove.");
+				}
+
+				return Ok(());
+			}
+
+			if *assets {
+				if assets::clean(&proj)? {
+					log::info!("Removed materialized assets.");
+				} else {
+					log::info!("No assets to remove.");
+				}
+
+				return Ok(());
+			}
+
+			if bin.is_some() || *all_targets {
+				let mut removed = 0;
+
+				for target in target::resolve(&proj, bin.as_deref(), *all_targets)? {
+					for out in proj.target_outputs(&target) {
+						if out.is_dir() {
+							std::fs::remove_dir_all(&out)?;
+						} else if out.exists() {
+							std::fs::remove_file(&out)?;
+						} else {
+							continue;
+						}
+
+						removed += 1;
+					}
+				}
+
+				log::info!("Removed {removed} target output(s).");
+
+				return Ok(());
+			}
+
+			let target = proj.target();
+
+			let paths = if *all {
+				vec![target]
+			} else if *tests || *doc || *vendor {
+				let mut paths = vec![];
+
+				if *tests {
+					paths.push(proj.out_dir().join("test"));
+					paths.push(proj.out_dir().join("release").join("test"));
+				}
+
+				if *doc {
+					paths.push(target.join("doc"));
+				}
+
+				if *vendor {
+					paths.push(proj.vendor());
+				}
+
+				paths
+			} else if proj.workspace().is_some() {
+				/* Other members' outputs and the shared vendor folder are left alone */
+				vec![proj.out_dir()]
+			} else {
+				/* Everything but vendored dependencies, which can take a while to fetch again */
+				std::fs::read_dir(&target)
+					.into_iter()
+					.flatten()
+					.flatten()
+					.map(|e| e.path())
+					.filter(|p| *p != proj.vendor())
+					.collect()
+			};
+
+			let mut removed = 0;
+			let mut reclaimed = 0;
+
+			for path in paths.iter().filter(|p| p.exists()) {
+				let size = Project::dir_size(path);
+
+				if path.is_dir() {
+					std::fs::remove_dir_all(path)?;
+				} else {
+					std::fs::remove_file(path)?;
+				}
+
+				let root = proj.workspace().map_or(proj.path(), |ws| ws.root());
+
+				log::info!(
+					"Removed {} ({})",
+					path.strip_prefix(root).unwrap_or(path).display(),
+					human_size(size)
+				);
+
+				removed += 1;
+				reclaimed += size;
+			}
+
+			if removed == 0 {
+				log::info!("Nothing to clean.");
+			} else {
+				log::info!("Reclaimed {}.", human_size(reclaimed).yellow());
+			}
+		}
+
+		cli::Commands::Doc { open, serve, watch } => {
+			let proj = Project::open(&cd)?;
+			let backend = docgen::try_locate(&proj)?;
+
+			let target = Project::get_or_mkdir(proj.target())?;
+			let doc = Project::get_or_mkdir(target.join("doc"))?;
+
+			let now = std::time::Instant::now();
+
+			backend.generate(&proj, &doc)?;
+
+			log::info!(
+				"Generated documentation in {}s",
+				now.elapsed().as_secs_f32()
+			);
+
+			if let Some(port) = serve {
+				let html = doc.join("html");
+				let root = if html.is_dir() { html } else { doc.clone() };
+
+				let listener = serve::bind(*port)?;
+				let url = format!("http://{}/", listener.local_addr()?);
+
+				let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+				let handler = stop.clone();
+				ctrlc::set_handler(move || handler.store(true, std::sync::atomic::Ordering::Relaxed))?;
+
+				let server = {
+					let stop = stop.clone();
+					std::thread::spawn(move || serve::serve(listener, root, stop))
+				};
+
+				log::info!("Serving documentation at {}", url.yellow());
+				log::info!("Press Ctrl-C to stop.");
+
+				if *open {
+					docgen::start_program(std::path::Path::new(&url))?;
+				}
+
+				let src = proj.src();
+				let mut last = serve::last_modified(&src);
+
+				while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+					std::thread::sleep(std::time::Duration::from_millis(500));
+
+					if *watch {
+						let modified = serve::last_modified(&src);
+
+						if modified != last {
+							last = modified;
+
+							match backend.generate(&proj, &doc) {
+								Ok(_) => log::info!("Regenerated documentation."),
+								Err(e) => eprintln!("{}", e.to_string().red()),
+							}
+						}
+					}
+				}
+
+				server.join().expect("Server thread panicked")?;
+
+				log::info!("Stopped serving documentation.");
+			} else if *open {
+				backend.open(&doc)?;
+			}
+		}
+
+		cli::Commands::Format { check, staged } => {
+			let p = Project::open(&cd)?;
+
+			let backend = format::try_locate(&p)?;
+
+			let paths = if *staged {
+				let staged = hooks::staged(p.path())?;
+
+				p.format_files()?
+					.into_iter()
+					.filter(|f| f.canonicalize().is_ok_and(|f| staged.contains(&f)))
+					.collect::<Vec<_>>()
+			} else {
+				p.format_files()?
+			};
+
+			if paths.is_empty() {
+				log::info!("No {}sources to format.", if *staged { "staged " } else { "" });
+				return Ok(());
+			}
+
+			let now = std::time::Instant::now();
+
+			backend.format(&p, &paths, *check)?;
+
+			if *check {
+				log::info!("Code is formatted.");
+			} else {
+				log::info!("Formatted code in {}s", now.elapsed().as_secs_f32());
+			}
+		}
+
+		cli::Commands::Generate { kind } => {
+			let proj = Project::open(&cd)?;
+
+			let generator: &dyn export::BuildfileGenerator = match kind {
+				cli::GenerateCommand::Make => &export::Make,
+				cli::GenerateCommand::Ninja => &export::Ninja,
+				cli::GenerateCommand::Pc { prefix } => {
+					if !target::defaults(&proj)?.contains(&target::Target::Lib) {
+						anyhow::bail!(
+							"{} isn't a library. Add \"lib\" to default-targets under [package] in cpkg.toml to build one.",
+							proj.name()
+						);
+					}
+
+					let file = format!("{}.pc", proj.name());
+					let target = Project::get_or_mkdir(proj.target())?;
+
+					std::fs::write(target.join(&file), export::pkg_config(&proj, prefix))?;
+
+					log::info!("Generated target/{file}.");
+					return Ok(());
+				}
+				cli::GenerateCommand::Ci { provider, force } => {
+					let generator: &dyn ci::CiGenerator = match provider {
+						cli::CiProvider::Github => &ci::GitHub,
+						cli::CiProvider::Gitlab => &ci::GitLab,
+					};
+
+					let pipeline = generator.generate(&ci::CiData::new(&proj))?;
+					return generate_file(&proj, generator.file_name(), &pipeline, *force);
+				}
+				cli::GenerateCommand::Gitignore { force } => {
+					return generate_file(&proj, ".gitignore", export::GITIGNORE, *force);
+				}
+				cli::GenerateCommand::ClangFormat { style, force } => {
+					return generate_file(&proj, ".clang-format", &format::clang_format_config(*style), *force);
+				}
+				cli::GenerateCommand::Hooks { remove } => {
+					if *remove {
+						hooks::remove(&proj)?;
+						log::info!("Removed the pre-commit hook.");
+					} else {
+						let hook = hooks::install(&proj)?;
+						log::info!("Generated {}.", hook.display());
+					}
+
+					return Ok(());
+				}
+				cli::GenerateCommand::Editorconfig { style, force } => {
+					return generate_file(&proj, ".editorconfig", &export::editorconfig(*style), *force);
+				}
+			};
+
+			let backend = compiler::try_locate(Some(&proj))?;
+
+			if !backend.gcc_style() {
+				anyhow::bail!(
+					"Generated {} files expect a gcc-style compiler, but {} isn't one. Set default under [compiler] to gcc or clang.",
+					generator.file_name(),
+					backend.bin()
+				);
+			}
+
+			let data = export::BuildData::new(&proj, backend.as_ref())?;
+			let file = generator.file_name();
+
+			generated::write(&proj, file, &format!("{}\n{}", generated::MARKER, generator.generate(&data)))?;
+
+			log::info!("Generated {file}.");
+		}
+
+		cli::Commands::Validate { fix } => {
+			let mut findings = validate::validate(&cd)?;
+
+			if *fix && findings.iter().any(|f| f.fix.is_some()) {
+				let proj = Project::open(&cd)?;
+				let fixed = validate::fix(&proj, &findings)?;
+
+				findings.retain(|f| f.fix.is_none());
+				log::info!("Fixed {} problem(s).", fixed.to_string().yellow());
+			}
+
+			validate::report(&findings);
+
+			let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+			let (errors, warnings) = (
+				count(validate::Severity::Error),
+				count(validate::Severity::Warning),
+			);
+
+			if findings.is_empty() {
+				log::info!("No problems found.");
+			} else {
+				eprintln!("Found {errors} error(s) and {warnings} warning(s).");
+			}
+
+			/* Exit status is the highest severity found, so CI can decide what to fail on */
+			match findings.iter().map(|f| f.severity).max() {
+				Some(validate::Severity::Error) => std::process::exit(2),
+				Some(validate::Severity::Warning) => std::process::exit(1),
+				None => {}
+			}
+		}
+
+		cli::Commands::Explain { code } => match validate::explain(code) {
+			Some(text) => println!("{}: {text}", code.to_uppercase().bold()),
+			None => anyhow::bail!(
+				"Unknown code {code}. Codes are {}.",
+				validate::CODES
+					.iter()
+					.map(|(c, _)| *c)
+					.collect::<Vec<_>>()
+					.join(", ")
+			),
+		},
+
+		cli::Commands::Add {
+			name,
+			git,
+			path,
+			lib,
+			search,
+			headers,
+			no_install,
+			yes,
+		} => {
+			let mut project = Project::open(&cd)?;
+
+			if git.is_some() && path.is_some() {
+				anyhow::bail!("Cannot be both git and path dependencies");
+			}
+
+			let dep = if let Some(git) = git {
+				ConfigDependency::Git {
+					git: git.clone(),
+					include: None,
+					build: None,
+					artifacts: vec![],
+					entry: None,
+				}
+			} else if let Some(path) = path {
+				ConfigDependency::Path {
+					path: path.clone(),
+					include: None,
+					build: None,
+					artifacts: vec![],
+					entry: None,
+				}
+			} else if let Some(lib) = lib {
+				ConfigDependency::Lib {
+					lib: lib.clone(),
+					search: search.clone(),
+					headers: headers.clone(),
+				}
+			} else {
+				let index = index::Index::load().map_err(|e| {
+					e.context("Couldn't look up the dependency, pass --git, --path or --lib to add it directly")
+				})?;
+
+				let Some((found, entry)) = index.get(name) else {
+					match index.closest(name) {
+						Some(close) => anyhow::bail!(
+							"{name} isn't in the package index, did you mean {close}? Otherwise pass --git, --path or --lib."
+						),
+						None => anyhow::bail!(
+							"{name} isn't in the package index. Pass --git, --path or --lib to add it, or see cpkg search."
+						),
+					}
+				};
+
+				println!("{} {}", found.bold(), entry.description);
+				println!("{}", entry.git.dimmed());
+
+				if !yes {
+					print!("Add it? (y/n) ");
+					std::io::Write::flush(&mut std::io::stdout())?;
+
+					let mut s = String::new();
+					std::io::stdin().read_line(&mut s)?;
 
-mod project;
-use project::*;
+					if !s.trim().eq_ignore_ascii_case("y") {
+						anyhow::bail!("Cancelled. Pass --yes to add it without being asked.");
+					}
+				}
 
-mod config;
-use config::*;
+				ConfigDependency::Git {
+					git: entry.git.clone(),
+					include: None,
+					build: None,
+					artifacts: vec![],
+					entry: None,
+				}
+			};
 
-fn build_script_check() -> bool {
-	println!("This project needs a build script to run. Accept? (y/n)");
+			let vendored = dep.is_vendored();
 
-	let mut s = String::new();
-	std::io::stdin().read_line(&mut s).unwrap();
+			let manifest = project.path().join("cpkg.toml");
+			let original = std::fs::read_to_string(&manifest)?;
 
-	s.find("y").is_some()
-}
+			project.add_dep(name.to_owned(), dep)?;
 
-fn main() -> anyhow::Result<()> {
-	let args: cli::Cli = clap::Parser::parse();
-	let cd = std::env::current_dir()?;
+			if *no_install {
+				log::info!("Added dependency to {}.", "cpkg.toml".yellow());
+				return Ok(());
+			}
 
-	match &args.command {
-		cli::Commands::New { name } => {
-			Project::create(name.as_ref())?;
+			/* Don't leave cpkg.toml referencing a dependency that never materialized */
+			let commit = match project.install_one(name) {
+				Ok(commit) => commit,
+				Err(e) => {
+					std::fs::write(&manifest, original)?;
+					return Err(e.context(format!("Couldn't install {name}, so cpkg.toml was left unchanged")));
+				}
+			};
+
+			if !vendored {
+				log::info!("Added {} to {}.", name.yellow(), "cpkg.toml".yellow());
+				return Ok(());
+			}
+
+			let installed = project.vendor().join(name);
+			let installed = installed.strip_prefix(project.path()).unwrap_or(&installed);
+
+			match commit {
+				Some(commit) => log::info!(
+					"Added {} to {}, installed to {} at commit {}.",
+					name.yellow(),
+					"cpkg.toml".yellow(),
+					installed.display(),
+					lockfile::short(&commit).yellow()
+				),
+				None => log::info!(
+					"Added {} to {}, installed to {}.",
+					name.yellow(),
+					"cpkg.toml".yellow(),
+					installed.display()
+				),
+			}
 		}
 
-		cli::Commands::Init => {
-			Project::init(&cd)?;
+		cli::Commands::Search { term } => {
+			let index = index::Index::load()?;
+			let found = index.search(term);
+
+			if found.is_empty() {
+				log::info!("No packages in the index match '{term}'.");
+				return Ok(());
+			}
+
+			let mut listing = table::Table::new(3).shrink(2);
+
+			for (name, entry) in found {
+				listing.row([
+					table::Cell::styled(name, |s| s.yellow()),
+					entry.description.clone().into(),
+					table::Cell::styled(entry.git.clone(), |s| s.dimmed()),
+				]);
+			}
+
+			listing.print();
 		}
 
-		cli::Commands::Test { print } => {
-			let proj = Project::open(&cd)?;
+		cli::Commands::Remove { name } => {
+			let mut proj = Project::open(&cd)?;
 
-			let now = std::time::Instant::now();
+			proj.remove_dep(name)?;
 
-			let results = proj.run_tests(compiler::try_locate(Some(&proj))?.as_ref(), *print)?;
+			log::info!("Removed {} from {}.", name.yellow(), "cpkg.toml".yellow());
+		}
 
-			for (passed, path, err) in &results {
-				if *passed {
-					println!(
-						"{} {}",
-						" PASSED ".on_bright_green().white(),
-						path.display()
-					);
-				} else {
-					eprintln!(
-						"{} {}: {}",
-						" FAILED ".on_bright_red().white(),
-						path.display(),
-						err.clone().unwrap().trim_end()
-					);
+		cli::Commands::Install { prune, repair: _, force } => {
+			/* Members share the root's vendor folder, so they're checked against each other before any is installed */
+			let Some(ws) = workspace::Workspace::open(&cd)? else {
+				return install_package(&cd, *prune, *force);
+			};
+
+			let conflicts = ws.conflicts().iter().map(ToString::to_string).collect::<Vec<_>>();
+			if !conflicts.is_empty() {
+				anyhow::bail!("{}", conflicts.join("\n\n"));
+			}
+
+			for member in ws.members() {
+				install_package(member, *prune, *force)?;
+			}
+		}
+
+		cli::Commands::Tree => match workspace::Workspace::open(&cd)? {
+			Some(ws) => {
+				let title = format!("{} (workspace)", ws.root().file_name().unwrap_or_default().to_string_lossy());
+				print_tree(&title, ws.root(), &ws.dependencies(), true)?;
+			}
+			None => {
+				let proj = Project::open(&cd)?;
+
+				let deps = workspace::requirements(&cd)
+					.into_iter()
+					.map(|(name, requirement)| (name, vec![requirement]))
+					.collect();
+
+				print_tree(&format!("{} v{}", proj.name(), proj.version()), proj.lock_dir(), &deps, false)?;
+			}
+		},
+
+		cli::Commands::Vendor { check } => {
+			let mut proj = Project::open(&cd)?;
+
+			if *check {
+				let problems = snapshot::problems(&proj);
+
+				if problems.is_empty() {
+					log::info!("Vendor snapshot matches cpkg.toml.");
+					return Ok(());
+				}
+
+				for problem in &problems {
+					eprintln!("  {problem}");
 				}
+
+				anyhow::bail!("Vendor snapshot is out of date, run cpkg vendor to update it.");
 			}
 
-			println!(
-				"Successfully ran {} tests in {}s.",
-				results.len(),
-				now.elapsed().as_secs_f32()
+			let copied = snapshot::create(&mut proj)?;
+
+			log::info!(
+				"Vendored {} dependencies into {}. Commit it to build without fetching anything.",
+				copied.len().to_string().yellow(),
+				snapshot::DIR.yellow()
 			);
 		}
 
-		cli::Commands::Build { bin } => {
-			let proj = Project::open(&cd)?;
+		cli::Commands::InstallBin { bin, root, force } => {
+			let proj = Project::open(&cd)?.with_profile(Profile::Release);
+			let target = target::resolve_run(&proj, bin.as_deref())?;
 
-			let now = std::time::Instant::now();
+			let dir = install_dir(root)?;
 
-			proj.build(
+			let outputs = proj.build(
 				compiler::try_locate(Some(&proj))?.as_ref(),
-				bin,
+				&[target],
 				build_script_check,
 			)?;
 
-			println!(
-				"Successfully built program(s) in {}s",
-				now.elapsed().as_secs_f32()
-			);
-		}
+			let built = &outputs[0];
+			let dest = dir.join(built.file_name().unwrap());
 
-		cli::Commands::Run { path, bin } => {
-			let proj = Project::open(&cd);
+			if dest.exists() && !*force {
+				anyhow::bail!(
+					"{} already exists. Use --force to overwrite it.",
+					dest.display()
+				);
+			}
 
-			if let Some(script) = path {
-				if let Ok(proj) = proj {
-					let c = proj.config();
+			std::fs::create_dir_all(&dir)?;
+			std::fs::copy(built, &dest)?;
 
-					if let Some(script) = c.scripts.get(script) {
-						#[cfg(target_os = "linux")]
-						std::process::Command::new("sh")
-							.arg("-c")
-							.arg(script)
-							.spawn()?
-							.wait()?;
-
-						#[cfg(target_os = "windows")]
-						std::process::Command::new("cmd.exe")
-							.arg("/c")
-							.arg(script)
-							.spawn()?
-							.wait()?;
+			#[cfg(unix)]
+			{
+				use std::os::unix::fs::PermissionsExt;
 
-						return Ok(());
-					}
+				let mut perms = std::fs::metadata(&dest)?.permissions();
+				perms.set_mode(perms.mode() | 0o755);
+				std::fs::set_permissions(&dest, perms)?;
+			}
+
+			log::info!("Installed {}.", dest.display().to_string().yellow());
+
+			let on_path = std::env::var_os("PATH")
+				.is_some_and(|path| std::env::split_paths(&path).any(|p| p == dir));
+
+			if !on_path {
+				log::warn(format!(
+					"{} isn't on your PATH, add it to run {} directly.",
+					dir.display(),
+					dest.file_name().unwrap().to_string_lossy()
+				));
+			}
+		}
+
+		cli::Commands::Uninstall { name, root } => {
+			let name = match name {
+				Some(name) => std::ffi::OsString::from(name),
+				None => {
+					let proj = Project::open(&cd)?.with_profile(Profile::Release);
+					let target = target::resolve_run(&proj, None)?;
+
+					let out = proj.target_outputs(&target).remove(0);
+					out.file_name().unwrap().to_owned()
 				}
+			};
 
-				let script = std::path::PathBuf::from(script);
-				if script.exists() {
-					let temp = tempfile::Builder::new()
-						.prefix("cpkg-repl")
-						.tempfile()?
-						.into_temp_path();
+			let dest = install_dir(root)?.join(&name);
 
-					compiler::try_locate(None)?.compile(&[script], &[], &temp, &[])?;
+			if !dest.is_file() {
+				anyhow::bail!("{} isn't installed.", dest.display());
+			}
 
-					std::process::Command::new(&temp).spawn()?;
+			std::fs::remove_file(&dest)?;
 
-					return Ok(());
+			log::info!("Removed {}.", dest.display().to_string().yellow());
+		}
+
+		cli::Commands::Repl { no_project } => {
+			let proj = if *no_project {
+				None
+			} else {
+				Project::open(&cd).ok()
+			};
+
+			repl::run(compiler::try_locate_fast(proj.as_ref())?, proj.as_ref())?;
+		}
+
+		cli::Commands::Cc {
+			files,
+			output,
+			flags,
+		} => {
+			let backend = compiler::try_locate(None)?;
+			let global = GlobalConfig::load()?;
+
+			let out = output
+				.clone()
+				.unwrap_or_else(|| files[0].file_stem().unwrap_or_default().into());
+
+			let flags = global
+				.compiler_flags()
+				.iter()
+				.chain(flags)
+				.cloned()
+				.collect::<Vec<_>>();
+
+			let mut cmd = backend.command(files, &[], &out, &flags);
+			log::command(&cmd);
+
+			let status = cmd.status()?;
+			std::process::exit(status.code().unwrap_or(1));
+		}
+
+		cli::Commands::Compile {
+			file,
+			output,
+			object: _,
+			asm,
+			preprocess,
+			profile,
+		} => {
+			let kind = if *asm {
+				compiler::Emit::Asm
+			} else if *preprocess {
+				compiler::Emit::Preprocessed
+			} else {
+				compiler::Emit::Object
+			};
+
+			if !file.is_file() {
+				anyhow::bail!("File not found: {}", file.display());
+			}
+
+			/* Beside the file, which is where compilers put it on their own */
+			let to = output
+				.clone()
+				.unwrap_or_else(|| file.with_extension(kind.extension()));
+
+			if cd.join("cpkg.toml").is_file() {
+				let proj = Project::open(&cd)?.with_profile(profile.get());
+
+				proj.emit(
+					compiler::try_locate(Some(&proj))?.as_ref(),
+					Some(file),
+					kind,
+					Some(&to),
+				)?;
+			} else {
+				/* Outside of a project there's only the user's flags, and headers beside the file */
+				let backend = compiler::try_locate(None)?;
+				let backend = if project::is_cxx_source(file) {
+					compiler::locate_cxx(backend.as_ref())?
 				} else {
-					return Err(anyhow::anyhow!("Script not found: {}", script.display()));
-				}
+					backend
+				};
+
+				let dir = file
+					.parent()
+					.filter(|p| !p.as_os_str().is_empty())
+					.unwrap_or(std::path::Path::new("."));
+
+				let flags = GlobalConfig::load()?
+					.compiler_flags()
+					.iter()
+					.cloned()
+					.chain(profile.get().flags().iter().map(|f| f.to_string()))
+					.collect::<Vec<_>>();
+
+				backend.emit(file, &[dir], &to, &flags, kind)?;
 			}
 
-			let proj = proj?;
-			let out = proj.build(
+			log::info!("Compiled {} to {}", file.display(), to.display());
+		}
+
+		cli::Commands::Dist { archive: args } => {
+			let proj = Project::open(&cd)?;
+
+			let targets = target::defaults(&proj)?
+				.into_iter()
+				.filter(|t| *t != target::Target::Tests)
+				.collect::<Vec<_>>();
+
+			let outputs = proj.build(
 				compiler::try_locate(Some(&proj))?.as_ref(),
-				bin,
+				&targets,
 				build_script_check,
 			)?;
 
-			std::process::Command::new(out).spawn()?;
-		}
+			let stem = format!("{}-{}", proj.name(), proj.version());
 
-		cli::Commands::Clean => {
-			let proj = Project::open(&cd)?;
+			let (format, out) = args.resolve(&proj.target().join("dist"), &stem)?;
 
-			let target = proj.target();
+			let mut entries = outputs
+				.into_iter()
+				.map(|out| {
+					let name = out.file_name().unwrap().to_string_lossy().into_owned();
+					let dir = if name.ends_with(".a") { "lib" } else { "bin" };
 
-			if !target.exists() {
-				anyhow::bail!("Failed to clean target directory. Doesn't seem to exist.");
+					archive::Entry {
+						name: format!("{stem}/{dir}/{name}"),
+						source: out,
+					}
+				})
+				.collect::<Vec<_>>();
+
+			for file in std::fs::read_dir(proj.path())? {
+				let file = file?;
+				let name = file.file_name().to_string_lossy().into_owned();
+
+				if file.file_type()?.is_file()
+					&& (name.starts_with("README") || name.starts_with("LICENSE"))
+				{
+					entries.push(archive::Entry {
+						name: format!("{stem}/{name}"),
+						source: file.path(),
+					});
+				}
 			}
 
-			std::fs::remove_dir_all(target)?;
+			archive::write(format, &out, entries, args.reproducible)?;
 
-			println!("Removed target directory.");
+			log::info!("Created {}.", out.display().to_string().yellow());
 		}
 
-		cli::Commands::Doc { open } => {
+		cli::Commands::Package { no_verify } => {
 			let proj = Project::open(&cd)?;
-			let backend = docgen::try_locate(&proj)?;
+			let backend = compiler::try_locate(Some(&proj))?;
 
-			let target = std::path::Path::new("target");
-			if !target.exists() {
-				std::fs::create_dir(target)?;
-			}
+			let stem = format!("{}-{}", proj.name(), proj.version());
+			let out = proj.target().join("package").join(format!("{stem}.tar.gz"));
 
-			let doc = target.join("doc");
-			if !doc.exists() {
-				std::fs::create_dir(&doc)?;
+			if !*no_verify {
+				let deps = package::unpackaged_deps(&proj);
+
+				if !deps.is_empty() {
+					anyhow::bail!(
+						"Path dependencies {} won't exist where the package is extracted, so it can't be verified. Run cpkg vendor to include them, or pass --no-verify.",
+						deps.join(", ")
+					);
+				}
 			}
 
-			let now = std::time::Instant::now();
+			/* Lets the package build with just make, without cpkg */
+			let makefile = tempfile::NamedTempFile::new()?;
+			std::fs::write(
+				makefile.path(),
+				export::BuildfileGenerator::generate(
+					&export::Make,
+					&export::BuildData::new(&proj, backend.as_ref())?,
+				),
+			)?;
 
-			let proj = std::path::Path::new("src");
-			backend.generate(proj, &doc)?;
+			let mut entries = package::files(&proj)?
+				.into_iter()
+				.map(|file| archive::Entry {
+					name: format!("{stem}/{}", file.to_string_lossy().replace('\\', "/")),
+					source: package::source(&proj, &file),
+				})
+				.collect::<Vec<_>>();
 
-			println!(
-				"Generated documentation in {}s",
-				now.elapsed().as_secs_f32()
-			);
+			entries.push(archive::Entry {
+				name: format!("{stem}/Makefile"),
+				source: makefile.path().to_owned(),
+			});
 
-			if *open {
-				backend.open(&doc)?;
+			entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+			for entry in &entries {
+				println!("  {}", &entry.name[stem.len() + 1..]);
 			}
-		}
 
-		cli::Commands::Format => {
-			let p = Project::open(&cd)?;
+			let package = &proj.config().package;
+
+			/* Nothing needs them to build, but whoever gets the package will want to know what it is */
+			let missing = [
+				("description", package.description.is_none()),
+				("license", package.license.is_none()),
+				("repository", package.repository.is_none() && package.homepage.is_none()),
+			]
+			.into_iter()
+			.filter(|(_, missing)| *missing)
+			.map(|(key, _)| key)
+			.collect::<Vec<_>>();
+
+			if !missing.is_empty() {
+				log::warn(format!(
+					"[package] in cpkg.toml has no {}, which the package's users may look for.",
+					missing.join(" or ")
+				));
+			}
 
-			let backend = format::try_locate(&p)?;
+			if let Some(ref license) = package.license {
+				if !entries.iter().any(|e| e.name[stem.len() + 1..].starts_with("LICENSE")) {
+					log::warn(format!(
+						"The package is licensed {license} in cpkg.toml, but has no LICENSE file to go with it."
+					));
+				}
+			}
 
-			let now = std::time::Instant::now();
+			let count = entries.len();
+			archive::write(archive::Format::TarGz, &out, entries, false)?;
 
-			backend.format(&p)?;
+			if !*no_verify {
+				log::info!("Verifying {} builds on its own..", out.display().to_string().yellow());
 
-			println!("Formatted code in {}s", now.elapsed().as_secs_f32());
-		}
+				package::verify(&out, &stem, build_script_check).map_err(|e| {
+					e.context("The package failed to build on its own, it may be missing files. Pass --no-verify to skip this check")
+				})?;
+			}
 
-		cli::Commands::Generate { kind } => match kind {
-			cli::GenerateCommand::Make => {
-				let proj = Project::open(&cd)?;
+			log::info!(
+				"Packaged {} files into {}.",
+				count.to_string().yellow(),
+				out.display().to_string().yellow()
+			);
+		}
 
-				let backend = compiler::try_locate(Some(&proj))?;
-				let make = backend.makefile(&proj);
-				std::fs::write("Makefile", make)?;
+		cli::Commands::Deps {
+			command:
+				cli::DepsCommand::MigrateIncludes {
+					files,
+					dry_run,
+					shims,
+					remove_shims,
+				},
+		} => {
+			let proj = Project::open(&cd)?;
 
-				println!("Generated Makefile.");
+			if *remove_shims {
+				let removed = includes::remove_shims(&proj.vendor())?;
+				log::info!("Removed {removed} shim header(s).");
+				return Ok(());
 			}
-		},
 
-		cli::Commands::Add { name, git, path } => {
-			let mut project = Project::open(&cd)?;
+			let migration = includes::Migration::find(&proj)?;
 
-			if git.is_some() && path.is_some() {
-				anyhow::bail!("Cannot be both git and path dependencies");
+			for (file, line, deps) in &migration.ambiguous {
+				log::warn(format!(
+					"{}:{line} matches headers of several dependencies ({}), leaving it alone.",
+					file.strip_prefix(proj.path()).unwrap_or(file).display(),
+					deps.join(", ")
+				));
 			}
 
-			let dep = if let Some(git) = git {
-				ConfigDependency::Git { git: git.clone() }
-			} else if let Some(path) = path {
-				ConfigDependency::Path { path: path.clone() }
+			let only = if files.is_empty() {
+				None
 			} else {
-				anyhow::bail!("Must provide either --git or --path, for now.");
+				Some(
+					files
+						.iter()
+						.map(|f| {
+							cd.join(f).canonicalize().map_err(|e| {
+								anyhow::anyhow!("Couldn't find {}: {e}", f.display())
+							})
+						})
+						.collect::<anyhow::Result<Vec<_>>>()?,
+				)
 			};
 
-			project.add_dep(name.to_owned(), dep)?;
+			/* Sources are found under the project path, which may not be canonical */
+			let root = proj.path().canonicalize()?;
+			let selected = |file: &std::path::Path| {
+				only.as_ref().is_none_or(|only| {
+					let file = root.join(file.strip_prefix(proj.path()).unwrap_or(file));
+					only.contains(&file)
+				})
+			};
+
+			if *dry_run {
+				for rewrite in migration.rewrites.iter().filter(|r| selected(&r.file)) {
+					let file = rewrite.file.strip_prefix(proj.path()).unwrap_or(&rewrite.file);
+
+					println!("{}", format!("{}:{}", file.display(), rewrite.line).bold());
+					println!("{}", format!("-{}", rewrite.before).red());
+					println!("{}", format!("+{}", rewrite.after).green());
+				}
+
+				if *shims {
+					for (header, dep) in migration.shims(|f| !selected(f)) {
+						println!("Would write shim {header} -> <{dep}/{header}>");
+					}
+				}
+
+				return Ok(());
+			}
 
-			println!("Added dependency to {}.", "cpkg.toml".yellow())
+			let count = migration.apply(selected)?;
+			log::info!("Rewrote {count} include(s).");
+
+			/* Only includes left in files that weren't rewritten need shims */
+			if *shims {
+				let written = includes::write_shims(&proj, migration.shims(|f| !selected(f)))?;
+				log::info!(
+					"Wrote {} shim header(s) to {}.",
+					written.len(),
+					proj.vendor().display()
+				);
+			}
 		}
 
-		cli::Commands::Remove { name } => {
+		cli::Commands::Version { command } => {
 			let mut proj = Project::open(&cd)?;
 
-			proj.remove_dep(name)?;
+			match command {
+				None => println!("{}", proj.version()),
+
+				Some(cli::VersionCommand::Bump { part }) => {
+					let old = proj.version().clone();
+
+					let new = proj.with_config(|conf| {
+						let version = &mut conf.package.version;
+
+						match part {
+							cli::VersionPart::Major => {
+								version.major += 1;
+								version.minor = 0;
+								version.patch = 0;
+							}
+							cli::VersionPart::Minor => {
+								version.minor += 1;
+								version.patch = 0;
+							}
+							cli::VersionPart::Patch => version.patch += 1,
+						}
+
+						version.pre = semver::Prerelease::EMPTY;
+						version.build = semver::BuildMetadata::EMPTY;
+
+						version.clone()
+					})?;
 
-			println!("Removed {} from {}.", name.yellow(), "cpkg.toml".yellow());
+					log::info!(
+						"Bumped {} from {} to {}.",
+						proj.name(),
+						old.to_string().yellow(),
+						new.to_string().yellow()
+					);
+				}
+			}
 		}
 
-		cli::Commands::Install => {
-			let proj = Project::open(&cd)?;
+		cli::Commands::Completions { shell } => {
+			let mut cmd = <cli::Cli as clap::CommandFactory>::command();
+			clap_complete::generate(*shell, &mut cmd, "cpkg", &mut std::io::stdout());
 
-			let now = std::time::Instant::now();
+			if let Some(hook) = cli::dynamic_completions(*shell) {
+				print!("{hook}");
+			}
+		}
 
-			proj.install_deps()?;
+		cli::Commands::Complete { kind } => {
+			/* Completion shouldn't print errors outside of a project */
+			if let Ok(proj) = Project::open(&cd) {
+				let names = match kind {
+					cli::CompleteKind::Scripts => proj.config().scripts.entries.keys().collect::<Vec<_>>(),
+					cli::CompleteKind::Deps => proj.config().dependencies.entries.keys().collect(),
+				};
 
-			println!(
-				"Installed {} dependencies in {} seconds.",
-				proj.config().dependencies.len().to_string().yellow(),
-				now.elapsed().as_secs_f32().to_string().yellow()
-			);
+				for name in names {
+					println!("{name}");
+				}
+			}
 		}
 
-		cli::Commands::Repl => {
-			use std::io::Write;
+		cli::Commands::Info { json } => {
+			let proj = Project::open(&cd)?;
+			let info = info::gather(&proj)?;
 
-			println!("{}", "Please note that the repl is very basic and experimental.\nYour code will run entirely each line.".yellow());
+			if *json {
+				println!("{}", serde_json::to_string_pretty(&info)?);
+				return Ok(());
+			}
 
-			let backend = compiler::try_locate(None)?;
+			println!("{} {}", info.name.bold(), info.version);
 
-			let temp_repl = tempfile::Builder::new()
-				.prefix("cpkg-repl")
-				.suffix(".c")
-				.tempfile()?
-				.into_temp_path();
+			if let Some(ref description) = info.description {
+				println!("{description}");
+			}
 
-			let temp_bin = tempfile::Builder::new().tempfile()?.into_temp_path();
+			let mut summary = table::Table::new(2);
 
-			let mut stdout = std::io::stdout().lock();
-			let mut buffer = String::new();
+			let details = [
+				("Authors", info.authors.join(", ")),
+				("License", info.license.clone().unwrap_or_default()),
+				("Repository", info.repository.clone().unwrap_or_default()),
+				("Homepage", info.homepage.clone().unwrap_or_default()),
+				("Keywords", info.keywords.join(", ")),
+			];
 
-			let mut editor = rustyline::DefaultEditor::new()?;
-			let mut marker = 0;
+			for (label, value) in details.into_iter().filter(|(_, v)| !v.is_empty()) {
+				summary.row([label.to_owned(), value]);
+			}
 
-			loop {
-				let temp = editor.readline("> ")?;
-				editor.add_history_entry(&temp)?;
+			let backend = |b: &info::Backend| -> table::Cell {
+				match (&b.selected, &b.configured) {
+					(Some(selected), Some(configured)) if selected != configured => {
+						format!("{selected} (configured: {configured})").into()
+					}
+					(Some(selected), _) => selected.as_str().into(),
+					(None, Some(configured)) => {
+						table::Cell::styled(format!("{configured} (not found)"), |s| s.red())
+					}
+					(None, None) => table::Cell::styled("none found", |s| s.red()),
+				}
+			};
 
-				let total = [buffer.clone(), temp].join("");
+			summary.row(["Targets".to_owned(), info.targets.join(", ")]);
+			summary.row(["Sources".to_owned(), format!("{} file(s)", info.sources)]);
+			summary.row(["Tests".to_owned(), format!("{} file(s)", info.tests)]);
+			summary.row(["Compiler".into(), backend(&info.compiler)]);
+			summary.row(["Formatter".into(), backend(&info.formatter)]);
+			summary.row(["Docgen".into(), backend(&info.docgen)]);
+			summary.row(["Target size".to_owned(), human_size(info.target_size)]);
 
-				#[rustfmt::skip]
-				std::fs::write(
-					&temp_repl,
-					indoc::formatdoc!(r#"
-						#include <stdio.h>
+			summary.print();
 
-						int main() {{
-							{total}
-							return 0;
-						}}
-					"#)
-				)?;
+			if !info.dependencies.is_empty() {
+				println!("\n{}", "Dependencies".bold());
 
-				match backend.compile(
-					&[temp_repl.to_path_buf()],
-					&[],
-					&temp_bin,
-					&["-w".to_owned(), "-fdiagnostics-color=always".to_owned()],
-				) {
-					Ok(_) => {
-						let out = std::process::Command::new(&temp_bin).output()?;
+				let mut deps = table::Table::new(3).indent(2).shrink(1);
 
-						if out.status.success() {
-							buffer = total; // Only update entire code if ran successfully
+				for dep in &info.dependencies {
+					let status = if dep.installed {
+						table::Cell::styled("installed", |s| s.green())
+					} else {
+						table::Cell::styled("not installed", |s| s.red())
+					};
 
-							let visible = &out.stdout[marker..];
+					deps.row([dep.name.as_str().into(), dep.source.as_str().into(), status]);
+				}
 
-							stdout.write(visible)?;
-							if !visible.ends_with(b"\n") {
-								stdout.write(b"\n")?;
-							}
+				deps.print();
+			}
+		}
 
-							marker = out.stdout.len();
-						} else {
-							stdout.write(b"Failed to run: ")?;
-							stdout.write(&out.stderr)?;
-							stdout.write(b"\n")?;
-						}
+		cli::Commands::Doctor => {
+			let proj = Project::open(&cd).ok();
+			let report = doctor::probe(proj.as_ref());
 
-						stdout.flush()?;
-					}
-					Err(e) => {
-						print!("{e}");
+			let mut tools = table::Table::new(5)
+				.header(&["Component", "Tool", "Path", "Version", ""])
+				.shrink(2);
+
+			for tool in &report.tools {
+				let path = match &tool.path {
+					Some(path) => path.display().to_string().into(),
+					None => table::Cell::styled("not found", |s| s.red()),
+				};
+
+				let selected = if tool.selected {
+					table::Cell::styled("selected", |s| s.green())
+				} else {
+					"".into()
+				};
+
+				tools.row([
+					tool.component.into(),
+					tool.name.as_str().into(),
+					path,
+					tool.version.as_deref().unwrap_or_default().into(),
+					selected,
+				]);
+			}
+
+			tools.print();
+
+			for warning in &report.warnings {
+				log::warn(warning);
+			}
+		}
+
+		cli::Commands::Upgrade {
+			check,
+			version,
+			prerelease,
+			yes,
+		} => {
+			let current = upgrade::current();
+
+			let release = match version {
+				Some(version) if *version == current => {
+					log::info!("Already on cpkg v{current}.");
+					return Ok(());
+				}
+				Some(version) => upgrade::find(version)?,
+				None => match upgrade::newer(*prerelease)? {
+					Some(release) => release,
+					None => {
+						log::info!("cpkg v{current} is up to date.");
+						return Ok(());
 					}
+				},
+			};
+
+			println!(
+				"{} v{current} -> v{}",
+				"cpkg".bold(),
+				release.version.yellow()
+			);
+
+			if let Some(notes) = release.body.as_deref().map(str::trim).filter(|b| !b.is_empty()) {
+				println!("\n{notes}\n");
+			}
+
+			if *check {
+				return Ok(());
+			}
+
+			if !yes {
+				print!("Replace the current binary? (y/n) ");
+				std::io::Write::flush(&mut std::io::stdout())?;
+
+				let mut s = String::new();
+				std::io::stdin().read_line(&mut s)?;
+
+				if !s.trim().eq_ignore_ascii_case("y") {
+					anyhow::bail!("Upgrade cancelled. Pass --yes to upgrade without being asked.");
 				}
 			}
-		}
 
-		cli::Commands::Upgrade => {
-			self_update::backends::github::Update::configure()
-				.repo_owner("DvvCz")
-				.repo_name("cpkg")
-				.bin_name("cpkg")
-				.show_download_progress(true)
-				.current_version(self_update::cargo_crate_version!())
-				.build()?
-				.update()?;
+			upgrade::install(&release)?;
+			log::info!("Upgraded to cpkg v{}.", release.version);
 		}
 	}
 
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A project with a script named like a file that always exists where tests run.
+	fn runner() -> tempfile::TempDir {
+		testing::project(
+			"\n[scripts]\nlint = \"true\"\n\"Cargo.toml\" = \"true\"\n",
+			&[],
+		)
+	}
+
+	fn resolve(
+		proj: Option<&Project>,
+		path: Option<&str>,
+		files: &[&str],
+		script: Option<&str>,
+		file: Option<&str>,
+	) -> anyhow::Result<Runnable> {
+		resolve_run(
+			proj,
+			&path.map(str::to_owned),
+			&files
+				.iter()
+				.map(std::path::PathBuf::from)
+				.collect::<Vec<_>>(),
+			&script.map(str::to_owned),
+			&file.map(std::path::PathBuf::from),
+		)
+	}
+
+	fn error(result: anyhow::Result<Runnable>) -> String {
+		match result {
+			Ok(_) => panic!("resolved when it should have failed"),
+			Err(e) => e.to_string(),
+		}
+	}
+
+	#[test]
+	fn runs_project_without_arguments() {
+		let temp = runner();
+		let proj = Project::open(temp.path()).unwrap();
+
+		assert!(matches!(
+			resolve(Some(&proj), None, &[], None, None).unwrap(),
+			Runnable::Project
+		));
+		assert!(matches!(
+			resolve(None, None, &[], None, None).unwrap(),
+			Runnable::Project
+		));
+	}
+
+	#[test]
+	fn resolves_scripts() {
+		let temp = runner();
+		let proj = Project::open(temp.path()).unwrap();
+
+		let by_flag = resolve(Some(&proj), None, &[], Some("lint"), None).unwrap();
+		assert!(matches!(by_flag, Runnable::Script(ref s) if s == "lint"));
+
+		let by_name = resolve(Some(&proj), Some("lint"), &[], None, None).unwrap();
+		assert!(matches!(by_name, Runnable::Script(ref s) if s == "lint"));
+
+		/* --script wins over a file of the same name */
+		let clash = resolve(Some(&proj), None, &[], Some("Cargo.toml"), None).unwrap();
+		assert!(matches!(clash, Runnable::Script(ref s) if s == "Cargo.toml"));
+	}
+
+	#[test]
+	fn reports_bad_scripts() {
+		let temp = runner();
+		let proj = Project::open(temp.path()).unwrap();
+
+		let unknown = error(resolve(Some(&proj), None, &[], Some("bench"), None));
+		assert!(
+			unknown.contains("Scripts are: Cargo.toml, lint"),
+			"{unknown}"
+		);
+
+		let extra = error(resolve(Some(&proj), None, &["more.c"], Some("lint"), None));
+		assert!(
+			extra.contains("only be compiled along with a file"),
+			"{extra}"
+		);
+
+		let extra = error(resolve(Some(&proj), Some("lint"), &["more.c"], None, None));
+		assert!(
+			extra.contains("only be compiled along with a file"),
+			"{extra}"
+		);
+
+		let outside = error(resolve(None, None, &[], Some("lint"), None));
+		assert!(outside.contains("no project here"), "{outside}");
+
+		std::fs::write(
+			temp.path().join("cpkg.toml"),
+			"[package]\nname = \"runner\"\n",
+		)
+		.unwrap();
+		let proj = Project::open(temp.path()).unwrap();
+
+		let none = error(resolve(Some(&proj), None, &[], Some("lint"), None));
+		assert!(none.contains("cpkg.toml has no scripts"), "{none}");
+	}
+
+	#[test]
+	fn resolves_files() {
+		let temp = runner();
+		let proj = Project::open(temp.path()).unwrap();
+
+		/* Doesn't exist, but isn't a script either, so compiling it reports what's wrong */
+		let bare = resolve(Some(&proj), Some("hello.c"), &["util.c"], None, None).unwrap();
+		assert!(
+			matches!(bare, Runnable::File(ref f, ref extra) if f.as_os_str() == "hello.c" && extra.len() == 1)
+		);
+
+		let outside = resolve(None, Some("lint"), &[], None, None).unwrap();
+		assert!(matches!(outside, Runnable::File(ref f, _) if f.as_os_str() == "lint"));
+
+		/* With --file, the positional argument is compiled along with it, even if it names a script */
+		let flagged = resolve(
+			Some(&proj),
+			Some("lint"),
+			&["b.c"],
+			None,
+			Some("Cargo.toml"),
+		)
+		.unwrap();
+		let Runnable::File(file, extra) = flagged else {
+			panic!("--file runs a file");
+		};
+
+		assert_eq!(file.as_os_str(), "Cargo.toml");
+		assert_eq!(extra, [std::path::PathBuf::from("lint"), "b.c".into()]);
+	}
+
+	#[test]
+	fn refuses_names_that_are_both() {
+		let temp = runner();
+		let proj = Project::open(temp.path()).unwrap();
+
+		let both = error(resolve(Some(&proj), Some("Cargo.toml"), &[], None, None));
+		assert!(
+			both.contains("--script Cargo.toml") && both.contains("--file Cargo.toml"),
+			"{both}"
+		);
+	}
+}
@@ -0,0 +1,111 @@
+/// Machine readable output for `--message-format json`, printed one object per line.
+/// Tools parse these, so fields should only ever be added, not renamed or removed.
+#[derive(serde::Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum Message<'a> {
+	/// A diagnostic the compiler printed about a source file
+	Diagnostic {
+		file: &'a std::path::Path,
+		line: usize,
+		column: Option<usize>,
+		severity: Severity,
+		message: &'a str,
+	},
+
+	/// Output of a failed compile with no errors that could be parsed, like from the linker
+	CompilerOutput {
+		output: &'a str,
+		/// Suggestion from cpkg on what went wrong
+		hint: Option<&'a str>,
+	},
+
+	/// A file the build produced
+	Artifact { path: &'a std::path::Path },
+
+	TestResult {
+		path: &'a std::path::Path,
+		/// Case within the file, for tests using the cpkg_test.h harness
+		case: Option<&'a str>,
+		/// Whether the test didn't fail, which includes expected failures and skipped tests
+		passed: bool,
+		status: crate::TestStatus,
+		/// Why the test was skipped, if it said
+		skip_reason: Option<&'a str>,
+		/// Whether the binary from a previous run was reused
+		cached: bool,
+		/// Seconds the test took to run
+		duration: f64,
+		/// What the test printed to stderr, if it failed
+		stderr: Option<&'a str>,
+		/// Compiled test, unless it failed to compile
+		binary: Option<&'a std::path::Path>,
+	},
+
+	/// Always the last message, telling whether everything succeeded
+	Finished { success: bool },
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+	Error,
+	Warning,
+	Note,
+}
+
+impl From<crate::compiler::Severity> for Severity {
+	fn from(severity: crate::compiler::Severity) -> Self {
+		match severity {
+			crate::compiler::Severity::Error => Self::Error,
+			crate::compiler::Severity::Warning => Self::Warning,
+			crate::compiler::Severity::Note => Self::Note,
+		}
+	}
+}
+
+pub fn emit(message: &Message) {
+	/* Only fails on maps with non-string keys, which these don't have */
+	if let Ok(json) = serde_json::to_string(message) {
+		println!("{json}");
+	}
+}
+
+/// Emits the diagnostics of a failed build.
+/// Returns false if the error wasn't from compiling, so should be reported normally.
+pub fn emit_error(err: &anyhow::Error) -> bool {
+	let failures = if let Some(errors) = err.downcast_ref::<crate::compiler::CompileErrors>() {
+		errors.failures.iter().collect::<Vec<_>>()
+	} else if let Some(error) = err.downcast_ref::<crate::compiler::CompileError>() {
+		vec![error]
+	} else {
+		return false;
+	};
+
+	let mut seen = std::collections::HashSet::new();
+
+	for failure in failures {
+		let parsed = failure
+			.diagnostics
+			.iter()
+			.any(|d| d.severity == crate::compiler::Severity::Error);
+
+		if !parsed {
+			emit(&Message::CompilerOutput {
+				output: &failure.output,
+				hint: failure.hint,
+			});
+		}
+
+		for diagnostic in failure.diagnostics.iter().filter(|d| seen.insert(*d)) {
+			emit(&Message::Diagnostic {
+				file: &diagnostic.file,
+				line: diagnostic.line,
+				column: diagnostic.column,
+				severity: diagnostic.severity.into(),
+				message: &diagnostic.message,
+			});
+		}
+	}
+
+	true
+}
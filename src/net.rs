@@ -0,0 +1,387 @@
+/// How network operations are retried, from the `[net]` config section.
+pub struct RetryPolicy {
+	pub attempts: u32,
+	/// Delay before the first retry, doubled for each one after
+	pub backoff: std::time::Duration,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			attempts: 3,
+			backoff: std::time::Duration::from_millis(500),
+		}
+	}
+}
+
+impl RetryPolicy {
+	pub fn from_config(config: &crate::Config) -> Self {
+		let mut policy = Self::default();
+
+		if let Some(ref net) = config.net {
+			if let Some(attempts) = net.attempts {
+				policy.attempts = attempts.max(1);
+			}
+
+			if let Some(backoff) = net.backoff {
+				policy.backoff = std::time::Duration::from_millis(backoff);
+			}
+		}
+
+		policy
+	}
+
+	/// Delay before the given retry (starting at 1), with up to 50% jitter.
+	fn delay(&self, retry: u32) -> std::time::Duration {
+		let base = self.backoff.saturating_mul(1 << (retry - 1).min(16));
+
+		/* Good enough randomness to keep parallel CI jobs from retrying in lockstep */
+		let nanos = std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.map(|d| d.subsec_nanos())
+			.unwrap_or(0);
+
+		base + base.mul_f64(f64::from(nanos % 1000) / 2000.0)
+	}
+
+	/// Runs `op` until it succeeds, fails permanently, or runs out of attempts.
+	/// `cleanup` runs between attempts so each one starts fresh.
+	pub fn run<T>(
+		&self,
+		what: &str,
+		mut op: impl FnMut() -> Result<T, Failure>,
+		mut cleanup: impl FnMut(),
+	) -> anyhow::Result<T> {
+		let mut attempt = 1;
+
+		loop {
+			match op() {
+				Ok(v) => return Ok(v),
+				Err(Failure::Other(e)) => return Err(e),
+				Err(Failure::Transient(e)) => {
+					if attempt >= self.attempts {
+						return Err(e.context(format!("{what} failed after {attempt} attempts")));
+					}
+
+					let delay = self.delay(attempt);
+					crate::log::warn(format!(
+						"{what} failed ({}), retrying in {:.1}s [{}/{}]",
+						e.to_string().lines().last().unwrap_or_default().trim(),
+						delay.as_secs_f32(),
+						attempt + 1,
+						self.attempts
+					));
+
+					cleanup();
+					std::thread::sleep(delay);
+
+					attempt += 1;
+				}
+			}
+		}
+	}
+}
+
+/// Clones a git repository into `to`, retrying transient failures.
+/// A shallow clone only fetches the latest commit.
+pub fn clone(
+	policy: &RetryPolicy,
+	what: &str,
+	url: &str,
+	to: &std::path::Path,
+	shallow: bool,
+) -> anyhow::Result<()> {
+	clone_with("git".as_ref(), policy, what, url, to, shallow)
+}
+
+/// [clone] with a specific git, so tests can stand in for it.
+fn clone_with(
+	git: &std::ffi::OsStr,
+	policy: &RetryPolicy,
+	what: &str,
+	url: &str,
+	to: &std::path::Path,
+	shallow: bool,
+) -> anyhow::Result<()> {
+	let clone = || {
+		let mut cmd = std::process::Command::new(git);
+		cmd.arg("clone");
+
+		if shallow {
+			cmd.args(["--depth", "1"]);
+		}
+
+		cmd.arg(url).arg(to);
+		crate::log::command(&cmd);
+
+		let out = cmd.output().map_err(|e| Failure::Other(e.into()))?;
+
+		if out.status.success() {
+			return Ok(());
+		}
+
+		let stderr = String::from_utf8_lossy(&out.stderr);
+		let err = anyhow::anyhow!("{}", stderr.trim());
+
+		if is_transient(out.status, &stderr) {
+			Err(Failure::Transient(err))
+		} else {
+			Err(Failure::Other(err))
+		}
+	};
+
+	/* A failed clone can leave a partial checkout behind */
+	let cleanup = || {
+		let _ = std::fs::remove_dir_all(to);
+	};
+
+	policy.run(what, clone, cleanup)
+}
+
+/// Result of a failed network operation.
+pub enum Failure {
+	/// Might succeed if tried again
+	Transient(anyhow::Error),
+	/// Won't succeed no matter how many times it's tried
+	Other(anyhow::Error),
+}
+
+/// Patterns in git's stderr that mean retrying is pointless.
+const PERMANENT: &[&str] = &[
+	"authentication failed",
+	"could not read username",
+	"could not read password",
+	"permission denied",
+	"repository not found",
+	"not found",
+	"does not appear to be a git repository",
+	"returned error: 401",
+	"returned error: 403",
+	"returned error: 404",
+	"already exists and is not an empty directory",
+];
+
+/// Patterns in git's stderr that look like a flaky network.
+const TRANSIENT: &[&str] = &[
+	"could not resolve host",
+	"could not resolve proxy",
+	"connection timed out",
+	"operation timed out",
+	"connection reset",
+	"connection refused",
+	"failed to connect",
+	"early eof",
+	"the remote end hung up unexpectedly",
+	"rpc failed",
+	"gnutls_handshake",
+	"ssl_connect",
+	"tls connection",
+	"returned error: 429",
+	"returned error: 500",
+	"returned error: 502",
+	"returned error: 503",
+	"returned error: 504",
+	"network is unreachable",
+	"temporary failure",
+];
+
+/// Whether a failed git command looks worth retrying, from its exit status and stderr.
+pub fn is_transient(status: std::process::ExitStatus, stderr: &str) -> bool {
+	let stderr = stderr.to_lowercase();
+
+	if PERMANENT.iter().any(|p| stderr.contains(p)) {
+		return false;
+	}
+
+	/* Killed by a signal, e.g. a CI timeout on a hung connection */
+	status.code().is_none() || TRANSIENT.iter().any(|p| stderr.contains(p))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[cfg(unix)]
+	fn status(code: i32) -> std::process::ExitStatus {
+		std::os::unix::process::ExitStatusExt::from_raw(code << 8)
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn classifies_failures() {
+		assert!(is_transient(
+			status(128),
+			"fatal: unable to access 'https://x/': Could not resolve host: x"
+		));
+		assert!(is_transient(
+			status(128),
+			"error: RPC failed; curl 56 GnuTLS recv error\nfatal: early EOF"
+		));
+		assert!(is_transient(
+			status(128),
+			"The requested URL returned error: 503"
+		));
+
+		assert!(!is_transient(
+			status(128),
+			"remote: Repository not found.\nfatal: repository 'x' not found"
+		));
+		assert!(!is_transient(
+			status(128),
+			"fatal: Authentication failed for 'https://x/'"
+		));
+		assert!(!is_transient(
+			status(128),
+			"The requested URL returned error: 403"
+		));
+		assert!(!is_transient(status(128), "fatal: some other problem"));
+
+		/* Killed by a signal */
+		assert!(is_transient(
+			std::os::unix::process::ExitStatusExt::from_raw(9),
+			""
+		));
+	}
+
+	#[test]
+	fn delay_backs_off_with_jitter() {
+		let policy = RetryPolicy {
+			attempts: 5,
+			backoff: std::time::Duration::from_millis(100),
+		};
+
+		for (retry, base) in [(1, 100), (2, 200), (3, 400)] {
+			let delay = policy.delay(retry).as_millis();
+			assert!(
+				(base..=base * 3 / 2).contains(&delay),
+				"retry {retry} waited {delay}ms"
+			);
+		}
+	}
+
+	/// A git that fails its first `failures` clones with `stderr`, leaving a partial checkout behind each time.
+	/// It refuses to clone into a folder that already exists, like the real one.
+	#[cfg(unix)]
+	fn fake_git(dir: &std::path::Path, failures: u32, stderr: &str) -> std::path::PathBuf {
+		use std::os::unix::fs::PermissionsExt;
+
+		let script = dir.join("git");
+		let count = dir.join("count");
+
+		std::fs::write(
+			&script,
+			format!(
+				indoc::indoc! {r#"
+					#!/bin/sh
+					for to; do :; done
+					if [ -e "$to" ]; then
+						echo "fatal: destination path '$to' already exists and is not an empty directory." >&2
+						exit 128
+					fi
+					echo x >> '{count}'
+					mkdir -p "$to"
+					if [ "$(wc -l < '{count}')" -le {failures} ]; then
+						touch "$to/partial"
+						echo '{stderr}' >&2
+						exit 128
+					fi
+					touch "$to/cloned"
+				"#},
+				count = count.display(),
+				failures = failures,
+				stderr = stderr,
+			),
+		)
+		.unwrap();
+
+		std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+		script
+	}
+
+	#[cfg(unix)]
+	fn attempts(dir: &std::path::Path) -> usize {
+		std::fs::read_to_string(dir.join("count")).map_or(0, |c| c.lines().count())
+	}
+
+	#[cfg(unix)]
+	fn policy() -> RetryPolicy {
+		RetryPolicy {
+			attempts: 3,
+			backoff: std::time::Duration::ZERO,
+		}
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn retries_transient_failures() {
+		let temp = tempfile::tempdir().unwrap();
+		let git = fake_git(
+			temp.path(),
+			2,
+			"fatal: unable to access: Could not resolve host: example.com",
+		);
+		let to = temp.path().join("dep");
+
+		clone_with(
+			git.as_os_str(),
+			&policy(),
+			"Cloning dep",
+			"https://example.com/dep",
+			&to,
+			false,
+		)
+		.unwrap();
+
+		assert_eq!(attempts(temp.path()), 3);
+		assert!(to.join("cloned").exists());
+		assert!(
+			!to.join("partial").exists(),
+			"partial checkouts are removed between attempts"
+		);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn gives_up_after_attempts() {
+		let temp = tempfile::tempdir().unwrap();
+		let git = fake_git(temp.path(), 5, "fatal: the remote end hung up unexpectedly");
+		let to = temp.path().join("dep");
+
+		let err = clone_with(
+			git.as_os_str(),
+			&policy(),
+			"Cloning dep",
+			"https://example.com/dep",
+			&to,
+			false,
+		)
+		.unwrap_err();
+
+		assert_eq!(attempts(temp.path()), 3);
+		assert!(
+			format!("{err:#}").contains("Cloning dep failed after 3 attempts"),
+			"{err:#}"
+		);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn permanent_failures_are_not_retried() {
+		let temp = tempfile::tempdir().unwrap();
+		let git = fake_git(temp.path(), 5, "remote: Repository not found.");
+		let to = temp.path().join("dep");
+
+		let err = clone_with(
+			git.as_os_str(),
+			&policy(),
+			"Cloning dep",
+			"https://example.com/dep",
+			&to,
+			true,
+		)
+		.unwrap_err();
+
+		assert_eq!(attempts(temp.path()), 1);
+		assert!(err.to_string().contains("Repository not found"), "{err}");
+	}
+}
@@ -0,0 +1,130 @@
+/// Everything in the project that goes into a source package, relative to the project root.
+/// The source and tests folders go in along with the committed vendor snapshot.
+/// Files matched by .gitignore or `exclude` under [package] are left out, and target/ never goes in.
+pub fn files(proj: &crate::Project) -> anyhow::Result<Vec<std::path::PathBuf>> {
+	let root = proj.path();
+
+	let mut ignore = ignore::gitignore::GitignoreBuilder::new(root);
+
+	if let Some(e) = ignore.add(root.join(".gitignore")) {
+		/* A missing .gitignore just means nothing is ignored */
+		if root.join(".gitignore").exists() {
+			anyhow::bail!("Failed to read .gitignore: {e}");
+		}
+	}
+
+	for pattern in &proj.config().package.exclude {
+		ignore
+			.add_line(None, pattern)
+			.map_err(|e| anyhow::anyhow!("Invalid pattern '{pattern}' in exclude: {e}"))?;
+	}
+
+	let ignore = ignore.build()?;
+
+	let dirs = proj
+		.src()
+		.into_iter()
+		.chain([proj.tests()])
+		.chain(proj.snapshot())
+		.filter(|d| d.is_dir());
+
+	let mut files = vec![];
+
+	for dir in dirs {
+		let walk = walkdir::WalkDir::new(dir)
+			.follow_links(true)
+			.into_iter()
+			.filter_entry(|e| !ignore.matched(e.path(), e.file_type().is_dir()).is_ignore());
+
+		for entry in walk {
+			let entry = entry?;
+
+			if entry.file_type().is_file() {
+				files.push(entry.path().strip_prefix(root)?.to_owned());
+			}
+		}
+	}
+
+	for file in [crate::lockfile::Lockfile::FILE, "build.c"] {
+		if source(proj, file.as_ref()).is_file() && !ignore.matched(file, false).is_ignore() {
+			files.push(file.into());
+		}
+	}
+
+	for file in std::fs::read_dir(root)? {
+		let file = file?;
+		let name = file.file_name().to_string_lossy().into_owned();
+
+		if file.file_type()?.is_file()
+			&& (name.starts_with("README") || name.starts_with("LICENSE"))
+			&& !ignore.matched(&name, false).is_ignore()
+		{
+			files.push(name.into());
+		}
+	}
+
+	/* The manifest is needed to build with cpkg, so it can't be excluded */
+	files.push("cpkg.toml".into());
+
+	files.sort();
+	Ok(files)
+}
+
+/// Where a file listed by [files] is read from.
+/// That's inside the project, besides cpkg.lock, which members of a workspace share from its root.
+pub fn source(proj: &crate::Project, file: &std::path::Path) -> std::path::PathBuf {
+	if file == std::path::Path::new(crate::lockfile::Lockfile::FILE) {
+		proj.lock_dir().join(file)
+	} else {
+		proj.path().join(file)
+	}
+}
+
+/// Path dependencies that won't exist next to an extracted package, since they point outside of it.
+pub fn unpackaged_deps(proj: &crate::Project) -> Vec<String> {
+	if proj.snapshot().is_some() && crate::snapshot::problems(proj).is_empty() {
+		return vec![];
+	}
+
+	proj.config()
+		.dependencies
+		.entries
+		.iter()
+		.filter(
+			|(_, dep)| matches!(dep, crate::ConfigDependency::Path { path, .. } if path.is_relative()),
+		)
+		.map(|(name, _)| name.clone())
+		.collect()
+}
+
+/// Extracts a source package to a temporary folder and builds it there, to catch files it's missing.
+pub fn verify(
+	archive: &std::path::Path,
+	stem: &str,
+	can_run_build: impl FnOnce() -> bool,
+) -> anyhow::Result<()> {
+	let dir = tempfile::Builder::new().prefix("cpkg-package").tempdir()?;
+
+	tar::Archive::new(flate2::read::GzDecoder::new(std::fs::File::open(archive)?))
+		.unpack(dir.path())?;
+
+	let root = dir.path().join(stem);
+	let proj = crate::Project::open(&root)?;
+
+	if proj.snapshot().is_none() || !crate::snapshot::problems(&proj).is_empty() {
+		proj.install_deps(false)?;
+	}
+
+	let targets = crate::target::defaults(&proj)?
+		.into_iter()
+		.filter(|t| *t != crate::target::Target::Tests)
+		.collect::<Vec<_>>();
+
+	proj.build(
+		crate::compiler::try_locate(Some(&proj))?.as_ref(),
+		&targets,
+		can_run_build,
+	)?;
+
+	Ok(())
+}
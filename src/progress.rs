@@ -0,0 +1,91 @@
+use colored::Colorize;
+
+/// Whether a status line is on screen without a newline after it.
+static SHOWING: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Reports a loop over slow items, like cloning dependencies or compiling tests, one `[3/12] Compiling x` line each.
+/// On a terminal the line shows while the item runs, then gets replaced with how long it took.
+/// Otherwise only that finished line is printed, and nothing is with `--quiet`.
+/// With a [crate::log::set_handler] handler, each item is just an info record before it runs.
+pub struct Progress {
+	total: usize,
+	done: usize,
+	shown: bool,
+	live: bool,
+}
+
+impl Progress {
+	pub fn new(total: usize) -> Self {
+		use std::io::IsTerminal;
+
+		Self {
+			total,
+			done: 0,
+			shown: crate::log::level() >= crate::log::Level::Normal,
+			live: std::io::stdout().is_terminal(),
+		}
+	}
+
+	/// Only reports anything if `shown`, for loops that aren't worth reporting when short.
+	pub fn shown(mut self, shown: bool) -> Self {
+		self.shown &= shown;
+		self
+	}
+
+	/// Runs `op` for the next item, reporting it with `verb` before and after.
+	/// A failure is reported on a line of its own, so the error printed afterward doesn't overwrite it.
+	pub fn item<T>(
+		&mut self,
+		verb: &str,
+		name: &str,
+		op: impl FnOnce() -> anyhow::Result<T>,
+	) -> anyhow::Result<T> {
+		use std::io::Write;
+
+		self.done += 1;
+
+		let width = self.total.to_string().len();
+		let count = format!("[{:>width$}/{}]", self.done, self.total);
+
+		if crate::log::handled() {
+			crate::log::status(&format!("{count} {verb} {name}"));
+			return op();
+		}
+
+		if self.shown && self.live {
+			print!("{} {verb} {name}..", count.dimmed());
+			let _ = std::io::stdout().flush();
+
+			SHOWING.store(true, std::sync::atomic::Ordering::Relaxed);
+		}
+
+		let start = std::time::Instant::now();
+		let result = op();
+		let elapsed = start.elapsed();
+
+		if self.shown {
+			/* Clear the status line, unless something else already moved past it */
+			if SHOWING.swap(false, std::sync::atomic::Ordering::Relaxed) {
+				print!("\r\x1b[2K");
+			}
+
+			match &result {
+				Ok(_) => println!(
+					"{} {verb} {name} {}",
+					count.dimmed(),
+					format!("({:.2}s)", elapsed.as_secs_f32()).dimmed()
+				),
+				Err(_) => println!("{} {verb} {name} {}", count.dimmed(), "failed".red()),
+			}
+		}
+
+		result
+	}
+}
+
+/// Ends a status line being shown, so output printed while an item runs starts on a line of its own.
+pub fn interrupt() {
+	if SHOWING.swap(false, std::sync::atomic::Ordering::Relaxed) {
+		println!();
+	}
+}
@@ -1,10 +1,220 @@
 use crate::ConfigDependency;
+use colored::Colorize;
+
+pub mod fingerprint;
+pub mod overrides;
+
+#[cfg(test)]
+pub mod testing;
+
+/// Files written by [Project::init], relative to the project root.
+/// Both paths and contents go through [crate::template::Vars::render].
+const SCAFFOLD: &[(&str, &str)] = &[
+	(
+		"src/main.c",
+		indoc::indoc! {r#"
+			#include <stdio.h>
+
+			int main() {
+				printf("Hello, world!\n");
+				return 0;
+			}
+		"#},
+	),
+	(
+		"src/main.test.c",
+		indoc::indoc! {r#"
+			#include <assert.h>
+
+			int main() {
+				assert( (1 + 2 == 3) && "C is broken" );
+			}
+		"#},
+	),
+];
+
+/// README.md written by [Project::init] when the project doesn't have a README, rendered like [SCAFFOLD].
+const README: &str = indoc::indoc! {"
+	# {{name}}
+
+	## Building
+
+	```sh
+	cpkg build
+	cpkg run
+	```
+
+	## Testing
+
+	```sh
+	cpkg test
+	```
+"};
+
+/// Options for [Project::create] and [Project::init].
+#[derive(Default)]
+pub struct InitOptions {
+	/// Extra template variables, from `--define key=value`
+	pub defines: Vec<(String, String)>,
+	/// Template to copy instead of the default scaffold, from `--template`
+	pub template: Option<String>,
+	/// Package name to use instead of the folder's, from `--name`
+	pub name: Option<String>,
+	/// Skips creating a git repository, from `--no-git`
+	pub no_git: bool,
+	/// License to write to LICENSE, from `--license`, the user config or asked for
+	pub license: crate::license::License,
+	/// Summary recorded under [package], from `--description`
+	pub description: Option<String>,
+	/// Authors recorded under [package], from `--author`, or the one LICENSE names if none
+	pub authors: Vec<String>,
+	/// Source repository url recorded under [package], from `--repository`
+	pub repository: Option<String>,
+	/// Website url recorded under [package], from `--homepage`
+	pub homepage: Option<String>,
+	/// Keywords recorded under [package], from `--keyword`
+	pub keywords: Vec<String>,
+}
+
+/// Which flags a build uses and where its outputs go.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, clap::ValueEnum)]
+pub enum Profile {
+	/// Unoptimized, with debug info from the flags under [compiler], built into target
+	#[default]
+	Debug,
+	/// Optimized, with asserts disabled, built into target/release
+	Release,
+}
+
+impl Profile {
+	pub fn flags(&self) -> &'static [&'static str] {
+		match self {
+			Self::Debug => &[],
+			Self::Release => &["-O2", "-DNDEBUG"],
+		}
+	}
+}
+
+impl std::fmt::Display for Profile {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Debug => write!(f, "debug"),
+			Self::Release => write!(f, "release"),
+		}
+	}
+}
+
+/// How much `cpkg build --timings` reports.
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Timings {
+	/// How long each step took
+	Summary,
+	/// Also where the compiler spent its time, for compilers that can say
+	Verbose,
+}
+
+/// How long a step of a build took, see [Project::with_timings].
+pub struct Timing {
+	pub step: Step,
+	pub output: std::path::PathBuf,
+	pub sources: Vec<std::path::PathBuf>,
+	pub duration: std::time::Duration,
+	/// Where the compiler says its time went, under [Timings::Verbose]
+	pub report: Option<String>,
+}
+
+/// Structured settings under [compiler], kept apart from raw flags so exported build files can lay them out.
+pub struct BuildOptions {
+	pub defines: Vec<(String, String)>,
+	pub include_dirs: Vec<std::path::PathBuf>,
+	pub lib_dirs: Vec<std::path::PathBuf>,
+	pub libs: Vec<String>,
+}
+
+impl BuildOptions {
+	/// gcc-style `-D` flag for a define, leaving off the `=` when it has no value.
+	pub fn define(name: &str, value: &str) -> String {
+		if value.is_empty() {
+			format!("-D{name}")
+		} else {
+			format!("-D{name}={value}")
+		}
+	}
+
+	/// gcc-style flags for compiling, which other backends translate.
+	/// Include dirs are left to [Project::include_paths], so they're fingerprinted along with every other header.
+	pub fn compile_flags(&self) -> Vec<String> {
+		self.defines
+			.iter()
+			.map(|(name, value)| Self::define(name, value))
+			.collect()
+	}
+
+	/// gcc-style flags for linking, which other backends translate.
+	pub fn link_flags(&self) -> Vec<String> {
+		self.lib_dirs
+			.iter()
+			.map(|d| format!("-L{}", d.display()))
+			.chain(self.libs.iter().map(|l| format!("-l{l}")))
+			.collect()
+	}
+}
+
+/// A platform selected with `--target`, from a `[target.<name>]` table or a bare triple.
+#[derive(Clone)]
+pub struct CrossTarget {
+	pub name: String,
+	pub config: crate::ConfigTarget,
+}
+
+impl CrossTarget {
+	/// Whether this builds with emscripten, which outputs an HTML page alongside its .js and .wasm
+	pub fn is_emscripten(&self) -> bool {
+		self.config
+			.compiler
+			.as_deref()
+			.and_then(|c| std::path::Path::new(c).file_stem())
+			.is_some_and(|s| s == "emcc")
+	}
+
+	/// Extension of executables built for this target, if it isn't the usual one.
+	pub fn extension(&self) -> Option<&str> {
+		let windows = self
+			.config
+			.triple
+			.as_deref()
+			.is_some_and(|t| t.contains("windows"));
+
+		self.config
+			.extension
+			.as_deref()
+			.or(self.is_emscripten().then_some("html"))
+			.or(windows.then_some("exe"))
+	}
+}
 
 /// A `cpkg` project.
 /// This is defined as a directory containing a cpkg.toml file inside of it.
 pub struct Project<'a> {
 	path: &'a std::path::Path,
 	config: crate::Config,
+	profile: Profile,
+	cross: Option<CrossTarget>,
+	/// Whether compile errors are passed through as the compiler printed them
+	raw_errors: bool,
+	/// Program to run binaries through, overriding the configured runners
+	runner: Option<String>,
+	/// Workspace the project is a member of, if any
+	workspace: Option<crate::workspace::Workspace>,
+	/// Whether builds record how long each step takes, into `timed`
+	timings: Option<Timings>,
+	timed: std::cell::RefCell<Vec<Timing>>,
+	/// Whether builds ignore the fingerprint and compile everything again
+	force: bool,
+	/// Whether builds include debug info without optimizations, whatever the profile, for running under a debugger
+	debug_info: bool,
+	/// How many compiles and archives the last build ran
+	compiled: std::cell::Cell<usize>,
 }
 
 impl<'a> Project<'a> {
@@ -20,42 +230,284 @@ impl<'a> Project<'a> {
 	/// Folder containing test files
 	const TESTS: &'static str = "tests";
 
+	/// Folder containing example programs
+	const EXAMPLES: &'static str = "examples";
+
+	/// Folder inside of src containing extra binaries, one per file
+	const BINS: &'static str = "bin";
+
+	/// Folder inside of the output folder holding the sources generated to check headers
+	const HEADERS: &'static str = "headers";
+
 	/// Prefix for build commands
 	const BUILD_COMMAND_PREFIX: &'static str = "cpkg::";
 
+	/// File inside of the vendor folder recording which dependencies cpkg installed
+	const VENDOR_LEDGER: &'static str = ".cpkg-installed";
+
+	/// Lines of a failed dependency build's log shown in the error
+	const BUILD_LOG_TAIL: usize = 20;
+
 	/*
 		Paths
 	*/
 
 	pub fn get_or_mkdir(path: std::path::PathBuf) -> anyhow::Result<std::path::PathBuf> {
 		if !path.is_dir() {
-			std::fs::create_dir(&path)?;
+			std::fs::create_dir_all(&path)?;
 		}
 
 		Ok(path)
 	}
 
-	pub fn src(&self) -> std::path::PathBuf {
-		self.path.join(Self::SRC)
+	/// Root directory of the project, containing cpkg.toml
+	pub fn path(&self) -> &std::path::Path {
+		self.path
+	}
+
+	/// Folders holding the sources, just src unless `src` under [package] lists others.
+	pub fn src(&self) -> Vec<std::path::PathBuf> {
+		match self.config.package.src {
+			Some(ref dirs) => dirs.iter().map(|d| self.path.join(d)).collect(),
+			None => vec![self.path.join(Self::SRC)],
+		}
 	}
 
+	/// Output folder, which is the workspace's when the project is a member of one.
 	pub fn target(&self) -> std::path::PathBuf {
-		self.path.join(Self::TARGET)
+		match self.workspace {
+			Some(ref ws) => ws.target(),
+			None => self.path.join(self.target_dir()),
+		}
+	}
+
+	/// Output folder relative to the project, for messages and ignore files.
+	pub fn target_dir(&self) -> &std::path::Path {
+		self.config
+			.package
+			.target
+			.as_deref()
+			.unwrap_or(std::path::Path::new(Self::TARGET))
 	}
 
 	pub fn vendor(&self) -> std::path::PathBuf {
 		self.target().join(Self::VENDOR)
 	}
 
+	/// Committed snapshot made by `cpkg vendor`, if cpkg.toml points at one that exists.
+	pub fn snapshot(&self) -> Option<std::path::PathBuf> {
+		self.config
+			.dependencies
+			.vendor_dir
+			.as_ref()
+			.map(|dir| self.path.join(dir))
+			.filter(|dir| dir.is_dir())
+	}
+
+	/// Folders dependencies are included from, the committed snapshot first.
+	pub fn vendor_dirs(&self) -> Vec<std::path::PathBuf> {
+		self.snapshot().into_iter().chain([self.vendor()]).collect()
+	}
+
+	/// Folders headers are searched for in when compiling `src`, shared by builds, tests, the repl and exports.
+	/// Dependencies are included as <dep/header.h>, besides the ones with `include = "flat"`, whose own folders are searched too.
+	/// `include-dirs` under [compiler] and the `headers` of system libraries come first, as they did when passed as flags.
+	pub fn include_paths(&self, src: &[std::path::PathBuf]) -> Vec<std::path::PathBuf> {
+		let flat = self
+			.config
+			.dependencies
+			.entries
+			.iter()
+			.filter(|(_, dep)| dep.include() == crate::ConfigInclude::Flat)
+			.map(|(name, _)| self.dep_dir(name));
+
+		self.build_options()
+			.include_dirs
+			.into_iter()
+			.chain(self.vendor_dirs())
+			.chain(flat)
+			.chain(src.iter().cloned())
+			.chain([self.generated_dir(), self.shim_dir()])
+			.collect()
+	}
+
+	/// Shim headers letting each dependency be included as <dep.h>, see [Self::write_shims].
+	/// Searched last, so they never shadow a header of the project or a dependency.
+	pub fn shim_dir(&self) -> std::path::PathBuf {
+		self.target().join("include")
+	}
+
+	/// Writes target/include/<dep>.h for each installed dependency, including the header it's used through.
+	/// That's its `entry` if it declares one, otherwise its only header at the top level.
+	/// Dependencies without one are left out, and reported by `cpkg install`.
+	pub fn write_shims(&self) -> anyhow::Result<Vec<(String, Shim)>> {
+		let deps = self
+			.config
+			.dependencies
+			.entries
+			.iter()
+			.filter(|(_, dep)| dep.is_vendored());
+
+		let mut shims = vec![];
+
+		for (name, dep) in deps {
+			let dir = self.dep_dir(name);
+
+			/* Not installed yet, which builds report on their own */
+			if !dir.exists() {
+				continue;
+			}
+
+			let entry = match dep.entry() {
+				Some(entry) if dir.join(entry).is_file() => entry.to_owned(),
+				Some(entry) => anyhow::bail!(
+					"Dependency '{name}' declares entry {}, but it isn't in {}.",
+					entry.display(),
+					dir.display()
+				),
+				None => {
+					let mut headers = std::fs::read_dir(&dir)?
+						.flatten()
+						.map(|e| e.path())
+						.filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "h"))
+						.collect::<Vec<_>>();
+
+					match headers.len() {
+						0 => {
+							shims.push((name.clone(), Shim::NoHeader));
+							continue;
+						}
+						1 => std::path::PathBuf::from(headers.remove(0).file_name().unwrap()),
+						n => {
+							shims.push((name.clone(), Shim::Ambiguous(n)));
+							continue;
+						}
+					}
+				}
+			};
+
+			let include = format!("{name}/{}", entry.to_string_lossy().replace('\\', "/"));
+
+			write_generated(
+				&self.shim_dir().join(format!("{name}.h")),
+				&format!("/* Generated by cpkg so {name} can be included as <{name}.h> */\n#include <{include}>\n"),
+			)?;
+
+			shims.push((name.clone(), Shim::Written(include)));
+		}
+
+		Ok(shims)
+	}
+
+	/// Headers cpkg writes before building, like cpkg_config.h, which sources can include.
+	/// Under the output folder, since they differ between profiles.
+	pub fn generated_dir(&self) -> std::path::PathBuf {
+		self.out_dir().join("generated")
+	}
+
+	/// Contents of cpkg_config.h, describing the package and the build to its sources.
+	fn config_header(&self) -> anyhow::Result<String> {
+		let mut defines = vec![
+			("CPKG_PROJECT_NAME".to_owned(), crate::quote::c_string(self.name())),
+			("CPKG_VERSION".to_owned(), crate::quote::c_string(&self.version().to_string())),
+			("CPKG_PROFILE".to_owned(), crate::quote::c_string(&self.profile.to_string())),
+		];
+
+		/* Left out of projects that aren't in a git repository, so sources can check with #ifdef */
+		if crate::probe::exists("git") {
+			if let Ok(commit) = crate::lockfile::head(self.path) {
+				defines.push(("CPKG_GIT_COMMIT".to_owned(), crate::quote::c_string(&commit)));
+			}
+		}
+
+		let metadata = self.config.package.metadata.iter().flat_map(|m| &m.defines);
+
+		for (name, value) in metadata {
+			let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+				&& name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+			if !valid {
+				anyhow::bail!("'{name}' under [package.metadata.defines] isn't a valid C identifier.");
+			}
+
+			let value = match value {
+				toml::Value::String(s) => crate::quote::c_string(s),
+				toml::Value::Integer(i) => i.to_string(),
+				toml::Value::Float(f) => f.to_string(),
+				toml::Value::Boolean(b) => (*b as u8).to_string(),
+				_ => anyhow::bail!(
+					"'{name}' under [package.metadata.defines] must be a string, number or boolean."
+				),
+			};
+
+			defines.push((name.clone(), value));
+		}
+
+		let lines = defines
+			.iter()
+			.map(|(name, value)| format!("#define {name} {value}\n"))
+			.collect::<String>();
+
+		Ok(format!(
+			"/* Generated by cpkg before each build, so edits are overwritten */\n#pragma once\n\n{lines}"
+		))
+	}
+
+	/// Writes cpkg_config.h, leaving it untouched when nothing in it changed so it doesn't cause rebuilds.
+	fn write_config_header(&self) -> anyhow::Result<()> {
+		write_generated(&self.config_header_path(), &self.config_header()?)
+	}
+
+	fn config_header_path(&self) -> std::path::PathBuf {
+		self.generated_dir().join("cpkg_config.h")
+	}
+
+	/// Where build outputs go for the current profile.
+	/// Debug builds use the target folder directly, to keep existing paths working.
+	/// Cross builds go in their own folder, target/<name>, so artifacts don't mix.
+	/// Likewise workspace members each build into target/<package>.
+	pub fn out_dir(&self) -> std::path::PathBuf {
+		let base = match self.workspace {
+			Some(_) => self.target().join(self.name()),
+			None => self.target(),
+		};
+
+		let base = match self.cross {
+			Some(ref cross) => base.join(&cross.name),
+			None => base,
+		};
+
+		match self.profile {
+			Profile::Debug => base,
+			Profile::Release => base.join("release"),
+		}
+	}
+
 	pub fn tests(&self) -> std::path::PathBuf {
-		self.path.join(Self::TESTS)
+		let dir = self.config.package.tests.as_deref();
+		self.path.join(dir.unwrap_or(std::path::Path::new(Self::TESTS)))
+	}
+
+	pub fn examples(&self) -> std::path::PathBuf {
+		self.path.join(Self::EXAMPLES)
+	}
+
+	/// Total size in bytes of the files under a path, without following symlinks.
+	pub fn dir_size(path: &std::path::Path) -> u64 {
+		walkdir::WalkDir::new(path)
+			.into_iter()
+			.flatten()
+			.filter(|e| e.file_type().is_file())
+			.filter_map(|e| e.metadata().ok())
+			.map(|m| m.len())
+			.sum()
 	}
 
 	/*
 		Instantiation
 	*/
 
-	pub fn create(path: &'a std::path::Path) -> anyhow::Result<Self> {
+	pub fn create(path: &'a std::path::Path, options: &InitOptions) -> anyhow::Result<Self> {
 		if path.exists() {
 			anyhow::bail!(
 				"Failed to create project at {}: path already exists",
@@ -63,12 +515,21 @@ impl<'a> Project<'a> {
 			);
 		}
 
+		/* Fetch first, so a bad template doesn't leave an empty folder behind */
+		let template = options
+			.template
+			.as_deref()
+			.map(crate::template::Template::fetch)
+			.transpose()?;
+
 		std::fs::create_dir(path)?;
 
-		Self::init(path)
+		Self::scaffold(path, options, template.as_ref()).inspect_err(|_| {
+			let _ = std::fs::remove_dir_all(path);
+		})
 	}
 
-	pub fn init(path: &'a std::path::Path) -> anyhow::Result<Self> {
+	pub fn init(path: &'a std::path::Path, options: &InitOptions) -> anyhow::Result<Self> {
 		if !path.is_dir() {
 			anyhow::bail!(
 				"Failed to initialize project at {}: not a directory.",
@@ -80,64 +541,207 @@ impl<'a> Project<'a> {
 			anyhow::bail!("Cannot initialize project at existing cpkg project.");
 		}
 
-		let src = Self::get_or_mkdir(path.join(Self::SRC))?;
+		let template = options
+			.template
+			.as_deref()
+			.map(crate::template::Template::fetch)
+			.transpose()?;
 
-		std::fs::write(
-			src.join("main.c"),
-			indoc::indoc! {r#"
-				#include <stdio.h>
+		Self::scaffold(path, options, template.as_ref())
+	}
 
-				int main() {
-					printf("Hello, world!\n");
-					return 0;
-				}
-			"#},
-		)?;
+	/// Writes the starter files from a template, or the default scaffold,
+	/// then adds a cpkg.toml and git repository if the template didn't provide them.
+	fn scaffold(
+		path: &'a std::path::Path,
+		options: &InitOptions,
+		template: Option<&crate::template::Template>,
+	) -> anyhow::Result<Self> {
+		let name = match options.name {
+			Some(ref name) => name.clone(),
+			None => String::from(path.file_name().unwrap().to_string_lossy()),
+		};
 
-		std::fs::write(
-			src.join("main.test.c"),
-			indoc::indoc! {r#"
-				#include <assert.h>
+		Self::validate_name(&name)?;
 
-				int main() {
-					assert( (1 + 2 == 3) && "C is broken" );
+		let mut vars = crate::template::Vars::new(&name);
+
+		if let Some(author) = crate::GlobalConfig::load()?.author {
+			vars.define("author", author);
+		}
+
+		if let Some(spdx) = options.license.spdx() {
+			vars.define("license_spdx", spdx);
+		}
+
+		if let Some(ref description) = options.description {
+			vars.define("description", description);
+		}
+
+		for (key, value) in &options.defines {
+			vars.define(key, value);
+		}
+
+		/* Existing code is what the project is about, so don't add a hello world beside it */
+		let has_sources = walkdir::WalkDir::new(path)
+			.into_iter()
+			.filter_entry(|e| e.file_name() != ".git" && e.file_name() != Self::TARGET)
+			.flatten()
+			.any(|e| e.file_type().is_file() && is_source(e.path()));
+
+		if let Some(template) = template {
+			template.copy_to(path, &vars)?;
+		} else if !has_sources {
+			for (file, contents) in SCAFFOLD {
+				let file = path.join(vars.render(file)?);
+
+				if let Some(parent) = file.parent() {
+					std::fs::create_dir_all(parent)?;
 				}
-			"#},
-		)?;
 
-		let config = crate::Config {
-			package: crate::ConfigPackage {
-				name: String::from(path.file_name().unwrap().to_string_lossy()),
-				bin: None,
-			},
+				std::fs::write(file, vars.render(contents)?)?;
+			}
+		}
 
-			dependencies: Default::default(),
-			scripts: Default::default(),
+		/* Existing ones, like a LICENSE.txt or README.rst, are kept */
+		let existing = |prefix: &str| -> anyhow::Result<bool> {
+			for entry in std::fs::read_dir(path)? {
+				if entry?.file_name().to_string_lossy().starts_with(prefix) {
+					return Ok(true);
+				}
+			}
 
-			compiler: None,
-			formatter: None,
-			docgen: None,
+			Ok(false)
 		};
 
-		std::fs::write(path.join("cpkg.toml"), toml::to_string(&config)?)?;
+		if let Some(text) = options.license.text() {
+			if !existing("LICENSE")? {
+				std::fs::write(path.join("LICENSE"), vars.render(text)?)?;
+			}
+		}
 
-		if let Ok(git) = which::which("git") {
-			std::fs::write(
-				path.join(".gitignore"),
-				indoc::indoc! {r#"
-					/target
-				"#},
-			)?;
+		if !existing("README")? {
+			std::fs::write(path.join("README.md"), vars.render(README)?)?;
+		}
+
+		if !path.join("cpkg.toml").exists() {
+			let config = crate::Config {
+				package: crate::ConfigPackage {
+					name,
+					version: vars.get("version").unwrap_or_default().parse().map_err(|e| {
+						anyhow::anyhow!("Invalid version for new project: {e}. Expected a semantic version like 1.2.3")
+					})?,
+					description: options.description.clone(),
+					license: options.license.spdx().map(str::to_owned),
+					authors: if options.authors.is_empty() {
+						vars.get("author").filter(|a| !a.is_empty()).map(str::to_owned).into_iter().collect()
+					} else {
+						options.authors.clone()
+					},
+					repository: options.repository.clone(),
+					homepage: options.homepage.clone(),
+					keywords: options.keywords.clone(),
+					bin: None,
+					entry: None,
+					src: None,
+					tests: None,
+					target: None,
+					language: None,
+					kind: None,
+					default_targets: None,
+					exclude: vec![],
+					metadata: None,
+				},
+
+				dependencies: Default::default(),
+				scripts: Default::default(),
+				target: Default::default(),
+
+				compiler: Some(crate::ConfigCompiler {
+					standard: Some(crate::ConfigStandard::C17),
+					..Default::default()
+				}),
+				formatter: None,
+				test: None,
+				net: None,
+				docgen: None,
+				debugger: None,
+			};
+
+			std::fs::write(path.join("cpkg.toml"), toml::to_string(&config)?)?;
+		}
+
+		if options.no_git || path.join(".git").exists() {
+			return Self::open(path);
+		}
+
+		if let Some(git) = crate::probe::which("git") {
+			let gitignore = path.join(".gitignore");
+
+			if !gitignore.exists() {
+				std::fs::write(gitignore, crate::export::GITIGNORE)?;
+			}
+
+			let mut cmd = std::process::Command::new(git);
+			cmd.arg("init").current_dir(path);
+
+			crate::log::command(&cmd);
+			cmd.output()?;
+		}
 
-			std::process::Command::new(git)
-				.arg("init")
-				.current_dir(path)
-				.output()?;
+		Self::open(path)
+	}
+
+	/// Checks the folders [package] points src, tests and target at stay inside of the project.
+	fn validate_layout(package: &crate::ConfigPackage) -> anyhow::Result<()> {
+		if package.src.as_ref().is_some_and(|dirs| dirs.is_empty()) {
+			anyhow::bail!("src under [package] is empty, list at least one folder.");
+		}
+
+		let dirs = package
+			.src
+			.iter()
+			.flatten()
+			.map(|d| ("src", d))
+			.chain(package.tests.iter().map(|d| ("tests", d)))
+			.chain(package.target.iter().map(|d| ("target", d)));
+
+		for (key, dir) in dirs {
+			use std::path::Component;
+
+			let inside = dir.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+				&& dir.components().any(|c| matches!(c, Component::Normal(_)));
+
+			if !inside {
+				anyhow::bail!(
+					"{key} = \"{}\" under [package] must be a folder inside of the project.",
+					dir.display()
+				);
+			}
 		}
 
-		let p = Project { path, config };
+		Ok(())
+	}
+
+	/// Checks a package name can be used as a file name and in build files.
+	pub fn validate_name(name: &str) -> anyhow::Result<()> {
+		let problem = if name.is_empty() {
+			Some("it's empty")
+		} else if name.chars().any(char::is_whitespace) {
+			Some("it contains whitespace")
+		} else if name.contains(['/', '\\']) {
+			Some("it contains a path separator")
+		} else if name == "." || name == ".." || name.starts_with('-') {
+			Some("it isn't a usable file name")
+		} else {
+			None
+		};
+
+		if let Some(problem) = problem {
+			anyhow::bail!("Invalid package name \"{name}\": {problem}. Choose another with --name.");
+		}
 
-		Ok(p)
+		Ok(())
 	}
 
 	pub fn open(path: &'a std::path::Path) -> anyhow::Result<Self> {
@@ -153,10 +757,38 @@ impl<'a> Project<'a> {
 			anyhow::bail!("No cpkg.toml detected, this doesn't seem to be a valid project.");
 		}
 
-		let config = std::fs::read_to_string(config)?;
-		let config = toml::from_str::<crate::Config>(&config)?;
+		if let Some(ws) = crate::workspace::Workspace::open(path)? {
+			anyhow::bail!(
+				"This is the root of a workspace, not a package. Run from a member's folder, or select one with -p/--package. Members are: {}",
+				ws.names().join(", ")
+			);
+		}
 
-		Ok(Project { path, config })
+		let text = std::fs::read_to_string(config)?;
+		let mut unknown = vec![];
+
+		let config: crate::Config =
+			serde_ignored::deserialize(toml::Deserializer::new(&text), |path| {
+				unknown.push(crate::validate::key_path(&path))
+			})?;
+
+		crate::validate::warn_config(path, &text, &config, &unknown);
+		Self::validate_layout(&config.package)?;
+
+		Ok(Project {
+			path,
+			config,
+			profile: Profile::default(),
+			cross: None,
+			raw_errors: false,
+			runner: None,
+			workspace: crate::workspace::Workspace::containing(path),
+			timings: None,
+			timed: Default::default(),
+			force: false,
+			debug_info: false,
+			compiled: Default::default(),
+		})
 	}
 
 	/*
@@ -171,13 +803,215 @@ impl<'a> Project<'a> {
 		&self.config.package.name
 	}
 
+	pub fn version(&self) -> &semver::Version {
+		&self.config.package.version
+	}
+
+	/// Records how long each step of builds takes from here on, see [Self::take_timings].
+	pub fn with_timings(mut self, timings: Option<Timings>) -> Self {
+		self.timings = timings;
+		self
+	}
+
+	/// Compiles everything again on builds from here on, even what's up to date.
+	pub fn with_force_rebuild(mut self, force: bool) -> Self {
+		self.force = force;
+		self
+	}
+
+	/// Builds with `-g -O0` from here on, whatever the profile, so a debugger can step through the code.
+	pub fn with_debug_info(mut self, debug_info: bool) -> Self {
+		self.debug_info = debug_info;
+		self
+	}
+
+	/// How many compiles and archives the last build ran, 0 if its outputs were all up to date.
+	pub fn compiled(&self) -> usize {
+		self.compiled.get()
+	}
+
+	/// Steps timed since the last call, in the order they ran.
+	pub fn take_timings(&self) -> Vec<Timing> {
+		self.timed.take()
+	}
+
+	fn record(
+		&self,
+		step: Step,
+		output: &std::path::Path,
+		sources: &[std::path::PathBuf],
+		duration: std::time::Duration,
+		report: Option<String>,
+	) {
+		if self.timings.is_some() {
+			self.timed.borrow_mut().push(Timing {
+				step,
+				output: output.to_owned(),
+				sources: sources.to_vec(),
+				duration,
+				report,
+			});
+		}
+	}
+
+	/// Switches the profile used by builds from here on.
+	pub fn with_profile(mut self, profile: Profile) -> Self {
+		self.profile = profile;
+		self
+	}
+
+	/// Builds for another platform from here on, given a name under [target],
+	/// `wasm` for emscripten, or a target triple. Triples use the `<triple>-gcc` cross compiler,
+	/// or zig when it's the default compiler.
+	pub fn with_cross(mut self, name: &str) -> anyhow::Result<Self> {
+		let config = match self.config.target.get(name) {
+			Some(config) => config.clone(),
+			None if name == "wasm" => crate::ConfigTarget {
+				compiler: Some("emcc".to_owned()),
+				..Default::default()
+			},
+			/* zig cross compiles by itself, everything else needs a cross toolchain */
+			None if name.contains('-') && self.default_compiler() == Some("zig") => {
+				crate::ConfigTarget {
+					triple: Some(name.to_owned()),
+					..Default::default()
+				}
+			}
+			None if name.contains('-') => crate::ConfigTarget {
+				compiler: Some(format!("{name}-gcc")),
+				..Default::default()
+			},
+			None => anyhow::bail!(
+				"Unknown target '{name}'. Add a [target.{name}] table to cpkg.toml, or pass a target triple."
+			),
+		};
+
+		self.cross = Some(CrossTarget {
+			name: name.to_owned(),
+			config,
+		});
+
+		Ok(self)
+	}
+
+	/// Overrides the runner binaries are run through, whether cross compiling or not.
+	pub fn with_runner(mut self, runner: String) -> Self {
+		self.runner = Some(runner);
+		self
+	}
+
+	/// Shows compile errors exactly as the compiler printed them, stopping at the first,
+	/// rather than collecting and deduplicating them across invocations.
+	pub fn with_raw_errors(mut self, raw: bool) -> Self {
+		self.raw_errors = raw;
+		self
+	}
+
+	/// Backend named by `default` under [compiler], if any.
+	pub fn default_compiler(&self) -> Option<&str> {
+		self.config.compiler.as_ref()?.default.as_deref()
+	}
+
+	pub fn workspace(&self) -> Option<&crate::workspace::Workspace> {
+		self.workspace.as_ref()
+	}
+
+	pub fn cross(&self) -> Option<&CrossTarget> {
+		self.cross.as_ref()
+	}
+
+	/// Program binaries run through, and where it was set, for errors.
+	/// `--runner` wins over the cross target's runner, which wins over the one under [test].
+	pub fn runner(&self) -> Option<(&str, String)> {
+		if let Some(ref runner) = self.runner {
+			return Some((runner, "with --runner".to_owned()));
+		}
+
+		if let Some(ref cross) = self.cross {
+			return cross
+				.config
+				.runner
+				.as_deref()
+				.map(|r| (r, format!("under [target.{}] in cpkg.toml", cross.name)));
+		}
+
+		self.config
+			.test
+			.as_ref()?
+			.runner
+			.as_deref()
+			.map(|r| (r, "under [test] in cpkg.toml".to_owned()))
+	}
+
+	/// Splits the runner into a program and its arguments, erroring if the program can't be found.
+	/// Called before building as well, so a missing runner is reported before any time is spent.
+	pub fn runner_argv(&self) -> anyhow::Result<Option<Vec<String>>> {
+		let Some((runner, source)) = self.runner() else {
+			return Ok(None);
+		};
+
+		let Some(argv) = shlex::split(runner) else {
+			anyhow::bail!("Couldn't parse the runner '{runner}' set {source}, it has unbalanced quotes.");
+		};
+
+		let Some(program) = argv.first() else {
+			anyhow::bail!("The runner set {source} is empty.");
+		};
+
+		if !crate::probe::exists(program) && !std::path::Path::new(program).is_file() {
+			anyhow::bail!(
+				"Couldn't find '{program}', the runner set {source}. Install it, or change the runner."
+			);
+		}
+
+		Ok(Some(argv))
+	}
+
+	/// Command that runs a built binary, through the runner if one is set.
+	pub fn run_command(&self, bin: &std::path::Path) -> anyhow::Result<std::process::Command> {
+		let Some(argv) = self.runner_argv()? else {
+			if let Some(ref cross) = self.cross {
+				/* Emscripten pages are served instead, see `cpkg run` */
+				anyhow::bail!(
+					"Can't run {} here, it was built for target '{}'. Set a runner under [target.{}] to run it through an emulator, e.g. runner = \"qemu-arm\".",
+					bin.display(),
+					cross.name,
+					cross.name
+				);
+			}
+
+			return Ok(std::process::Command::new(bin));
+		};
+
+		/* The HTML page loads the .js, which runners like node can run directly */
+		let bin = if self.cross.as_ref().is_some_and(|c| c.is_emscripten()) {
+			bin.with_extension("js")
+		} else {
+			bin.to_owned()
+		};
+
+		let mut cmd = std::process::Command::new(&argv[0]);
+		cmd.args(&argv[1..]).arg(&bin);
+
+		Ok(cmd)
+	}
+
 	/// Saves the config to cpkg.toml  
+	/// Only keys that changed are rewritten, so comments and ordering in the rest of the file are kept.  
 	/// Shouldn't need to use this, as [Self::with_config] calls this for you.
 	pub fn save_config(&self) -> anyhow::Result<()> {
-		std::fs::write(
-			self.path.join("cpkg.toml"),
-			toml::to_string_pretty(&self.config)?,
-		)?;
+		let file = self.path.join("cpkg.toml");
+		let new = toml::to_string_pretty(&self.config)?.parse::<toml_edit::DocumentMut>()?;
+
+		/* A file that's gone or no longer parses has nothing worth keeping */
+		let mut doc = match std::fs::read_to_string(&file).map(|s| s.parse::<toml_edit::DocumentMut>()) {
+			Ok(Ok(doc)) => doc,
+			_ => return Ok(std::fs::write(file, new.to_string())?),
+		};
+
+		merge_toml(doc.as_table_mut(), new.as_table(), true);
+		std::fs::write(file, doc.to_string())?;
+
 		Ok(())
 	}
 
@@ -199,7 +1033,7 @@ impl<'a> Project<'a> {
 	#[must_use = "Ensure successfully added dependency"]
 	pub fn add_dep(&mut self, name: String, dep: crate::ConfigDependency) -> anyhow::Result<()> {
 		self.with_config(|conf| {
-			conf.dependencies.insert(name, dep);
+			conf.dependencies.entries.insert(name, dep);
 		})
 	}
 
@@ -209,36 +1043,132 @@ impl<'a> Project<'a> {
 
 		// Convert Result<Option<T>> to Result<T> for case that the dependency didn't exist.
 		// Might change this to just return Result<Option<T>> in the future.
-		let r = self.with_config(|conf| conf.dependencies.remove(name));
+		let r = self.with_config(|conf| conf.dependencies.entries.remove(name));
 
-		r.and_then(|o| {
+		let dep = r.and_then(|o| {
 			o.ok_or(anyhow::anyhow!(
 				"Could not find dependency {} to remove",
 				name
 			))
-		})
+		})?;
+
+		let mut lock = crate::lockfile::Lockfile::load(self.lock_dir())?;
+
+		if lock.dependencies.remove(name).is_some() {
+			lock.save(self.lock_dir())?;
+		}
+
+		let shim = self.shim_dir().join(format!("{name}.h"));
+
+		if shim.exists() {
+			std::fs::remove_file(shim)?;
+		}
+
+		Ok(dep)
+	}
+
+	/// Folder holding cpkg.lock, which is shared by a workspace like its vendor folder.
+	pub fn lock_dir(&self) -> &std::path::Path {
+		self.workspace.as_ref().map_or(self.path, |ws| ws.root())
+	}
+
+	/// Fails if other members of the workspace install one of this package's dependencies from somewhere else,
+	/// since they'd overwrite each other's copy in the shared vendor folder.
+	fn check_conflicts(&self) -> anyhow::Result<()> {
+		let Some(ref ws) = self.workspace else {
+			return Ok(());
+		};
+
+		let conflicts = ws
+			.conflicts()
+			.into_iter()
+			.filter(|c| self.config.dependencies.entries.contains_key(&c.name))
+			.map(|c| c.to_string())
+			.collect::<Vec<_>>();
+
+		if !conflicts.is_empty() {
+			anyhow::bail!("{}", conflicts.join("\n\n"));
+		}
+
+		Ok(())
+	}
+
+	/// Installs a single declared dependency, for `cpkg add`.
+	/// Returns the commit a git dependency was pinned to.
+	pub fn install_one(&self, name: &str) -> anyhow::Result<Option<String>> {
+		let Some(dep) = self.config.dependencies.entries.get(name) else {
+			anyhow::bail!("No dependency named {name} in cpkg.toml");
+		};
+
+		if matches!(dep, ConfigDependency::Git { .. }) && !crate::probe::exists("git") {
+			anyhow::bail!("Cannot install dependency '{name}' without git.");
+		}
+
+		if !dep.is_vendored() {
+			self.check_lib(name, dep)?;
+			return Ok(None);
+		}
+
+		self.check_conflicts()?;
+
+		Self::get_or_mkdir(self.target())?;
+		let install_dir = Self::get_or_mkdir(self.vendor())?.join(name);
+
+		if install_dir.symlink_metadata().is_ok() {
+			anyhow::bail!(
+				"Something is already installed at {}. Remove it or pass --no-install.",
+				install_dir.display()
+			);
+		}
+
+		crate::progress::Progress::new(1).item("Installing", name, || {
+			let commit = self.install_dep(name, dep, &install_dir)?;
+
+			let mut ledger = self.vendor_ledger();
+			ledger.insert(name.to_owned());
+			self.save_vendor_ledger(&ledger)?;
+
+			self.build_dep(name, dep, &install_dir, false)?;
+
+			Ok(commit)
+		})
 	}
 
-	pub fn install_deps(&self) -> anyhow::Result<()> {
-		let target = Self::get_or_mkdir(self.target())?;
-		let build = Self::get_or_mkdir(target.join("vendor"))?;
+	/// Installs every declared dependency that isn't yet, then runs the build commands of those that have one.
+	/// With `force`, dependencies that were already built are built again.
+	pub fn install_deps(&self, force: bool) -> anyhow::Result<()> {
+		self.check_conflicts()?;
+
+		Self::get_or_mkdir(self.target())?;
+		let build = Self::get_or_mkdir(self.vendor())?;
 
 		/*
-			Create compile_flags.txt for intellisense
+			Create compile_flags.txt for intellisense, keeping up with the include paths unless it was written by hand
 			TODO: Generate more robust compile_commands.json instead
 		*/
-		if which::which("clangd").is_ok() {
-			let clangd = self.path.join("compile_flags.txt");
-			if !clangd.exists() {
-				std::fs::write(clangd, "-I./target/vendor")?;
+		if crate::probe::exists("clangd") {
+			const CLANGD: &str = "compile_flags.txt";
+
+			if !self.path.join(CLANGD).exists() || crate::generated::is_generated(self, CLANGD) {
+				let flags = self
+					.include_paths(&self.src())
+					.iter()
+					.map(|p| match p.strip_prefix(self.path) {
+						Ok(rel) => format!("-I./{}\n", rel.display()),
+						Err(_) => format!("-I{}\n", p.display()),
+					})
+					.collect::<String>();
+
+				crate::generated::write(self, CLANGD, &flags)?;
 			}
 		}
 
-		let has_git = which::which("git").is_ok();
+		let has_git = crate::probe::exists("git");
 
 		let needs_git = self
 			.config
 			.dependencies
+			.entries
 			.iter()
 			.find(|dep| matches!(dep.1, ConfigDependency::Git { .. }))
 			.map(|dep| dep.0);
@@ -247,256 +1177,2801 @@ impl<'a> Project<'a> {
 			anyhow::ensure!(has_git, "Cannot install dependency '{dep}' without git.");
 		}
 
-		for (name, dep) in &self.config.dependencies {
-			let install_dir = build.join(name);
+		/* Like a clone that was interrupted, which would otherwise count as installed */
+		let broken = self.check_deps();
+
+		for b in &broken {
+			crate::log::warn(format!(
+				"Reinstalling {}, its vendored copy is broken: {}.",
+				b.name, b.problem
+			));
+		}
+
+		self.repair_deps(&broken)?;
 
-			/* Already installed */
-			if install_dir.exists() {
+		let mut ledger = self.vendor_ledger();
+
+		let vendored = self
+			.config
+			.dependencies
+			.entries
+			.iter()
+			.filter(|(_, dep)| dep.is_vendored());
+
+		let missing = vendored
+			.clone()
+			.filter(|(name, _)| build.join(name).symlink_metadata().is_err())
+			.count();
+
+		/* Installed dependencies whose build is out of date */
+		let stale = vendored
+			.filter(|(name, dep)| {
+				let install_dir = build.join(name);
+
+				install_dir.symlink_metadata().is_ok()
+					&& Self::build_stamp(dep, &install_dir)
+						.is_some_and(|stamp| force || !self.is_built(name, &stamp))
+			})
+			.map(|(name, _)| name)
+			.collect::<std::collections::HashSet<_>>();
+
+		/* Only dependencies with something to do are reported, so a no-op install stays quiet */
+		let mut progress = crate::progress::Progress::new(missing + stale.len());
+
+		for (name, dep) in &self.config.dependencies.entries {
+			/* Prebuilt libraries stay where they are, so there's only checking they're there */
+			if !dep.is_vendored() {
+				self.check_lib(name, dep)?;
 				continue;
 			}
 
-			match dep {
-				ConfigDependency::Path { path } => {
-					std::fs::hard_link(path, install_dir)?;
+			let install_dir = build.join(name);
+
+			/* Already installed, and repaired above if it was broken */
+			if install_dir.symlink_metadata().is_ok() {
+				ledger.insert(name.clone());
+
+				/* Pin checkouts from before there was a lockfile */
+				if let ConfigDependency::Git { git, .. } = dep {
+					if is_git_checkout(&install_dir) {
+						self.lock(name, git, &install_dir)?;
+					}
 				}
-				ConfigDependency::Git { git } => {
-					std::process::Command::new("git")
-						.arg("clone")
-						.arg(git)
-						.arg(install_dir)
-						.spawn()?;
+
+				if stale.contains(name) {
+					progress.item("Building", name, || self.build_dep(name, dep, &install_dir, force))?;
 				}
+			} else {
+				progress.item("Installing", name, || {
+					self.install_dep(name, dep, &install_dir)?;
+
+					ledger.insert(name.clone());
+					self.save_vendor_ledger(&ledger)?;
+
+					self.build_dep(name, dep, &install_dir, force)
+				})?;
 			}
 		}
 
-		Ok(())
+		self.save_vendor_ledger(&ledger)
 	}
 
-	/*
-		File Iterators
-	*/
+	/// Runs a dependency's build command inside of its vendored folder, logging to target/vendor/<name>.log.
+	/// A stamp beside the log records what was built, so it only runs again once the command or commit changes.
+	fn build_dep(
+		&self,
+		name: &str,
+		dep: &ConfigDependency,
+		install_dir: &std::path::Path,
+		force: bool,
+	) -> anyhow::Result<()> {
+		let (Some(build), Some(stamp)) = (dep.build(), Self::build_stamp(dep, install_dir)) else {
+			return Ok(());
+		};
 
-	pub fn test_files(&self) -> impl std::iter::Iterator<Item = std::path::PathBuf> {
-		let inline_tests = walkdir::WalkDir::new(self.src())
-			.into_iter()
-			.flat_map(std::convert::identity)
-			.filter(|e| e.path().is_file())
-			.filter(|e| e.path().to_string_lossy().ends_with(".test.c"))
-			.map(|e| e.path().to_owned());
+		if !force && self.is_built(name, &stamp) {
+			return Ok(());
+		}
 
-		let explicit_tests = walkdir::WalkDir::new(self.tests())
-			.into_iter()
-			.flat_map(std::convert::identity)
-			.filter(|e| e.path().is_file())
-			.filter(|e| e.path().to_string_lossy().ends_with(".c"))
-			.map(|e| e.path().to_owned());
+		let vendor = self.vendor();
+		let stamp_path = vendor.join(format!("{name}.built"));
+		let log_path = vendor.join(format!("{name}.log"));
+
+		/* A stamp left from before would claim a failed build succeeded */
+		if stamp_path.exists() {
+			std::fs::remove_file(&stamp_path)?;
+		}
+
+		let log = std::fs::File::create(&log_path)?;
+
+		let mut script = crate::scripts::command(self, build)?;
+		script
+			.cmd
+			.current_dir(install_dir)
+			.stdout(log.try_clone()?)
+			.stderr(log);
+
+		crate::log::command(&script.cmd);
+		let status = script.cmd.status()?;
 
-		inline_tests.chain(explicit_tests)
+		if !status.success() {
+			let output = std::fs::read_to_string(&log_path).unwrap_or_default();
+			let lines = output.lines().collect::<Vec<_>>();
+			let tail = lines[lines.len().saturating_sub(Self::BUILD_LOG_TAIL)..].join("\n");
+
+			anyhow::bail!(
+				"Failed to build dependency '{name}' with `{build}` ({status}). Last lines of {}:\n{tail}",
+				log_path.strip_prefix(self.path).unwrap_or(&log_path).display()
+			);
+		}
+
+		std::fs::write(stamp_path, stamp)?;
+
+		Ok(())
 	}
 
-	pub fn c_files(&self, src: impl AsRef<std::path::Path>) -> impl std::iter::Iterator<Item = std::path::PathBuf> {
-		walkdir::WalkDir::new(src)
-			.into_iter()
-			.flat_map(std::convert::identity)
-			.filter(|e| e.path().is_file())
-			.filter(|e| e.path().to_string_lossy().ends_with(".c"))
-			.filter(|e| !e.path().to_string_lossy().ends_with(".test.c"))
-			.map(|e| e.path().to_owned())
+	/// What a dependency's build stamp holds once it's built, or [None] if it has no build command.
+	fn build_stamp(dep: &ConfigDependency, install_dir: &std::path::Path) -> Option<String> {
+		let build = dep.build()?;
+
+		let commit = match dep {
+			ConfigDependency::Git { .. } => crate::lockfile::head(install_dir).ok(),
+			_ => None,
+		};
+
+		Some(format!("{build}\n{}\n", commit.unwrap_or_default()))
 	}
 
-	pub fn src_files(&self) -> impl std::iter::Iterator<Item = std::path::PathBuf> {
-		walkdir::WalkDir::new(self.src())
-			.into_iter()
-			.flat_map(std::convert::identity)
-			.filter(|e| e.path().is_file())
-			.map(|e| e.path().to_owned())
+	/// Whether a dependency was last built with the given stamp.
+	fn is_built(&self, name: &str, stamp: &str) -> bool {
+		std::fs::read_to_string(self.vendor().join(format!("{name}.built"))).is_ok_and(|s| s == stamp)
 	}
 
-	/*
-		Building
-	*/
+	/// Checks a prebuilt library and its headers are where cpkg.toml says.
+	/// Libraries without a search folder are left to the linker to find.
+	pub fn check_lib(&self, name: &str, dep: &ConfigDependency) -> anyhow::Result<()> {
+		let ConfigDependency::Lib { lib, search, headers } = dep else {
+			return Ok(());
+		};
 
-	pub fn build_flags(
-		&self,
-		_backend: &dyn crate::compiler::Compiler,
-	) -> std::borrow::Cow<Vec<String>> {
-		/* TODO: Support backend-specific flags */
-		if let Some(provided) = self.config.compiler.as_ref() {
-			if let Some(ref flags) = provided.flags {
-				return std::borrow::Cow::Borrowed(&flags);
+		if let Some(headers) = headers {
+			let dir = self.path.join(headers);
+
+			if !dir.is_dir() {
+				anyhow::bail!(
+					"Dependency '{name}' has its headers in {}, which doesn't exist.",
+					dir.display()
+				);
 			}
 		}
 
-		std::borrow::Cow::Owned(vec![])
-	}
+		if let Some(search) = search {
+			let dir = self.path.join(search);
 
-	/// Returns PathBuf to desired executable location
-	pub fn build_out(&self, entrypoint: Option<&std::path::Path>) -> std::path::PathBuf {
-		if let Some(ref bin) = self.config.package.bin {
-			std::path::PathBuf::from(bin)
-		} else if let Some(entrypoint) = entrypoint {
-			self.target().join(entrypoint.file_stem().unwrap())
-		} else {
-			self.target().join(&self.config.package.name)
+			let files = [
+				format!("lib{lib}.a"),
+				format!("lib{lib}.so"),
+				format!("lib{lib}.dylib"),
+				format!("{lib}.lib"),
+			];
+
+			if !files.iter().any(|f| dir.join(f).is_file()) {
+				anyhow::bail!(
+					"Dependency '{name}' links {lib} from {}, but none of {} are there.",
+					dir.display(),
+					files.join(", ")
+				);
+			}
 		}
+
+		Ok(())
 	}
 
-	/// Builds the project at provided entrypoint, returning executable path.
-	#[must_use = "Ensure actually built correctly"]
-	pub fn build(
+	/// Folder a dependency is included and linked from, the committed snapshot's copy if there is one.
+	fn dep_dir(&self, name: &str) -> std::path::PathBuf {
+		self.vendor_dirs()
+			.iter()
+			.map(|v| v.join(name))
+			.find(|d| d.exists())
+			.unwrap_or_else(|| self.vendor().join(name))
+	}
+
+	/// Files built by dependencies' build commands that get linked into the project.
+	/// Dependencies that aren't installed yet are left out, since builds report them as missing.
+	pub fn dep_artifacts(&self) -> Vec<std::path::PathBuf> {
+		self.config
+			.dependencies
+			.entries
+			.iter()
+			.map(|(name, dep)| (self.dep_dir(name), dep))
+			.filter(|(dir, _)| dir.exists())
+			.flat_map(|(dir, dep)| dep.artifacts().iter().map(move |a| dir.join(a)))
+			.collect()
+	}
+
+	/// Installs a dependency, checking a git one out at the commit in cpkg.lock if it has one.
+	/// Returns the commit a git dependency ends up pinned to.
+	fn install_dep(
 		&self,
-		backend: &dyn crate::compiler::Compiler,
-		entrypoint: &Option<String>,
-		can_run_build: impl FnOnce() -> bool,
-	) -> anyhow::Result<std::path::PathBuf> {
-		let mut src = self.src();
+		name: &str,
+		dep: &ConfigDependency,
+		install_dir: &std::path::Path,
+	) -> anyhow::Result<Option<String>> {
+		match dep {
+			/* Members refer to each other relative to the workspace root */
+			ConfigDependency::Path { path, .. } => {
+				let base = self.workspace.as_ref().map_or(self.path, |ws| ws.root());
+				symlink_dir(&base.join(path), install_dir)?;
+
+				Ok(None)
+			}
+			ConfigDependency::Git { git, .. } => {
+				let policy = crate::net::RetryPolicy::from_config(&self.config);
 
-		if !self.target().exists() {
-			std::fs::create_dir(self.target())?;
-		}
+				/* Cloned beside it and moved into place once checked out, so an interrupted clone isn't mistaken for an installed one */
+				let partial = partial_path(install_dir);
 
-		let build_c = self.path.join("build.c");
-		if build_c.exists() {
-			if can_run_build() {
-				let t = tempfile::Builder::new().tempfile()?.into_temp_path();
+				if partial.exists() {
+					std::fs::remove_dir_all(&partial)?;
+				}
 
-				backend.compile(&[build_c], &[], &t, &[])?;
+				crate::net::clone(&policy, &format!("Cloning '{name}'"), git, &partial, false)
+					.map_err(|e| e.context(format!("Failed to clone dependency '{name}' from {git}")))?;
 
-				let out = std::process::Command::new(&t).output()?;
+				let lock = crate::lockfile::Lockfile::load(self.lock_dir())?;
 
-				if !out.status.success() {
-					anyhow::bail!(
-						"Build script failed: {}",
-						String::from_utf8_lossy(&out.stderr)
-					);
+				if let Some(commit) = lock.pinned(name, git) {
+					crate::lockfile::checkout(&partial, commit)?;
 				}
 
-				let out = String::from_utf8_lossy(&out.stdout);
-				let mut ptr = 0;
+				std::fs::rename(&partial, install_dir)?;
+				self.lock(name, git, install_dir).map(Some)
+			}
+			ConfigDependency::Lib { .. } => Ok(None),
+		}
+	}
+
+	/// Records the commit a git dependency is checked out at into cpkg.lock.
+	fn lock(&self, name: &str, git: &str, install_dir: &std::path::Path) -> anyhow::Result<String> {
+		let commit = crate::lockfile::head(install_dir)?;
+		let mut lock = crate::lockfile::Lockfile::load(self.lock_dir())?;
+
+		if lock.pinned(name, git) != Some(commit.as_str()) {
+			lock.dependencies.insert(
+				name.to_owned(),
+				crate::lockfile::Locked {
+					git: git.to_owned(),
+					commit: commit.clone(),
+				},
+			);
 
-				while let Some(r) = out[ptr..].find(Self::BUILD_COMMAND_PREFIX) {
-					let cmd = out[r..].split_once(" \t\n").map(|p| p.0).unwrap_or(&out[r..]);
-					let cmd = &cmd[Self::BUILD_COMMAND_PREFIX.len()..];
+			lock.save(self.lock_dir())?;
+		}
 
-					if cmd.starts_with("set_src") {
-						if let Some((_, to)) = cmd.split_once('=') {
-							src = std::env::current_dir()?.join(to.trim());
-						}
-					}
+		Ok(commit)
+	}
+
+	/// Validates every installed dependency inside of the vendor folder.
+	/// Dependencies that haven't been installed yet aren't reported.
+	pub fn check_deps(&self) -> Vec<BrokenDep<'_>> {
+		let vendor = self.vendor();
+
+		self.config
+			.dependencies
+			.entries
+			.iter()
+			.filter_map(|(name, dep)| {
+				let install_dir = vendor.join(name);
+				let meta = install_dir.symlink_metadata().ok()?;
+
+				let problem = if meta.is_symlink() && !install_dir.exists() {
+					DepProblem::BrokenLink
+				} else if std::fs::read_dir(&install_dir).map_or(true, |mut d| d.next().is_none()) {
+					DepProblem::Empty
+				} else if matches!(dep, ConfigDependency::Git { .. }) && !is_git_checkout(&install_dir) {
+					DepProblem::NotARepo
+				} else {
+					return None;
+				};
+
+				Some(BrokenDep { name, dep, problem })
+			})
+			.collect()
+	}
+
+	/// Removes and reinstalls the given broken dependencies.
+	pub fn repair_deps(&self, broken: &[BrokenDep]) -> anyhow::Result<()> {
+		let vendor = self.vendor();
+		let mut progress = crate::progress::Progress::new(broken.len());
+
+		for b in broken {
+			let install_dir = vendor.join(b.name);
 
-					ptr = r + 1;
+			progress.item("Repairing", b.name, || {
+				if install_dir.symlink_metadata()?.is_symlink() {
+					remove_symlink_dir(&install_dir)?;
+				} else {
+					std::fs::remove_dir_all(&install_dir)?;
 				}
+
+				self.install_dep(b.name, b.dep, &install_dir)?;
+				self.build_dep(b.name, b.dep, &install_dir, true)
+			})?;
+		}
+
+		Ok(())
+	}
+
+	/// Removes what interrupted commands left in the target folder: broken vendored dependencies,
+	/// outputs that were never moved into place, and empty binaries from before outputs were written that way.
+	/// Returns every path removed.
+	pub fn clean_broken(&self) -> anyhow::Result<Vec<std::path::PathBuf>> {
+		let vendor = self.vendor();
+		let mut removed = vec![];
+
+		for b in self.check_deps() {
+			let install_dir = vendor.join(b.name);
+
+			if install_dir.symlink_metadata()?.is_symlink() {
+				remove_symlink_dir(&install_dir)?;
 			} else {
-				anyhow::bail!(
-					"This project requires a build script to run, but was not permitted to run it."
-				);
+				std::fs::remove_dir_all(&install_dir)?;
 			}
+
+			removed.push(install_dir);
 		}
 
-		if let Some(entrypoint) = entrypoint {
-			let entrypoint = src.join(entrypoint).with_extension("c");
-			let out = self.build_out(Some(&entrypoint));
+		/* The insides of dependencies are theirs, so only the vendor folder's own entries are looked at */
+		let partial = walkdir::WalkDir::new(self.target())
+			.into_iter()
+			.filter_entry(|e| !(e.file_type().is_dir() && e.path().parent() == Some(&vendor) && !is_partial(e.path())))
+			.flatten()
+			.filter(|e| is_partial(e.path()))
+			.map(|e| e.into_path())
+			.collect::<Vec<_>>();
+
+		let mut outputs = vec![];
 
-			let mut c_files = self.c_files(&src).collect::<Vec<_>>();
-			if let Some(pos) = c_files.iter().position(|p| **p == entrypoint) {
-				/* Swap to beginning, so that its main is registered first by linker. */
-				c_files.swap(pos, 0);
+		for target in crate::target::resolve(self, None, true)? {
+			outputs.extend(self.target_outputs(&target));
+		}
+
+		let empty = outputs
+			.into_iter()
+			.filter(|o| o.metadata().is_ok_and(|m| m.is_file() && m.len() == 0));
+
+		for path in partial.into_iter().chain(empty) {
+			/* Already gone with a folder removed before it */
+			if path.symlink_metadata().is_err() {
+				continue;
+			}
+
+			if path.is_dir() {
+				std::fs::remove_dir_all(&path)?;
 			} else {
-				anyhow::bail!("Entrypoint {} does not exist!", entrypoint.display());
+				std::fs::remove_file(&path)?;
 			}
 
-			let mut flags = self.build_flags(backend).to_vec();
-			flags.push("-zmuldefs".to_owned()); /* Tell linker to allow multiple entrypoints, taking first encountered */
+			removed.push(path);
+		}
+
+		Ok(removed)
+	}
 
-			backend.compile(&c_files, &[&src, &self.vendor()], &out, &flags)?;
+	/// Names of dependencies that cpkg has installed into the vendor folder at some point.
+	fn vendor_ledger(&self) -> std::collections::BTreeSet<String> {
+		std::fs::read_to_string(self.vendor().join(Self::VENDOR_LEDGER))
+			.map(|s| s.lines().map(str::to_owned).collect())
+			.unwrap_or_default()
+	}
 
-			Ok(out)
-		} else {
-			/* Traditional main entrypoint */
-			let main = src.join("main.c");
-			let out = self.build_out(None);
+	fn save_vendor_ledger(&self, ledger: &std::collections::BTreeSet<String>) -> anyhow::Result<()> {
+		let mut contents = String::new();
+		for name in ledger {
+			contents.push_str(name);
+			contents.push('\n');
+		}
+
+		std::fs::write(self.vendor().join(Self::VENDOR_LEDGER), contents)?;
+		Ok(())
+	}
+
+	/// Lists every entry inside of the vendor folder, and how it relates to the current config.
+	pub fn vendor_entries(&self) -> anyhow::Result<Vec<VendorEntry>> {
+		let vendor = self.vendor();
+		if !vendor.is_dir() {
+			return Ok(vec![]);
+		}
+
+		let ledger = self.vendor_ledger();
+		let mut entries = vec![];
+
+		for entry in std::fs::read_dir(&vendor)? {
+			let entry = entry?;
+			let name = entry.file_name().to_string_lossy().into_owned();
+
+			if name == Self::VENDOR_LEDGER || crate::includes::is_shim_entry(&vendor, &name) {
+				continue;
+			}
+
+			/* Logs and stamps of dependency builds are listed with the dependency itself */
+			let build_file = [".log", ".built"]
+				.iter()
+				.any(|ext| name.strip_suffix(ext).is_some_and(|dep| vendor.join(dep).symlink_metadata().is_ok()));
+
+			if build_file {
+				continue;
+			}
+
+			let path = entry.path();
+			let linked = entry.file_type()?.is_symlink();
+
+			let declared = match self.workspace {
+				/* The vendor folder is shared, so dependencies of other members are still in use */
+				Some(ref ws) => ws
+					.configs()
+					.any(|c| c.dependencies.entries.contains_key(&name)),
+				None => self.config.dependencies.entries.contains_key(&name),
+			};
+
+			let status = if declared {
+				VendorStatus::Declared
+			} else if ledger.contains(&name) {
+				VendorStatus::Stale
+			} else {
+				VendorStatus::Unknown
+			};
+
+			let size = if linked { 0 } else { Self::dir_size(&path) };
+
+			entries.push(VendorEntry {
+				name,
+				path,
+				linked,
+				size,
+				status,
+			});
+		}
 
-			if main.exists() {
-				let c_files = self.c_files(&src).collect::<Vec<_>>();
-				let flags = self.build_flags(backend);
+		entries.sort_by(|a, b| a.name.cmp(&b.name));
 
-				backend.compile(&c_files, &[&self.vendor(), &src], &out, &flags)?;
+		Ok(entries)
+	}
 
-				Ok(out)
+	/// Removes vendored dependencies that cpkg installed, but are no longer declared in cpkg.toml.
+	/// Entries cpkg can't attribute to a past install are left alone.
+	/// Returns every vendor entry as it was before pruning.
+	pub fn prune_deps(&self) -> anyhow::Result<Vec<VendorEntry>> {
+		let entries = self.vendor_entries()?;
+		let mut ledger = self.vendor_ledger();
+
+		for entry in entries.iter().filter(|e| e.status == VendorStatus::Stale) {
+			if entry.linked {
+				/* Only remove the link, never what it points to */
+				remove_symlink_dir(&entry.path)?;
 			} else {
-				anyhow::bail!("Couldn't find main.c to build!");
+				std::fs::remove_dir_all(&entry.path)?;
+			}
+
+			for ext in ["log", "built"] {
+				let file = self.vendor().join(format!("{}.{ext}", entry.name));
+
+				if file.exists() {
+					std::fs::remove_file(file)?;
+				}
 			}
+
+			ledger.remove(&entry.name);
+		}
+
+		if self.vendor().is_dir() {
+			self.save_vendor_ledger(&ledger)?;
 		}
+
+		Ok(entries)
 	}
 
 	/*
-		Tests
+		File Iterators
 	*/
 
-	pub fn compile_tests(
-		&self,
-		backend: &dyn crate::compiler::Compiler,
-	) -> anyhow::Result<Vec<(std::path::PathBuf, std::path::PathBuf)>> {
-		let src = self.src();
+	/// Inline tests ending in .test.c or .test.cpp under the sources, and every source under the tests folder, sorted within each.
+	/// Folders that don't exist are skipped, like tests/ in a new project.
+	pub fn test_files(&self) -> impl std::iter::Iterator<Item = std::path::PathBuf> {
+		let roots = self
+			.src()
+			.into_iter()
+			.map(|root| (root, is_inline_test as fn(&std::path::Path) -> bool))
+			.chain([(self.tests(), is_source as fn(&std::path::Path) -> bool)])
+			.filter(|(root, _)| root.is_dir());
+
+		roots.flat_map(|(root, is_test)| {
+			walkdir::WalkDir::new(root)
+				.sort_by_file_name()
+				.into_iter()
+				.filter_map(|e| {
+					e.map_err(|e| crate::log::detail(format!("Skipped while looking for tests: {e}")))
+						.ok()
+				})
+				.filter(|e| e.path().is_file())
+				.filter(move |e| is_test(e.path()))
+				.map(|e| e.path().to_owned())
+		})
+	}
 
-		let mut c_files = self
-			.c_files(&src)
-			.filter(|f| f.file_name().unwrap() != "main.c")
-			.collect::<Vec<_>>();
+	/// Where [Self::test_files] looks, relative to the project, for telling the user.
+	pub fn test_locations(&self) -> String {
+		let rel = |p: &std::path::Path| {
+			p.strip_prefix(self.path)
+				.unwrap_or(p)
+				.to_string_lossy()
+				.replace('\\', "/")
+		};
 
-		let out_dir = Self::get_or_mkdir(Self::get_or_mkdir(self.target())?.join("test"))?;
-		let flags = self.build_flags(backend);
+		self.src()
+			.iter()
+			.map(|root| format!("{}/**/*.test.c", rel(root)))
+			.chain([format!("{}/", rel(&self.tests()))])
+			.collect::<Vec<_>>()
+			.join(" and ")
+	}
 
-		let mut compiled = vec![];
+	/// Sources shared by every binary, which leaves out the entrypoints under src/bin, sorted within each folder in `src`
+	pub fn c_files(&self, src: &[std::path::PathBuf]) -> impl std::iter::Iterator<Item = std::path::PathBuf> {
+		/* Owned, since the iterator outlives the borrow */
+		let src = src.to_vec();
+
+		src.into_iter().flat_map(|root| {
+			let bins = root.join(Self::BINS);
+
+			walkdir::WalkDir::new(root)
+				.sort_by_file_name()
+				.into_iter()
+				.filter_entry(move |e| e.path() != bins)
+				.flatten()
+				.filter(|e| e.path().is_file())
+				.filter(|e| is_source(e.path()) && !is_inline_test(e.path()))
+				.map(|e| e.path().to_owned())
+		})
+	}
 
-		let tests = self.tests();
+	/// Sources making up the library, which is everything but the entrypoint
+	pub fn lib_files(&self, src: &[std::path::PathBuf]) -> impl std::iter::Iterator<Item = std::path::PathBuf> {
+		let main = self.main_file(src);
 
-		for test in self.test_files() {
-			let hash = {
-				use std::hash::{Hash, Hasher};
+		self.c_files(src)
+			.filter(move |f| Some(f) != main.as_ref())
+	}
 
-				let mut hasher = std::hash::DefaultHasher::new();
-				test.hash(&mut hasher);
-				hasher.finish().to_string()
-			};
+	/// Entrypoints under src/bin, each built as its own binary named after the file, sorted
+	pub fn bin_files(&self, src: &[std::path::PathBuf]) -> impl std::iter::Iterator<Item = std::path::PathBuf> {
+		let src = src.to_vec();
+
+		src.into_iter().flat_map(|root| {
+			walkdir::WalkDir::new(root.join(Self::BINS))
+				.max_depth(1)
+				.sort_by_file_name()
+				.into_iter()
+				.flatten()
+				.filter(|e| e.path().is_file())
+				.filter(|e| is_source(e.path()))
+				.map(|e| e.path().to_owned())
+		})
+	}
+
+	/// Entrypoint of the package binary, or [None] if there isn't exactly one.
+	pub fn main_file(&self, src: &[std::path::PathBuf]) -> Option<std::path::PathBuf> {
+		self.entry_file(src).ok().flatten()
+	}
 
-			let out_path = out_dir.join(&hash);
+	/// Entrypoint of the package binary: `[package] entry`, else the traditional main.c (or main.cpp) from the
+	/// first source folder that has one, else the only source defining main().
+	/// Several sources defining main() is an error listing them, as is an `entry` that doesn't exist.
+	pub fn entry_file(&self, src: &[std::path::PathBuf]) -> anyhow::Result<Option<std::path::PathBuf>> {
+		if let Some(ref entry) = self.config.package.entry {
+			if !self.path.join(entry).is_file() {
+				anyhow::bail!("entry {} in cpkg.toml doesn't exist", entry.display());
+			}
 
-			c_files.push(test);
-			backend.compile(&c_files, &[&tests, &src], &out_path, &flags)?;
-			let test = c_files.pop().unwrap();
+			return Ok(Some(self.path.join(entry)));
+		}
 
-			compiled.push((test, out_path));
+		if let Some(main) = src.iter().find_map(|root| source_named(&root.join("main"))) {
+			return Ok(Some(main));
 		}
 
-		Ok(compiled)
+		match self.mains(src).as_slice() {
+			[] => Ok(None),
+			[main] => Ok(Some(main.clone())),
+			mains => anyhow::bail!(
+				"Found main() in {}, set [package] entry in cpkg.toml to the one to build",
+				mains
+					.iter()
+					.map(|m| m.strip_prefix(self.path).unwrap_or(m).display().to_string())
+					.collect::<Vec<_>>()
+					.join(", ")
+			),
+		}
 	}
 
-	pub fn run_tests(
-		&self,
-		backend: &dyn crate::compiler::Compiler,
-		print: bool,
-	) -> anyhow::Result<Vec<(bool, std::path::PathBuf, Option<String>)>> {
-		let compiled = self.compile_tests(backend)?;
+	/// Sources besides those under src/bin that define a main(), sorted.
+	pub fn mains(&self, src: &[std::path::PathBuf]) -> Vec<std::path::PathBuf> {
+		let mut mains = self
+			.c_files(src)
+			.filter(|f| std::fs::read_to_string(f).is_ok_and(|s| defines_main(&s)))
+			.collect::<Vec<_>>();
 
-		let mut results = Vec::with_capacity(compiled.len());
+		mains.sort();
+		mains
+	}
 
-		for (src, compiled) in compiled {
-			let mut out = std::process::Command::new(&compiled);
+	/// Entrypoint of a named binary, preferring `src/bin/<name>.c` over `src/<name>.c`, or .cpp for either.
+	pub fn bin_entry(&self, src: &[std::path::PathBuf], name: &str) -> std::path::PathBuf {
+		let bins = src.iter().map(|root| root.join(Self::BINS).join(name));
+		let loose = src.iter().map(|root| root.join(name));
 
-			let out = if print {
-				out.spawn()?.wait_with_output()?
-			} else {
-				out.output()?
-			};
+		bins.chain(loose)
+			.find_map(|entry| source_named(&entry))
+			/* Where it'd be expected, for errors about it missing */
+			.unwrap_or_else(|| src.first().map(|root| root.join(name).with_extension("c")).unwrap_or_default())
+	}
 
-			if out.status.success() {
-				results.push((true, src, None));
-			} else {
-				results.push((false, src, Some(String::from_utf8(out.stderr)?)))
-			}
-		}
+	/// Whether an entrypoint is under one of the src/bin folders, rather than beside the sources.
+	pub fn is_bin_entry(&self, src: &[std::path::PathBuf], entry: &std::path::Path) -> bool {
+		src.iter().any(|root| entry.starts_with(root.join(Self::BINS)))
+	}
 
-		Ok(results)
+	/// Path of a source relative to the folder it's in, for laying out generated files.
+	/// With several source folders the folder's own name is kept, so files named alike in each don't collide.
+	fn src_relative<'f>(&self, src: &[std::path::PathBuf], file: &'f std::path::Path) -> &'f std::path::Path {
+		let root = match src {
+			[root] => root.as_path(),
+			_ => self.path,
+		};
+
+		file.strip_prefix(root).unwrap_or(file)
+	}
+
+	/// Public headers, checked by the headers target of header-only packages
+	pub fn header_files(&self, src: &[std::path::PathBuf]) -> impl std::iter::Iterator<Item = std::path::PathBuf> {
+		let src = src.to_vec();
+
+		src.into_iter().flat_map(|root| {
+			walkdir::WalkDir::new(root)
+				.sort_by_file_name()
+				.into_iter()
+				.flatten()
+				.filter(|e| e.path().is_file())
+				.filter(|e| e.path().to_string_lossy().ends_with(".h"))
+				.map(|e| e.path().to_owned())
+		})
+	}
+
+	pub fn example_files(&self) -> impl std::iter::Iterator<Item = std::path::PathBuf> {
+		walkdir::WalkDir::new(self.examples())
+			.max_depth(1)
+			.into_iter()
+			.flatten()
+			.filter(|e| e.path().is_file())
+			.filter(|e| e.path().to_string_lossy().ends_with(".c"))
+			.map(|e| e.path().to_owned())
+	}
+
+	/// C sources and headers `cpkg format` formats, under the source and tests folders.
+	/// Files matched by `exclude` under [formatter] are left out, like generated code placed in src.
+	pub fn format_files(&self) -> anyhow::Result<Vec<std::path::PathBuf>> {
+		let mut exclude = ignore::gitignore::GitignoreBuilder::new(self.path);
+
+		for pattern in self.config.formatter.iter().flat_map(|f| &f.exclude) {
+			exclude.add_line(None, pattern).map_err(|e| {
+				anyhow::anyhow!("Invalid pattern '{pattern}' in exclude under [formatter]: {e}")
+			})?;
+		}
+
+		let exclude = exclude.build()?;
+
+		let dirs = self
+			.src()
+			.into_iter()
+			.chain([self.tests()])
+			.filter(|d| d.is_dir());
+
+		let mut files = vec![];
+
+		for dir in dirs {
+			let walk = walkdir::WalkDir::new(dir)
+				.into_iter()
+				.filter_entry(|e| !exclude.matched(e.path(), e.file_type().is_dir()).is_ignore());
+
+			for entry in walk {
+				let entry = entry?;
+
+				/* Tests can hold fixtures and expected output, which aren't for a C formatter */
+				if entry.file_type().is_file()
+					&& entry.path().extension().is_some_and(|e| e == "c" || e == "h")
+				{
+					files.push(entry.into_path());
+				}
+			}
+		}
+
+		/* A tests folder inside of the sources would otherwise be formatted twice */
+		files.sort();
+		files.dedup();
+
+		Ok(files)
+	}
+
+	pub fn src_files(&self) -> impl std::iter::Iterator<Item = std::path::PathBuf> {
+		self.src()
+			.into_iter()
+			.flat_map(walkdir::WalkDir::new)
+			.flatten()
+			.filter(|e| e.path().is_file())
+			.map(|e| e.path().to_owned())
+	}
+
+	/*
+		Building
+	*/
+
+	pub fn build_flags(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+	) -> std::borrow::Cow<'_, [String]> {
+		std::borrow::Cow::Owned(self.flags(backend, true))
+	}
+
+	/// Flags besides the ones from [Self::build_options], for exported build files that lay those out themselves.
+	pub fn raw_flags(&self, backend: &dyn crate::compiler::Compiler) -> Vec<String> {
+		self.flags(backend, false)
+	}
+
+	/// Libraries, defines and search folders from cpkg.toml, with folders resolved against the project.
+	/// Prebuilt library dependencies come after the ones under [compiler].
+	pub fn build_options(&self) -> BuildOptions {
+		let compiler = self.config.compiler.as_ref();
+		let dirs = |dirs: Option<&Vec<std::path::PathBuf>>| {
+			dirs.into_iter()
+				.flatten()
+				.map(|d| self.path.join(d))
+				.collect::<Vec<_>>()
+		};
+
+		let mut options = BuildOptions {
+			defines: compiler
+				.and_then(|c| c.defines.as_ref())
+				.into_iter()
+				.flatten()
+				.map(|(k, v)| (k.clone(), v.clone()))
+				.collect(),
+			include_dirs: dirs(compiler.and_then(|c| c.include_dirs.as_ref())),
+			lib_dirs: dirs(compiler.and_then(|c| c.lib_dirs.as_ref())),
+			libs: compiler.and_then(|c| c.libs.clone()).unwrap_or_default(),
+		};
+
+		for dep in self.config.dependencies.entries.values() {
+			if let ConfigDependency::Lib { lib, search, headers } = dep {
+				options.include_dirs.extend(headers.iter().map(|h| self.path.join(h)));
+				options.lib_dirs.extend(search.iter().map(|s| self.path.join(s)));
+				options.libs.push(lib.clone());
+			}
+		}
+
+		options
+	}
+
+	fn flags(&self, backend: &dyn crate::compiler::Compiler, options: bool) -> Vec<String> {
+		let compiler = self.config.compiler.as_ref();
+		let cxx = crate::compiler::is_cxx(backend);
+
+		/* Comes first, so anything under flags can override it */
+		let standard = if cxx {
+			compiler.and_then(|c| c.cxx_standard).map(|s| s.flag())
+		} else {
+			compiler.and_then(|c| c.standard).map(|s| s.flag())
+		};
+
+		let mut flags = standard
+			.map(str::to_owned)
+			.into_iter()
+			.chain(
+				compiler
+					.and_then(|c| c.warnings)
+					.into_iter()
+					.flat_map(|w| w.flags().iter().map(|f| f.to_string())),
+			)
+			.collect::<Vec<_>>();
+
+		/* TODO: Support backend-specific flags for gcc and clang */
+		flags.extend(compiler.and_then(|c| c.flags.clone()).unwrap_or_default());
+
+		if options {
+			flags.extend(self.build_options().compile_flags());
+		}
+
+		let emcc = compiler.and_then(|c| c.emcc.as_ref());
+		let is_emcc = std::path::Path::new(backend.bin()).file_stem().is_some_and(|s| s == "emcc");
+
+		if let Some(emcc) = emcc.filter(|_| is_emcc) {
+			flags.extend(emcc.flags.iter().flatten().cloned());
+			flags.extend(emcc.settings.iter().flatten().map(|s| format!("-s{s}")));
+		}
+
+		flags.extend(self.profile.flags().iter().map(|f| f.to_string()));
+
+		/* After the profile's, so -O0 wins over its optimizations */
+		if self.debug_info {
+			flags.extend(["-g".to_owned(), "-O0".to_owned()]);
+		}
+
+		if let Some(ref cross) = self.cross {
+			let triple = cross.config.triple.as_ref();
+
+			if let Some(triple) = triple.filter(|_| crate::compiler::is_zig(backend)) {
+				flags.push("-target".to_owned());
+				flags.push(triple.clone());
+			}
+
+			flags.extend(cross.config.flags.iter().flatten().cloned());
+		}
+
+		/* Last, so the environment can override the project like with make */
+		flags.extend(crate::compiler::env_flags(if cxx { "CXXFLAGS" } else { "CFLAGS" }));
+
+		/* Lets C code embed the package version */
+		flags.push(format!("-DCPKG_PKG_VERSION=\"{}\"", self.version()));
+
+		flags
+	}
+
+	/// Flags for invocations that link, which is everything but compiling library objects.
+	pub fn link_flags(&self, backend: &dyn crate::compiler::Compiler) -> Vec<String> {
+		let mut flags = self.build_flags(backend).to_vec();
+
+		if let Some(ref cross) = self.cross {
+			flags.extend(cross.config.ldflags.iter().flatten().cloned());
+		}
+
+		/* Before libraries, which the artifacts may need too */
+		flags.extend(self.dep_artifacts().iter().map(|a| a.display().to_string()));
+		flags.extend(self.build_options().link_flags());
+		flags.extend(crate::compiler::env_flags("LDFLAGS"));
+
+		flags
+	}
+
+	/// Returns PathBuf to desired executable location
+	/// Only the main binary goes to `bin` under [package], so other binaries don't overwrite it.
+	pub fn build_out(&self, entrypoint: Option<&std::path::Path>) -> std::path::PathBuf {
+		if let Some(entrypoint) = entrypoint {
+			self.exe(self.out_dir().join(entrypoint.file_stem().unwrap()))
+		} else if let Some(ref bin) = self.config.package.bin {
+			self.exe(bin.clone())
+		} else {
+			self.exe(self.out_dir().join(&self.config.package.name))
+		}
+	}
+
+	/// Extension of the executables this project builds: the cross target's, or .exe when building for Windows.
+	pub fn exe_extension(&self) -> Option<&str> {
+		match self.cross {
+			Some(ref cross) => cross.extension(),
+			None => Some(std::env::consts::EXE_EXTENSION).filter(|e| !e.is_empty()),
+		}
+	}
+
+	/// Gives an executable the extension the platform it's built for expects, if any.
+	fn exe(&self, path: std::path::PathBuf) -> std::path::PathBuf {
+		with_exe_extension(path, self.exe_extension())
+	}
+
+	/// Returns the project's build script, if it has one.
+	pub fn build_script(&self) -> Option<std::path::PathBuf> {
+		let build_c = self.path.join("build.c");
+		build_c.exists().then_some(build_c)
+	}
+
+	/// Compiles and runs the build script, returning the source directory it selected, if any.
+	fn run_build_script(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		build_c: std::path::PathBuf,
+	) -> anyhow::Result<Option<std::path::PathBuf>> {
+		let t = tempfile::Builder::new()
+			.suffix(std::env::consts::EXE_SUFFIX)
+			.tempfile()?
+			.into_temp_path();
+
+		/* The build script runs on this machine, so it can't use a cross compiler */
+		let host;
+		let backend = if self.cross.is_some() {
+			host = crate::compiler::try_locate(None)?;
+			host.as_ref()
+		} else {
+			backend
+		};
+
+		backend.compile(&[build_c], &[], &t, &[], &mut crate::compiler::echo)?;
+
+		let mut cmd = std::process::Command::new(&t);
+		crate::log::command(&cmd);
+
+		let out = cmd.output()?;
+
+		if !out.status.success() {
+			anyhow::bail!(
+				"Build script failed: {}",
+				String::from_utf8_lossy(&out.stderr)
+			);
+		}
+
+		let out = String::from_utf8_lossy(&out.stdout);
+		let mut ptr = 0;
+		let mut src = None;
+
+		while let Some(r) = out[ptr..].find(Self::BUILD_COMMAND_PREFIX) {
+			let cmd = out[r..].split_once(" \t\n").map(|p| p.0).unwrap_or(&out[r..]);
+			let cmd = &cmd[Self::BUILD_COMMAND_PREFIX.len()..];
+
+			if cmd.starts_with("set_src") {
+				if let Some((_, to)) = cmd.split_once('=') {
+					src = Some(std::env::current_dir()?.join(to.trim()));
+				}
+			}
+
+			ptr = r + 1;
+		}
+
+		Ok(src)
+	}
+
+	/// Output of the static library target
+	pub fn lib_out(&self) -> std::path::PathBuf {
+		self.out_dir().join(format!("lib{}.a", self.config.package.name))
+	}
+
+	/// Umbrella header of header-only packages, which includes every public header
+	pub fn umbrella_out(&self) -> std::path::PathBuf {
+		self.target()
+			.join("include")
+			.join(format!("{}.h", self.config.package.name))
+	}
+
+	pub fn is_header_only(&self) -> bool {
+		self.config.package.kind == Some(crate::config::ConfigKind::HeaderOnly)
+	}
+
+	/// Output of an example target
+	pub fn example_out(&self, name: &str) -> std::path::PathBuf {
+		self.exe(self.out_dir().join(Self::EXAMPLES).join(name))
+	}
+
+	/// Files or folders a target writes to, removed by `cpkg clean` for that target.
+	pub fn target_outputs(&self, target: &crate::target::Target) -> Vec<std::path::PathBuf> {
+		use crate::target::Target;
+
+		match target {
+			Target::Bin(name) if name == Target::MAIN => vec![self.build_out(None)],
+			Target::Bin(name) => vec![self.build_out(Some(&self.bin_entry(&self.src(), name)))],
+			Target::Lib => vec![self.lib_out(), self.out_dir().join("obj")],
+			Target::Example(name) => vec![self.example_out(name)],
+			Target::Tests => vec![self.out_dir().join("test")],
+			Target::Headers => vec![self.out_dir().join(Self::HEADERS), self.umbrella_out()],
+		}
+	}
+
+	/// Works out what building the given targets would do, without doing any of it.
+	/// A build script may change the source directory, which can only be known by running it.
+	pub fn plan(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		targets: &[crate::target::Target],
+		src: &[std::path::PathBuf],
+	) -> anyhow::Result<BuildPlan> {
+		use crate::target::Target;
+
+		let vendors = self.vendor_dirs();
+		let includes = self.include_paths(src);
+
+		let missing = self
+			.config
+			.dependencies
+			.entries
+			.iter()
+			.filter(|(_, dep)| dep.is_vendored())
+			.map(|(name, _)| name)
+			.filter(|name| !vendors.iter().any(|v| v.join(name).symlink_metadata().is_ok()))
+			.cloned()
+			.collect();
+
+		let mut plan = BuildPlan {
+			missing,
+			build_script: self.build_script(),
+			/* Written before planning when building, as the fingerprint covers it, but listed so the plan shows it */
+			generated: vec![(self.config_header_path(), self.config_header()?)],
+			invocations: vec![],
+			archives: vec![],
+			outputs: vec![],
+			fingerprint: fingerprint::Fingerprint::new(self, backend, src)?,
+		};
+
+		for target in targets {
+			match target {
+				Target::Bin(name) if name == Target::MAIN => {
+					/* Traditional main entrypoint */
+					let out = self.build_out(None);
+
+					let Some(entrypoint) = self.entry_file(src)? else {
+						anyhow::bail!("Couldn't find main.c, or any source defining main(), to build!");
+					};
+
+					/* Others defining main() are left to --bin, so an entry can be picked between them */
+					let mains = self.mains(src);
+					let c_files = self
+						.c_files(src)
+						.filter(|f| *f == entrypoint || !mains.contains(f))
+						.collect::<Vec<_>>();
+					let cxx = self.cxx_backend(backend, &c_files)?;
+					let linker = cxx.as_deref().unwrap_or(backend);
+					let c_files = self.split_overrides(backend, cxx.as_deref(), c_files, &includes, &mut plan.invocations)?;
+					let flags = self.link_flags(linker);
+
+					plan.invocations.push(Invocation::new(
+						linker,
+						c_files,
+						includes.clone(),
+						out.clone(),
+						flags,
+					));
+					plan.outputs.push(out);
+				}
+
+				Target::Bin(name) if self.is_bin_entry(src, &self.bin_entry(src, name)) => {
+					let entrypoint = self.bin_entry(src, name);
+					let out = self.build_out(Some(&entrypoint));
+
+					/* Has its own main, so it's only linked with what the package entrypoint would be */
+					let c_files = std::iter::once(entrypoint).chain(self.lib_files(src)).collect::<Vec<_>>();
+					let cxx = self.cxx_backend(backend, &c_files)?;
+					let linker = cxx.as_deref().unwrap_or(backend);
+					let c_files = self.split_overrides(backend, cxx.as_deref(), c_files, &includes, &mut plan.invocations)?;
+
+					plan.invocations.push(Invocation::new(
+						linker,
+						c_files,
+						includes.clone(),
+						out.clone(),
+						self.link_flags(linker),
+					));
+					plan.outputs.push(out);
+				}
+
+				Target::Bin(entrypoint) => {
+					let entrypoint = self.bin_entry(src, entrypoint);
+					let out = self.build_out(Some(&entrypoint));
+
+					if !entrypoint.is_file() {
+						anyhow::bail!("Entrypoint {} does not exist!", entrypoint.display());
+					}
+
+					/* Linked with every source but the others defining main() */
+					let mains = self.mains(src);
+					let c_files = std::iter::once(entrypoint.clone())
+						.chain(self.c_files(src).filter(|f| *f != entrypoint && !mains.contains(f)))
+						.collect::<Vec<_>>();
+
+					let includes = includes.clone();
+					let cxx = self.cxx_backend(backend, &c_files)?;
+					let linker = cxx.as_deref().unwrap_or(backend);
+					let c_files = self.split_overrides(backend, cxx.as_deref(), c_files, &includes, &mut plan.invocations)?;
+
+					let flags = self.link_flags(linker);
+
+					plan.invocations.push(Invocation::new(
+						linker,
+						c_files,
+						includes,
+						out.clone(),
+						flags,
+					));
+					plan.outputs.push(out);
+				}
+
+				Target::Lib => {
+					let obj_dir = self.out_dir().join("obj");
+
+					let overrides = overrides::Overrides::new(self.path, &self.config)?;
+
+					let files = self.lib_files(src).collect::<Vec<_>>();
+					let cxx = self.cxx_backend(backend, &files)?;
+
+					let mut objects = vec![];
+
+					for file in files {
+						let compiler = cxx.as_deref().filter(|_| is_cxx_source(&file)).unwrap_or(backend);
+						let obj = object_path(&obj_dir, self.src_relative(src, &file));
+
+						let mut flags = self.build_flags(compiler).to_vec();
+						flags.push("-c".to_owned());
+						let flags = overrides.apply(&file, &flags);
+
+						plan.invocations.push(Invocation::new(
+							compiler,
+							vec![file.clone()],
+							includes.clone(),
+							obj.clone(),
+							flags,
+						));
+
+						objects.push(obj);
+					}
+
+					if objects.is_empty() {
+						anyhow::bail!("No sources besides the entrypoint to build a library from!");
+					}
+
+					let out = self.lib_out();
+					plan.archives.push(StaticLib::new(objects, out.clone()));
+					plan.outputs.push(out);
+				}
+
+				Target::Example(name) => {
+					let example = self.examples().join(name).with_extension("c");
+					let out = self.example_out(name);
+
+					if !example.exists() {
+						anyhow::bail!("Example {} does not exist!", example.display());
+					}
+
+					let mut c_files = vec![example];
+					c_files.extend(self.lib_files(src));
+
+					let cxx = self.cxx_backend(backend, &c_files)?;
+					let linker = cxx.as_deref().unwrap_or(backend);
+					let c_files = self.split_overrides(backend, cxx.as_deref(), c_files, &includes, &mut plan.invocations)?;
+
+					plan.invocations.push(Invocation::new(
+						linker,
+						c_files,
+						includes.clone(),
+						out.clone(),
+						self.link_flags(linker),
+					));
+					plan.outputs.push(out);
+				}
+
+				Target::Headers => {
+					let dir = self.out_dir().join(Self::HEADERS);
+					let headers = self.header_files(src).collect::<Vec<_>>();
+
+					if headers.is_empty() {
+						anyhow::bail!("No headers in the sources to check!");
+					}
+
+					let mut flags = self.build_flags(backend).to_vec();
+					flags.push("-fsyntax-only".to_owned());
+
+					/* Relative to the source folder, which is searched, so the same lines work in the umbrella header */
+					let include = |header: &std::path::PathBuf| {
+						let rel = src
+							.iter()
+							.find_map(|root| header.strip_prefix(root).ok())
+							.unwrap_or(header);
+						format!("#include \"{}\"\n", rel.to_string_lossy().replace('\\', "/"))
+					};
+
+					/* Each header on its own catches one that needs another included before it */
+					for header in &headers {
+						let rel = self.src_relative(src, header);
+						let check = dir.join(rel).with_extension("h.c");
+
+						plan.generated.push((
+							check.clone(),
+							format!(
+								"/* Generated by cpkg to check {} compiles on its own */\n{}",
+								rel.display(),
+								include(header)
+							),
+						));
+
+						plan.invocations.push(Invocation::new(
+							backend,
+							vec![check.clone()],
+							includes.clone(),
+							dir.join(rel),
+							flags.clone(),
+						));
+					}
+
+					let lines = headers.iter().map(include).collect::<String>();
+					let name = &self.config.package.name;
+
+					/* Then all of them together, for headers that conflict with each other */
+					let check = dir.join(format!("{name}.c"));
+
+					plan.generated.push((
+						check.clone(),
+						format!("/* Generated by cpkg to check the headers of {name} compile together */\n{lines}"),
+					));
+
+					plan.invocations.push(Invocation::new(
+						backend,
+						vec![check.clone()],
+						includes.clone(),
+						dir.join(name),
+						flags,
+					));
+
+					let out = self.umbrella_out();
+
+					plan.generated.push((
+						out.clone(),
+						format!("/* Generated by cpkg, includes every public header of {name} */\n#pragma once\n\n{lines}"),
+					));
+
+					plan.outputs.push(out);
+				}
+
+				Target::Tests => {
+					for (_, invocation) in self.test_invocations(backend, src, &mut plan.invocations)? {
+						plan.outputs.push(invocation.output.clone());
+						plan.invocations.push(invocation);
+					}
+				}
+			}
+		}
+
+		let fresh = self.is_fresh(&plan.fingerprint);
+
+		for invocation in &mut plan.invocations {
+			invocation.fresh = fresh && invocation.output.exists();
+		}
+
+		for archive in &mut plan.archives {
+			archive.fresh = fresh && archive.output.exists();
+		}
+
+		Ok(plan)
+	}
+
+	/// Whether outputs of the last build into the output folder can be reused, as the compiler, flags and sources are all as they were.
+	/// Never the case with --force-rebuild, or when the last build didn't succeed.
+	fn is_fresh(&self, fingerprint: &fingerprint::Fingerprint) -> bool {
+		!self.force
+			&& fingerprint::Fingerprint::load(&self.out_dir()).is_some_and(|previous| fingerprint.changes(&previous).is_empty())
+	}
+
+	/// Compiler for the C++ among `files`, which then links all of them, or [None] when they're all C.
+	/// `language = "c++"` under [package] links through it regardless, for C sources calling into C++ libraries.
+	pub fn cxx_backend(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		files: &[std::path::PathBuf],
+	) -> anyhow::Result<Option<Box<dyn crate::compiler::Compiler>>> {
+		let wanted = self.config.package.language == Some(crate::config::ConfigLanguage::Cxx)
+			|| files.iter().any(|f| is_cxx_source(f));
+
+		wanted.then(|| crate::compiler::locate_cxx(backend)).transpose()
+	}
+
+	/// Compiles the sources matched under [compiler.overrides] to objects of their own, since they need other flags.
+	/// With `cxx`, every source is, since linking through it would compile C sources as C++.
+	/// Returns `files` with those sources swapped for their objects, which are linked in their place.
+	/// Objects already in `objects` aren't compiled twice, as targets share sources.
+	fn split_overrides(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		cxx: Option<&dyn crate::compiler::Compiler>,
+		files: Vec<std::path::PathBuf>,
+		includes: &[std::path::PathBuf],
+		objects: &mut Vec<Invocation>,
+	) -> anyhow::Result<Vec<std::path::PathBuf>> {
+		let overrides = overrides::Overrides::new(self.path, &self.config)?;
+
+		let compile_flags = |compiler| {
+			let mut flags = self.build_flags(compiler).to_vec();
+			flags.push("-c".to_owned());
+			flags
+		};
+
+		let flags = compile_flags(backend);
+		let cxx = cxx.map(|cxx| (cxx, compile_flags(cxx)));
+
+		Ok(files
+			.into_iter()
+			.map(|file| {
+				let overridden = overrides.matches(&file);
+
+				if !overridden && cxx.is_none() {
+					return file;
+				}
+
+				let (compiler, flags) = match cxx {
+					Some((cxx, ref cxx_flags)) if is_cxx_source(&file) => (cxx, cxx_flags),
+					_ => (backend, &flags),
+				};
+
+				/* Generated sources, like a workspace's test mains, can be outside of the project */
+				let rel = file
+					.strip_prefix(self.path)
+					.map(std::path::Path::to_path_buf)
+					.unwrap_or_else(|_| file.file_name().map(Into::into).unwrap_or_default());
+
+				let dir = if overridden { "overrides" } else { "obj" };
+				let obj = object_path(&self.out_dir().join(dir), &rel);
+
+				if !objects.iter().any(|i| i.output == obj) {
+					objects.push(Invocation::new(
+						compiler,
+						vec![file.clone()],
+						includes.to_vec(),
+						obj.clone(),
+						overrides.apply(&file, flags),
+					));
+				}
+
+				obj
+			})
+			.collect())
+	}
+
+	/// Builds the given targets, returning what each of them output.
+	#[must_use = "Ensure actually built correctly"]
+	pub fn build(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		targets: &[crate::target::Target],
+		can_run_build: impl FnOnce() -> bool,
+	) -> anyhow::Result<Vec<std::path::PathBuf>> {
+		self.check_conflicts()?;
+
+		let mut src = self.src();
+
+		let broken = self.check_deps();
+		if !broken.is_empty() {
+			let list = broken
+				.iter()
+				.map(|b| format!("  {b}"))
+				.collect::<Vec<_>>()
+				.join("\n");
+
+			anyhow::bail!(
+				"Some vendored dependencies are broken:\n{list}\nRun `cpkg install` to fix them."
+			);
+		}
+
+		let unbuilt = self
+			.dep_artifacts()
+			.into_iter()
+			.filter(|a| !a.exists())
+			.map(|a| format!("  {}", a.strip_prefix(self.path).unwrap_or(&a).display()))
+			.collect::<Vec<_>>();
+
+		if !unbuilt.is_empty() {
+			anyhow::bail!(
+				"Some dependencies haven't built what they declare under artifacts:\n{}\nRun `cpkg install` to build them.",
+				unbuilt.join("\n")
+			);
+		}
+
+		if !self.target().exists() {
+			std::fs::create_dir_all(self.target())?;
+		}
+
+		if let Some(build_c) = self.build_script() {
+			if can_run_build() {
+				if let Some(to) = self.run_build_script(backend, build_c)? {
+					src = vec![to];
+				}
+			} else {
+				anyhow::bail!(
+					"This project requires a build script to run, but was not permitted to run it."
+				);
+			}
+		}
+
+		self.write_config_header()?;
+		self.write_shims()?;
+		let plan = self.plan(backend, targets, &src)?;
+
+		let out_dir = self.out_dir();
+		let fingerprint = &plan.fingerprint;
+
+		/* The plan already knows what's fresh, this explains why the rest isn't */
+		match fingerprint::Fingerprint::load(&out_dir) {
+			_ if self.force => {
+				crate::log::detail("Rebuilding everything, since --force-rebuild was passed");
+			}
+			Some(previous) => {
+				let changes = fingerprint.changes(&previous);
+
+				let rebuild = changes
+					.iter()
+					.filter(|c| **c != fingerprint::Change::Sources)
+					.map(|c| c.to_string())
+					.collect::<Vec<_>>();
+
+				if !rebuild.is_empty() {
+					crate::log::info!("Rebuilding from scratch, since {}.", rebuild.join(" and "));
+
+					let obj_dir = out_dir.join("obj");
+
+					if obj_dir.exists() {
+						std::fs::remove_dir_all(obj_dir)?;
+					}
+				}
+
+				if changes.contains(&fingerprint::Change::Sources) {
+					/* The newest is most likely what was just edited, or touched by surprise */
+					let changed = fingerprint.changed_sources(&previous);
+					let newest = changed
+						.iter()
+						.max_by_key(|f| std::fs::metadata(f).and_then(|m| m.modified()).ok());
+
+					if let Some(newest) = newest {
+						let others = match changed.len() - 1 {
+							0 => String::new(),
+							1 => " and 1 other".to_owned(),
+							n => format!(" and {n} others"),
+						};
+
+						crate::log::detail(format!(
+							"Rebuilding, since {}{others} changed",
+							newest.strip_prefix(self.path).unwrap_or(newest).display()
+						));
+					}
+				}
+
+				if !changes.is_empty() {
+					fingerprint::Fingerprint::remove(&out_dir)?;
+				}
+			}
+			None => {}
+		}
+
+		let stale = plan
+			.invocations
+			.iter()
+			.filter(|i| !i.fresh)
+			.map(|i| (i.output.strip_prefix(self.path).unwrap_or(&i.output).display().to_string(), i))
+			.collect::<Vec<_>>();
+
+		let archives = plan
+			.archives
+			.iter()
+			.filter(|a| !a.fresh)
+			.collect::<Vec<_>>();
+
+		/* Left out of the plan itself, so it can be looked at without touching anything */
+		for (path, contents) in &plan.generated {
+			write_generated(path, contents)?;
+		}
+
+		self.compiled.set(stale.len() + archives.len());
+		self.execute(backend, stale)?;
+
+		for archive in archives {
+			let start = std::time::Instant::now();
+			archive.execute()?;
+
+			self.record(Step::Archive, &archive.output, &archive.objects, start.elapsed(), None);
+		}
+
+		fingerprint.save(&out_dir)?;
+
+		Ok(plan.outputs)
+	}
+
+	/// Compiles a single source only as far as `kind`, for looking at what the compiler makes of it.
+	/// Defaults to main.c, and returns where the output went, which is `to` or under target/emit.
+	pub fn emit(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		file: Option<&std::path::Path>,
+		kind: crate::compiler::Emit,
+		to: Option<&std::path::Path>,
+	) -> anyhow::Result<std::path::PathBuf> {
+		let src = self.src();
+
+		let file = match file {
+			/* Paths are taken from where cpkg was ran, falling back to the source folder */
+			Some(file) if file.is_file() => file.to_owned(),
+			Some(file) => match src.iter().map(|root| root.join(file)).find(|f| f.is_file()) {
+				Some(found) => found,
+				None => anyhow::bail!("Couldn't find {} to emit.", file.display()),
+			},
+			None => match self.entry_file(&src)? {
+				Some(main) => main,
+				None => anyhow::bail!("Couldn't find main.c, or any source defining main(), pass the file to emit."),
+			},
+		};
+
+		let to = match to {
+			Some(to) => to.to_owned(),
+			None => self
+				.out_dir()
+				.join("emit")
+				.join(file.file_stem().unwrap_or_default())
+				.with_extension(kind.extension()),
+		};
+
+		if let Some(parent) = to.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+
+		self.write_config_header()?;
+		self.write_shims()?;
+
+		let cxx;
+		let backend = if is_cxx_source(&file) {
+			cxx = crate::compiler::locate_cxx(backend)?;
+			cxx.as_ref()
+		} else {
+			backend
+		};
+
+		let includes = self.include_paths(&src);
+		let includes = includes.iter().map(|p| p.as_path()).collect::<Vec<_>>();
+
+		match backend.emit(&file, &includes, &to, &self.build_flags(backend), kind) {
+			Err(e) if !self.raw_errors => match e.downcast::<crate::compiler::CompileError>() {
+				Ok(failure) => Err(crate::compiler::CompileErrors {
+					failures: vec![failure],
+					root: self.path.to_owned(),
+				}
+				.into()),
+				Err(e) => Err(e),
+			},
+			result => result.map(|_| to),
+		}
+	}
+
+	/// Runs every invocation, reporting progress by the name given with each when there's more than one.
+	/// Then reports the compile errors of all that failed at once, so an error in a shared header is only shown once.
+	fn execute<'b>(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		invocations: impl IntoIterator<Item = (String, &'b Invocation)>,
+	) -> anyhow::Result<()> {
+		let mut failures = vec![];
+
+		self.execute_each(backend, invocations, |_, failure| {
+			/* Printed as the compiler printed it, so there's no reporting several together */
+			if self.raw_errors {
+				return Err(failure.into());
+			}
+
+			failures.push(failure);
+			Ok(())
+		})?;
+
+		if !failures.is_empty() {
+			return Err(crate::compiler::CompileErrors {
+				failures,
+				root: self.path.to_owned(),
+			}
+			.into());
+		}
+
+		Ok(())
+	}
+
+	/// Runs each invocation, handing the ones that fail to compile to `failed` along with their index.
+	/// Anything else going wrong, like the compiler not starting, stops the rest from running.
+	fn execute_each<'b>(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		invocations: impl IntoIterator<Item = (String, &'b Invocation)>,
+		mut failed: impl FnMut(usize, crate::compiler::CompileError) -> anyhow::Result<()>,
+	) -> anyhow::Result<()> {
+		let invocations = invocations.into_iter().collect::<Vec<_>>();
+		let mut progress = crate::progress::Progress::new(invocations.len()).shown(invocations.len() > 1);
+
+		let report = backend.time_report().filter(|_| self.timings == Some(Timings::Verbose));
+
+		for (index, (name, invocation)) in invocations.into_iter().enumerate() {
+			/* Headers are only checked, which doesn't output anything */
+			let verb = if invocation.step() == Step::Check {
+				"Checking"
+			} else {
+				"Compiling"
+			};
+
+			let start = std::time::Instant::now();
+
+			let result = progress.item(verb, &name, || match report {
+				Some(flag) => invocation.execute_reporting(backend, flag).map(Some),
+				None => invocation.execute(backend).map(|_| None),
+			});
+
+			match result {
+				Ok(report) => self.record(
+					invocation.step(),
+					&invocation.output,
+					&invocation.sources,
+					start.elapsed(),
+					report,
+				),
+				Err(e) => failed(index, e.downcast::<crate::compiler::CompileError>()?)?,
+			}
+		}
+
+		Ok(())
+	}
+
+	/*
+		Tests
+	*/
+
+	/// One invocation per test file, each linked with the library sources.
+	/// Sources under [compiler.overrides] are compiled on their own into `objects`, see [Self::split_overrides].
+	fn test_invocations(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		src: &[std::path::PathBuf],
+		objects: &mut Vec<Invocation>,
+	) -> anyhow::Result<Vec<(std::path::PathBuf, Invocation)>> {
+		let c_files = self.lib_files(src).collect::<Vec<_>>();
+
+		let out_dir = self.out_dir().join("test");
+		let flags = self.link_flags(backend);
+
+		/* Some compilers warn about searching folders that don't exist, like tests/ in a new project */
+		let includes = std::iter::once(self.tests())
+			.chain(self.include_paths(src))
+			.filter(|dir| dir.is_dir())
+			.collect::<Vec<_>>();
+
+		let tests = self.test_files().collect::<Vec<_>>();
+
+		/* Named after where the test is, so target/test mirrors the project and a binary can be found to debug */
+		let base = |test: &std::path::Path| match test.strip_prefix(self.path) {
+			Ok(rel) => rel.with_extension(""),
+			Err(_) => std::path::PathBuf::from(test.file_stem().unwrap_or_default()),
+		};
+
+		tests
+			.iter()
+			.map(|test| {
+				let mut name = base(test);
+
+				/* Only tests differing in extension, like foo.c and foo.cpp, share a name, so they keep it */
+				if tests.iter().filter(|t| base(t) == name).count() > 1 {
+					let mut renamed = name.into_os_string();
+					renamed.push("-");
+					renamed.push(test.extension().unwrap_or_default());
+					name = renamed.into();
+				}
+
+				let mut files = c_files.clone();
+				files.push(test.clone());
+
+				let mut includes = includes.clone();
+
+				/* Tests using the harness get a generated main running their cases */
+				let source = std::fs::read_to_string(test).unwrap_or_default();
+
+				if let Some(cases) = crate::harness::cases(&source) {
+					let header = crate::assets::locate(Some(self), crate::harness::HEADER)?;
+					includes.extend(header.parent().map(|p| p.to_owned()));
+
+					let main = with_exe_extension(out_dir.join(&name), Some("main.c"));
+					write_generated(&main, &crate::harness::main(&cases))?;
+
+					files.push(main);
+				}
+
+				let cxx = self.cxx_backend(backend, &files)?;
+				let files = self.split_overrides(backend, cxx.as_deref(), files, &includes, objects)?;
+
+				let invocation = match cxx {
+					Some(cxx) => Invocation::new(
+						cxx.as_ref(),
+						files,
+						includes,
+						self.exe(out_dir.join(&name)),
+						self.link_flags(cxx.as_ref()),
+					),
+					None => Invocation::new(backend, files, includes, self.exe(out_dir.join(&name)), flags.to_vec()),
+				};
+
+				Ok((test.clone(), invocation))
+			})
+			.collect()
+	}
+
+	/// Compiles every test, reusing binaries whose inputs haven't changed since they were compiled
+	/// unless `cache` is false. Returns each test's source and binary, with whether the binary was reused.
+	/// A test that fails to compile doesn't stop the others, and gets its compile error instead.
+	pub fn compile_tests(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		cache: bool,
+	) -> anyhow::Result<Vec<CompiledTest>> {
+		self.check_conflicts()?;
+		self.write_config_header()?;
+		self.write_shims()?;
+
+		let mut objects = vec![];
+		let invocations = self.test_invocations(backend, &self.src(), &mut objects)?;
+
+		self.prune_tests(&invocations)?;
+
+		/* Every test searches the same folders, so their headers are only read once */
+		let folders = objects
+			.iter()
+			.chain(invocations.iter().map(|(_, i)| i))
+			.flat_map(|i| i.includes.iter().cloned())
+			.collect::<std::collections::BTreeSet<_>>();
+
+		let headers = folders
+			.into_iter()
+			.map(|dir| Ok((dir.clone(), fingerprint::digest_sources([dir])?)))
+			.collect::<anyhow::Result<IncludeDigests>>()?;
+
+		/* Before the tests, which hash the objects they link as inputs. Every test links them, so there's no going on without them */
+		let mut failures = self
+			.compile_stale(backend, objects.iter().map(|o| (&o.sources[0], o)), &headers, cache)?
+			.into_iter()
+			.filter_map(Result::err)
+			.collect::<Vec<_>>();
+
+		if self.raw_errors && !failures.is_empty() {
+			return Err(failures.remove(0).into());
+		}
+
+		if !failures.is_empty() {
+			return Err(crate::compiler::CompileErrors {
+				failures,
+				root: self.path.to_owned(),
+			}
+			.into());
+		}
+
+		let cached = self.compile_stale(backend, invocations.iter().map(|(test, i)| (test, i)), &headers, cache)?;
+
+		Ok(invocations
+			.into_iter()
+			.zip(cached)
+			.map(|((test, invocation), cached)| (test, invocation.output, cached))
+			.collect())
+	}
+
+	/// Runs the invocations whose inputs changed since they last ran, each named after the given path.
+	/// Returns whether each was reused instead, which is never the case without `cache`, or how it failed to compile.
+	fn compile_stale<'i>(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		invocations: impl Iterator<Item = (&'i std::path::PathBuf, &'i Invocation)>,
+		headers: &IncludeDigests,
+		cache: bool,
+	) -> anyhow::Result<Vec<Result<bool, crate::compiler::CompileError>>> {
+		let mut stale = vec![];
+		let mut reused = vec![];
+
+		for (path, invocation) in invocations {
+			let stamp = stamp_path(&invocation.output);
+			let hash = Self::inputs_hash(invocation, headers)?;
+
+			let cached = cache
+				&& invocation.output.exists()
+				&& std::fs::read_to_string(&stamp).is_ok_and(|s| s == hash);
+
+			if !cached {
+				let name = path.strip_prefix(self.path).unwrap_or(path).display().to_string();
+				stale.push((reused.len(), name, invocation, stamp, hash));
+			}
+
+			reused.push(Ok(cached));
+		}
+
+		let mut failed = std::collections::HashSet::new();
+
+		self.execute_each(
+			backend,
+			stale.iter().map(|(_, name, invocation, ..)| (name.clone(), *invocation)),
+			|index, failure| {
+				failed.insert(index);
+				reused[stale[index].0] = Err(failure);
+				Ok(())
+			},
+		)?;
+
+		/* Without a stamp, a failed compile is tried again next time */
+		for (index, (.., stamp, hash)) in stale.into_iter().enumerate() {
+			if !failed.contains(&index) {
+				std::fs::write(stamp, hash)?;
+			}
+		}
+
+		Ok(reused)
+	}
+
+	/// Hash of everything that goes into an invocation: its command line,
+	/// sources, and the sources and headers under its include paths, as digested in `headers`.
+	fn inputs_hash(invocation: &Invocation, headers: &IncludeDigests) -> anyhow::Result<String> {
+		use std::hash::Hasher;
+
+		/* Stamps outlive the cpkg that wrote them, so this can't change between builds of it */
+		let mut hasher = crate::assets::StableHasher::default();
+
+		for arg in &invocation.argv {
+			hasher.write(arg.as_bytes());
+			hasher.write(&[0]);
+		}
+
+		for file in &invocation.sources {
+			let contents = std::fs::read(file)?;
+
+			hasher.write(file.as_os_str().as_encoded_bytes());
+			hasher.write(&[0]);
+			hasher.write(&(contents.len() as u64).to_le_bytes());
+			hasher.write(&contents);
+		}
+
+		for (file, digest) in invocation.includes.iter().filter_map(|dir| headers.get(dir)).flatten() {
+			hasher.write(file.as_os_str().as_encoded_bytes());
+			hasher.write(&[0]);
+			hasher.write(digest.as_bytes());
+		}
+
+		Ok(format!("{:016x}", hasher.finish()))
+	}
+
+	/// Removes binaries of tests that were deleted or renamed, along with their stamps.
+	fn prune_tests(&self, invocations: &[(std::path::PathBuf, Invocation)]) -> anyhow::Result<()> {
+		let keep = invocations
+			.iter()
+			.flat_map(|(_, i)| [i.output.clone(), stamp_path(&i.output)])
+			/* Along with the generated mains of harness tests */
+			.chain(invocations.iter().flat_map(|(_, i)| i.sources.iter().cloned()))
+			.collect::<std::collections::HashSet<_>>();
+
+		/* Also clears out binaries named by hash, from before they were named after the test */
+		let entries = walkdir::WalkDir::new(self.out_dir().join("test"))
+			.contents_first(true)
+			.into_iter()
+			.flatten();
+
+		for entry in entries {
+			let path = entry.path();
+
+			if entry.file_type().is_file() && !keep.contains(path) {
+				std::fs::remove_file(path)?;
+			} else if entry.file_type().is_dir() && entry.depth() > 0 {
+				/* Only succeeds once the folder is empty, like after its only test was removed */
+				let _ = std::fs::remove_dir(path);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Runs every test whose path contains `filter`, or that has a harness case whose name does.
+	pub fn run_tests(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		print: bool,
+		cache: bool,
+		filter: Option<&str>,
+	) -> anyhow::Result<Vec<TestResult>> {
+		self.runner_argv()?;
+
+		let compiled = self.compile_tests(backend, cache)?;
+
+		let mut results = Vec::with_capacity(compiled.len());
+
+		for (src, compiled, cached) in compiled {
+			let shown = src.strip_prefix(self.path).unwrap_or(&src).to_string_lossy().into_owned();
+			let path_matches = filter.is_none_or(|f| shown.contains(f));
+
+			let source = std::fs::read_to_string(&src).unwrap_or_default();
+			let marker = crate::harness::marker(&source);
+
+			/* For tests that don't run their cases, which still show when the filter names one */
+			let case_matches = crate::harness::cases(&source)
+				.into_iter()
+				.flatten()
+				.any(|c| filter.is_some_and(|f| c.contains(f)));
+
+			let cached = match cached {
+				Ok(cached) => cached,
+				Err(failure) => {
+					if path_matches || case_matches {
+						results.push(TestResult {
+							status: TestStatus::CompileError,
+							path: src,
+							case: None,
+							stderr: Some(failure.to_string()),
+							reason: None,
+							cached: false,
+							duration: std::time::Duration::ZERO,
+							binary: None,
+						});
+					}
+
+					continue;
+				}
+			};
+
+			if let Some(crate::harness::Marker::Unknown(ref marker)) = marker {
+				crate::log::warn(format!(
+					"Unknown marker 'cpkg: {marker}' in {shown}, expected should-fail or skip."
+				));
+			}
+
+			if let Some(crate::harness::Marker::Skip(reason)) = marker {
+				if path_matches || case_matches {
+					results.push(TestResult {
+						status: TestStatus::Skipped,
+						path: src,
+						case: None,
+						stderr: None,
+						reason,
+						cached,
+						duration: std::time::Duration::ZERO,
+						binary: Some(compiled),
+					});
+				}
+
+				continue;
+			}
+
+			let should_fail = matches!(marker, Some(crate::harness::Marker::ShouldFail));
+
+			/* A should-fail test that passes means the bug it documents was fixed, so the marker is stale */
+			let judge = |stderr: Option<String>| match (stderr, should_fail) {
+				(None, false) => (TestStatus::Passed, None),
+				(Some(err), false) => (TestStatus::Failed, Some(err)),
+				(Some(err), true) => (TestStatus::ExpectedFailure, Some(err)),
+				(None, true) => (
+					TestStatus::Failed,
+					Some("Passed, but is marked `// cpkg: should-fail`".to_owned()),
+				),
+			};
+
+			let Some(cases) = crate::harness::cases(&source) else {
+				if !path_matches {
+					continue;
+				}
+
+				let start = std::time::Instant::now();
+				let out = self.run_test(&compiled, &[], print.then_some(shown.as_str()))?;
+
+				let (status, stderr) = judge(
+					(!out.status.success())
+						.then(|| String::from_utf8(out.stderr))
+						.transpose()?,
+				);
+
+				results.push(TestResult {
+					status,
+					path: src,
+					case: None,
+					stderr,
+					reason: None,
+					cached,
+					duration: start.elapsed(),
+					binary: Some(compiled),
+				});
+
+				continue;
+			};
+
+			let mut pending = cases
+				.into_iter()
+				.filter(|c| path_matches || filter.is_some_and(|f| c.contains(f)))
+				.collect::<Vec<_>>();
+
+			/* A case that aborts, like through assert(), takes the rest of the run with it, so they're run again */
+			while !pending.is_empty() {
+				let start = std::time::Instant::now();
+				let out = self.run_test(&compiled, &pending, print.then_some(shown.as_str()))?;
+				let duration = start.elapsed();
+
+				let mut current = None;
+				let mut finished = vec![];
+
+				for event in String::from_utf8_lossy(&out.stdout).lines().filter_map(crate::harness::parse) {
+					match event {
+						crate::harness::Event::Start(case) => current = Some(case),
+						crate::harness::Event::Pass(case) => {
+							current = None;
+							finished.push((case, None));
+						}
+						crate::harness::Event::Fail(case, message) => {
+							current = None;
+							finished.push((case, Some(message)));
+						}
+					}
+				}
+
+				let stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+
+				if let Some(case) = current {
+					finished.push((case, Some(stderr)));
+				} else if finished.is_empty() {
+					/* Didn't get as far as any case, e.g. it crashed before main */
+					finished.extend(pending.drain(..).map(|case| (case, Some(stderr.clone()))));
+				}
+
+				pending.retain(|c| !finished.iter().any(|(f, _)| f == c));
+
+				results.extend(finished.into_iter().map(|(case, stderr)| {
+					let (status, stderr) = judge(stderr);
+
+					TestResult {
+						status,
+						path: src.clone(),
+						case: Some(case),
+						stderr,
+						reason: None,
+						cached,
+						duration,
+						binary: Some(compiled.clone()),
+					}
+				}));
+			}
+		}
+
+		Ok(results)
+	}
+
+	/// Compiles the tests and picks the one `filter` names, for running under a debugger.
+	/// Returns its binary and the arguments to give it, which is the case to run when the filter names a harness case.
+	pub fn debug_test(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		filter: &str,
+	) -> anyhow::Result<(std::path::PathBuf, Vec<String>)> {
+		let mut matches = vec![];
+
+		for (src, compiled, cached) in self.compile_tests(backend, true)? {
+			let shown = src.strip_prefix(self.path).unwrap_or(&src).to_string_lossy().into_owned();
+			let source = std::fs::read_to_string(&src).unwrap_or_default();
+
+			let found = if shown.contains(filter) {
+				vec![(shown.clone(), vec![])]
+			} else {
+				crate::harness::cases(&source)
+					.into_iter()
+					.flatten()
+					.filter(|c| c.contains(filter))
+					.map(|c| (format!("{shown} ({c})"), vec![c]))
+					.collect()
+			};
+
+			if !found.is_empty() {
+				if let Err(failure) = cached {
+					anyhow::bail!("Failed to compile {shown}:\n{failure}");
+				}
+			}
+
+			matches.extend(found.into_iter().map(|(name, args)| (name, compiled.clone(), args)));
+		}
+
+		match matches.len() {
+			0 => anyhow::bail!("No test matches '{filter}'."),
+			1 => {
+				let (_, binary, args) = matches.remove(0);
+				Ok((binary, args))
+			}
+			_ => {
+				let names = matches.iter().map(|(name, ..)| format!("  {name}")).collect::<Vec<_>>();
+				anyhow::bail!(
+					"'{filter}' matches {} tests, but only one can be debugged at a time:\n{}",
+					matches.len(),
+					names.join("\n")
+				)
+			}
+		}
+	}
+
+	/// Runs a compiled test with the given arguments, capturing its output.
+	/// Under `print` its output is echoed as it's printed too, besides the lines of the harness.
+	/// Past the timeout under [test] it's killed, along with everything its runner started.
+	fn run_test(
+		&self,
+		bin: &std::path::Path,
+		args: &[String],
+		print: Option<&str>,
+	) -> anyhow::Result<std::process::Output> {
+		let timeout = self
+			.config
+			.test
+			.as_ref()
+			.and_then(|t| t.timeout)
+			.map(std::time::Duration::from_secs_f64);
+
+		let mut cmd = self.run_command(bin)?;
+		cmd.args(args)
+			.stdout(std::process::Stdio::piped())
+			.stderr(std::process::Stdio::piped());
+
+		/* Own process group, so a timeout kills the test and not only a runner like qemu */
+		#[cfg(unix)]
+		if timeout.is_some() {
+			std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+		}
+
+		crate::log::command(&cmd);
+		let mut child = cmd.spawn()?;
+
+		let stdout = child.stdout.take();
+		let stderr = child.stderr.take();
+
+		std::thread::scope(|s| {
+			let stdout = s.spawn(move || {
+				let mut captured = String::new();
+
+				for line in stdout.map(std::io::BufReader::new).into_iter().flat_map(std::io::BufRead::lines) {
+					let line = line?;
+
+					if let Some(label) = print.filter(|_| crate::harness::parse(&line).is_none()) {
+						let line = format!("{} {line}", format!("[{label}]").dimmed());
+						crate::log::output(crate::log::Stream::Stdout, &line);
+					}
+
+					captured.push_str(&line);
+					captured.push('\n');
+				}
+
+				std::io::Result::Ok(captured.into_bytes())
+			});
+
+			let stderr = s.spawn(move || {
+				let mut captured = vec![];
+
+				if let Some(pipe) = stderr {
+					let mut reader = std::io::BufReader::new(pipe);
+
+					/* Read by line rather than as text, so a test printing invalid UTF-8 is still captured as is */
+					loop {
+						let start = captured.len();
+						if std::io::BufRead::read_until(&mut reader, b'\n', &mut captured)? == 0 {
+							break;
+						}
+
+						if let Some(label) = print {
+							let line = String::from_utf8_lossy(&captured[start..]);
+							let line = format!("{} {}", format!("[{label}]").dimmed(), line.trim_end_matches(['\r', '\n']));
+							crate::log::output(crate::log::Stream::Stderr, &line);
+						}
+					}
+				}
+
+				std::io::Result::Ok(captured)
+			});
+
+			let mut timed_out = false;
+
+			let status = match timeout {
+				None => child.wait()?,
+				Some(timeout) => {
+					let deadline = std::time::Instant::now() + timeout;
+
+					loop {
+						if let Some(status) = child.try_wait()? {
+							break status;
+						}
+
+						if std::time::Instant::now() >= deadline {
+							#[cfg(unix)]
+							unsafe {
+								libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+							}
+
+							let _ = child.kill();
+							timed_out = true;
+
+							break child.wait()?;
+						}
+
+						std::thread::sleep(std::time::Duration::from_millis(10));
+					}
+				}
+			};
+
+			let join = |h: std::thread::ScopedJoinHandle<std::io::Result<Vec<u8>>>| {
+				h.join().unwrap_or_else(|e| std::panic::resume_unwind(e))
+			};
+
+			let stdout = join(stdout)?;
+			let mut stderr = join(stderr)?;
+
+			if let Some(timeout) = timeout.filter(|_| timed_out) {
+				stderr.extend(format!("Timed out after {}s\n", timeout.as_secs_f64()).bytes());
+			}
+
+			Ok(std::process::Output {
+				status,
+				stdout,
+				stderr,
+			})
+		})
+	}
+}
+
+/// Test source, its binary, and whether that was reused from a previous run or how it failed to compile.
+/// Digests of the sources and headers under each folder tests search, see [fingerprint::digest_sources].
+type IncludeDigests = std::collections::HashMap<std::path::PathBuf, std::collections::BTreeMap<std::path::PathBuf, String>>;
+
+type CompiledTest = (std::path::PathBuf, std::path::PathBuf, Result<bool, crate::compiler::CompileError>);
+
+/// Outcome of a single test, see [Project::run_tests].
+pub struct TestResult {
+	pub status: TestStatus,
+	pub path: std::path::PathBuf,
+	/// Harness case within the file, for tests using cpkg_test.h
+	pub case: Option<String>,
+	/// What the test printed to stderr, if it failed
+	pub stderr: Option<String>,
+	/// Why the test was skipped, if its `// cpkg: skip` marker says
+	pub reason: Option<String>,
+	/// Whether the binary from a previous run was reused
+	pub cached: bool,
+	pub duration: std::time::Duration,
+	/// Compiled test, for running it again under a debugger. [None] if it didn't compile
+	pub binary: Option<std::path::PathBuf>,
+}
+
+/// How a test went, taking its `// cpkg:` marker into account.
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+	Passed,
+	Failed,
+	/// Failed as its `// cpkg: should-fail` marker expects
+	#[serde(rename = "xfail")]
+	ExpectedFailure,
+	/// Compiled but not run, because of a `// cpkg: skip` marker
+	Skipped,
+	/// Failed to compile, so it couldn't run
+	#[serde(rename = "compile-error")]
+	CompileError,
+}
+
+impl TestStatus {
+	/// Whether the test counts against the run, by failing or not compiling.
+	pub fn failed(self) -> bool {
+		matches!(self, Self::Failed | Self::CompileError)
+	}
+}
+
+/// Everything a build would do, see [Project::plan].
+#[derive(serde::Serialize)]
+pub struct BuildPlan {
+	/// Declared dependencies that aren't installed
+	pub missing: Vec<String>,
+	/// Build script that runs before compiling
+	pub build_script: Option<std::path::PathBuf>,
+	/// Files written before compiling, like the checks of a header-only package, with what goes in them
+	pub generated: Vec<(std::path::PathBuf, String)>,
+	/// Compiler invocations, in order
+	pub invocations: Vec<Invocation>,
+	/// Static libraries archived once everything is compiled
+	pub archives: Vec<StaticLib>,
+	/// Everything the selected targets produce
+	pub outputs: Vec<std::path::PathBuf>,
+	/// What the build is made from, which the outputs of the last one are checked against
+	#[serde(skip)]
+	fingerprint: fingerprint::Fingerprint,
+}
+
+/// A single compile and link of a set of sources.
+#[derive(serde::Serialize)]
+pub struct Invocation {
+	pub sources: Vec<std::path::PathBuf>,
+	pub includes: Vec<std::path::PathBuf>,
+	pub flags: Vec<String>,
+	pub output: std::path::PathBuf,
+	/// Full command line the backend will run
+	pub argv: Vec<String>,
+	/// Whether it runs through the C++ counterpart of the backend
+	#[serde(skip_serializing_if = "std::ops::Not::not")]
+	pub cxx: bool,
+	/// Whether its output is left from the last build and up to date, so building skips it
+	pub fresh: bool,
+}
+
+impl Invocation {
+	pub fn new(
+		backend: &dyn crate::compiler::Compiler,
+		sources: Vec<std::path::PathBuf>,
+		includes: Vec<std::path::PathBuf>,
+		output: std::path::PathBuf,
+		flags: Vec<String>,
+	) -> Self {
+		let deps = includes.iter().map(|p| p.as_path()).collect::<Vec<_>>();
+		let cmd = backend.command(&sources, &deps, &output, &flags);
+
+		let argv = std::iter::once(cmd.get_program())
+			.chain(cmd.get_args())
+			.map(|a| a.to_string_lossy().into_owned())
+			.collect();
+
+		Self {
+			sources,
+			includes,
+			flags,
+			output,
+			argv,
+			cxx: crate::compiler::is_cxx(backend),
+			fresh: false,
+		}
+	}
+
+	pub fn execute(&self, backend: &dyn crate::compiler::Compiler) -> anyhow::Result<()> {
+		if let Some(parent) = self.output.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+
+		let cxx;
+		let backend = if self.cxx {
+			cxx = crate::compiler::locate_cxx(backend)?;
+			cxx.as_ref()
+		} else {
+			backend
+		};
+
+		let deps = self.includes.iter().map(|p| p.as_path()).collect::<Vec<_>>();
+
+		/* Written under another name and moved into place, so an interrupted compile can't leave a truncated output behind */
+		let to = if backend.writes_beside() {
+			self.output.clone()
+		} else {
+			partial_path(&self.output)
+		};
+
+		/* Added here rather than to the flags, so --color doesn't change the fingerprint */
+		let compiled = match backend.color_flag().filter(|_| crate::log::color()) {
+			Some(flag) => {
+				let flags = self.flags.iter().cloned().chain([flag.to_owned()]).collect::<Vec<_>>();
+				backend.compile(&self.sources, &deps, &to, &flags, &mut crate::compiler::echo)
+			}
+			None => backend.compile(&self.sources, &deps, &to, &self.flags, &mut crate::compiler::echo),
+		};
+
+		finish_partial(&to, &self.output, compiled)
+	}
+
+	/// Like [Self::execute], adding `flag` so the compiler prints where its time went, and returning what it printed.
+	pub fn execute_reporting(
+		&self,
+		backend: &dyn crate::compiler::Compiler,
+		flag: &str,
+	) -> anyhow::Result<String> {
+		if let Some(parent) = self.output.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+
+		/* Like execute, but the output is already in the command line */
+		let output = self.output.display().to_string();
+		let partial = partial_path(&self.output);
+
+		let mut cmd = std::process::Command::new(&self.argv[0]);
+
+		for arg in &self.argv[1..] {
+			match *arg == output && !backend.writes_beside() {
+				true => cmd.arg(&partial),
+				false => cmd.arg(arg),
+			};
+		}
+
+		cmd.arg(flag);
+
+		crate::log::command(&cmd);
+		let out = cmd.output()?;
+		let stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+
+		if !out.status.success() {
+			let _ = std::fs::remove_file(&partial);
+			return Err(crate::compiler::CompileError::new(backend, stderr, None).into());
+		}
+
+		if partial.exists() {
+			std::fs::rename(&partial, &self.output)?;
+		}
+
+		Ok(stderr)
+	}
+
+	/// What the invocation does, going by its flags.
+	pub fn step(&self) -> Step {
+		if self.flags.iter().any(|f| f == "-c") {
+			Step::Compile
+		} else if self.flags.iter().any(|f| f == "-fsyntax-only") {
+			Step::Check
+		} else {
+			Step::CompileAndLink
+		}
+	}
+}
+
+/// Kind of step in a build, as shown by plans and timings.
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Step {
+	/// A source compiled to an object
+	Compile,
+	/// A header checked to compile on its own
+	Check,
+	/// Sources compiled and linked into a binary in one go, which can't be timed apart
+	CompileAndLink,
+	/// Objects bundled into a static library
+	Archive,
+}
+
+impl std::fmt::Display for Step {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Compile => write!(f, "Compile"),
+			Self::Check => write!(f, "Check"),
+			Self::CompileAndLink => write!(f, "Compile and link"),
+			Self::Archive => write!(f, "Archive"),
+		}
+	}
+}
+
+/// Bundling of compiled objects into a static library with `ar`.
+#[derive(serde::Serialize)]
+pub struct StaticLib {
+	pub objects: Vec<std::path::PathBuf>,
+	pub output: std::path::PathBuf,
+	/// Full command line that will be ran
+	pub argv: Vec<String>,
+	/// Whether the library is left from the last build and up to date, so building skips it
+	pub fresh: bool,
+}
+
+impl StaticLib {
+	pub fn new(objects: Vec<std::path::PathBuf>, output: std::path::PathBuf) -> Self {
+		let argv = ["ar".to_owned(), "rcs".to_owned()]
+			.into_iter()
+			.chain(std::iter::once(&output).chain(&objects).map(|p| p.display().to_string()))
+			.collect();
+
+		Self {
+			objects,
+			output,
+			argv,
+			fresh: false,
+		}
+	}
+
+	pub fn execute(&self) -> anyhow::Result<()> {
+		let partial = partial_path(&self.output);
+
+		/* ar only ever adds members, so start fresh to drop deleted sources */
+		if partial.exists() {
+			std::fs::remove_file(&partial)?;
+		}
+
+		let mut cmd = std::process::Command::new(&self.argv[0]);
+		cmd.arg("rcs").arg(&partial).args(&self.objects);
+
+		crate::log::command(&cmd);
+		let out = cmd.output()?;
+
+		let archived = match out.status.success() {
+			true => Ok(()),
+			false => Err(anyhow::anyhow!(
+				"Failed to archive {}: {}",
+				self.output.display(),
+				String::from_utf8_lossy(&out.stderr)
+			)),
+		};
+
+		finish_partial(&partial, &self.output, archived)
+	}
+}
+
+/// What [Project::write_shims] did for a dependency.
+pub enum Shim {
+	/// Written, including the header at this path
+	Written(String),
+	/// Nothing to include, since it has no header at the top level
+	NoHeader,
+	/// Not written, since it has this many headers at the top level and no `entry` saying which
+	Ambiguous(usize),
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum VendorStatus {
+	/// Declared in cpkg.toml
+	Declared,
+	/// Installed by cpkg previously, but no longer declared
+	Stale,
+	/// Not installed by cpkg, so it won't be touched
+	Unknown,
+}
+
+/// A directory or symlink inside of the vendor folder.
+pub struct VendorEntry {
+	pub name: String,
+	pub path: std::path::PathBuf,
+	/// Whether this is a symlink to a path dependency
+	pub linked: bool,
+	/// Size in bytes owned by the vendor folder (zero for symlinks)
+	pub size: u64,
+	pub status: VendorStatus,
+}
+
+#[derive(Debug)]
+pub enum DepProblem {
+	/// Symlink to a path dependency that no longer exists
+	BrokenLink,
+	/// Git dependency without a .git folder or HEAD
+	NotARepo,
+	/// Installed directory has nothing inside of it
+	Empty,
+}
+
+impl std::fmt::Display for DepProblem {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::BrokenLink => write!(f, "symlink target no longer exists"),
+			Self::NotARepo => write!(f, "git checkout is missing .git or HEAD"),
+			Self::Empty => write!(f, "directory is empty"),
+		}
+	}
+}
+
+/// An installed dependency whose vendor entry is unusable.
+pub struct BrokenDep<'a> {
+	pub name: &'a str,
+	pub dep: &'a ConfigDependency,
+	pub problem: DepProblem,
+}
+
+impl std::fmt::Display for BrokenDep<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} ({}): {}", self.name, self.dep, self.problem)
+	}
+}
+
+/// Marker in the names of outputs still being written, see [partial_path].
+const PARTIAL: &str = "partial";
+
+/// Where `output` is written before it's complete, beside it so moving it into place is a rename.
+/// The extension is kept, as compilers like mingw's gcc add .exe to outputs without one.
+pub fn partial_path(output: &std::path::Path) -> std::path::PathBuf {
+	let mut name = output.file_stem().unwrap_or_default().to_owned();
+	name.push(format!(".{PARTIAL}"));
+
+	if let Some(ext) = output.extension() {
+		name.push(".");
+		name.push(ext);
+	}
+
+	output.with_file_name(name)
+}
+
+/// Whether `path` was left by a write that never finished, like a compile or clone that was interrupted.
+pub fn is_partial(path: &std::path::Path) -> bool {
+	path.file_name()
+		.map(|n| n.to_string_lossy())
+		.is_some_and(|n| n.ends_with(&format!(".{PARTIAL}")) || n.contains(&format!(".{PARTIAL}.")))
+}
+
+/// Moves a `partial` output to `output` once `written` succeeded, or removes it if not.
+/// Outputs that were written in place, when `partial` is `output`, are left alone.
+fn finish_partial(partial: &std::path::Path, output: &std::path::Path, written: anyhow::Result<()>) -> anyhow::Result<()> {
+	if partial == output {
+		return written;
+	}
+
+	match written {
+		/* Checks like -fsyntax-only succeed without writing anything */
+		Ok(()) if !partial.exists() => Ok(()),
+		Ok(()) => Ok(std::fs::rename(partial, output)?),
+		Err(e) => {
+			let _ = std::fs::remove_file(partial);
+			Err(e)
+		}
+	}
+}
+
+/// Appends an executable extension to `path`, unlike [std::path::Path::with_extension]
+/// which would replace anything after a dot in a name like `app.v2`.
+pub fn with_exe_extension(path: std::path::PathBuf, extension: Option<&str>) -> std::path::PathBuf {
+	match extension {
+		Some(ext) if path.extension().is_none_or(|e| e != ext) => {
+			let mut path = path.into_os_string();
+			path.push(".");
+			path.push(ext);
+			path.into()
+		}
+		_ => path,
+	}
+}
+
+/// Where the hash of what went into an output is kept, like foo.o.inputs beside foo.o.
+/// Appended rather than swapped for the extension, so tests/a and tests/a.test don't share one.
+fn stamp_path(output: &std::path::Path) -> std::path::PathBuf {
+	with_exe_extension(output.to_owned(), Some("inputs"))
+}
+
+/// Where the object of a source goes under `dir`, like foo.o for foo.c.
+/// C++ sources keep their extension, as in foo.cpp.o, so they don't clash with a C source named alike.
+pub fn object_path(dir: &std::path::Path, rel: &std::path::Path) -> std::path::PathBuf {
+	if is_cxx_source(rel) {
+		with_exe_extension(dir.join(rel), Some("o"))
+	} else {
+		dir.join(rel).with_extension("o")
+	}
+}
+
+/// Extensions of C++ sources, which can sit beside the C ones.
+pub const CXX_EXTENSIONS: &[&str] = &["cpp", "cc", "cxx"];
+
+/// Whether a file is a C++ source, going by its extension.
+pub fn is_cxx_source(path: &std::path::Path) -> bool {
+	path.extension()
+		.and_then(|e| e.to_str())
+		.is_some_and(|e| CXX_EXTENSIONS.contains(&e))
+}
+
+/// Whether a file is a C or C++ source, going by its extension.
+pub fn is_source(path: &std::path::Path) -> bool {
+	path.extension().is_some_and(|e| e == "c") || is_cxx_source(path)
+}
+
+/// Extensions of C and C++ headers.
+pub const HEADER_EXTENSIONS: &[&str] = &["h", "hpp", "hh", "hxx"];
+
+/// Whether a file is a C or C++ header, going by its extension.
+pub fn is_header(path: &std::path::Path) -> bool {
+	path.extension()
+		.and_then(|e| e.to_str())
+		.is_some_and(|e| HEADER_EXTENSIONS.contains(&e))
+}
+
+/// Whether a source is an inline test like foo.test.c, which builds leave out.
+pub fn is_inline_test(path: &std::path::Path) -> bool {
+	is_source(path) && path.file_stem().is_some_and(|s| s.to_string_lossy().ends_with(".test"))
+}
+
+/// The C or C++ source at `stem`, like main.c or main.cpp for `src/main`, preferring C.
+fn source_named(stem: &std::path::Path) -> Option<std::path::PathBuf> {
+	std::iter::once("c")
+		.chain(CXX_EXTENSIONS.iter().copied())
+		.map(|ext| stem.with_extension(ext))
+		.find(|file| file.is_file())
+}
+
+/// Whether a C source looks like it defines main(), going by lines starting its definition.
+/// Functions that only start with main, like main_menu, don't count.
+pub fn defines_main(source: &str) -> bool {
+	source.lines().any(|l| {
+		let l = l.trim_start();
+
+		/* The return type can also be on the line before, as in K&R style */
+		let l = l
+			.strip_prefix("int")
+			.filter(|rest| rest.starts_with(char::is_whitespace))
+			.map_or(l, str::trim_start);
+
+		l.strip_prefix("main").is_some_and(|rest| rest.trim_start().starts_with('('))
+	})
+}
+
+/// Makes `old` hold the same data as `new`, only touching the keys that differ.
+/// Tables are merged key by key whether they're written inline or not, and a replaced value keeps its comments.
+/// Tables added below the `root` are written inline, so they land beside the keys they're added to.
+fn merge_toml(old: &mut dyn toml_edit::TableLike, new: &dyn toml_edit::TableLike, root: bool) {
+	let removed = old
+		.iter()
+		.map(|(key, _)| key.to_owned())
+		.filter(|key| !new.contains_key(key))
+		.collect::<Vec<_>>();
+
+	for key in removed {
+		old.remove(&key);
+	}
+
+	for (key, item) in new.iter() {
+		let Some(existing) = old.get_mut(key) else {
+			/* Empty tables, like [scripts] without any, are left out until they hold something */
+			match item {
+				toml_edit::Item::Table(table) if table.is_empty() => {}
+				toml_edit::Item::Table(table) if !root => {
+					old.insert(key, toml_edit::value(table.clone().into_inline_table()));
+				}
+				_ => {
+					old.insert(key, item.clone());
+				}
+			}
+
+			continue;
+		};
+
+		if existing.is_table_like() && item.is_table_like() {
+			merge_toml(existing.as_table_like_mut().unwrap(), item.as_table_like().unwrap(), false);
+			continue;
+		}
+
+		if toml_value(existing) != toml_value(item) {
+			let mut item = item.clone();
+
+			if let (Some(value), Some(previous)) = (item.as_value_mut(), existing.as_value()) {
+				*value.decor_mut() = previous.decor().clone();
+			}
+
+			*existing = item;
+		}
+	}
+}
+
+/// Data of a toml item regardless of how it's written, for comparing them.
+fn toml_value(item: &toml_edit::Item) -> Option<toml::Value> {
+	let mut doc = toml_edit::DocumentMut::new();
+	doc.insert("v", item.clone());
+
+	toml::from_str::<toml::Table>(&doc.to_string()).ok()?.remove("v")
+}
+
+fn is_git_checkout(dir: &std::path::Path) -> bool {
+	let git = dir.join(".git");
+
+	/* .git is a file for worktrees and submodules */
+	git.is_file() || git.join("HEAD").is_file()
+}
+
+#[cfg(unix)]
+fn symlink_dir(from: &std::path::Path, to: &std::path::Path) -> std::io::Result<()> {
+	std::os::unix::fs::symlink(from, to)
+}
+
+#[cfg(windows)]
+fn symlink_dir(from: &std::path::Path, to: &std::path::Path) -> std::io::Result<()> {
+	std::os::windows::fs::symlink_dir(from, to)
+}
+
+#[cfg(unix)]
+fn remove_symlink_dir(path: &std::path::Path) -> std::io::Result<()> {
+	std::fs::remove_file(path)
+}
+
+#[cfg(windows)]
+fn remove_symlink_dir(path: &std::path::Path) -> std::io::Result<()> {
+	std::fs::remove_dir(path)
+}
+
+/// Writes a generated file, leaving it alone when it already has the same contents.
+/// Rewriting it every time would defeat caching by modification time in exported builds.
+fn write_generated(path: &std::path::Path, contents: &str) -> anyhow::Result<()> {
+	if std::fs::read_to_string(path).ok().as_deref() == Some(contents) {
+		return Ok(());
+	}
+
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent)?;
+	}
+
+	std::fs::write(path, contents)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_main_definitions() {
+		assert!(defines_main("int main() {\n}\n"));
+		assert!(defines_main("int main (void) {\n}\n"));
+		assert!(defines_main("int\tmain(int argc, char** argv)\n"));
+		assert!(defines_main("int\nmain(void)\n{\n}\n"));
+		assert!(defines_main(
+			"static int helper(void);\n\n  int main(void);\n"
+		));
+
+		assert!(!defines_main("int main_menu(void) {\n}\n"));
+		assert!(!defines_main("int mainloop(int n) {\n}\n"));
+		assert!(!defines_main("int\nmain_menu(void)\n"));
+		assert!(!defines_main("intmain(void)\n"));
+		assert!(!defines_main("int domain(void) {\n}\n"));
 	}
 }
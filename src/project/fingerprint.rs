@@ -0,0 +1,403 @@
+/// What a build's outputs were made from, kept beside them so a change to any of it is noticed.
+/// Lives in the output folder as .fingerprint, written after every successful build.
+#[derive(serde::Serialize, serde::Deserialize, PartialEq)]
+pub struct Fingerprint {
+	/// Command line invoking the compiler
+	pub compiler: Vec<String>,
+	/// First line the compiler printed for `--version`, if it gave one
+	pub version: Option<String>,
+	/// Flags every invocation was given, as resolved from cpkg.toml, the profile and the environment
+	pub flags: Vec<String>,
+	/// Flags for sources matching [compiler.overrides], by pattern
+	#[serde(default)]
+	pub overrides: std::collections::BTreeMap<String, crate::ConfigOverride>,
+	/// Digest of every source and header that could be included, by path
+	pub sources: std::collections::BTreeMap<std::path::PathBuf, String>,
+}
+
+/// Part of a fingerprint that differs from the last build's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+	Compiler,
+	Flags,
+	Sources,
+}
+
+impl std::fmt::Display for Change {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Compiler => write!(f, "the compiler changed"),
+			Self::Flags => write!(f, "the flags changed"),
+			Self::Sources => write!(f, "sources changed"),
+		}
+	}
+}
+
+impl Fingerprint {
+	pub const FILE: &'static str = ".fingerprint";
+
+	/// Fingerprints a build of the project's `src` with a backend.
+	pub fn new(
+		proj: &crate::Project,
+		backend: &dyn crate::compiler::Compiler,
+		src: &[std::path::PathBuf],
+	) -> anyhow::Result<Self> {
+		let bin = crate::probe::which(backend.bin()).unwrap_or_else(|| backend.bin().into());
+		let flags = proj.link_flags(backend);
+
+		/* Every folder the compiler searches, including ones only named in raw flags */
+		let dirs = proj
+			.include_paths(src)
+			.into_iter()
+			.chain(flag_includes(&flags).map(|d| proj.path().join(d)));
+
+		let sources = digest_sources(dirs)?;
+
+		Ok(Self {
+			compiler: backend.driver(),
+			version: crate::probe::version(&bin),
+			flags,
+			overrides: proj
+				.config()
+				.compiler
+				.as_ref()
+				.map(|c| c.overrides.clone())
+				.unwrap_or_default(),
+			sources,
+		})
+	}
+
+	/// Fingerprint of the last successful build into a folder, if there was one.
+	pub fn load(dir: &std::path::Path) -> Option<Self> {
+		let contents = std::fs::read_to_string(dir.join(Self::FILE)).ok()?;
+		serde_json::from_str(&contents).ok()
+	}
+
+	pub fn save(&self, dir: &std::path::Path) -> anyhow::Result<()> {
+		std::fs::create_dir_all(dir)?;
+		std::fs::write(dir.join(Self::FILE), serde_json::to_string(self)?)?;
+		Ok(())
+	}
+
+	/// Forgets the last build, so its outputs aren't trusted until the next one succeeds.
+	pub fn remove(dir: &std::path::Path) -> anyhow::Result<()> {
+		match std::fs::remove_file(dir.join(Self::FILE)) {
+			Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+			_ => Ok(()),
+		}
+	}
+
+	/// Sources that were added, edited or removed since `previous`.
+	pub fn changed_sources<'s>(&'s self, previous: &'s Self) -> Vec<&'s std::path::Path> {
+		let edited = self
+			.sources
+			.iter()
+			.filter(|(path, digest)| previous.sources.get(*path) != Some(digest))
+			.map(|(path, _)| path.as_path());
+
+		let removed = previous
+			.sources
+			.keys()
+			.filter(|path| !self.sources.contains_key(*path))
+			.map(|path| path.as_path());
+
+		edited.chain(removed).collect()
+	}
+
+	/// What changed since `previous`, empty if outputs built from it can be reused.
+	pub fn changes(&self, previous: &Self) -> Vec<Change> {
+		let mut changes = vec![];
+
+		if self.compiler != previous.compiler || self.version != previous.version {
+			changes.push(Change::Compiler);
+		}
+
+		if self.flags != previous.flags || self.overrides != previous.overrides {
+			changes.push(Change::Flags);
+		}
+
+		if self.sources != previous.sources {
+			changes.push(Change::Sources);
+		}
+
+		changes
+	}
+}
+
+/// Folders given to `-I` or `-isystem` in a list of flags, either joined to the flag or after it.
+fn flag_includes(flags: &[String]) -> impl Iterator<Item = &str> {
+	let mut flags = flags.iter().map(String::as_str);
+
+	std::iter::from_fn(move || loop {
+		let flag = flags.next()?;
+
+		let dir = match ["-isystem", "-I"].iter().find_map(|f| flag.strip_prefix(f)) {
+			Some("") => flags.next(),
+			dir => dir,
+		};
+
+		if dir.is_some() {
+			return dir;
+		}
+	})
+}
+
+/// Digest of every C or C++ source and header under `dirs`, by path.
+/// Symlinks aren't followed and .git is skipped, so the trees of dependencies, which the lockfile pins, aren't read whole.
+pub fn digest_sources(
+	dirs: impl IntoIterator<Item = std::path::PathBuf>,
+) -> anyhow::Result<std::collections::BTreeMap<std::path::PathBuf, String>> {
+	let mut sources = std::collections::BTreeMap::new();
+
+	for dir in dirs {
+		let files = walkdir::WalkDir::new(&dir)
+			.into_iter()
+			.filter_entry(|e| e.file_name() != ".git")
+			.flatten()
+			.filter(|e| e.file_type().is_file())
+			.filter(|e| crate::is_source(e.path()) || crate::is_header(e.path()));
+
+		for file in files {
+			sources.insert(file.path().to_owned(), digest(&std::fs::read(file.path())?));
+		}
+	}
+
+	Ok(sources)
+}
+
+fn digest(contents: &[u8]) -> String {
+	format!("{:016x}", crate::assets::hash(contents))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A project whose main includes a header from a folder only named in its flags,
+	/// either joined to `-I` or as the argument after it.
+	fn flagged(joined: bool) -> tempfile::TempDir {
+		let temp = crate::project::testing::project(
+			"",
+			&[
+				("extra/value.h", "#define VALUE 1\n"),
+				(
+					"src/main.c",
+					"#include \"value.h\"\n\nint main() {\n\treturn VALUE - 1;\n}\n",
+				),
+			],
+		);
+
+		/* Absolute, as the compiler runs wherever the tests do */
+		let extra = temp.path().join("extra").display().to_string();
+		let flags = match joined {
+			true => vec![format!("-I{extra}")],
+			false => vec!["-I".to_owned(), extra],
+		};
+
+		let manifest = temp.path().join("cpkg.toml");
+		let contents = std::fs::read_to_string(&manifest).unwrap();
+		std::fs::write(
+			manifest,
+			format!("{contents}\n[compiler]\nflags = {flags:?}\n"),
+		)
+		.unwrap();
+
+		temp
+	}
+
+	/// Builds the project with `bin`, returning how many outputs had to be made.
+	fn build(dir: &std::path::Path, bin: &str) -> usize {
+		let proj = crate::Project::open(dir).unwrap();
+		let backend = crate::compiler::from_bin(bin).expect("gcc is installed");
+
+		let targets = crate::target::defaults(&proj).unwrap();
+		proj.build(backend.as_ref(), &targets, || true).unwrap();
+
+		proj.compiled()
+	}
+
+	fn fingerprint(dir: &std::path::Path, bin: &str) -> Fingerprint {
+		let proj = crate::Project::open(dir).unwrap();
+		let backend = crate::compiler::from_bin(bin).unwrap();
+
+		Fingerprint::new(&proj, backend.as_ref(), &proj.src()).unwrap()
+	}
+
+	#[test]
+	fn reuses_unchanged_builds() {
+		let temp = flagged(false);
+
+		assert!(build(temp.path(), "gcc") > 0);
+		assert_eq!(build(temp.path(), "gcc"), 0);
+	}
+
+	#[test]
+	fn flag_change_rebuilds() {
+		let temp = flagged(false);
+		build(temp.path(), "gcc");
+		let before = fingerprint(temp.path(), "gcc");
+
+		let manifest = std::fs::read_to_string(temp.path().join("cpkg.toml")).unwrap();
+		std::fs::write(
+			temp.path().join("cpkg.toml"),
+			manifest.replace("\"]\n", "\", \"-DUNUSED\"]\n"),
+		)
+		.unwrap();
+
+		assert_eq!(
+			fingerprint(temp.path(), "gcc").changes(&before),
+			[Change::Flags]
+		);
+		assert!(build(temp.path(), "gcc") > 0);
+	}
+
+	/// Writes a compiler at `dir/cc` that passes everything on to gcc, besides printing `version` for --version.
+	#[cfg(unix)]
+	fn fake_compiler(dir: &std::path::Path, version: &str) -> String {
+		use std::os::unix::fs::PermissionsExt;
+
+		let path = dir.join("cc");
+
+		std::fs::write(
+			&path,
+			format!("#!/bin/sh\nif [ \"$1\" = --version ]; then\n\techo '{version}'\n\texit 0\nfi\nexec gcc \"$@\"\n"),
+		)
+		.unwrap();
+
+		std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+		path.display().to_string()
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn compiler_change_rebuilds() {
+		let temp = flagged(false);
+		let bin = tempfile::tempdir().unwrap();
+
+		build(temp.path(), "gcc");
+
+		/* Another compiler entirely */
+		let fake = fake_compiler(bin.path(), "fakecc 1.0");
+
+		let changes = fingerprint(temp.path(), &fake).changes(&fingerprint(temp.path(), "gcc"));
+		assert_eq!(changes, [Change::Compiler]);
+		assert!(build(temp.path(), &fake) > 0);
+		assert_eq!(build(temp.path(), &fake), 0);
+
+		/* The same one, upgraded in place */
+		let before = fingerprint(temp.path(), &fake);
+		fake_compiler(bin.path(), "fakecc 2.0");
+
+		assert_eq!(
+			fingerprint(temp.path(), &fake).changes(&before),
+			[Change::Compiler]
+		);
+		assert!(build(temp.path(), &fake) > 0);
+	}
+
+	#[test]
+	fn headers_in_flag_includes_rebuild() {
+		let temp = flagged(true);
+		let header = temp.path().join("extra/value.h");
+
+		build(temp.path(), "gcc");
+
+		let before = fingerprint(temp.path(), "gcc");
+		assert!(before.sources.contains_key(&header));
+
+		std::fs::write(&header, "#define VALUE 2\n").unwrap();
+
+		let after = fingerprint(temp.path(), "gcc");
+		assert_eq!(after.changes(&before), [Change::Sources]);
+		assert_eq!(after.changed_sources(&before), [header.as_path()]);
+		assert!(build(temp.path(), "gcc") > 0);
+	}
+
+	#[test]
+	fn fingerprints_include_dirs_and_library_headers() {
+		let temp = flagged(true);
+		let dir = temp.path();
+
+		for folder in ["include", "sys"] {
+			std::fs::create_dir(dir.join(folder)).unwrap();
+			std::fs::write(dir.join(folder).join("api.h"), "").unwrap();
+		}
+
+		let manifest = std::fs::read_to_string(dir.join("cpkg.toml")).unwrap();
+		let manifest = manifest.replace(
+			"[compiler]\n",
+			"[dependencies]\nm = { lib = \"m\", headers = \"sys\" }\n\n[compiler]\ninclude-dirs = [\"include\"]\n",
+		);
+		std::fs::write(dir.join("cpkg.toml"), manifest).unwrap();
+
+		let sources = fingerprint(dir, "gcc").sources;
+		assert!(sources.contains_key(&dir.join("include/api.h")));
+		assert!(sources.contains_key(&dir.join("sys/api.h")));
+	}
+
+	#[test]
+	fn digests_only_sources_and_headers() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+
+		for file in [
+			"a.c",
+			"b.h",
+			"nested/c.cpp",
+			"nested/d.hpp",
+			"notes.txt",
+			"libdep.a",
+			".git/objects/e.h",
+			"elsewhere/f.h",
+		] {
+			std::fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+			std::fs::write(dir.join(file), file).unwrap();
+		}
+
+		#[cfg(unix)]
+		std::os::unix::fs::symlink(dir.join("elsewhere"), dir.join("nested/linked")).unwrap();
+
+		let sources = digest_sources([dir.to_owned()]).unwrap();
+		let files = sources
+			.keys()
+			.map(|p| {
+				p.strip_prefix(dir)
+					.unwrap()
+					.to_string_lossy()
+					.replace('\\', "/")
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			files,
+			[
+				"a.c",
+				"b.h",
+				"elsewhere/f.h",
+				"nested/c.cpp",
+				"nested/d.hpp"
+			]
+		);
+	}
+
+	#[test]
+	fn finds_include_flags() {
+		let flags = [
+			"-O2",
+			"-Ia",
+			"-I",
+			"b",
+			"-isystem",
+			"c",
+			"-isystemd",
+			"-DI=1",
+			"-I",
+		]
+		.map(str::to_owned);
+
+		assert_eq!(
+			flag_includes(&flags).collect::<Vec<_>>(),
+			["a", "b", "c", "d"]
+		);
+	}
+}
@@ -0,0 +1,82 @@
+/// Patterns under [compiler.overrides], matched against sources like lines of a .gitignore relative to the project.
+/// A pattern naming a folder, like "src/legacy/", applies to everything under it.
+pub struct Overrides<'c> {
+	root: std::path::PathBuf,
+	patterns: Vec<Pattern<'c>>,
+}
+
+struct Pattern<'c> {
+	text: &'c str,
+	matcher: ignore::gitignore::Gitignore,
+	rule: &'c crate::ConfigOverride,
+}
+
+impl<'c> Overrides<'c> {
+	pub fn new(root: &std::path::Path, config: &'c crate::Config) -> anyhow::Result<Self> {
+		let overrides = config.compiler.iter().flat_map(|c| &c.overrides);
+		let mut patterns = vec![];
+
+		for (pattern, flags) in overrides {
+			let mut matcher = ignore::gitignore::GitignoreBuilder::new(root);
+
+			matcher.add_line(None, pattern).map_err(|e| {
+				anyhow::anyhow!("Invalid pattern '{pattern}' under [compiler.overrides]: {e}")
+			})?;
+
+			patterns.push(Pattern {
+				text: pattern,
+				matcher: matcher.build()?,
+				rule: flags,
+			});
+		}
+
+		Ok(Self {
+			root: root.to_owned(),
+			patterns,
+		})
+	}
+
+	fn matching<'s>(&'s self, file: &'s std::path::Path) -> impl Iterator<Item = &'s Pattern<'c>> {
+		/* Files outside of the project, like a workspace's generated test mains, can't match */
+		let inside = file.starts_with(&self.root);
+
+		self.patterns.iter().filter(move |p| {
+			inside
+				&& p.matcher
+					.matched_path_or_any_parents(file, false)
+					.is_ignore()
+		})
+	}
+
+	/// Whether any pattern applies to `file`, so it needs compiling on its own.
+	pub fn matches(&self, file: &std::path::Path) -> bool {
+		self.matching(file).next().is_some()
+	}
+
+	/// `base` with every matching pattern applied in order, taking out its removals then adding its flags.
+	pub fn apply(&self, file: &std::path::Path, base: &[String]) -> Vec<String> {
+		let mut flags = base.to_vec();
+
+		for pattern in self.matching(file) {
+			flags.retain(|f| !pattern.rule.remove.contains(f));
+			flags.extend(pattern.rule.flags.iter().cloned());
+		}
+
+		flags
+	}
+
+	/// Patterns that match none of `files`, which are likely typos.
+	pub fn unused<'f>(&self, files: impl IntoIterator<Item = &'f std::path::Path>) -> Vec<&'c str> {
+		let mut used = std::collections::HashSet::new();
+
+		for file in files {
+			used.extend(self.matching(file).map(|p| p.text));
+		}
+
+		self.patterns
+			.iter()
+			.map(|p| p.text)
+			.filter(|p| !used.contains(p))
+			.collect()
+	}
+}
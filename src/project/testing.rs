@@ -0,0 +1,24 @@
+/* Fixtures for unit tests, shared with the binary's which include this file by path */
+
+/// Folder with a cpkg.toml with `package` added under [package], and the given files.
+/// Its name has a space in it, so paths that should be quoted but aren't break tests.
+pub fn project(package: &str, files: &[(&str, &str)]) -> tempfile::TempDir {
+	let dir = tempfile::Builder::new()
+		.prefix("cpkg test")
+		.tempdir()
+		.unwrap();
+
+	std::fs::write(
+		dir.path().join("cpkg.toml"),
+		format!("[package]\nname = \"demo\"\n{package}"),
+	)
+	.unwrap();
+
+	for (file, contents) in files {
+		let path = dir.path().join(file);
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, contents).unwrap();
+	}
+
+	dir
+}
@@ -0,0 +1,162 @@
+/// Whether a word is safe to embed anywhere as is.
+/// Anything else, including non-ASCII text, gets quoted to be safe.
+fn is_plain(s: &str) -> bool {
+	!s.is_empty()
+		&& s.chars()
+			.all(|c| c.is_ascii_alphanumeric() || "-_=+./:,@".contains(c))
+}
+
+/// Quotes a word for a POSIX shell.
+pub fn sh(s: &str) -> String {
+	if is_plain(s) {
+		s.to_owned()
+	} else {
+		format!("'{}'", s.replace('\'', r"'\''"))
+	}
+}
+
+/// Quotes a word for cmd.exe, following the rules programs use to split their command line:
+/// backslashes are only special right before a quote.
+#[cfg(target_os = "windows")]
+pub fn cmd(s: &str) -> String {
+	if is_plain(s) {
+		return s.to_owned();
+	}
+
+	let mut out = String::from('"');
+	let mut backslashes = 0;
+
+	for c in s.chars() {
+		match c {
+			'\\' => backslashes += 1,
+			'"' => {
+				out.push_str(&"\\".repeat(backslashes * 2 + 1));
+				out.push('"');
+				backslashes = 0;
+			}
+			_ => {
+				out.push_str(&"\\".repeat(backslashes));
+				out.push(c);
+				backslashes = 0;
+			}
+		}
+	}
+
+	/* Backslashes before the closing quote would escape it */
+	out.push_str(&"\\".repeat(backslashes * 2));
+	out.push('"');
+
+	out
+}
+
+/// Quotes a word for the shell cpkg runs scripts with on this platform.
+pub fn shell(s: &str) -> String {
+	#[cfg(target_os = "windows")]
+	return cmd(s);
+
+	#[cfg(not(target_os = "windows"))]
+	return sh(s);
+}
+
+/// Joins words into a command line that can be pasted into this platform's shell.
+pub fn command(argv: &[String]) -> String {
+	argv.iter().map(|a| shell(a)).collect::<Vec<_>>().join(" ")
+}
+
+/// Quotes text as a C string literal.
+/// Control characters are written as octal escapes, and anything else non-ASCII is kept as UTF-8.
+pub fn c_string(s: &str) -> String {
+	let mut out = String::from('"');
+
+	for c in s.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\t' => out.push_str("\\t"),
+			'\r' => out.push_str("\\r"),
+			/* Three digits, so a digit after it can't be taken as part of the escape */
+			c if c.is_ascii_control() => out.push_str(&format!("\\{:03o}", c as u8)),
+			/* Keeps ?? from starting a trigraph */
+			'?' if out.ends_with('?') => out.push_str("\\?"),
+			c => out.push(c),
+		}
+	}
+
+	out.push('"');
+	out
+}
+
+/// Quotes a word in a Makefile recipe or ninja command,
+/// which both expand `$` before handing the command to sh.
+pub fn build_file(s: &str) -> String {
+	sh(s).replace('$', "$$")
+}
+
+/// Escapes a Makefile target or prerequisite, where spaces separate words.
+pub fn make_target(s: &str) -> String {
+	s.replace('$', "$$")
+		.replace(' ', r"\ ")
+		.replace(':', r"\:")
+		.replace('#', r"\#")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A Windows path with spaces, non-ASCII characters and a trailing backslash.
+	const WINDOWS: &str = r"C:\Users\José García\My Projects\hw 1\";
+
+	#[test]
+	fn leaves_plain_words() {
+		for word in [
+			"main.c",
+			"-DNAME=1",
+			"target/obj/src/main.o",
+			"user@host:src",
+		] {
+			assert_eq!(sh(word), word);
+			assert_eq!(build_file(word), word);
+		}
+	}
+
+	#[test]
+	fn quotes_for_sh() {
+		assert_eq!(sh(""), "''");
+		assert_eq!(sh("hw 1"), "'hw 1'");
+		assert_eq!(sh("José"), "'José'");
+		assert_eq!(sh("it's"), r"'it'\''s'");
+		assert_eq!(sh("$HOME"), "'$HOME'");
+		assert_eq!(sh(WINDOWS), format!("'{WINDOWS}'"));
+	}
+
+	#[cfg(target_os = "windows")]
+	#[test]
+	fn quotes_for_cmd() {
+		assert_eq!(cmd("main.c"), "main.c");
+		assert_eq!(cmd(WINDOWS), r#""C:\Users\José García\My Projects\hw 1\\""#);
+		assert_eq!(cmd(r#"say "hi""#), r#""say \"hi\"""#);
+		assert_eq!(cmd(r#"a\"b"#), r#""a\\\"b""#);
+	}
+
+	#[test]
+	fn quotes_for_make() {
+		assert_eq!(build_file("src/ünï code.c"), "'src/ünï code.c'");
+		assert_eq!(build_file("$(price)"), "'$$(price)'");
+
+		assert_eq!(make_target("src/ünï code.c"), r"src/ünï\ code.c");
+		assert_eq!(make_target("C:/a b/#1$"), r"C\:/a\ b/\#1$$");
+	}
+
+	#[test]
+	fn quotes_c_strings() {
+		assert_eq!(
+			c_string(WINDOWS),
+			r#""C:\\Users\\José García\\My Projects\\hw 1\\""#
+		);
+		assert_eq!(c_string("say \"hi\"\n"), r#""say \"hi\"\n""#);
+		assert_eq!(c_string("\x01"), r#""\001""#);
+		assert_eq!(c_string("what??!"), r#""what?\?!""#);
+	}
+}
@@ -0,0 +1,531 @@
+use colored::Colorize;
+use std::io::Write;
+
+const COMMANDS: &[(&str, &str)] = &[
+	(":show", "Prints the current program with line numbers"),
+	(":clear", "Resets the session"),
+	(":undo", "Removes the last successful line"),
+	(":save <path>", "Writes the current program to a file"),
+	(
+		":load <path>",
+		"Runs a file's contents as if it were typed in",
+	),
+	(":timeout <secs>", "Sets how long a line may run before it's killed"),
+	(
+		":limit <procs|memory> <n|off>",
+		"Caps processes, or memory in MiB, for each run (Unix only)",
+	),
+	(":help", "Lists these commands"),
+];
+
+/// How long a line may run by default before it's killed.
+const DEFAULT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Best-effort caps applied to the program on every run, so a fork bomb
+/// or runaway allocation doesn't take the machine down with it.
+#[derive(Clone, Copy)]
+struct Limits {
+	/// RLIMIT_NPROC, which counts every process of the user, not just the program's
+	procs: Option<u64>,
+	/// RLIMIT_AS, in MiB
+	memory: Option<u64>,
+}
+
+impl Default for Limits {
+	fn default() -> Self {
+		Self {
+			procs: Some(256),
+			memory: Some(1024),
+		}
+	}
+}
+
+impl Limits {
+	/// Applies the limits to the current process, meant to run between fork and exec.
+	#[cfg(unix)]
+	fn apply(&self) -> std::io::Result<()> {
+		let set = |resource, value: u64| {
+			let limit = libc::rlimit {
+				rlim_cur: value as libc::rlim_t,
+				rlim_max: value as libc::rlim_t,
+			};
+
+			if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+				return Err(std::io::Error::last_os_error());
+			}
+
+			Ok(())
+		};
+
+		if let Some(procs) = self.procs {
+			set(libc::RLIMIT_NPROC, procs)?;
+		}
+
+		if let Some(memory) = self.memory {
+			set(libc::RLIMIT_AS, memory * 1024 * 1024)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// State of a REPL session.
+/// Every line is recompiled and rerun, so only output past the last marker is shown.
+pub struct Session {
+	backend: Box<dyn crate::compiler::Compiler>,
+
+	/// Lines that compiled and ran successfully
+	lines: Vec<String>,
+	/// Length of the program's stdout after each line in [Self::lines]
+	markers: Vec<usize>,
+
+	/// Project sources compiled alongside the session
+	sources: Vec<std::path::PathBuf>,
+	/// Include paths from the project
+	includes: Vec<std::path::PathBuf>,
+	flags: Vec<String>,
+	/// Start of every program, from the repl.h asset
+	prelude: String,
+
+	/// How long a line may run before it's killed
+	timeout: std::time::Duration,
+	limits: Limits,
+
+	temp_repl: tempfile::TempPath,
+	temp_bin: tempfile::TempPath,
+}
+
+impl Session {
+	pub fn new(backend: Box<dyn crate::compiler::Compiler>) -> anyhow::Result<Self> {
+		let temp_repl = tempfile::Builder::new()
+			.prefix("cpkg-repl")
+			.suffix(".c")
+			.tempfile()?
+			.into_temp_path();
+
+		let temp_bin = tempfile::Builder::new()
+			.suffix(std::env::consts::EXE_SUFFIX)
+			.tempfile()?
+			.into_temp_path();
+
+		let flags = std::iter::once("-w")
+			.chain(backend.color_flag().filter(|_| crate::log::color()))
+			.map(str::to_owned)
+			.collect();
+
+		Ok(Self {
+			backend,
+			lines: vec![],
+			markers: vec![],
+			sources: vec![],
+			includes: vec![],
+			flags,
+			prelude: crate::assets::read(None, "repl.h")?,
+			timeout: DEFAULT_TIMEOUT,
+			limits: Limits::default(),
+			temp_repl,
+			temp_bin,
+		})
+	}
+
+	/// Compiles the session alongside a project's sources, like its tests are.
+	pub fn attach(&mut self, proj: &crate::Project) -> anyhow::Result<()> {
+		let src = proj.src();
+
+		/* Projects may override the prelude */
+		self.prelude = crate::assets::read(Some(proj), "repl.h")?;
+
+		self.sources = proj.lib_files(&src).collect();
+
+		self.includes = proj.include_paths(&src);
+		self.flags
+			.extend(proj.build_flags(self.backend.as_ref()).iter().cloned());
+
+		Ok(())
+	}
+
+	/// Generates the full C program for the session, optionally with a pending line.
+	fn program(&self, pending: Option<&str>) -> String {
+		let body = self
+			.lines
+			.iter()
+			.map(String::as_str)
+			.chain(pending)
+			.collect::<Vec<_>>()
+			.join("\n\t");
+
+		let prelude = &self.prelude;
+
+		#[rustfmt::skip]
+		let program = indoc::formatdoc!(r#"
+			{prelude}
+			int main() {{
+				{body}
+				return 0;
+			}}
+		"#);
+
+		program
+	}
+
+	fn marker(&self) -> usize {
+		self.markers.last().copied().unwrap_or(0)
+	}
+
+	/// Compiles the session with a pending line into the temporary binary.
+	fn compile(&self, line: &str) -> anyhow::Result<()> {
+		std::fs::write(&self.temp_repl, self.program(Some(line)))?;
+
+		let mut files = vec![self.temp_repl.to_path_buf()];
+		files.extend(self.sources.iter().cloned());
+
+		let includes = self
+			.includes
+			.iter()
+			.map(|p| p.as_path())
+			.collect::<Vec<_>>();
+
+		self.backend
+			.compile(&files, &includes, &self.temp_bin, &self.flags, &mut |_| {})
+	}
+
+	/// Runs the compiled binary under the session's limits,
+	/// killing it and everything it spawned if it runs past the timeout.
+	/// Returns [None] if it timed out.
+	fn run_binary(&self) -> anyhow::Result<Option<std::process::Output>> {
+		let mut cmd = std::process::Command::new(&self.temp_bin);
+		cmd.stdin(std::process::Stdio::null())
+			.stdout(std::process::Stdio::piped())
+			.stderr(std::process::Stdio::piped());
+
+		#[cfg(unix)]
+		{
+			use std::os::unix::process::CommandExt;
+
+			let limits = self.limits;
+
+			/* Own process group, so children it forks are killed along with it */
+			cmd.process_group(0);
+			unsafe {
+				cmd.pre_exec(move || limits.apply());
+			}
+		}
+
+		let mut child = cmd.spawn()?;
+
+		let read = |pipe: Option<Box<dyn std::io::Read + Send>>| {
+			std::thread::spawn(move || {
+				let mut buf = vec![];
+				if let Some(mut pipe) = pipe {
+					let _ = pipe.read_to_end(&mut buf);
+				}
+				buf
+			})
+		};
+
+		let stdout = read(child.stdout.take().map(|p| Box::new(p) as _));
+		let stderr = read(child.stderr.take().map(|p| Box::new(p) as _));
+
+		let deadline = std::time::Instant::now() + self.timeout;
+
+		let status = loop {
+			if let Some(status) = child.try_wait()? {
+				break status;
+			}
+
+			if std::time::Instant::now() >= deadline {
+				#[cfg(unix)]
+				unsafe {
+					libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+				}
+
+				let _ = child.kill();
+				let _ = child.wait();
+
+				/* Readers are left behind, anything escaping the group may hold the pipes open */
+				return Ok(None);
+			}
+
+			std::thread::sleep(std::time::Duration::from_millis(10));
+		};
+
+		Ok(Some(std::process::Output {
+			status,
+			stdout: stdout.join().unwrap_or_default(),
+			stderr: stderr.join().unwrap_or_default(),
+		}))
+	}
+
+	/// Runs the compiled binary, keeping the line if it succeeded.
+	fn execute(&mut self, line: String) -> anyhow::Result<()> {
+		let Some(out) = self.run_binary()? else {
+			println!(
+				"{}",
+				format!(
+					"Execution timed out after {}s, the line wasn't kept.",
+					self.timeout.as_secs_f32()
+				)
+				.red()
+			);
+
+			return Ok(());
+		};
+
+		let mut stdout = std::io::stdout().lock();
+
+		if out.status.success() {
+			/* Only update entire code if ran successfully */
+			let visible = out.stdout.get(self.marker()..).unwrap_or_default();
+
+			stdout.write_all(visible)?;
+			if !visible.is_empty() && !visible.ends_with(b"\n") {
+				stdout.write_all(b"\n")?;
+			}
+
+			self.lines.push(line);
+			self.markers.push(out.stdout.len());
+		} else {
+			stdout.write_all(b"Failed to run: ")?;
+			stdout.write_all(&out.stderr)?;
+			stdout.write_all(b"\n")?;
+		}
+
+		stdout.flush()?;
+
+		Ok(())
+	}
+
+	/// Compiles and runs the session with a new line, keeping it if it succeeded.
+	/// Expressions have their value printed, falling back to a plain statement if that fails to compile.
+	/// The value is kept in a temporary, so the expression only runs once. Its type is taken after a comma,
+	/// which turns arrays into pointers the temporary can be initialized with.
+	pub fn eval(&mut self, line: &str) -> anyhow::Result<()> {
+		let candidates = match expression(line) {
+			Some(expr) => vec![
+				format!(
+					"{{ __typeof__(((void)0, ({expr}))) __cpkg_value = ({expr}); printf(__cpkg_fmt(__cpkg_value), __cpkg_value); }}"
+				),
+				format!("{expr};"),
+			],
+			None => vec![line.to_owned()],
+		};
+
+		let mut result = Ok(());
+
+		for candidate in candidates {
+			result = self.compile(&candidate);
+
+			if result.is_ok() {
+				return self.execute(candidate);
+			}
+		}
+
+		if let Err(e) = result {
+			print!("{e}");
+		}
+
+		Ok(())
+	}
+
+	/// Handles a colon-prefixed meta command.
+	pub fn command(&mut self, input: &str) -> anyhow::Result<()> {
+		let (cmd, arg) = input
+			.split_once(char::is_whitespace)
+			.map(|(c, a)| (c, a.trim()))
+			.unwrap_or((input, ""));
+
+		match (cmd, arg) {
+			(":show", _) => {
+				let program = self.program(None);
+				let width = program.lines().count().to_string().len();
+
+				for (n, line) in program.lines().enumerate() {
+					println!("{} {line}", format!("{:>width$} |", n + 1).blue());
+				}
+			}
+
+			(":clear", _) => {
+				self.lines.clear();
+				self.markers.clear();
+
+				println!("Cleared session.");
+			}
+
+			(":undo", _) => {
+				if let Some(line) = self.lines.pop() {
+					self.markers.pop();
+					println!("Removed {}", line.yellow());
+				} else {
+					println!("Nothing to undo.");
+				}
+			}
+
+			(":save", path) if !path.is_empty() => {
+				std::fs::write(path, self.program(None))?;
+				println!("Saved session to {}.", path.yellow());
+			}
+
+			(":timeout", secs) if !secs.is_empty() => match secs.parse::<f32>() {
+				Ok(secs) if secs > 0.0 => {
+					self.timeout = std::time::Duration::from_secs_f32(secs);
+					println!("Lines now time out after {secs}s.");
+				}
+				_ => println!("Invalid timeout {}, expected seconds.", secs.red()),
+			},
+
+			(":limit", arg) if !arg.is_empty() => {
+				let (which, value) = arg
+					.split_once(char::is_whitespace)
+					.map(|(w, v)| (w, v.trim()))
+					.unwrap_or((arg, ""));
+
+				let value = match value {
+					"off" => None,
+					n => match n.parse::<u64>() {
+						Ok(n) if n > 0 => Some(n),
+						_ => {
+							println!("Invalid limit {}, expected a number or off.", n.red());
+							return Ok(());
+						}
+					},
+				};
+
+				match which {
+					"procs" => self.limits.procs = value,
+					"memory" => self.limits.memory = value,
+					_ => {
+						println!("Unknown limit {}, expected procs or memory.", which.red());
+						return Ok(());
+					}
+				}
+
+				match value {
+					Some(n) => println!("Set {which} limit to {n}."),
+					None => println!("Removed {which} limit."),
+				}
+			}
+
+			(":load", path) if !path.is_empty() => {
+				let contents = std::fs::read_to_string(path)?;
+				self.eval(contents.trim_end())?;
+			}
+
+			_ => {
+				if cmd != ":help" {
+					println!("Unknown command {}.", input.red());
+				}
+
+				println!("Available commands:");
+				for (name, desc) in COMMANDS {
+					println!("  {:<30} {}", name.yellow(), desc);
+				}
+
+				let limit = |l: Option<u64>, unit: &str| {
+					l.map_or("off".to_owned(), |n| format!("{n}{unit}"))
+				};
+
+				println!(
+					"Currently: timeout {}s, procs {}, memory {}.",
+					self.timeout.as_secs_f32(),
+					limit(self.limits.procs, ""),
+					limit(self.limits.memory, " MiB")
+				);
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Returns the line if it looks like a bare expression rather than a statement.
+fn expression(line: &str) -> Option<&str> {
+	let line = line.trim();
+
+	let statement = line.is_empty()
+		|| line.starts_with('#')
+		|| line.ends_with(';')
+		|| line.ends_with('{')
+		|| line.ends_with('}');
+
+	(!statement).then_some(line)
+}
+
+/// Where REPL history is kept between sessions.
+fn history_path() -> Option<std::path::PathBuf> {
+	#[cfg(target_os = "windows")]
+	let base = std::env::var_os("LOCALAPPDATA").map(std::path::PathBuf::from);
+
+	#[cfg(not(target_os = "windows"))]
+	let base = std::env::var_os("XDG_STATE_HOME")
+		.map(std::path::PathBuf::from)
+		.or_else(|| {
+			std::env::var_os("HOME").map(|h| std::path::Path::new(&h).join(".local/state"))
+		});
+
+	base.map(|b| b.join("cpkg").join("repl_history"))
+}
+
+pub fn run(
+	backend: Box<dyn crate::compiler::Compiler>,
+	proj: Option<&crate::Project>,
+) -> anyhow::Result<()> {
+	println!("{}", "Please note that the repl is very basic and experimental.\nYour code will run entirely each line.".yellow());
+
+	let mut session = Session::new(backend)?;
+
+	if let Some(proj) = proj {
+		session.attach(proj)?;
+
+		/* Report broken project sources once, instead of on every line */
+		if let Err(e) = session.compile("") {
+			eprintln!(
+				"{}",
+				"Project sources failed to compile, continuing without them:".red()
+			);
+			eprint!("{e}");
+
+			session.sources.clear();
+		} else {
+			println!("Using sources from project {}.", proj.name().yellow());
+		}
+	}
+	let mut editor = rustyline::DefaultEditor::new()?;
+
+	let history = history_path();
+	if let Some(ref history) = history {
+		/* Missing on first run */
+		let _ = editor.load_history(history);
+	}
+
+	loop {
+		let line = match editor.readline("> ") {
+			Ok(line) => line,
+			Err(rustyline::error::ReadlineError::Interrupted) => continue,
+			Err(rustyline::error::ReadlineError::Eof) => {
+				println!("Goodbye!");
+				break;
+			}
+			Err(e) => return Err(e.into()),
+		};
+
+		editor.add_history_entry(&line)?;
+
+		if line.trim_start().starts_with(':') {
+			if let Err(e) = session.command(line.trim()) {
+				eprintln!("{}", e.to_string().red());
+			}
+		} else {
+			session.eval(&line)?;
+		}
+	}
+
+	if let Some(history) = history {
+		if let Some(parent) = history.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+
+		editor.save_history(&history)?;
+	}
+
+	Ok(())
+}
@@ -0,0 +1,225 @@
+use crate::ConfigShell;
+
+/// A script from cpkg.toml ready to run, along with the file it was written to if it spans several lines.
+pub struct Script {
+	pub cmd: std::process::Command,
+	/// Deleted once the script is dropped, so it has to outlive the command
+	_file: Option<tempfile::TempPath>,
+}
+
+/// Shell scripts run in when [scripts] doesn't pick one, which was the only choice before it could be.
+fn default_shell() -> ConfigShell {
+	if cfg!(target_os = "windows") {
+		ConfigShell::Cmd
+	} else {
+		ConfigShell::Sh
+	}
+}
+
+/// Executable a shell is started with. PowerShell outside of Windows is the cross platform pwsh.
+fn program(shell: ConfigShell) -> &'static str {
+	match shell {
+		ConfigShell::Sh => "sh",
+		ConfigShell::Cmd => "cmd.exe",
+		ConfigShell::Powershell if cfg!(target_os = "windows") => "powershell",
+		ConfigShell::Powershell => "pwsh",
+	}
+}
+
+/// Extension the shell expects of a script file.
+fn extension(shell: ConfigShell) -> &'static str {
+	match shell {
+		ConfigShell::Sh => "sh",
+		ConfigShell::Cmd => "bat",
+		ConfigShell::Powershell => "ps1",
+	}
+}
+
+/// Contents of the file a multi-line script is written to.
+fn contents(shell: ConfigShell, script: &str) -> String {
+	match shell {
+		/* Batch files are read with Windows line endings, and echo every line unless told not to */
+		ConfigShell::Cmd => std::iter::once("@echo off")
+			.chain(script.lines())
+			.map(|l| format!("{l}\r\n"))
+			.collect(),
+		_ => format!("{}\n", script.trim_end()),
+	}
+}
+
+/// Creates the command running a script in the shell chosen under [scripts].
+/// Single lines are passed to the shell directly, anything longer is written to a temporary file it runs.
+pub fn command(proj: &crate::Project, script: &str) -> anyhow::Result<Script> {
+	let shell = proj.config().scripts.shell.unwrap_or_else(default_shell);
+
+	if shell == ConfigShell::Cmd && !cfg!(target_os = "windows") {
+		anyhow::bail!(
+			"cmd can only run scripts on Windows. Set shell under [scripts] in cpkg.toml to sh or powershell."
+		);
+	}
+
+	if !crate::probe::exists(program(shell)) {
+		anyhow::bail!(
+			"Couldn't find {}, the shell scripts run in. Install it, or change shell under [scripts] in cpkg.toml.",
+			program(shell)
+		);
+	}
+
+	let mut cmd = std::process::Command::new(program(shell));
+	let mut file = None;
+
+	if script.trim().contains('\n') {
+		let mut temp = tempfile::Builder::new()
+			.prefix("cpkg-script")
+			.suffix(&format!(".{}", extension(shell)))
+			.tempfile()?;
+
+		std::io::Write::write_all(&mut temp, contents(shell, script).as_bytes())?;
+		let path = temp.into_temp_path();
+
+		match shell {
+			ConfigShell::Sh => cmd.arg(&path),
+			ConfigShell::Cmd => cmd.args(["/d", "/c"]).arg(&path),
+			ConfigShell::Powershell => cmd
+				.args(["-NoProfile", "-ExecutionPolicy", "Bypass", "-File"])
+				.arg(&path),
+		};
+
+		file = Some(path);
+	} else {
+		match shell {
+			ConfigShell::Sh => {
+				cmd.arg("-c").arg(script);
+			}
+			ConfigShell::Cmd => {
+				/* cmd.exe doesn't split its command line like other programs, so pass it through untouched */
+				#[cfg(target_os = "windows")]
+				{
+					use std::os::windows::process::CommandExt;
+					cmd.arg("/c").raw_arg(script);
+				}
+			}
+			ConfigShell::Powershell => {
+				cmd.args(["-NoProfile", "-Command"]).arg(script);
+			}
+		}
+	}
+
+	/* Paths go through the environment rather than into the script, so they never need quoting */
+	cmd.env("CPKG_ROOT", proj.path())
+		.env("CPKG_TARGET_DIR", proj.out_dir())
+		.env("CPKG_PKG_NAME", proj.name())
+		.env("CPKG_PKG_VERSION", proj.version().to_string());
+
+	Ok(Script { cmd, _file: file })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A project with scripts run by `shell`.
+	fn scripted(shell: &str) -> tempfile::TempDir {
+		crate::project::testing::project(&format!("\n[scripts]\nshell = \"{shell}\"\n"), &[])
+	}
+
+	fn args(cmd: &std::process::Command) -> Vec<String> {
+		cmd.get_args()
+			.map(|a| a.to_string_lossy().into_owned())
+			.collect()
+	}
+
+	#[test]
+	fn writes_files_each_shell_reads() {
+		let script = "echo one\necho two\n\n";
+
+		assert_eq!(contents(ConfigShell::Sh, script), "echo one\necho two\n");
+		assert_eq!(
+			contents(ConfigShell::Powershell, script),
+			"echo one\necho two\n"
+		);
+		assert_eq!(
+			contents(ConfigShell::Cmd, script),
+			"@echo off\r\necho one\r\necho two\r\n\r\n"
+		);
+
+		assert_eq!(extension(ConfigShell::Sh), "sh");
+		assert_eq!(extension(ConfigShell::Cmd), "bat");
+		assert_eq!(extension(ConfigShell::Powershell), "ps1");
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn passes_single_lines_untouched() {
+		let temp = scripted("sh");
+		let proj = crate::Project::open(temp.path()).unwrap();
+
+		let line = r#"printf '%s|' "a b" 'c "d"' $CPKG_PKG_NAME"#;
+		let mut script = command(&proj, line).unwrap();
+
+		assert_eq!(args(&script.cmd), ["-c", line]);
+		assert!(script._file.is_none());
+
+		let output = script.cmd.output().unwrap();
+		assert_eq!(
+			String::from_utf8_lossy(&output.stdout),
+			r#"a b|c "d"|demo|"#
+		);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn runs_multiple_lines_from_a_file() {
+		let temp = scripted("sh");
+		let proj = crate::Project::open(temp.path()).unwrap();
+
+		let mut script = command(&proj, "cd \"$CPKG_ROOT\"\ntest -f cpkg.toml\nexit 3\n").unwrap();
+
+		let path = std::path::PathBuf::from(&args(&script.cmd)[0]);
+		let name = path.file_name().unwrap().to_string_lossy().into_owned();
+
+		assert!(
+			name.starts_with("cpkg-script") && name.ends_with(".sh"),
+			"{name}"
+		);
+		assert_eq!(
+			std::fs::read_to_string(&path).unwrap(),
+			"cd \"$CPKG_ROOT\"\ntest -f cpkg.toml\nexit 3\n"
+		);
+
+		/* Exit codes come back from the file, after the folder with a space in it was found */
+		assert_eq!(script.cmd.status().unwrap().code(), Some(3));
+
+		drop(script);
+		assert!(!path.exists(), "the file goes with the script");
+	}
+
+	#[cfg(not(target_os = "windows"))]
+	#[test]
+	fn refuses_cmd_elsewhere() {
+		let temp = scripted("cmd");
+		let proj = crate::Project::open(temp.path()).unwrap();
+
+		let err = command(&proj, "echo hi").err().unwrap().to_string();
+		assert!(err.contains("cmd can only run scripts on Windows"), "{err}");
+	}
+
+	#[test]
+	fn reports_missing_powershell() {
+		if crate::probe::exists(program(ConfigShell::Powershell)) {
+			return;
+		}
+
+		let temp = scripted("powershell");
+		let proj = crate::Project::open(temp.path()).unwrap();
+
+		let err = command(&proj, "Write-Output hi").err().unwrap().to_string();
+		assert!(
+			err.contains(&format!(
+				"Couldn't find {}",
+				program(ConfigShell::Powershell)
+			)),
+			"{err}"
+		);
+	}
+}
@@ -0,0 +1,169 @@
+use std::io::{BufRead, Write};
+
+/// Binds to the given port on localhost, or any free port if it's taken.
+pub fn bind(port: u16) -> std::io::Result<std::net::TcpListener> {
+	std::net::TcpListener::bind(("127.0.0.1", port))
+		.or_else(|_| std::net::TcpListener::bind(("127.0.0.1", 0)))
+}
+
+/// Serves files under `root` until `stop` is set.
+pub fn serve(
+	listener: std::net::TcpListener,
+	root: std::path::PathBuf,
+	stop: std::sync::Arc<std::sync::atomic::AtomicBool>,
+) -> std::io::Result<()> {
+	listener.set_nonblocking(true)?;
+
+	while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+		match listener.accept() {
+			Ok((stream, _)) => {
+				/* A broken connection shouldn't take down the server */
+				let _ = respond(stream, &root);
+			}
+			Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+				std::thread::sleep(std::time::Duration::from_millis(50));
+			}
+			Err(e) => return Err(e),
+		}
+	}
+
+	Ok(())
+}
+
+fn respond(mut stream: std::net::TcpStream, root: &std::path::Path) -> std::io::Result<()> {
+	stream.set_nonblocking(false)?;
+
+	let mut request = String::new();
+	std::io::BufReader::new(&stream).read_line(&mut request)?;
+
+	let mut parts = request.split_whitespace();
+	let (method, target) = (
+		parts.next().unwrap_or_default(),
+		parts.next().unwrap_or("/"),
+	);
+
+	if method != "GET" && method != "HEAD" {
+		return write_response(
+			&mut stream,
+			"405 Method Not Allowed",
+			"text/plain",
+			b"Method not allowed",
+			method == "HEAD",
+		);
+	}
+
+	let Some(path) = resolve(root, target) else {
+		return write_response(
+			&mut stream,
+			"404 Not Found",
+			"text/plain",
+			b"Not found",
+			method == "HEAD",
+		);
+	};
+
+	match std::fs::read(&path) {
+		Ok(body) => write_response(
+			&mut stream,
+			"200 OK",
+			content_type(&path),
+			&body,
+			method == "HEAD",
+		),
+		Err(_) => write_response(
+			&mut stream,
+			"404 Not Found",
+			"text/plain",
+			b"Not found",
+			method == "HEAD",
+		),
+	}
+}
+
+/// Maps a request target to a file under root, refusing anything that escapes it.
+fn resolve(root: &std::path::Path, target: &str) -> Option<std::path::PathBuf> {
+	let path = target.split(['?', '#']).next().unwrap_or_default();
+	let path = percent_decode(path)?;
+
+	let mut out = root.to_owned();
+	for part in path.split('/') {
+		match part {
+			"" | "." => {}
+			".." => return None,
+			part if part.contains('\\') => return None,
+			part => out.push(part),
+		}
+	}
+
+	if out.is_dir() {
+		out.push("index.html");
+	}
+
+	out.is_file().then_some(out)
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+	let bytes = s.as_bytes();
+	let mut out = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+
+	while i < bytes.len() {
+		if bytes[i] == b'%' {
+			let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+			out.push(u8::from_str_radix(hex, 16).ok()?);
+			i += 3;
+		} else {
+			out.push(bytes[i]);
+			i += 1;
+		}
+	}
+
+	String::from_utf8(out).ok()
+}
+
+fn content_type(path: &std::path::Path) -> &'static str {
+	match path.extension().and_then(|e| e.to_str()) {
+		Some("html" | "htm") => "text/html; charset=utf-8",
+		Some("css") => "text/css",
+		Some("js") => "text/javascript",
+		Some("json") => "application/json",
+		Some("wasm") => "application/wasm",
+		Some("svg") => "image/svg+xml",
+		Some("png") => "image/png",
+		Some("jpg" | "jpeg") => "image/jpeg",
+		Some("gif") => "image/gif",
+		Some("ico") => "image/x-icon",
+		Some("woff2") => "font/woff2",
+		_ => "application/octet-stream",
+	}
+}
+
+fn write_response(
+	stream: &mut std::net::TcpStream,
+	status: &str,
+	content_type: &str,
+	body: &[u8],
+	head: bool,
+) -> std::io::Result<()> {
+	write!(
+		stream,
+		"HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+		body.len()
+	)?;
+
+	if !head {
+		stream.write_all(body)?;
+	}
+
+	stream.flush()
+}
+
+/// Latest modification time of any file under some directories.
+pub fn last_modified(dirs: &[std::path::PathBuf]) -> Option<std::time::SystemTime> {
+	dirs.iter()
+		.flat_map(walkdir::WalkDir::new)
+		.flatten()
+		.filter_map(|e| e.metadata().ok())
+		.filter_map(|m| m.modified().ok())
+		.max()
+}
@@ -0,0 +1,167 @@
+/// Folder `cpkg vendor` copies dependencies into, relative to the project.
+pub const DIR: &str = "vendor";
+
+/// File inside of the snapshot recording where each dependency was copied from.
+const MANIFEST: &str = ".cpkg-snapshot";
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct Manifest {
+	#[serde(default)]
+	dependencies: std::collections::BTreeMap<String, Entry>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, PartialEq)]
+struct Entry {
+	/// Git url or path, as declared in cpkg.toml
+	source: String,
+	/// Commit a git dependency was copied at
+	#[serde(skip_serializing_if = "Option::is_none")]
+	commit: Option<String>,
+}
+
+fn read_manifest(dir: &std::path::Path) -> Option<Manifest> {
+	let contents = std::fs::read_to_string(dir.join(MANIFEST)).ok()?;
+	toml::from_str(&contents).ok()
+}
+
+/// Copies every installed dependency into the project's vendor folder, replacing any previous snapshot,
+/// then points cpkg.toml at it. Returns the names of the dependencies copied.
+pub fn create(proj: &mut crate::Project) -> anyhow::Result<Vec<String>> {
+	let installed = proj.vendor();
+
+	/* Prebuilt libraries are outside of target/vendor, so there's nothing to copy */
+	let deps = proj
+		.config()
+		.dependencies
+		.entries
+		.iter()
+		.filter(|(_, dep)| dep.is_vendored())
+		.collect::<std::collections::BTreeMap<_, _>>();
+
+	let missing = deps
+		.keys()
+		.filter(|name| !installed.join(name).exists())
+		.map(|name| name.as_str())
+		.collect::<Vec<_>>();
+
+	if !missing.is_empty() {
+		anyhow::bail!(
+			"Some dependencies aren't installed: {}. Run cpkg install first.",
+			missing.join(", ")
+		);
+	}
+
+	let lock = crate::lockfile::Lockfile::load(proj.lock_dir())?;
+	let dir = proj.path().join(DIR);
+
+	if dir.exists() {
+		std::fs::remove_dir_all(&dir)?;
+	}
+
+	std::fs::create_dir(&dir)?;
+
+	let mut manifest = Manifest::default();
+
+	for (name, dep) in deps {
+		copy_tree(&installed.join(name), &dir.join(name))?;
+
+		let commit = match dep {
+			crate::ConfigDependency::Git { git, .. } => lock.pinned(name, git).map(str::to_owned),
+			_ => None,
+		};
+
+		manifest.dependencies.insert(
+			name.to_owned(),
+			Entry {
+				source: dep.to_string(),
+				commit,
+			},
+		);
+	}
+
+	std::fs::write(dir.join(MANIFEST), toml::to_string(&manifest)?)?;
+
+	proj.with_config(|c| c.dependencies.vendor_dir = Some(DIR.into()))?;
+
+	Ok(manifest.dependencies.into_keys().collect())
+}
+
+/// Copies a dependency's files, following the symlinks of path dependencies and leaving out git metadata.
+fn copy_tree(from: &std::path::Path, to: &std::path::Path) -> anyhow::Result<()> {
+	let walk = walkdir::WalkDir::new(from)
+		.follow_links(true)
+		.into_iter()
+		.filter_entry(|e| e.file_name() != ".git");
+
+	for entry in walk {
+		let entry = entry?;
+		let dest = to.join(entry.path().strip_prefix(from)?);
+
+		if entry.file_type().is_dir() {
+			std::fs::create_dir_all(&dest)?;
+		} else {
+			std::fs::copy(entry.path(), &dest)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Ways the committed snapshot differs from cpkg.toml and cpkg.lock, empty when it's complete.
+pub fn problems(proj: &crate::Project) -> Vec<String> {
+	let Some(dir) = proj.snapshot() else {
+		return vec!["No vendor snapshot, run cpkg vendor to create one".to_owned()];
+	};
+
+	let Some(manifest) = read_manifest(&dir) else {
+		return vec![format!(
+			"{} wasn't made by cpkg vendor, run it again",
+			dir.display()
+		)];
+	};
+
+	let lock = crate::lockfile::Lockfile::load(proj.lock_dir()).unwrap_or_default();
+	let deps = &proj.config().dependencies.entries;
+	let mut problems = vec![];
+
+	for (name, dep) in deps.iter().filter(|(_, dep)| dep.is_vendored()) {
+		let Some(entry) = manifest.dependencies.get(name) else {
+			problems.push(format!("{name} is declared but not vendored"));
+			continue;
+		};
+
+		if !dir.join(name).is_dir() {
+			problems.push(format!("{name} is missing from {}", dir.display()));
+		}
+
+		if entry.source != dep.to_string() {
+			problems.push(format!(
+				"{name} was vendored from {}, but cpkg.toml declares {dep}",
+				entry.source
+			));
+		}
+
+		if let crate::ConfigDependency::Git { git, .. } = dep {
+			let locked = lock.pinned(name, git);
+
+			if locked.is_some() && locked != entry.commit.as_deref() {
+				problems.push(format!(
+					"{name} was vendored at commit {}, but cpkg.lock pins {}",
+					entry
+						.commit
+						.as_deref()
+						.map_or("unknown", crate::lockfile::short),
+					locked.map_or("", crate::lockfile::short)
+				));
+			}
+		}
+	}
+
+	for name in manifest.dependencies.keys() {
+		if !deps.contains_key(name) {
+			problems.push(format!("{name} is vendored but no longer declared"));
+		}
+	}
+
+	problems
+}
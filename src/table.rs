@@ -0,0 +1,314 @@
+use colored::Colorize;
+
+/// Space between columns.
+const GAP: usize = 2;
+
+/// Narrowest a shrunk column gets before rows are left to wrap.
+const MIN_SHRINK: usize = 12;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+	Left,
+	Right,
+}
+
+/// A single cell, with styling applied to its text after padding is worked out.
+pub struct Cell {
+	text: String,
+	style: Option<fn(&str) -> colored::ColoredString>,
+}
+
+impl Cell {
+	pub fn styled(text: impl Into<String>, style: fn(&str) -> colored::ColoredString) -> Self {
+		Self {
+			text: text.into(),
+			style: Some(style),
+		}
+	}
+}
+
+impl From<String> for Cell {
+	fn from(text: String) -> Self {
+		Self { text, style: None }
+	}
+}
+
+impl From<&str> for Cell {
+	fn from(text: &str) -> Self {
+		text.to_owned().into()
+	}
+}
+
+/// Aligned columns for commands that print many records.
+/// When stdout isn't a terminal, rows are printed plainly, one per line.
+pub struct Table {
+	headers: Option<Vec<String>>,
+	align: Vec<Align>,
+	/// Column to shorten when rows don't fit the terminal, usually a path
+	shrink: Option<usize>,
+	indent: usize,
+	rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+	pub fn new(columns: usize) -> Self {
+		Self {
+			headers: None,
+			align: vec![Align::Left; columns],
+			shrink: None,
+			indent: 0,
+			rows: vec![],
+		}
+	}
+
+	pub fn header(mut self, headers: &[&str]) -> Self {
+		self.headers = Some(headers.iter().map(|h| h.to_string()).collect());
+		self
+	}
+
+	pub fn align(mut self, column: usize, align: Align) -> Self {
+		self.align[column] = align;
+		self
+	}
+
+	pub fn shrink(mut self, column: usize) -> Self {
+		self.shrink = Some(column);
+		self
+	}
+
+	pub fn indent(mut self, indent: usize) -> Self {
+		self.indent = indent;
+		self
+	}
+
+	pub fn row<C: Into<Cell>>(&mut self, cells: impl IntoIterator<Item = C>) {
+		self.rows.push(cells.into_iter().map(Into::into).collect());
+	}
+
+	/// Renders aligned to fit in `width` columns, or plainly if there's no terminal.
+	pub fn render(&self, width: Option<usize>) -> String {
+		let Some(width) = width else {
+			return self
+				.rows
+				.iter()
+				.map(|row| {
+					let cells = row.iter().map(|c| c.text.trim()).collect::<Vec<_>>();
+					format!("{}\n", cells.join(" ").trim_end())
+				})
+				.collect();
+		};
+
+		let columns = self.align.len();
+		let mut widths = vec![0; columns];
+
+		let headers = self.headers.iter().flatten().map(|h| h.as_str());
+		let cells = self
+			.rows
+			.iter()
+			.flat_map(|row| row.iter().map(|c| c.text.as_str()).enumerate());
+
+		for (i, header) in headers.enumerate() {
+			widths[i] = widths[i].max(header.chars().count());
+		}
+
+		for (i, text) in cells {
+			widths[i] = widths[i].max(text.chars().count());
+		}
+
+		let total = self.indent + widths.iter().sum::<usize>() + GAP * columns.saturating_sub(1);
+
+		if let Some(column) = self.shrink {
+			if total > width {
+				widths[column] = widths[column]
+					.saturating_sub(total - width)
+					.max(MIN_SHRINK.min(widths[column]));
+			}
+		}
+
+		let mut out = String::new();
+
+		if let Some(ref headers) = self.headers {
+			let cells = headers
+				.iter()
+				.map(|h| Cell::styled(h.as_str(), |s| s.bold()))
+				.collect::<Vec<_>>();
+
+			self.render_row(&mut out, &cells, &widths);
+		}
+
+		for row in &self.rows {
+			self.render_row(&mut out, row, &widths);
+		}
+
+		out
+	}
+
+	fn render_row(&self, out: &mut String, row: &[Cell], widths: &[usize]) {
+		let mut line = " ".repeat(self.indent);
+
+		for (i, cell) in row.iter().enumerate() {
+			let text = ellipsize(&cell.text, widths[i]);
+			let pad = " ".repeat(widths[i] - text.chars().count());
+
+			let styled = match cell.style {
+				Some(style) => style(&text).to_string(),
+				None => text,
+			};
+
+			if i > 0 {
+				line.push_str(&" ".repeat(GAP));
+			}
+
+			match self.align[i] {
+				Align::Left if i + 1 == row.len() => line.push_str(&styled),
+				Align::Left => {
+					line.push_str(&styled);
+					line.push_str(&pad);
+				}
+				Align::Right => {
+					line.push_str(&pad);
+					line.push_str(&styled);
+				}
+			}
+		}
+
+		out.push_str(&line);
+		out.push('\n');
+	}
+
+	pub fn print(&self) {
+		print!("{}", self.render(width()));
+	}
+}
+
+/// Width of the terminal stdout is attached to, or [None] if it's not a terminal.
+pub fn width() -> Option<usize> {
+	use std::io::IsTerminal;
+
+	if !std::io::stdout().is_terminal() {
+		return None;
+	}
+
+	let width = terminal_size::terminal_size()
+		.map(|(w, _)| w.0 as usize)
+		.or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+		.unwrap_or(80);
+
+	Some(width)
+}
+
+/// Shortens text to `width` characters by cutting out its middle,
+/// which keeps both the start and the file name of a path visible.
+fn ellipsize(text: &str, width: usize) -> String {
+	let len = text.chars().count();
+	if len <= width {
+		return text.to_owned();
+	}
+
+	if width <= 1 {
+		return "…".repeat(width);
+	}
+
+	let keep = width - 1;
+	let head = keep / 2;
+	let tail = keep - head;
+
+	let start = text.chars().take(head).collect::<String>();
+	let end = text.chars().skip(len - tail).collect::<String>();
+
+	format!("{start}…{end}")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A listing like `cpkg vendor` prints, with a long path to shrink.
+	fn listing() -> Table {
+		colored::control::set_override(false);
+
+		let mut table = Table::new(3)
+			.header(&["Name", "Size", "Path"])
+			.align(1, Align::Right)
+			.shrink(2);
+
+		table.row(["zlib", "1.2 MB", "target/vendor/zlib"]);
+		table.row([
+			Cell::from("stb"),
+			Cell::from("48 KB"),
+			Cell::styled(
+				"target/vendor/stb/deeply/nested/single-header/stb_image.h",
+				|s| s.dimmed(),
+			),
+		]);
+		table.row(["ünïcode", "0 B", "target/vendor/ünïcode"]);
+
+		table
+	}
+
+	#[test]
+	fn renders_plainly_without_terminal() {
+		assert_eq!(
+			listing().render(None),
+			"zlib 1.2 MB target/vendor/zlib\n\
+			 stb 48 KB target/vendor/stb/deeply/nested/single-header/stb_image.h\n\
+			 ünïcode 0 B target/vendor/ünïcode\n"
+		);
+	}
+
+	#[test]
+	fn renders_at_80_columns() {
+		assert_eq!(
+			listing().render(Some(80)),
+			"Name       Size  Path\n\
+			 zlib     1.2 MB  target/vendor/zlib\n\
+			 stb       48 KB  target/vendor/stb/deeply/nested/single-header/stb_image.h\n\
+			 ünïcode     0 B  target/vendor/ünïcode\n"
+		);
+	}
+
+	#[test]
+	fn shrinks_path_at_40_columns() {
+		assert_eq!(
+			listing().render(Some(40)),
+			"Name       Size  Path\n\
+			 zlib     1.2 MB  target/vendor/zlib\n\
+			 stb       48 KB  target/vend…stb_image.h\n\
+			 ünïcode     0 B  target/vendor/ünïcode\n"
+		);
+	}
+
+	#[test]
+	fn stops_shrinking_at_20_columns() {
+		/* Paths don't get shorter than MIN_SHRINK, so rows are left for the terminal to wrap */
+		assert_eq!(
+			listing().render(Some(20)),
+			"Name       Size  Path\n\
+			 zlib     1.2 MB  targe…r/zlib\n\
+			 stb       48 KB  targe…mage.h\n\
+			 ünïcode     0 B  targe…nïcode\n"
+		);
+	}
+
+	#[test]
+	fn indents_rows() {
+		colored::control::set_override(false);
+
+		let mut table = Table::new(2).indent(2);
+		table.row(["a.h", "(includes b.h)"]);
+		table.row(["longer.h", ""]);
+
+		assert_eq!(
+			table.render(Some(80)),
+			"  a.h       (includes b.h)\n  longer.h  \n"
+		);
+	}
+
+	#[test]
+	fn ellipsizes_middle() {
+		assert_eq!(ellipsize("short", 10), "short");
+		assert_eq!(ellipsize("abcdefghij", 5), "ab…ij");
+		assert_eq!(ellipsize("abcdefghij", 1), "…");
+		assert_eq!(ellipsize("abcdefghij", 0), "");
+	}
+}
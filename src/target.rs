@@ -0,0 +1,458 @@
+use crate::Project;
+
+/// Something `cpkg build` can produce.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Target {
+	/// Executable with its entrypoint at `src/bin/<name>.c` or `src/<name>.c`, or the package binary for `main`
+	Bin(String),
+	/// Static library of every source besides the entrypoint
+	Lib,
+	/// Executable from `examples/<name>.c`, linked with the library sources
+	Example(String),
+	/// Test binaries, compiled but not ran
+	Tests,
+	/// Checks every header under src compiles on its own and along with the rest,
+	/// then writes an umbrella header including them all. The default of header-only packages
+	Headers,
+}
+
+impl Target {
+	/// Name of the package binary, from `src/main.c` or whichever source defines main().
+	pub const MAIN: &'static str = "main";
+
+	pub fn is_runnable(&self) -> bool {
+		matches!(self, Self::Bin(_) | Self::Example(_))
+	}
+
+	/// Makes sure whatever this target is built from exists.
+	fn validate(&self, proj: &Project) -> anyhow::Result<()> {
+		match self {
+			Self::Bin(name) => {
+				let entry = proj.bin_entry(&proj.src(), name);
+
+				if !entry.exists() {
+					anyhow::bail!("No entrypoint {} for target {self}", entry.display());
+				}
+
+				if proj.is_bin_entry(&proj.src(), &entry) {
+					check_bin(proj, name)?;
+				}
+			}
+			Self::Lib => {
+				if proj.lib_files(&proj.src()).next().is_none() {
+					anyhow::bail!("No sources besides the entrypoint to build target {self} from");
+				}
+			}
+			Self::Example(name) => {
+				let entry = proj.examples().join(name).with_extension("c");
+
+				if !entry.exists() {
+					anyhow::bail!("No example {} for target {self}", entry.display());
+				}
+			}
+			Self::Headers => {
+				if proj.header_files(&proj.src()).next().is_none() {
+					anyhow::bail!("No headers in the sources to build target {self} from");
+				}
+			}
+			Self::Tests => {}
+		}
+
+		Ok(())
+	}
+}
+
+impl std::str::FromStr for Target {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.split_once(':') {
+			Some(("bin", name)) if !name.is_empty() => Ok(Self::Bin(name.to_owned())),
+			Some(("example", name)) if !name.is_empty() => Ok(Self::Example(name.to_owned())),
+			None if s == "lib" => Ok(Self::Lib),
+			None if s == "tests" => Ok(Self::Tests),
+			None if s == "headers" => Ok(Self::Headers),
+			_ => anyhow::bail!(
+				"Invalid target '{s}', expected one of bin:<name>, example:<name>, lib, headers or tests"
+			),
+		}
+	}
+}
+
+impl std::fmt::Display for Target {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Bin(name) => write!(f, "bin:{name}"),
+			Self::Lib => write!(f, "lib"),
+			Self::Example(name) => write!(f, "example:{name}"),
+			Self::Tests => write!(f, "tests"),
+			Self::Headers => write!(f, "headers"),
+		}
+	}
+}
+
+/// Targets from `[package] default-targets`, or the main binary if unset.
+/// Header-only packages default to checking their headers instead.
+pub fn defaults(proj: &Project) -> anyhow::Result<Vec<Target>> {
+	let Some(ref specs) = proj.config().package.default_targets else {
+		if proj.is_header_only() {
+			return Ok(vec![Target::Headers]);
+		}
+
+		let bins = bins(proj)?;
+
+		/* Main stays the default when there's nothing else to build, so a missing or ambiguous entrypoint is reported */
+		let main = proj.main_file(&proj.src()).is_some() || bins.is_empty();
+
+		return Ok(main
+			.then(|| Target::MAIN.to_owned())
+			.into_iter()
+			.chain(bins)
+			.map(Target::Bin)
+			.collect());
+	};
+
+	if specs.is_empty() {
+		anyhow::bail!("default-targets in cpkg.toml is empty, list at least one target");
+	}
+
+	specs
+		.iter()
+		.map(|spec| {
+			spec.parse::<Target>()
+				.and_then(|target| target.validate(proj).map(|_| target))
+				.map_err(|e| anyhow::anyhow!("Bad entry in default-targets: {e}"))
+		})
+		.collect()
+}
+
+/// Every target the project can build: the main binary, any bins named in
+/// default-targets, the library or headers, each example and the tests.
+pub fn all(proj: &Project) -> anyhow::Result<Vec<Target>> {
+	let mut targets = vec![];
+
+	if proj.main_file(&proj.src()).is_some() {
+		targets.push(Target::Bin(Target::MAIN.to_owned()));
+	}
+
+	targets.extend(bins(proj)?.into_iter().map(Target::Bin));
+
+	if proj.config().package.default_targets.is_some() {
+		for target in defaults(proj)? {
+			if matches!(target, Target::Bin(_)) && !targets.contains(&target) {
+				targets.push(target);
+			}
+		}
+	}
+
+	if proj.lib_files(&proj.src()).next().is_some() {
+		targets.push(Target::Lib);
+	}
+
+	if proj.is_header_only() {
+		targets.push(Target::Headers);
+	}
+
+	let mut examples = proj
+		.example_files()
+		.filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))
+		.collect::<Vec<_>>();
+
+	examples.sort();
+	targets.extend(examples.into_iter().map(Target::Example));
+
+	targets.push(Target::Tests);
+
+	Ok(targets)
+}
+
+/// Names of the binaries under src/bin, sorted.
+pub fn bins(proj: &Project) -> anyhow::Result<Vec<String>> {
+	let mut names = proj
+		.bin_files(&proj.src())
+		.filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))
+		.collect::<Vec<_>>();
+
+	names.sort();
+
+	for name in &names {
+		check_bin(proj, name)?;
+	}
+
+	Ok(names)
+}
+
+/// Makes sure a binary under src/bin wouldn't be mistaken for, or overwrite, the package's own.
+fn check_bin(proj: &Project, name: &str) -> anyhow::Result<()> {
+	if name == Target::MAIN {
+		anyhow::bail!(
+			"src/bin/main.c can't be built, since bin:main is the package's binary from src/main.c. Rename it."
+		);
+	}
+
+	let main = proj.main_file(&proj.src()).is_some();
+	let output = proj.build_out(None);
+
+	if main && output == proj.build_out(Some(&proj.bin_entry(&proj.src(), name))) {
+		anyhow::bail!(
+			"src/bin/{name}.c would be built to {}, the same as src/main.c since the package is named {name}. Rename one of them.",
+			output.display()
+		);
+	}
+
+	Ok(())
+}
+
+/// Decides which targets a command works on, from an explicit `--bin`,
+/// `--all-targets`, or the project's defaults.
+pub fn resolve(
+	proj: &Project,
+	bin: Option<&str>,
+	all_targets: bool,
+) -> anyhow::Result<Vec<Target>> {
+	if let Some(bin) = bin {
+		let target = Target::Bin(bin.to_owned());
+		target.validate(proj)?;
+
+		Ok(vec![target])
+	} else if all_targets {
+		all(proj)
+	} else {
+		defaults(proj)
+	}
+}
+
+/// Picks the single target `cpkg run` should execute.
+pub fn resolve_run(proj: &Project, bin: Option<&str>) -> anyhow::Result<Target> {
+	let targets = resolve(proj, bin, false)?;
+	let runnable = targets
+		.iter()
+		.filter(|t| t.is_runnable())
+		.cloned()
+		.collect::<Vec<_>>();
+
+	match runnable.as_slice() {
+		[target] => Ok(target.clone()),
+		[] => anyhow::bail!(
+			"None of the default targets ({}) can be run. Pick a binary with --bin",
+			list(&targets)
+		),
+		_ => anyhow::bail!(
+			"Multiple default targets can be run ({}). Pick a binary with --bin",
+			list(&runnable)
+		),
+	}
+}
+
+fn list(targets: &[Target]) -> String {
+	targets
+		.iter()
+		.map(|t| t.to_string())
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::project::testing::project;
+
+	const MAIN: &str = "int main() { return 0; }\n";
+	const LIB: &str = "int add(int a, int b) { return a + b; }\n";
+
+	fn targets(specs: &[&str]) -> Vec<Target> {
+		specs.iter().map(|s| s.parse().unwrap()).collect()
+	}
+
+	fn defaults_of(package: &str, files: &[(&str, &str)]) -> anyhow::Result<Vec<Target>> {
+		let dir = project(package, files);
+		defaults(&Project::open(dir.path())?)
+	}
+
+	#[test]
+	fn parses_and_displays_specs() {
+		for spec in ["bin:tool", "example:demo", "lib", "tests", "headers"] {
+			assert_eq!(spec.parse::<Target>().unwrap().to_string(), spec);
+		}
+
+		for bad in ["bin:", "bin", "example:", "library", "", "lib:x"] {
+			assert!(bad.parse::<Target>().is_err(), "{bad}");
+		}
+	}
+
+	#[test]
+	fn defaults_to_main() {
+		assert_eq!(
+			defaults_of("", &[("src/main.c", MAIN)]).unwrap(),
+			targets(&["bin:main"])
+		);
+	}
+
+	#[test]
+	fn defaults_include_bins() {
+		let files = [
+			("src/main.c", MAIN),
+			("src/bin/zed.c", MAIN),
+			("src/bin/tool.c", MAIN),
+		];
+		assert_eq!(
+			defaults_of("", &files).unwrap(),
+			targets(&["bin:main", "bin:tool", "bin:zed"])
+		);
+	}
+
+	#[test]
+	fn defaults_to_bins_without_main() {
+		let files = [
+			("src/util.c", LIB),
+			("src/bin/b.c", MAIN),
+			("src/bin/a.c", MAIN),
+		];
+		assert_eq!(
+			defaults_of("", &files).unwrap(),
+			targets(&["bin:a", "bin:b"])
+		);
+	}
+
+	#[test]
+	fn header_only_defaults_to_headers() {
+		let files = [("src/demo.h", "int demo(void);\n")];
+		assert_eq!(
+			defaults_of("kind = \"header-only\"\n", &files).unwrap(),
+			targets(&["headers"])
+		);
+	}
+
+	#[test]
+	fn configured_defaults() {
+		let files = [
+			("src/main.c", MAIN),
+			("src/util.c", LIB),
+			("src/bin/tool.c", MAIN),
+		];
+		let package = "default-targets = [\"bin:tool\", \"lib\"]\n";
+
+		assert_eq!(
+			defaults_of(package, &files).unwrap(),
+			targets(&["bin:tool", "lib"])
+		);
+	}
+
+	#[test]
+	fn configured_defaults_must_exist() {
+		let files = [("src/main.c", MAIN)];
+
+		let missing_bin = defaults_of("default-targets = [\"bin:nope\"]\n", &files)
+			.unwrap_err()
+			.to_string();
+		assert!(
+			missing_bin.contains("Bad entry in default-targets")
+				&& missing_bin.contains("bin:nope"),
+			"{missing_bin}"
+		);
+
+		let no_lib = defaults_of("default-targets = [\"lib\"]\n", &files)
+			.unwrap_err()
+			.to_string();
+		assert!(no_lib.contains("target lib"), "{no_lib}");
+
+		let no_example = defaults_of("default-targets = [\"example:demo\"]\n", &files)
+			.unwrap_err()
+			.to_string();
+		assert!(no_example.contains("No example"), "{no_example}");
+
+		let invalid = defaults_of("default-targets = [\"binary\"]\n", &files)
+			.unwrap_err()
+			.to_string();
+		assert!(invalid.contains("Invalid target 'binary'"), "{invalid}");
+
+		let empty = defaults_of("default-targets = []\n", &files)
+			.unwrap_err()
+			.to_string();
+		assert!(empty.contains("is empty"), "{empty}");
+	}
+
+	#[test]
+	fn bin_named_main_is_refused() {
+		let files = [("src/main.c", MAIN), ("src/bin/main.c", MAIN)];
+		assert!(defaults_of("", &files)
+			.unwrap_err()
+			.to_string()
+			.contains("src/bin/main.c"));
+	}
+
+	#[test]
+	fn all_targets() {
+		let files = [
+			("src/main.c", MAIN),
+			("src/util.c", LIB),
+			("src/bin/tool.c", MAIN),
+			("examples/zed.c", MAIN),
+			("examples/basic.c", MAIN),
+		];
+
+		let dir = project("", &files);
+		let proj = Project::open(dir.path()).unwrap();
+
+		assert_eq!(
+			all(&proj).unwrap(),
+			targets(&[
+				"bin:main",
+				"bin:tool",
+				"lib",
+				"example:basic",
+				"example:zed",
+				"tests"
+			])
+		);
+	}
+
+	#[test]
+	fn resolves_bin_over_defaults() {
+		let files = [("src/main.c", MAIN), ("src/bin/tool.c", MAIN)];
+		let dir = project("", &files);
+		let proj = Project::open(dir.path()).unwrap();
+
+		assert_eq!(
+			resolve(&proj, Some("tool"), false).unwrap(),
+			targets(&["bin:tool"])
+		);
+		assert_eq!(
+			resolve(&proj, None, false).unwrap(),
+			targets(&["bin:main", "bin:tool"])
+		);
+		assert!(resolve(&proj, Some("nope"), false).is_err());
+	}
+
+	#[test]
+	fn resolves_single_runnable() {
+		let dir = project("", &[("src/main.c", MAIN)]);
+		let proj = Project::open(dir.path()).unwrap();
+		assert_eq!(
+			resolve_run(&proj, None).unwrap(),
+			Target::Bin(Target::MAIN.to_owned())
+		);
+
+		let dir = project("", &[("src/main.c", MAIN), ("src/bin/tool.c", MAIN)]);
+		let proj = Project::open(dir.path()).unwrap();
+		let several = resolve_run(&proj, None).unwrap_err().to_string();
+		assert!(
+			several.contains("Multiple default targets") && several.contains("bin:tool"),
+			"{several}"
+		);
+		assert_eq!(
+			resolve_run(&proj, Some("tool")).unwrap(),
+			Target::Bin("tool".to_owned())
+		);
+
+		let dir = project(
+			"default-targets = [\"lib\"]\n",
+			&[("src/main.c", MAIN), ("src/util.c", LIB)],
+		);
+		let proj = Project::open(dir.path()).unwrap();
+		assert!(resolve_run(&proj, None)
+			.unwrap_err()
+			.to_string()
+			.contains("can be run"));
+	}
+}
@@ -0,0 +1,359 @@
+/// Placeholder substitution for scaffolded files.
+///
+/// `{{key}}` is replaced with the value of `key`, and `\{{` produces a literal `{{`.
+/// Using a placeholder that isn't defined is an error rather than being left as-is.
+pub struct Vars {
+	vars: std::collections::BTreeMap<String, String>,
+}
+
+impl Vars {
+	/// Creates the builtin variables for a project with the given name.
+	pub fn new(name: &str) -> Self {
+		let mut vars = std::collections::BTreeMap::new();
+
+		vars.insert("name".to_owned(), name.to_owned());
+		vars.insert("version".to_owned(), "0.1.0".to_owned());
+		vars.insert("year".to_owned(), current_year().to_string());
+		vars.insert("author".to_owned(), author());
+		vars.insert("license_spdx".to_owned(), String::new());
+		vars.insert("description".to_owned(), String::new());
+
+		Self { vars }
+	}
+
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.vars.get(key).map(String::as_str)
+	}
+
+	pub fn define(&mut self, key: impl Into<String>, value: impl Into<String>) {
+		self.vars.insert(key.into(), value.into());
+	}
+
+	pub fn render(&self, text: &str) -> anyhow::Result<String> {
+		let mut out = String::with_capacity(text.len());
+		let mut rest = text;
+
+		while let Some(start) = rest.find("{{") {
+			if rest[..start].ends_with('\\') {
+				out.push_str(&rest[..start - 1]);
+				out.push_str("{{");
+				rest = &rest[start + 2..];
+				continue;
+			}
+
+			/* In `{{{name}}}` the placeholder is the innermost pair, with braces around it */
+			if rest[start + 2..].starts_with('{') {
+				out.push_str(&rest[..=start]);
+				rest = &rest[start + 1..];
+				continue;
+			}
+
+			out.push_str(&rest[..start]);
+
+			let Some(len) = rest[start + 2..].find("}}") else {
+				anyhow::bail!("Unterminated placeholder in template: {}", &rest[start..]);
+			};
+
+			let key = rest[start + 2..start + 2 + len].trim();
+			let Some(value) = self.vars.get(key) else {
+				anyhow::bail!(
+					"Unknown placeholder {{{{{key}}}}} in template. Provide it with --define {key}=<value>"
+				);
+			};
+
+			out.push_str(value);
+			rest = &rest[start + 2 + len + 2..];
+		}
+
+		out.push_str(rest);
+
+		Ok(out)
+	}
+}
+
+/// A project template given to `--template`, ready to copy from.
+pub struct Template {
+	root: std::path::PathBuf,
+	/// Checkout of a git template, removed once done with
+	_checkout: Option<tempfile::TempDir>,
+}
+
+impl Template {
+	/// Locates a template by a name registered under `[templates]` in the user config,
+	/// a local directory, or a git url, cloning it if needed.
+	pub fn fetch(spec: &str) -> anyhow::Result<Self> {
+		let global = crate::GlobalConfig::load()?;
+
+		let source = match global.templates.get(spec) {
+			Some(registered) => {
+				let path = std::path::Path::new(registered);
+
+				match crate::GlobalConfig::dir() {
+					Some(dir) if path.is_relative() && !is_git_url(registered) => {
+						dir.join(path).to_string_lossy().into_owned()
+					}
+					_ => registered.clone(),
+				}
+			}
+			None => spec.to_owned(),
+		};
+
+		let template = if std::path::Path::new(&source).is_dir() {
+			Self {
+				root: source.into(),
+				_checkout: None,
+			}
+		} else if is_git_url(&source) {
+			let checkout = tempfile::Builder::new().prefix("cpkg-template").tempdir()?;
+			let root = checkout.path().join("template");
+
+			crate::net::clone(
+				&crate::net::RetryPolicy::default(),
+				&format!("Cloning template '{spec}'"),
+				&source,
+				&root,
+				true,
+			)
+			.map_err(|e| e.context(format!("Failed to clone template from {source}")))?;
+
+			Self {
+				root,
+				_checkout: Some(checkout),
+			}
+		} else {
+			anyhow::bail!(
+				"Template '{spec}' is not a directory, a git url, or a name registered under [templates] in {}",
+				crate::GlobalConfig::path()
+					.map(|p| p.display().to_string())
+					.unwrap_or_else(|| "the user config".to_owned())
+			);
+		};
+
+		if !template
+			.files()
+			.any(|f| f.extension().is_some_and(|e| e == "c"))
+		{
+			anyhow::bail!("Template '{spec}' has no C source files in it");
+		}
+
+		Ok(template)
+	}
+
+	/// Files inside of the template, skipping its git folder.
+	fn files(&self) -> impl Iterator<Item = std::path::PathBuf> {
+		walkdir::WalkDir::new(&self.root)
+			.into_iter()
+			.filter_entry(|e| e.file_name() != ".git")
+			.flatten()
+			.filter(|e| e.file_type().is_file())
+			.map(|e| e.path().to_owned())
+	}
+
+	/// Copies the template into `to`, substituting placeholders in file names and text contents.
+	/// Nothing is written if any file would be overwritten.
+	pub fn copy_to(&self, to: &std::path::Path, vars: &Vars) -> anyhow::Result<()> {
+		let mut copies = vec![];
+
+		for file in self.files() {
+			let rel = file.strip_prefix(&self.root)?;
+
+			let mut dest = to.to_owned();
+			for part in rel.iter() {
+				dest.push(vars.render(&part.to_string_lossy())?);
+			}
+
+			if dest.exists() {
+				anyhow::bail!("Template would overwrite existing file {}", dest.display());
+			}
+
+			copies.push((file, dest));
+		}
+
+		for (file, dest) in copies {
+			if let Some(parent) = dest.parent() {
+				std::fs::create_dir_all(parent)?;
+			}
+
+			let contents = std::fs::read(&file)?;
+
+			match String::from_utf8(contents) {
+				Ok(text) => std::fs::write(&dest, vars.render(&text)?)?,
+				/* Binary files are copied as-is */
+				Err(e) => std::fs::write(&dest, e.into_bytes())?,
+			}
+
+			std::fs::set_permissions(&dest, std::fs::metadata(&file)?.permissions())?;
+		}
+
+		Ok(())
+	}
+}
+
+fn is_git_url(s: &str) -> bool {
+	s.contains("://") || s.starts_with("git@") || s.ends_with(".git")
+}
+
+/// Parses a `key=value` pair given to `--define`.
+pub fn parse_define(s: &str) -> Result<(String, String), String> {
+	s.split_once('=')
+		.map(|(k, v)| (k.trim().to_owned(), v.to_owned()))
+		.filter(|(k, _)| !k.is_empty())
+		.ok_or_else(|| format!("expected key=value, got '{s}'"))
+}
+
+fn current_year() -> i64 {
+	let secs = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.map(|d| d.as_secs())
+		.unwrap_or(0);
+
+	/* Civil from days, see http://howardhinnant.github.io/date_algorithms.html */
+	let z = (secs / 86400) as i64 + 719468;
+	let era = z.div_euclid(146097);
+	let doe = z.rem_euclid(146097);
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+
+	yoe + era * 400 + i64::from(month <= 2)
+}
+
+/// Best guess at the user's name, from git or the environment.
+fn author() -> String {
+	let git = std::process::Command::new("git")
+		.args(["config", "user.name"])
+		.output()
+		.ok()
+		.filter(|o| o.status.success())
+		.map(|o| String::from_utf8_lossy(&o.stdout).trim().to_owned())
+		.filter(|s| !s.is_empty());
+
+	git.or_else(|| std::env::var("USER").ok())
+		.or_else(|| std::env::var("USERNAME").ok())
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn vars() -> Vars {
+		let mut vars = Vars::new("demo");
+		vars.define("author", "Jane");
+		vars
+	}
+
+	#[test]
+	fn renders_placeholders() {
+		let out = vars()
+			.render("{{name}} v{{ version }} by {{author}}")
+			.unwrap();
+		assert_eq!(out, "demo v0.1.0 by Jane");
+	}
+
+	#[test]
+	fn leaves_text_without_placeholders() {
+		assert_eq!(
+			vars().render("int main() { return 0; }").unwrap(),
+			"int main() { return 0; }"
+		);
+		assert_eq!(vars().render("").unwrap(), "");
+		assert_eq!(vars().render("a } b }} c {").unwrap(), "a } b }} c {");
+	}
+
+	#[test]
+	fn escaped_braces_are_literal() {
+		assert_eq!(vars().render("\\{{name}}").unwrap(), "{{name}}");
+		assert_eq!(
+			vars().render("\\{{name}} is {{name}}").unwrap(),
+			"{{name}} is demo"
+		);
+	}
+
+	#[test]
+	fn adjacent_placeholders() {
+		assert_eq!(vars().render("{{name}}{{name}}").unwrap(), "demodemo");
+		assert_eq!(vars().render("{{{name}}}").unwrap(), "{demo}");
+	}
+
+	#[test]
+	fn unknown_placeholder_is_an_error() {
+		let err = vars().render("{{nope}}").unwrap_err().to_string();
+		assert!(err.contains("{{nope}}"), "{err}");
+		assert!(err.contains("--define nope=<value>"), "{err}");
+	}
+
+	#[test]
+	fn unterminated_placeholder_is_an_error() {
+		assert!(vars().render("{{name").is_err());
+	}
+
+	#[test]
+	fn defines_add_and_override() {
+		let mut vars = vars();
+		vars.define("board", "rp2040");
+		vars.define("version", "2.0.0");
+
+		assert_eq!(
+			vars.render("{{board}} {{version}}").unwrap(),
+			"rp2040 2.0.0"
+		);
+	}
+
+	#[test]
+	fn values_are_not_rendered_again() {
+		let mut vars = vars();
+		vars.define("tricky", "{{name}}");
+
+		assert_eq!(vars.render("{{tricky}}").unwrap(), "{{name}}");
+	}
+
+	#[test]
+	fn parses_defines() {
+		assert_eq!(
+			parse_define("board=rp2040"),
+			Ok(("board".to_owned(), "rp2040".to_owned()))
+		);
+		assert_eq!(
+			parse_define(" key =a=b"),
+			Ok(("key".to_owned(), "a=b".to_owned()))
+		);
+		assert_eq!(
+			parse_define("empty="),
+			Ok(("empty".to_owned(), String::new()))
+		);
+		assert!(parse_define("novalue").is_err());
+		assert!(parse_define("=value").is_err());
+	}
+
+	#[test]
+	fn copies_template_with_substitution() {
+		let template = tempfile::tempdir().unwrap();
+		std::fs::create_dir_all(template.path().join("src")).unwrap();
+		std::fs::write(template.path().join("src/{{name}}.c"), "/* {{name}} */\n").unwrap();
+		std::fs::write(template.path().join("notes.bin"), [0xff, 0xfe, b'{', b'{']).unwrap();
+
+		let template = Template {
+			root: template.path().to_owned(),
+			_checkout: None,
+		};
+
+		let out = tempfile::tempdir().unwrap();
+		template.copy_to(out.path(), &vars()).unwrap();
+
+		assert_eq!(
+			std::fs::read_to_string(out.path().join("src/demo.c")).unwrap(),
+			"/* demo */\n"
+		);
+		assert_eq!(
+			std::fs::read(out.path().join("notes.bin")).unwrap(),
+			[0xff, 0xfe, b'{', b'{']
+		);
+
+		assert!(
+			template.copy_to(out.path(), &vars()).is_err(),
+			"existing files aren't overwritten"
+		);
+	}
+}
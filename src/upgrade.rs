@@ -0,0 +1,80 @@
+/// Owner and name of the repository cpkg is released from.
+const OWNER: &str = "DvvCz";
+const REPO: &str = "cpkg";
+
+/// Version of the running cpkg.
+pub fn current() -> semver::Version {
+	semver::Version::parse(self_update::cargo_crate_version!()).expect("crate version is semver")
+}
+
+/// Every release with a binary for this platform, newest first.
+/// Pre-releases, tagged like v0.13.0-rc.1, are left out unless `prerelease`.
+fn releases(
+	prerelease: bool,
+) -> anyhow::Result<Vec<(semver::Version, self_update::update::Release)>> {
+	let releases = self_update::backends::github::ReleaseList::configure()
+		.repo_owner(OWNER)
+		.repo_name(REPO)
+		.with_target(self_update::get_target())
+		.build()?
+		.fetch()
+		.map_err(|e| anyhow::anyhow!("Couldn't fetch the releases of cpkg: {e}"))?;
+
+	/* Releases tagged with something other than a version can't be compared, so they're skipped */
+	let mut releases = releases
+		.into_iter()
+		.filter_map(|r| Some((semver::Version::parse(&r.version).ok()?, r)))
+		.filter(|(v, _)| prerelease || v.pre.is_empty())
+		.collect::<Vec<_>>();
+
+	releases.sort_by(|a, b| b.0.cmp(&a.0));
+	Ok(releases)
+}
+
+/// Newest release that's newer than the running cpkg, if there is one.
+pub fn newer(prerelease: bool) -> anyhow::Result<Option<self_update::update::Release>> {
+	let current = current();
+
+	Ok(releases(prerelease)?
+		.into_iter()
+		.find(|(v, _)| *v > current)
+		.map(|(_, r)| r))
+}
+
+/// Release of a specific version, which may be older than the running cpkg.
+pub fn find(version: &semver::Version) -> anyhow::Result<self_update::update::Release> {
+	releases(true)?
+		.into_iter()
+		.find(|(v, _)| v == version)
+		.map(|(_, r)| r)
+		.ok_or_else(|| {
+			anyhow::anyhow!(
+				"No release of cpkg v{version} has a binary for {}.",
+				self_update::get_target()
+			)
+		})
+}
+
+/// Replaces the running binary with the one from `release`.
+/// It's downloaded to a temporary folder first, so a failed download leaves the current binary as it was.
+pub fn install(release: &self_update::update::Release) -> anyhow::Result<()> {
+	self_update::backends::github::Update::configure()
+		.repo_owner(OWNER)
+		.repo_name(REPO)
+		.bin_name("cpkg")
+		.target_version_tag(&format!("v{}", release.version))
+		.show_download_progress(crate::log::level() >= crate::log::Level::Normal)
+		.show_output(false)
+		.no_confirm(true)
+		.current_version(self_update::cargo_crate_version!())
+		.build()?
+		.update()
+		.map_err(|e| {
+			anyhow::anyhow!(
+				"Failed to upgrade to v{}, the current binary was left as it was: {e}",
+				release.version
+			)
+		})?;
+
+	Ok(())
+}
@@ -0,0 +1,836 @@
+use colored::Colorize;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Severity {
+	Warning,
+	Error,
+}
+
+impl std::fmt::Display for Severity {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Warning => write!(f, "warning"),
+			Self::Error => write!(f, "error"),
+		}
+	}
+}
+
+/// A mechanical fix `cpkg validate --fix` can apply.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Fix {
+	/// Append the target folder, `/target` by default, to .gitignore
+	IgnoreTarget,
+	/// Remove a vendored dependency cpkg installed that's no longer declared
+	PruneStale,
+}
+
+/// A problem found in the project.
+pub struct Finding {
+	pub severity: Severity,
+	pub code: &'static str,
+	/// File, and line if known, relative to the project root
+	pub location: Option<String>,
+	pub message: String,
+	/// Suggestion on how to resolve it by hand
+	pub help: Option<String>,
+	pub fix: Option<Fix>,
+}
+
+/// Every code `cpkg validate` reports, with the explanation `cpkg explain` prints.
+pub const CODES: &[(&str, &str)] = &[
+	(
+		"V001",
+		"cpkg.toml couldn't be parsed.\nNothing else can be checked until it's fixed, as every command needs to read it.",
+	),
+	(
+		"V002",
+		"cpkg.toml has a key cpkg doesn't know about.\nIt's ignored, which usually means it's misspelled or in the wrong table.",
+	),
+	(
+		"V003",
+		"A dependency is declared in cpkg.toml but isn't in target/vendor.\nBuilds will fail to find its headers. Run `cpkg install` to fetch it.",
+	),
+	(
+		"V004",
+		"target/vendor has an entry no dependency in cpkg.toml refers to.\nEntries cpkg installed itself can be removed with `cpkg validate --fix` or `cpkg clean --deps`.\nOthers were put there by hand and are left alone.",
+	),
+	(
+		"V005",
+		"A vendored dependency is broken, e.g. a dangling symlink or a git checkout without .git.\nRun `cpkg install` to fetch it again, or `cpkg clean --broken` to remove it.",
+	),
+	(
+		"V006",
+		"A file looks like a test but `cpkg test` will never run it.\nTests are discovered as src/**/*.test.c and tests/**/*.c.",
+	),
+	(
+		"V007",
+		"A .c file is #included by another source while also being compiled on its own.\nIts definitions end up in the program twice, which fails to link.",
+	),
+	(
+		"V008",
+		"A build output would overwrite a source file or another target's output.\nChange `bin` or the conflicting target in cpkg.toml.",
+	),
+	(
+		"V009",
+		"A script in cpkg.toml refers to a file that doesn't exist.",
+	),
+	(
+		"V010",
+		".gitignore doesn't ignore the target directory, so build outputs and vendored\ndependencies can end up committed. `cpkg validate --fix` adds it.",
+	),
+	(
+		"V011",
+		"A `default` in cpkg.toml names a compiler, formatter, doc generator or debugger cpkg doesn't support.\nThe command using it will fail until it's one of the supported names.",
+	),
+	(
+		"V012",
+		"A path dependency points at a folder that doesn't exist.\nPaths are relative to the project, or to the workspace root for workspace members.",
+	),
+	(
+		"V013",
+		"A config file for a backend, like uncrustify's config or a Doxyfile, doesn't exist.\nThe backend will fail when it's run. Paths are relative to the project.",
+	),
+	(
+		"V014",
+		"A pattern under [compiler.overrides] matches no file in the project, so its flags are never used.\nPatterns are relative to the project root, like `src/legacy/*.c` or `src/legacy/`.",
+	),
+	(
+		"V015",
+		"A detail under [package], like `repository` or `keywords`, doesn't look right, e.g. a url without http:// or https://.\nNothing fails because of it, but tools showing the package's details will show it as is.",
+	),
+];
+
+/// Longer explanation of a finding's code.
+pub fn explain(code: &str) -> Option<&'static str> {
+	CODES
+		.iter()
+		.find(|(c, _)| c.eq_ignore_ascii_case(code))
+		.map(|(_, text)| *text)
+}
+
+/// Audits the project at `dir` without building anything.
+pub fn validate(dir: &std::path::Path) -> anyhow::Result<Vec<Finding>> {
+	let manifest = dir.join("cpkg.toml");
+	let text = std::fs::read_to_string(&manifest)
+		.map_err(|e| anyhow::anyhow!("Couldn't read {}: {e}", manifest.display()))?;
+
+	let mut findings = vec![];
+	let mut unknown = vec![];
+
+	let parsed = serde_ignored::deserialize(toml::Deserializer::new(&text), |path| {
+		unknown.push(key_path(&path))
+	});
+
+	let manifest = Manifest::new(&text);
+
+	let config: crate::Config = match parsed {
+		Ok(config) => config,
+		Err(e) => {
+			findings.push(Finding {
+				severity: Severity::Error,
+				code: "V001",
+				location: Some(manifest.location(e.span().map(|s| s.start))),
+				message: e.message().to_owned(),
+				help: None,
+				fix: None,
+			});
+
+			return Ok(findings);
+		}
+	};
+
+	findings.extend(check_config(&manifest, &config, &unknown));
+	findings.extend(check_overrides(dir, &manifest, &config));
+
+	/* Reported above already, so opening the project shouldn't warn about them again */
+	warned().insert(dir.to_owned());
+
+	let proj = crate::Project::open(dir)?;
+
+	check_vendor(&proj, &mut findings)?;
+	check_tests(&proj, &mut findings);
+	check_includes(&proj, &mut findings);
+	check_outputs(&proj, &mut findings);
+	check_paths(&proj, &manifest, &mut findings);
+	check_scripts(&proj, &manifest, &mut findings);
+	check_gitignore(&proj, &mut findings);
+
+	Ok(findings)
+}
+
+/// Dotted path of a key ignored while parsing cpkg.toml, like `compiler.default`.
+pub fn key_path(path: &serde_ignored::Path) -> String {
+	/* Optional tables show up as `?` segments */
+	path.to_string()
+		.split('.')
+		.filter(|s| *s != "?")
+		.collect::<Vec<_>>()
+		.join(".")
+}
+
+/// Projects whose cpkg.toml has been checked by `warn_config` during this run.
+fn warned() -> std::sync::MutexGuard<'static, std::collections::HashSet<std::path::PathBuf>> {
+	static WARNED: std::sync::OnceLock<std::sync::Mutex<std::collections::HashSet<std::path::PathBuf>>> =
+		std::sync::OnceLock::new();
+
+	WARNED
+		.get_or_init(Default::default)
+		.lock()
+		.unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Prints the cheap checks of cpkg.toml when a project is opened, once per project per run.
+/// `unknown` holds the paths of keys that were ignored while parsing it.
+pub fn warn_config(dir: &std::path::Path, text: &str, config: &crate::Config, unknown: &[String]) {
+	if !warned().insert(dir.to_owned()) {
+		return;
+	}
+
+	let manifest = Manifest::new(text);
+
+	let mut findings = check_config(&manifest, config, unknown);
+	findings.extend(check_overrides(dir, &manifest, config));
+
+	/* The command may not even use what's wrong, so it's up to it to fail */
+	for finding in &mut findings {
+		finding.severity = Severity::Warning;
+	}
+
+	report(&findings);
+}
+
+/// Patterns under [compiler.overrides] matching no file in the project, which are likely typos.
+/// Only walks the project when there are some, since it runs whenever a project is opened.
+fn check_overrides(dir: &std::path::Path, manifest: &Manifest, config: &crate::Config) -> Vec<Finding> {
+	if config.compiler.as_ref().is_none_or(|c| c.overrides.is_empty()) {
+		return vec![];
+	}
+
+	/* An invalid pattern fails the build with its own error */
+	let Ok(overrides) = crate::project::overrides::Overrides::new(dir, config) else {
+		return vec![];
+	};
+
+	let target = dir.join(config.package.target.as_deref().unwrap_or(std::path::Path::new("target")));
+
+	let files = walkdir::WalkDir::new(dir)
+		.into_iter()
+		.filter_entry(|e| e.depth() == 0 || !(e.file_name().to_string_lossy().starts_with('.') || e.path() == target))
+		.flatten()
+		.filter(|e| e.file_type().is_file())
+		.map(|e| e.into_path())
+		.collect::<Vec<_>>();
+
+	overrides
+		.unused(files.iter().map(|f| f.as_path()))
+		.into_iter()
+		.map(|pattern| Finding {
+			severity: Severity::Warning,
+			code: "V014",
+			location: Some(manifest.key("compiler.overrides")),
+			message: format!("override pattern `{pattern}` matches no file in the project"),
+			help: Some("patterns are relative to the project root, like `src/legacy/*.c`".to_owned()),
+			fix: None,
+		})
+		.collect()
+}
+
+/// Source of cpkg.toml, for pointing findings at the line and column of a key.
+struct Manifest<'a> {
+	text: &'a str,
+	/* Only parsed again once something needs locating */
+	doc: std::cell::OnceCell<Option<toml_edit::ImDocument<&'a str>>>,
+}
+
+impl<'a> Manifest<'a> {
+	fn new(text: &'a str) -> Self {
+		Self {
+			text,
+			doc: std::cell::OnceCell::new(),
+		}
+	}
+
+	/// Where a byte offset is, as `cpkg.toml:line:column`.
+	fn location(&self, offset: Option<usize>) -> String {
+		let Some(offset) = offset.filter(|o| *o <= self.text.len()) else {
+			return "cpkg.toml".to_owned();
+		};
+
+		let before = &self.text[..offset];
+		let line = before.matches('\n').count() + 1;
+		let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+
+		format!("cpkg.toml:{line}:{column}")
+	}
+
+	/// Where a key is, given its dotted path like `compiler.default`.
+	/// Falls back to the deepest table along the path that exists.
+	fn key(&self, path: &str) -> String {
+		let doc = self
+			.doc
+			.get_or_init(|| toml_edit::ImDocument::parse(self.text).ok());
+
+		let Some(doc) = doc else {
+			return self.location(None);
+		};
+
+		let mut table: &dyn toml_edit::TableLike = doc.as_table();
+		let mut span = None;
+
+		for segment in path.split('.') {
+			let Some((key, item)) = table.get_key_value(segment) else {
+				break;
+			};
+
+			span = key.span();
+
+			match item.as_table_like() {
+				Some(t) => table = t,
+				None => break,
+			}
+		}
+
+		self.location(span.map(|s| s.start))
+	}
+}
+
+/// Closest candidate to a misspelled word, if any is close enough to be what was meant.
+pub fn closest<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+	candidates
+		.into_iter()
+		.map(|c| (strsim::levenshtein(word, c), c))
+		.filter(|(distance, c)| *distance <= (word.len() / 3).max(2) && *distance < c.len())
+		.min_by_key(|(distance, _)| *distance)
+		.map(|(_, c)| c)
+}
+
+/// Keys a table accepts, given the dotted path of one of its keys.
+fn known_keys(key: &str) -> Option<&'static [&'static str]> {
+	let segments = key.split('.').collect::<Vec<_>>();
+	let table = &segments[..segments.len() - 1];
+
+	crate::config::KEYS
+		.iter()
+		.find(|(path, _)| {
+			let path = path.split('.').filter(|s| !s.is_empty()).collect::<Vec<_>>();
+
+			path.len() == table.len() && path.iter().zip(table).all(|(p, t)| *p == "*" || p == t)
+		})
+		.map(|(_, keys)| *keys)
+}
+
+/// Checks of cpkg.toml that only need its contents, cheap enough to run whenever a project is opened.
+fn check_config(manifest: &Manifest, config: &crate::Config, unknown: &[String]) -> Vec<Finding> {
+	let mut findings = vec![];
+
+	for key in unknown {
+		let name = key.rsplit('.').next().unwrap_or(key);
+		let known = known_keys(key).unwrap_or_default();
+
+		/* Like `flags` under [package], when it belongs under [compiler] */
+		let elsewhere = crate::config::KEYS
+			.iter()
+			.find(|(path, keys)| !path.is_empty() && keys.contains(&name))
+			.map(|(path, _)| path.replace('*', "<name>"));
+
+		let help = match (closest(name, known.iter().copied()), elsewhere) {
+			(Some(suggestion), _) => Some(format!("did you mean `{suggestion}`?")),
+			(None, Some(table)) => Some(format!("`{name}` goes under [{table}]")),
+			(None, None) if !known.is_empty() => Some(format!("expected one of {}", known.join(", "))),
+			(None, None) => None,
+		};
+
+		findings.push(Finding {
+			severity: Severity::Warning,
+			code: "V002",
+			location: Some(manifest.key(key)),
+			message: format!("unknown key `{key}`"),
+			help,
+			fix: None,
+		});
+	}
+
+	let backends = [
+		(
+			"compiler.default",
+			"compiler",
+			config.compiler.as_ref().and_then(|c| c.default.as_deref()),
+			crate::compiler::supported().collect::<Vec<_>>(),
+		),
+		(
+			"formatter.default",
+			"formatter",
+			config.formatter.as_ref().and_then(|f| f.default.as_deref()),
+			crate::format::supported().collect(),
+		),
+		(
+			"docgen.default",
+			"doc generator",
+			config.docgen.as_ref().and_then(|d| d.default.as_deref()),
+			crate::docgen::supported().collect(),
+		),
+		(
+			"debugger.default",
+			"debugger",
+			config.debugger.as_ref().and_then(|d| d.default.as_deref()),
+			crate::debugger::supported().collect(),
+		),
+	];
+
+	findings.extend(check_metadata(manifest, &config.package));
+
+	for (key, kind, value, supported) in backends {
+		let Some(value) = value.filter(|v| !supported.contains(v)) else {
+			continue;
+		};
+
+		let help = match closest(value, supported.iter().copied()) {
+			Some(suggestion) => format!("did you mean `{suggestion}`?"),
+			None => format!("supported are {}", supported.join(", ")),
+		};
+
+		findings.push(Finding {
+			severity: Severity::Error,
+			code: "V011",
+			location: Some(manifest.key(key)),
+			message: format!("`{key}` is `{value}`, which isn't a supported {kind}"),
+			help: Some(help),
+			fix: None,
+		});
+	}
+
+	findings
+}
+
+/// Details under [package] that are there for people rather than builds, so are only warned about.
+fn check_metadata(manifest: &Manifest, package: &crate::ConfigPackage) -> Vec<Finding> {
+	let mut findings = vec![];
+
+	let finding = |key: &str, message: String, help: &str| Finding {
+		severity: Severity::Warning,
+		code: "V015",
+		location: Some(manifest.key(key)),
+		message,
+		help: Some(help.to_owned()),
+		fix: None,
+	};
+
+	let urls = [("package.repository", &package.repository), ("package.homepage", &package.homepage)];
+
+	for (key, url) in urls {
+		let Some(url) = url else {
+			continue;
+		};
+
+		if !(url.starts_with("https://") || url.starts_with("http://")) {
+			findings.push(finding(
+				key,
+				format!("`{key}` is `{url}`, which isn't a url"),
+				"expected something like `https://github.com/user/repo`",
+			));
+		}
+	}
+
+	let lists = [("package.authors", &package.authors), ("package.keywords", &package.keywords)];
+
+	for (key, list) in lists {
+		if list.iter().any(|s| s.trim().is_empty()) {
+			findings.push(finding(key, format!("`{key}` has an empty entry"), "remove it, or fill it in"));
+		}
+	}
+
+	if let Some(keyword) = package.keywords.iter().find(|k| k.contains(char::is_whitespace)) {
+		findings.push(finding(
+			"package.keywords",
+			format!("keyword `{keyword}` has a space in it"),
+			"keywords are single words, so give each its own entry",
+		));
+	}
+
+	findings
+}
+
+fn location(file: &str, line: Option<usize>) -> String {
+	match line {
+		Some(line) => format!("{file}:{line}"),
+		None => file.to_owned(),
+	}
+}
+
+fn rel(proj: &crate::Project, path: &std::path::Path) -> String {
+	path.strip_prefix(proj.path())
+		.unwrap_or(path)
+		.display()
+		.to_string()
+}
+
+fn check_vendor(proj: &crate::Project, findings: &mut Vec<Finding>) -> anyhow::Result<()> {
+	let vendor = proj.vendor();
+
+	let mut missing = proj
+		.config()
+		.dependencies
+		.entries
+		.iter()
+		.filter(|(name, dep)| dep.is_vendored() && vendor.join(name).symlink_metadata().is_err())
+		.map(|(name, _)| name)
+		.collect::<Vec<_>>();
+
+	missing.sort();
+
+	for name in missing {
+		findings.push(Finding {
+			severity: Severity::Warning,
+			code: "V003",
+			location: Some("cpkg.toml".to_owned()),
+			message: format!("dependency `{name}` is declared but not installed"),
+			help: None,
+			fix: None,
+		});
+	}
+
+	for entry in proj.vendor_entries()? {
+		let (message, fix) = match entry.status {
+			crate::VendorStatus::Declared => continue,
+			crate::VendorStatus::Stale => (
+				format!(
+					"`{}` was installed by cpkg but is no longer declared",
+					entry.name
+				),
+				Some(Fix::PruneStale),
+			),
+			crate::VendorStatus::Unknown => (
+				format!(
+					"`{}` isn't declared and wasn't installed by cpkg",
+					entry.name
+				),
+				None,
+			),
+		};
+
+		findings.push(Finding {
+			severity: Severity::Warning,
+			code: "V004",
+			location: Some(rel(proj, &entry.path)),
+			message,
+			help: None,
+			fix,
+		});
+	}
+
+	for broken in proj.check_deps() {
+		findings.push(Finding {
+			severity: Severity::Warning,
+			code: "V005",
+			location: Some(rel(proj, &vendor.join(broken.name))),
+			message: broken.to_string(),
+			help: None,
+			fix: None,
+		});
+	}
+
+	Ok(())
+}
+
+fn check_tests(proj: &crate::Project, findings: &mut Vec<Finding>) {
+	let looks_like_test = |name: &str| {
+		name.ends_with("_test.c")
+			|| name.ends_with("-test.c")
+			|| name.ends_with(".tests.c")
+			|| name.starts_with("test_")
+	};
+
+	let in_src = proj
+		.src()
+		.into_iter()
+		.flat_map(walkdir::WalkDir::new)
+		.flatten()
+		.filter(|e| e.file_type().is_file())
+		.filter(|e| looks_like_test(&e.file_name().to_string_lossy()))
+		.map(|e| e.into_path());
+
+	/* A singular test/ folder is an easy mistake to make, unless it's where tests were configured to go */
+	let test = proj.path().join("test");
+	let in_test = walkdir::WalkDir::new(&test)
+		.into_iter()
+		.filter(|_| test != proj.tests())
+		.flatten()
+		.filter(|e| e.file_type().is_file())
+		.filter(|e| e.path().extension().is_some_and(|ext| ext == "c"))
+		.map(|e| e.into_path());
+
+	for file in in_src.chain(in_test) {
+		findings.push(Finding {
+			severity: Severity::Warning,
+			code: "V006",
+			location: Some(rel(proj, &file)),
+			message: format!(
+				"looks like a test but won't be discovered, rename it to *.test.c or move it into {}/",
+				rel(proj, &proj.tests())
+			),
+			help: None,
+			fix: None,
+		});
+	}
+}
+
+fn check_includes(proj: &crate::Project, findings: &mut Vec<Finding>) {
+	let src = proj.src();
+	let compiled = proj
+		.c_files(&src)
+		.chain(proj.bin_files(&src))
+		.filter_map(|f| f.canonicalize().ok())
+		.collect::<std::collections::HashSet<_>>();
+
+	for file in proj.c_files(&src).chain(proj.bin_files(&src)).chain(proj.test_files()) {
+		let Ok(contents) = std::fs::read_to_string(&file) else {
+			continue;
+		};
+
+		for include in crate::includes::scan(&contents) {
+			if include.angled || !include.path.ends_with(".c") {
+				continue;
+			}
+
+			let resolved = crate::includes::resolve(&include, &file, &src)
+				.and_then(|p| p.canonicalize().ok());
+
+			if resolved.is_some_and(|p| compiled.contains(&p)) {
+				findings.push(Finding {
+					severity: Severity::Warning,
+					code: "V007",
+					location: Some(location(&rel(proj, &file), Some(include.line))),
+					message: format!(
+						"`{}` is included here but is also compiled on its own",
+						include.path
+					),
+					help: None,
+					fix: None,
+				});
+			}
+		}
+	}
+}
+
+fn check_outputs(proj: &crate::Project, findings: &mut Vec<Finding>) {
+	if let Some(ref bin) = proj.config().package.bin {
+		let bin = proj.path().join(bin);
+
+		if proj.src().iter().any(|src| bin.starts_with(src)) || bin.starts_with(proj.tests()) {
+			findings.push(Finding {
+				severity: Severity::Error,
+				code: "V008",
+				location: Some("cpkg.toml".to_owned()),
+				message: format!("`bin` outputs to {}, among the sources", rel(proj, &bin)),
+				help: None,
+				fix: None,
+			});
+		}
+	}
+
+	let Ok(targets) = crate::target::all(proj) else {
+		/* Bad default-targets are reported by the build itself */
+		return;
+	};
+
+	let mut seen = std::collections::HashMap::new();
+
+	for target in targets {
+		for out in proj.target_outputs(&target) {
+			if let Some(other) = seen.insert(out.clone(), target.clone()) {
+				if other != target {
+					findings.push(Finding {
+						severity: Severity::Error,
+						code: "V008",
+						location: Some("cpkg.toml".to_owned()),
+						message: format!(
+							"targets {other} and {target} both output to {}",
+							rel(proj, &out)
+						),
+						help: None,
+						fix: None,
+					});
+				}
+			}
+		}
+	}
+}
+
+fn check_paths(proj: &crate::Project, manifest: &Manifest, findings: &mut Vec<Finding>) {
+	let config = proj.config();
+	let base = proj.workspace().map_or(proj.path(), |ws| ws.root());
+
+	for (name, dep) in &config.dependencies.entries {
+		let crate::ConfigDependency::Path { path, .. } = dep else {
+			continue;
+		};
+
+		if !base.join(path).exists() {
+			findings.push(Finding {
+				severity: Severity::Error,
+				code: "V012",
+				location: Some(manifest.key(&format!("dependencies.{name}.path"))),
+				message: format!(
+					"path dependency `{name}` points at {}, which doesn't exist",
+					path.display()
+				),
+				help: Some(format!(
+					"fix the path, or remove the dependency with `cpkg remove {name}`"
+				)),
+				fix: None,
+			});
+		}
+	}
+
+	let files = [
+		(
+			"formatter.uncrustify.config",
+			config
+				.formatter
+				.as_ref()
+				.and_then(|f| f.uncrustify.as_ref())
+				.map(|u| &u.config),
+			"uncrustify --show-config >",
+		),
+		(
+			"docgen.doxygen.doxyfile",
+			config
+				.docgen
+				.as_ref()
+				.and_then(|d| d.doxygen.as_ref())
+				.map(|d| &d.doxyfile),
+			"doxygen -g",
+		),
+	];
+
+	for (key, path, generate) in files {
+		let Some(path) = path.filter(|p| !proj.path().join(p).exists()) else {
+			continue;
+		};
+
+		findings.push(Finding {
+			severity: Severity::Warning,
+			code: "V013",
+			location: Some(manifest.key(key)),
+			message: format!("`{key}` points at {}, which doesn't exist", path.display()),
+			help: Some(format!(
+				"create a default one with `{generate} {}`",
+				path.display()
+			)),
+			fix: None,
+		});
+	}
+}
+
+fn check_scripts(proj: &crate::Project, manifest: &Manifest, findings: &mut Vec<Finding>) {
+	for (name, script) in &proj.config().scripts.entries {
+		let paths = script
+			.split_whitespace()
+			.map(|word| word.trim_matches(['"', '\'', ';', '&', '|', '(', ')']))
+			.filter(|word| word.contains('/') && !word.starts_with('-') && !word.contains("://"))
+			.filter(|word| !word.contains(['*', '?', '$', '~', '=', '{', '<', '>']))
+			/* Build outputs won't exist until something is built */
+			.filter(|word| !std::path::Path::new(word.trim_start_matches("./")).starts_with(proj.target_dir()));
+
+		for path in paths {
+			let full = proj.path().join(path);
+
+			if !full.exists() {
+				findings.push(Finding {
+					severity: Severity::Warning,
+					code: "V009",
+					location: Some(manifest.key(&format!("scripts.{name}"))),
+					message: format!("script `{name}` refers to {path}, which doesn't exist"),
+					help: Some("paths are relative to the project root".to_owned()),
+					fix: None,
+				});
+			}
+		}
+	}
+}
+
+fn check_gitignore(proj: &crate::Project, findings: &mut Vec<Finding>) {
+	/* Projects outside of git don't need one */
+	if !proj.path().join(".git").exists() {
+		return;
+	}
+
+	let gitignore = std::fs::read_to_string(proj.path().join(".gitignore")).unwrap_or_default();
+
+	let target = proj.target_dir().to_string_lossy().replace('\\', "/");
+
+	let ignored = gitignore
+		.lines()
+		.map(|l| l.trim().trim_start_matches('/').trim_end_matches('/'))
+		.any(|l| l == target);
+
+	if !ignored {
+		findings.push(Finding {
+			severity: Severity::Warning,
+			code: "V010",
+			location: Some(".gitignore".to_owned()),
+			message: "the target directory isn't ignored".to_owned(),
+			help: None,
+			fix: Some(Fix::IgnoreTarget),
+		});
+	}
+}
+
+/// Applies every mechanical fix, returning how many findings were resolved.
+pub fn fix(proj: &crate::Project, findings: &[Finding]) -> anyhow::Result<usize> {
+	let fixes = findings
+		.iter()
+		.filter_map(|f| f.fix.as_ref())
+		.collect::<Vec<_>>();
+
+	if fixes.contains(&&Fix::IgnoreTarget) {
+		let path = proj.path().join(".gitignore");
+		let mut gitignore = std::fs::read_to_string(&path).unwrap_or_default();
+
+		if !gitignore.is_empty() && !gitignore.ends_with('\n') {
+			gitignore.push('\n');
+		}
+
+		gitignore.push_str(&format!("/{}\n", proj.target_dir().to_string_lossy().replace('\\', "/")));
+		std::fs::write(path, gitignore)?;
+	}
+
+	if fixes.contains(&&Fix::PruneStale) {
+		let _ = proj.prune_deps()?;
+	}
+
+	Ok(fixes.len())
+}
+
+/// Prints findings like compiler diagnostics.
+pub fn report(findings: &[Finding]) {
+	for finding in findings {
+		if crate::log::dispatch(&crate::log::Record::Finding(finding)) {
+			continue;
+		}
+
+		let severity = match finding.severity {
+			Severity::Warning => finding.severity.to_string().yellow(),
+			Severity::Error => finding.severity.to_string().red(),
+		};
+
+		eprintln!(
+			"{}{} {}",
+			severity.bold(),
+			format!("[{}]:", finding.code).bold(),
+			finding.message
+		);
+
+		if let Some(ref location) = finding.location {
+			eprintln!("  {} {location}", "-->".blue());
+		}
+
+		if let Some(ref help) = finding.help {
+			eprintln!("  {} help: {help}", "=".blue());
+		}
+
+		if finding.fix.is_some() {
+			eprintln!("  {} fixable with `cpkg validate --fix`", "=".blue());
+		}
+	}
+}
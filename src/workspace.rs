@@ -0,0 +1,194 @@
+/// Several packages in one repository, listed by a cpkg.toml at its root.
+/// Members share the root's target folder, so dependencies are only vendored once.
+#[derive(Clone)]
+pub struct Workspace {
+	root: std::path::PathBuf,
+	members: Vec<std::path::PathBuf>,
+}
+
+impl Workspace {
+	/// Reads the workspace rooted at `path`, if its cpkg.toml has a [workspace] table.
+	pub fn open(path: &std::path::Path) -> anyhow::Result<Option<Self>> {
+		let Ok(contents) = std::fs::read_to_string(path.join("cpkg.toml")) else {
+			return Ok(None);
+		};
+
+		if !toml::from_str::<toml::Table>(&contents)?.contains_key("workspace") {
+			return Ok(None);
+		}
+
+		let config = toml::from_str::<crate::WorkspaceConfig>(&contents)?;
+
+		Ok(Some(Self {
+			root: path.to_owned(),
+			members: config
+				.workspace
+				.members
+				.iter()
+				.map(|m| path.join(m))
+				.collect(),
+		}))
+	}
+
+	/// Finds the workspace a package is a member of, looking through the folders above it.
+	pub fn containing(package: &std::path::Path) -> Option<Self> {
+		let package = package.canonicalize().ok()?;
+
+		package
+			.ancestors()
+			.skip(1)
+			.find_map(|dir| Self::open(dir).ok().flatten())
+			.filter(|ws| {
+				ws.members
+					.iter()
+					.any(|m| m.canonicalize().is_ok_and(|m| m == package))
+			})
+	}
+
+	pub fn root(&self) -> &std::path::Path {
+		&self.root
+	}
+
+	/// Folders of every member package.
+	pub fn members(&self) -> &[std::path::PathBuf] {
+		&self.members
+	}
+
+	/// Target folder shared by every member.
+	pub fn target(&self) -> std::path::PathBuf {
+		self.root.join("target")
+	}
+
+	/// Configs of every member, skipping any that can't be read.
+	pub fn configs(&self) -> impl Iterator<Item = crate::Config> + '_ {
+		self.members.iter().filter_map(|m| Self::config(m))
+	}
+
+	fn config(member: &std::path::Path) -> Option<crate::Config> {
+		let config = std::fs::read_to_string(member.join("cpkg.toml")).ok()?;
+		toml::from_str(&config).ok()
+	}
+
+	/// Finds a member by its package name, or its folder relative to the root.
+	pub fn member(&self, name: &str) -> anyhow::Result<&std::path::Path> {
+		let found = self.members.iter().find(|path| {
+			path.strip_prefix(&self.root).is_ok_and(|p| p == std::path::Path::new(name))
+				|| Self::config(path).is_some_and(|c| c.package.name == name)
+		});
+
+		match found {
+			Some(path) => Ok(path),
+			None => anyhow::bail!(
+				"No package named {name} in the workspace. Members are: {}",
+				self.names().join(", ")
+			),
+		}
+	}
+
+	/// Package names of every member, for messages.
+	pub fn names(&self) -> Vec<String> {
+		self.configs().map(|c| c.package.name).collect()
+	}
+
+	/// Dependencies of every member by name, with each member declaring them.
+	/// Members share target/vendor, so a dependency is installed once for all of them.
+	pub fn dependencies(&self) -> std::collections::BTreeMap<String, Vec<Requirement>> {
+		let mut deps = std::collections::BTreeMap::<String, Vec<Requirement>>::new();
+
+		for member in &self.members {
+			for (name, requirement) in requirements(member) {
+				deps.entry(name).or_default().push(requirement);
+			}
+		}
+
+		deps
+	}
+
+	/// Dependencies that members install from different sources, which would overwrite each other.
+	pub fn conflicts(&self) -> Vec<Conflict> {
+		self.dependencies()
+			.into_iter()
+			.filter_map(|(name, requirements)| {
+				let mut vendored = requirements.iter().filter(|r| r.dep.is_vendored());
+				let first = vendored.next()?;
+
+				vendored
+					.any(|r| r.source != first.source)
+					.then_some(Conflict { name, requirements })
+			})
+			.collect()
+	}
+}
+
+/// A dependency as one package declares it.
+pub struct Requirement {
+	/// Name of the package declaring it
+	pub member: String,
+	pub dep: crate::ConfigDependency,
+	/// Where it's installed from, comparable between packages: the git url, or the folder a path points to
+	pub source: String,
+}
+
+/// Dependencies declared by the package in `dir`, or none if its cpkg.toml can't be read.
+pub fn requirements(dir: &std::path::Path) -> Vec<(String, Requirement)> {
+	let Some(config) = Workspace::config(dir) else {
+		return vec![];
+	};
+
+	config
+		.dependencies
+		.entries
+		.into_iter()
+		.map(|(name, dep)| {
+			let source = match dep {
+				crate::ConfigDependency::Path { ref path, .. } => {
+					let path = dir.join(path);
+					path.canonicalize().unwrap_or(path).display().to_string()
+				}
+				_ => dep.to_string(),
+			};
+
+			let requirement = Requirement {
+				member: config.package.name.clone(),
+				dep,
+				source,
+			};
+
+			(name, requirement)
+		})
+		.collect()
+}
+
+/// Dependency that members of a workspace install from different sources.
+/// Only one of them fits in the shared target/vendor under its name.
+pub struct Conflict {
+	pub name: String,
+	/// Every member declaring it, including those that agree
+	pub requirements: Vec<Requirement>,
+}
+
+impl std::fmt::Display for Conflict {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let name = &self.name;
+		writeln!(f, "Workspace members depend on '{name}' from different sources:")?;
+
+		for r in &self.requirements {
+			writeln!(f, "  {} uses {}", r.member, r.source)?;
+		}
+
+		let first = &self.requirements[0];
+		let others = self
+			.requirements
+			.iter()
+			.filter(|r| r.source != first.source)
+			.map(|r| r.member.as_str())
+			.collect::<Vec<_>>();
+
+		write!(
+			f,
+			"They'd share target/vendor/{name}, so pick one: declare '{name}' in {} the same way {} does, or rename it in one of them.",
+			others.join(", "),
+			first.member
+		)
+	}
+}
@@ -0,0 +1 @@
+DEBUG = 0
@@ -0,0 +1,3 @@
+int parse(const char *s) {
+	return s[0];
+}
@@ -0,0 +1,5 @@
+#include <math.h>
+
+int main(void) {
+	return (int)sqrt(4.0) - 2;
+}
@@ -0,0 +1,7 @@
+#include <stdio.h>
+
+/* {{name}} {{version}}, for {{board}}. \{{not a placeholder}} */
+int main() {
+	printf("Hello from {{name}} on {{board}}!\n");
+	return 0;
+}
@@ -0,0 +1,778 @@
+/* Drives cpkg through its library, the way other tools would, against projects in temporary folders */
+
+/// Warnings handed to the log handler, shared by every test in this binary.
+fn warnings() -> &'static std::sync::Mutex<Vec<String>> {
+	static WARNINGS: std::sync::OnceLock<std::sync::Mutex<Vec<String>>> =
+		std::sync::OnceLock::new();
+
+	WARNINGS.get_or_init(|| {
+		cpkg::log::set_handler(|record| {
+			if let cpkg::log::Record::Warning(message) = record {
+				warnings().lock().unwrap().push(message.to_string());
+			}
+		});
+
+		Default::default()
+	})
+}
+
+fn create(dir: &std::path::Path) -> cpkg::Project<'_> {
+	warnings();
+
+	let options = cpkg::InitOptions {
+		no_git: true,
+		..Default::default()
+	};
+
+	cpkg::Project::create(dir, &options).expect("project is created")
+}
+
+#[test]
+fn create_scaffolds_project() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("hello");
+
+	let proj = create(&dir);
+
+	assert_eq!(proj.config().package.name, "hello");
+	assert!(dir.join("cpkg.toml").is_file());
+	assert!(dir.join("src/main.c").is_file());
+	assert!(dir.join("src/main.test.c").is_file());
+
+	let opened = cpkg::Project::open(&dir).expect("created project opens");
+	assert_eq!(opened.config().package.name, "hello");
+
+	assert!(
+		cpkg::Project::create(&dir, &Default::default()).is_err(),
+		"existing folder is refused"
+	);
+}
+
+#[test]
+fn build_produces_binary() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("hello");
+
+	let proj = create(&dir);
+	let backend = cpkg::compiler::try_locate(Some(&proj)).expect("a C compiler is installed");
+
+	let targets = cpkg::target::defaults(&proj).unwrap();
+	let outputs = proj
+		.build(backend.as_ref(), &targets, || true)
+		.expect("scaffold builds");
+
+	let binary = outputs.first().expect("main is built");
+	let output = std::process::Command::new(binary).output().unwrap();
+
+	assert!(output.status.success());
+	assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello, world!\n");
+}
+
+#[test]
+fn run_tests_reports_each_test() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("tested");
+
+	create(&dir);
+
+	std::fs::write(
+		dir.join("src/broken.test.c"),
+		"// cpkg: unknown-marker\nint main() { return 1; }\n",
+	)
+	.unwrap();
+
+	let proj = cpkg::Project::open(&dir).unwrap();
+	let backend = cpkg::compiler::try_locate(Some(&proj)).expect("a C compiler is installed");
+
+	let mut results = proj.run_tests(backend.as_ref(), false, true, None).unwrap();
+	results.sort_by(|a, b| a.path.cmp(&b.path));
+
+	let statuses = results
+		.iter()
+		.map(|r| {
+			(
+				r.path.file_name().unwrap().to_string_lossy().into_owned(),
+				r.status,
+			)
+		})
+		.collect::<Vec<_>>();
+
+	assert_eq!(
+		statuses,
+		[
+			("broken.test.c".to_owned(), cpkg::TestStatus::Failed),
+			("main.test.c".to_owned(), cpkg::TestStatus::Passed),
+		]
+	);
+
+	/* Handed to the handler rather than printed */
+	assert!(warnings()
+		.lock()
+		.unwrap()
+		.iter()
+		.any(|w| w.contains("cpkg: unknown-marker")));
+
+	let filtered = proj
+		.run_tests(backend.as_ref(), false, true, Some("main"))
+		.unwrap();
+	assert_eq!(filtered.len(), 1);
+	assert!(filtered[0].cached, "unchanged test is reused");
+}
+
+/// Project with several dependencies, scripts and sources, declared out of order.
+const MANIFEST: &str = r#"
+[package]
+name = "ordered"
+
+[dependencies]
+zlib = { lib = "z" }
+math = { lib = "m" }
+pthread = { lib = "pthread" }
+
+[scripts]
+lint = "echo lint"
+bench = "echo bench"
+check = "echo check"
+"#;
+
+#[test]
+fn manifest_round_trips_identically() {
+	let once = toml::to_string_pretty(&toml::from_str::<cpkg::Config>(MANIFEST).unwrap()).unwrap();
+	let twice = toml::to_string_pretty(&toml::from_str::<cpkg::Config>(&once).unwrap()).unwrap();
+
+	assert_eq!(once, twice);
+
+	let names = toml::from_str::<cpkg::Config>(&once).unwrap();
+	assert_eq!(
+		names.dependencies.entries.keys().collect::<Vec<_>>(),
+		["math", "pthread", "zlib"]
+	);
+	assert_eq!(
+		names.scripts.entries.keys().collect::<Vec<_>>(),
+		["bench", "check", "lint"]
+	);
+}
+
+#[test]
+fn generate_make_is_stable() {
+	use cpkg::export::BuildfileGenerator;
+
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("ordered");
+
+	create(&dir);
+	std::fs::write(dir.join("cpkg.toml"), MANIFEST).unwrap();
+
+	for file in [
+		"src/zeta.c",
+		"src/alpha.c",
+		"src/nested/mid.c",
+		"src/beta.test.c",
+		"src/alpha.test.c",
+	] {
+		let path = dir.join(file);
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, "int unused(void) { return 0; }\n").unwrap();
+	}
+
+	/* Opened anew each time, like separate runs of `cpkg generate make` */
+	let generate = || {
+		let proj = cpkg::Project::open(&dir).unwrap();
+		let backend = cpkg::compiler::try_locate(Some(&proj)).expect("a C compiler is installed");
+
+		cpkg::export::Make.generate(&cpkg::export::BuildData::new(&proj, backend.as_ref()).unwrap())
+	};
+
+	let first = generate();
+	assert_eq!(first, generate());
+
+	let proj = cpkg::Project::open(&dir).unwrap();
+	let sources = proj.c_files(&proj.src()).collect::<Vec<_>>();
+	assert!(
+		sources.windows(2).all(|w| w[0] < w[1]),
+		"sources are sorted: {sources:?}"
+	);
+
+	let libs = first.find("-lm").zip(first.find("-lz"));
+	assert!(
+		libs.is_some_and(|(m, z)| m < z),
+		"libraries follow the dependency names"
+	);
+}
+
+#[test]
+fn create_from_fixture_template() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("blinky");
+	let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/template");
+
+	warnings();
+
+	let mut options = cpkg::InitOptions {
+		no_git: true,
+		template: Some(fixture.to_string_lossy().into_owned()),
+		..Default::default()
+	};
+
+	let missing = cpkg::Project::create(&dir, &options)
+		.err()
+		.expect("board isn't defined");
+	assert!(
+		missing.to_string().contains("--define board=<value>"),
+		"{missing}"
+	);
+	assert!(!dir.exists(), "nothing is left behind");
+
+	options
+		.defines
+		.push(("board".to_owned(), "rp2040".to_owned()));
+	let proj = cpkg::Project::create(&dir, &options).unwrap();
+
+	let source = std::fs::read_to_string(dir.join("src/blinky.c")).unwrap();
+	assert!(
+		source.contains("/* blinky 0.1.0, for rp2040. {{not a placeholder}} */"),
+		"{source}"
+	);
+	assert_eq!(
+		std::fs::read_to_string(dir.join("README.md")).unwrap(),
+		"# blinky\n\nMade for rp2040.\n"
+	);
+
+	let backend = cpkg::compiler::try_locate(Some(&proj)).expect("a C compiler is installed");
+	let outputs = proj
+		.build(
+			backend.as_ref(),
+			&cpkg::target::defaults(&proj).unwrap(),
+			|| true,
+		)
+		.unwrap();
+
+	let output = std::process::Command::new(&outputs[0]).output().unwrap();
+	assert_eq!(
+		String::from_utf8_lossy(&output.stdout),
+		"Hello from blinky on rp2040!\n"
+	);
+}
+
+#[test]
+fn workspace_conflicts_name_each_member() {
+	let temp = tempfile::tempdir().unwrap();
+	let root = temp.path();
+
+	std::fs::write(
+		root.join("cpkg.toml"),
+		"[workspace]\nmembers = [\"app\", \"lib\"]\n",
+	)
+	.unwrap();
+
+	for member in ["app", "lib"] {
+		create(&root.join(member));
+		let manifest = format!("[package]\nname = \"{member}\"\n\n[dependencies]\nshared = {{ path = \"../shared\" }}\n");
+		std::fs::write(root.join(member).join("cpkg.toml"), manifest).unwrap();
+	}
+
+	std::fs::create_dir_all(root.join("shared")).unwrap();
+	std::fs::create_dir_all(root.join("lib/shared")).unwrap();
+
+	let ws = cpkg::workspace::Workspace::open(root).unwrap().unwrap();
+
+	let deps = ws.dependencies();
+	let users = deps["shared"]
+		.iter()
+		.map(|r| r.member.as_str())
+		.collect::<Vec<_>>();
+	assert_eq!(users, ["app", "lib"]);
+	assert!(
+		ws.conflicts().is_empty(),
+		"the same folder from both members agrees"
+	);
+
+	std::fs::write(
+		root.join("lib/cpkg.toml"),
+		"[package]\nname = \"lib\"\n\n[dependencies]\nshared = { path = \"shared\" }\n",
+	)
+	.unwrap();
+
+	let conflicts = ws.conflicts();
+	assert_eq!(conflicts.len(), 1);
+
+	let message = conflicts[0].to_string();
+	assert!(
+		message.contains("'shared'")
+			&& message.contains("app uses")
+			&& message.contains("lib uses"),
+		"{message}"
+	);
+
+	let app_dir = root.join("app");
+	let app = cpkg::Project::open(&app_dir).unwrap();
+	let err = app.install_deps(false).unwrap_err();
+	assert!(err.to_string().contains("from different sources"), "{err}");
+}
+
+#[test]
+fn zig_cross_compiles_template() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("hello");
+
+	create(&dir);
+
+	let manifest = std::fs::read_to_string(dir.join("cpkg.toml")).unwrap();
+	let manifest = manifest.replacen("[compiler]\n", "[compiler]\ndefault = \"zig\"\n", 1);
+	std::fs::write(dir.join("cpkg.toml"), manifest).unwrap();
+
+	/* Triples only become targets of their own with zig, which has the sysroots for them */
+	let windows = cpkg::Project::open(&dir)
+		.unwrap()
+		.with_cross("x86_64-windows-gnu")
+		.unwrap();
+	assert_eq!(windows.build_out(None).extension().unwrap(), "exe");
+
+	let has_zig = std::process::Command::new("zig")
+		.arg("version")
+		.output()
+		.is_ok_and(|o| o.status.success());
+
+	if !has_zig {
+		eprintln!("zig isn't installed, not cross compiling");
+		return;
+	}
+
+	for (triple, magic) in [
+		("x86_64-windows-gnu", &b"MZ"[..]),
+		("x86_64-linux-musl", &b"\x7fELF"[..]),
+	] {
+		let proj = cpkg::Project::open(&dir)
+			.unwrap()
+			.with_cross(triple)
+			.unwrap();
+		let backend = cpkg::compiler::try_locate(Some(&proj)).unwrap();
+		assert!(
+			cpkg::compiler::is_zig(backend.as_ref()),
+			"{triple} is built with zig"
+		);
+
+		let outputs = proj
+			.build(
+				backend.as_ref(),
+				&cpkg::target::defaults(&proj).unwrap(),
+				|| true,
+			)
+			.unwrap();
+
+		let binary = std::fs::read(&outputs[0]).unwrap();
+		assert!(
+			binary.starts_with(magic),
+			"{} is built for {triple}",
+			outputs[0].display()
+		);
+	}
+}
+
+#[test]
+fn include_dirs_invalidate_tests() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("answers");
+
+	create(&dir);
+
+	let manifest = std::fs::read_to_string(dir.join("cpkg.toml")).unwrap();
+	let manifest = manifest.replacen(
+		"[compiler]\n",
+		"[compiler]\ninclude-dirs = [\"include\"]\n",
+		1,
+	);
+	std::fs::write(dir.join("cpkg.toml"), manifest).unwrap();
+
+	std::fs::create_dir(dir.join("include")).unwrap();
+	std::fs::write(dir.join("include/answer.h"), "#define ANSWER 42\n").unwrap();
+	std::fs::write(
+		dir.join("src/answer.test.c"),
+		"#include \"answer.h\"\n\nint main() {\n\treturn ANSWER == 42 ? 0 : 1;\n}\n",
+	)
+	.unwrap();
+
+	let proj = cpkg::Project::open(&dir).unwrap();
+	let backend = cpkg::compiler::try_locate(Some(&proj)).expect("a C compiler is installed");
+
+	let run = || {
+		let results = proj
+			.run_tests(backend.as_ref(), false, true, Some("answer"))
+			.unwrap();
+		assert_eq!(results.len(), 1);
+		(results[0].status, results[0].cached)
+	};
+
+	assert_eq!(run(), (cpkg::TestStatus::Passed, false));
+	assert_eq!(run(), (cpkg::TestStatus::Passed, true));
+
+	/* Headers under include-dirs count as inputs like any other */
+	std::fs::write(dir.join("include/answer.h"), "#define ANSWER 41\n").unwrap();
+	assert_eq!(run(), (cpkg::TestStatus::Failed, false));
+}
+
+#[test]
+fn config_header_is_stable_and_escaped() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("configured");
+
+	create(&dir);
+
+	let mut manifest = std::fs::read_to_string(dir.join("cpkg.toml")).unwrap();
+	manifest.push_str(concat!(
+		"\n[package.metadata.defines]\n",
+		"GREETING = 'say \"hi\" to C:\\Users\\José'\n",
+		"LINES = \"one\\ntwo\\ttab??!\"\n",
+		"ANSWER = 42\n",
+		"ENABLED = true\n",
+	));
+	std::fs::write(dir.join("cpkg.toml"), manifest).unwrap();
+
+	std::fs::write(
+		dir.join("src/main.c"),
+		"#include <stdio.h>\n#include <cpkg_config.h>\n\nint main() {\n\tprintf(\"%s|%s|%s|%d|%d\", CPKG_PROJECT_NAME, GREETING, LINES, ANSWER, ENABLED);\n\treturn 0;\n}\n",
+	)
+	.unwrap();
+
+	let proj = cpkg::Project::open(&dir).unwrap();
+	let backend = cpkg::compiler::try_locate(Some(&proj)).expect("a C compiler is installed");
+	let targets = cpkg::target::defaults(&proj).unwrap();
+
+	let outputs = proj.build(backend.as_ref(), &targets, || true).unwrap();
+	let output = std::process::Command::new(&outputs[0]).output().unwrap();
+
+	/* Values come back out of the compiler exactly as they were written in the manifest */
+	assert_eq!(
+		String::from_utf8_lossy(&output.stdout),
+		"configured|say \"hi\" to C:\\Users\\José|one\ntwo\ttab??!|42|1"
+	);
+
+	let header = proj.generated_dir().join("cpkg_config.h");
+	let contents = std::fs::read_to_string(&header).unwrap();
+	let modified = std::fs::metadata(&header).unwrap().modified().unwrap();
+
+	assert!(
+		contents.contains("#define CPKG_PROJECT_NAME \"configured\"\n"),
+		"{contents}"
+	);
+	assert!(
+		!contents.contains("CPKG_GIT_COMMIT"),
+		"projects outside of git have no commit"
+	);
+
+	/* A no-op rebuild leaves the header alone, so nothing including it is rebuilt */
+	std::thread::sleep(std::time::Duration::from_millis(50));
+	proj.build(backend.as_ref(), &targets, || true).unwrap();
+
+	assert_eq!(std::fs::read_to_string(&header).unwrap(), contents);
+	assert_eq!(
+		std::fs::metadata(&header).unwrap().modified().unwrap(),
+		modified
+	);
+}
+
+#[test]
+fn format_files_honors_exclude() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("tidy");
+
+	create(&dir);
+
+	let mut manifest = std::fs::read_to_string(dir.join("cpkg.toml")).unwrap();
+	manifest.push_str(
+		"\n[formatter]\nexclude = [\"src/generated/**\", \"*.min.c\", \"/src/vendor\", \"!src/vendor/keep.h\"]\n",
+	);
+	std::fs::write(dir.join("cpkg.toml"), manifest).unwrap();
+
+	for file in [
+		"src/util.h",
+		"src/nested/deep.c",
+		"src/generated/parser.c",
+		"src/generated/more/lexer.h",
+		"src/fast.min.c",
+		"src/vendor/lib.c",
+		"src/vendor/keep.h",
+		"tests/helpers.c",
+		"tests/bundled.min.c",
+		"tests/fixtures/expected.txt",
+	] {
+		let path = dir.join(file);
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, "").unwrap();
+	}
+
+	let proj = cpkg::Project::open(&dir).unwrap();
+
+	let files = proj
+		.format_files()
+		.unwrap()
+		.into_iter()
+		.map(|f| {
+			f.strip_prefix(&dir)
+				.unwrap()
+				.to_string_lossy()
+				.replace('\\', "/")
+		})
+		.collect::<Vec<_>>();
+
+	/* Excluded folders aren't walked at all, so even a negated pattern inside one can't bring a file back */
+	assert_eq!(
+		files,
+		[
+			"src/main.c",
+			"src/main.test.c",
+			"src/nested/deep.c",
+			"src/util.h",
+			"tests/helpers.c",
+		]
+	);
+
+	let manifest = std::fs::read_to_string(dir.join("cpkg.toml")).unwrap();
+	std::fs::write(
+		dir.join("cpkg.toml"),
+		manifest.replace("\"*.min.c\"", "\"src/{a\""),
+	)
+	.unwrap();
+
+	let proj = cpkg::Project::open(&dir).unwrap();
+	let err = proj.format_files().unwrap_err().to_string();
+	assert!(err.contains("Invalid pattern 'src/{a'"), "{err}");
+}
+
+/// Manifest written by hand, with comments and ordering cpkg wouldn't produce itself.
+const ANNOTATED: &str = r#"# Kept in sync with the release notes
+[package]
+name    = "annotated"   # aligned on purpose
+version = "1.2.3"
+
+[dependencies]
+# Linked statically on release builds
+pthread = { lib = "pthread" }   # see docs/threads.md
+math = { lib = "m" }
+
+[compiler]
+# -Wshadow catches the loop bugs from #12
+flags = [
+	"-Wall",
+	"-Wshadow", # keep
+]
+standard = "c17"
+
+[scripts]
+lint = "echo lint"   # runs in CI
+"#;
+
+#[test]
+fn add_and_remove_keep_manifest_bytes() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("annotated");
+
+	create(&dir);
+	std::fs::write(dir.join("cpkg.toml"), ANNOTATED).unwrap();
+
+	let mut proj = cpkg::Project::open(&dir).unwrap();
+
+	proj.add_dep(
+		"zlib".to_owned(),
+		cpkg::ConfigDependency::Lib {
+			lib: "z".to_owned(),
+			search: None,
+			headers: None,
+		},
+	)
+	.unwrap();
+
+	let added = std::fs::read_to_string(dir.join("cpkg.toml")).unwrap();
+	assert_eq!(
+		added,
+		ANNOTATED.replacen(
+			"math = { lib = \"m\" }\n",
+			"math = { lib = \"m\" }\nzlib = { lib = \"z\" }\n",
+			1
+		)
+	);
+
+	proj.remove_dep("zlib").unwrap();
+	assert_eq!(
+		std::fs::read_to_string(dir.join("cpkg.toml")).unwrap(),
+		ANNOTATED
+	);
+
+	/* Removing a dependency that was there from the start only takes its own line */
+	proj.remove_dep("math").unwrap();
+	assert_eq!(
+		std::fs::read_to_string(dir.join("cpkg.toml")).unwrap(),
+		ANNOTATED.replacen("math = { lib = \"m\" }\n", "", 1)
+	);
+}
+
+#[test]
+fn exe_paths_for_each_platform() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("paths");
+
+	create(&dir);
+
+	let mut manifest = std::fs::read_to_string(dir.join("cpkg.toml")).unwrap();
+	manifest.push_str(concat!(
+		"\n[target.win]\ncompiler = \"x86_64-w64-mingw32-gcc\"\ntriple = \"x86_64-w64-windows-gnu\"\n",
+		"\n[target.pi]\ncompiler = \"aarch64-linux-gnu-gcc\"\ntriple = \"aarch64-linux-gnu\"\n",
+		"\n[target.web]\ncompiler = \"emcc\"\n",
+		"\n[target.board]\ncompiler = \"arm-none-eabi-gcc\"\nextension = \"elf\"\n",
+	));
+	std::fs::write(dir.join("cpkg.toml"), manifest).unwrap();
+
+	let demo = std::path::Path::new("examples/demo.c");
+	let target = dir.join("target");
+
+	/* The host gets its own suffix, which is none outside of Windows */
+	let host = cpkg::Project::open(&dir).unwrap();
+	let suffix = if cfg!(windows) { ".exe" } else { "" };
+
+	assert_eq!(host.exe_extension(), cfg!(windows).then_some("exe"));
+	assert_eq!(host.build_out(None), target.join(format!("paths{suffix}")));
+	assert_eq!(
+		host.build_out(Some(demo)),
+		target.join(format!("demo{suffix}"))
+	);
+
+	/* Cross targets get their platform's suffix whichever platform builds them */
+	for (name, extension) in [
+		("win", Some("exe")),
+		("pi", None),
+		("web", Some("html")),
+		("board", Some("elf")),
+	] {
+		let proj = cpkg::Project::open(&dir).unwrap().with_cross(name).unwrap();
+		let file = |stem: &str| match extension {
+			Some(ext) => target.join(name).join(format!("{stem}.{ext}")),
+			None => target.join(name).join(stem),
+		};
+
+		assert_eq!(proj.exe_extension(), extension, "{name}");
+		assert_eq!(proj.build_out(None), file("paths"), "{name}");
+		assert_eq!(proj.build_out(Some(demo)), file("demo"), "{name}");
+	}
+
+	/* Names with dots keep them, and a suffix that's already there isn't doubled */
+	assert_eq!(
+		cpkg::with_exe_extension("hw1.2".into(), Some("exe")),
+		std::path::Path::new("hw1.2.exe")
+	);
+	assert_eq!(
+		cpkg::with_exe_extension("tool.exe".into(), Some("exe")),
+		std::path::Path::new("tool.exe")
+	);
+	assert_eq!(
+		cpkg::with_exe_extension("tool".into(), None),
+		std::path::Path::new("tool")
+	);
+}
+
+#[test]
+fn links_helpers_named_like_main() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("menus");
+
+	let proj = create(&dir);
+
+	std::fs::write(
+		dir.join("src/menu.c"),
+		"int main_menu(void) {\n\treturn 4;\n}\n\nint\nmainloop(int n) {\n\treturn n * 10;\n}\n",
+	)
+	.unwrap();
+
+	std::fs::write(
+		dir.join("src/main.c"),
+		"#include <stdio.h>\n\nint main_menu(void);\nint mainloop(int n);\n\nint main (void) {\n\tprintf(\"%d\\n\", mainloop(main_menu()));\n\treturn 0;\n}\n",
+	)
+	.unwrap();
+
+	let backend = cpkg::compiler::try_locate(Some(&proj)).expect("a C compiler is installed");
+	let targets = cpkg::target::defaults(&proj).unwrap();
+
+	assert_eq!(proj.mains(&proj.src()), [dir.join("src/main.c")]);
+
+	let outputs = proj.build(backend.as_ref(), &targets, || true).unwrap();
+	let output = std::process::Command::new(&outputs[0]).output().unwrap();
+
+	assert_eq!(String::from_utf8_lossy(&output.stdout), "40\n");
+}
+
+#[test]
+fn header_plan_touches_nothing() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("headers");
+
+	create(&dir);
+
+	let manifest = std::fs::read_to_string(dir.join("cpkg.toml")).unwrap();
+	let manifest = manifest.replacen("[package]\n", "[package]\nkind = \"header-only\"\n", 1);
+	std::fs::write(dir.join("cpkg.toml"), manifest).unwrap();
+
+	std::fs::write(dir.join("src/api.h"), "#pragma once\n\nint api(void);\n").unwrap();
+
+	let proj = cpkg::Project::open(&dir).unwrap();
+	let backend = cpkg::compiler::try_locate(Some(&proj)).expect("a C compiler is installed");
+	let targets = cpkg::target::defaults(&proj).unwrap();
+
+	let plan = proj.plan(backend.as_ref(), &targets, &proj.src()).unwrap();
+	let generated = plan
+		.generated
+		.iter()
+		.map(|(path, _)| path.clone())
+		.collect::<Vec<_>>();
+
+	assert!(generated.contains(&proj.umbrella_out()), "{generated:?}");
+	assert!(
+		generated.iter().all(|p| !p.exists()),
+		"planning doesn't write {generated:?}"
+	);
+
+	proj.build(backend.as_ref(), &targets, || true).unwrap();
+
+	for (path, contents) in &plan.generated {
+		assert_eq!(&std::fs::read_to_string(path).unwrap(), contents);
+	}
+}
+
+#[test]
+fn plan_knows_what_is_fresh() {
+	let temp = tempfile::tempdir().unwrap();
+	let dir = temp.path().join("planned");
+
+	let proj = create(&dir);
+	let backend = cpkg::compiler::try_locate(Some(&proj)).expect("a C compiler is installed");
+	let targets = cpkg::target::defaults(&proj).unwrap();
+
+	let fresh = || {
+		let plan = proj.plan(backend.as_ref(), &targets, &proj.src()).unwrap();
+		assert!(!plan.invocations.is_empty());
+
+		plan.invocations.iter().map(|i| i.fresh).collect::<Vec<_>>()
+	};
+
+	assert!(fresh().iter().all(|f| !f), "nothing is built yet");
+
+	proj.build(backend.as_ref(), &targets, || true).unwrap();
+	assert!(fresh().iter().all(|f| *f), "everything was just built");
+
+	/* Planning doesn't build, so it goes on saying the same as the build would */
+	std::fs::write(dir.join("src/main.c"), "int main() {\n\treturn 0;\n}\n").unwrap();
+	assert!(fresh().iter().all(|f| !f));
+	assert!(fresh().iter().all(|f| !f));
+
+	proj.build(backend.as_ref(), &targets, || true).unwrap();
+	assert!(proj.compiled() > 0);
+	assert!(fresh().iter().all(|f| *f));
+
+	let plan = proj.plan(backend.as_ref(), &targets, &proj.src()).unwrap();
+	let json = serde_json::to_value(&plan).unwrap();
+	assert_eq!(json["invocations"][0]["fresh"], true);
+
+	let forced = cpkg::Project::open(&dir).unwrap().with_force_rebuild(true);
+	let plan = forced
+		.plan(backend.as_ref(), &targets, &forced.src())
+		.unwrap();
+	assert!(plan.invocations.iter().all(|i| !i.fresh), "--force-rebuild");
+}
@@ -0,0 +1,125 @@
+/* Drives the cpkg binary against a project whose path has spaces and non-ASCII characters in it */
+
+/// Runs cpkg in `dir`, failing the test with its output if it doesn't succeed.
+fn cpkg(dir: &std::path::Path, args: &[&str]) -> String {
+	let output = std::process::Command::new(env!("CARGO_BIN_EXE_cpkg"))
+		.args(args)
+		.current_dir(dir)
+		.stdin(std::process::Stdio::null())
+		.env("NO_COLOR", "1")
+		.output()
+		.unwrap();
+
+	assert!(
+		output.status.success(),
+		"cpkg {} failed in {}:\n{}",
+		args.join(" "),
+		dir.display(),
+		String::from_utf8_lossy(&output.stderr)
+	);
+
+	String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn spaces_and_unicode_in_paths() {
+	let temp = tempfile::tempdir().unwrap();
+	let parent = temp.path().join("José García").join("My Projects");
+	let dir = parent.join("hw 1");
+
+	std::fs::create_dir_all(&parent).unwrap();
+	cpkg(&parent, &["new", "hw 1", "--name", "hw1", "--no-git"]);
+
+	/* Source files with spaces too, which Makefiles split on unless escaped */
+	std::fs::write(
+		dir.join("src/main.c"),
+		"void greet(void);\n\nint main() {\n\tgreet();\n\treturn 0;\n}\n",
+	)
+	.unwrap();
+
+	std::fs::write(
+		dir.join("src/ünï code.c"),
+		"#include <stdio.h>\n\nvoid greet(void) {\n\tprintf(\"Hello from ünï code!\\n\");\n}\n",
+	)
+	.unwrap();
+
+	std::fs::write(
+		dir.join("src/ünï code.test.c"),
+		"int main() {\n\treturn 0;\n}\n",
+	)
+	.unwrap();
+
+	cpkg(&dir, &["build"]);
+	cpkg(&dir, &["test"]);
+
+	let run = cpkg(&dir, &["run"]);
+	assert!(run.contains("Hello from ünï code!"), "{run}");
+
+	std::fs::create_dir(dir.join("one off")).unwrap();
+	std::fs::write(
+		dir.join("one off/say hi.c"),
+		"#include <stdio.h>\n\nint main() {\n\tprintf(\"hi\\n\");\n\treturn 0;\n}\n",
+	)
+	.unwrap();
+
+	let file = cpkg(&dir, &["run", "--file", "one off/say hi.c"]);
+	assert!(file.contains("hi"), "{file}");
+
+	#[cfg(unix)]
+	{
+		let manifest = std::fs::read_to_string(dir.join("cpkg.toml")).unwrap();
+		let manifest = manifest.replacen(
+			"[scripts]\n",
+			"[scripts]\nwhere = 'test -f \"$CPKG_ROOT/cpkg.toml\" && printf %s \"$CPKG_ROOT\"'\n",
+			1,
+		);
+		std::fs::write(dir.join("cpkg.toml"), manifest).unwrap();
+
+		let root = cpkg(&dir, &["run", "--script", "where"]);
+		assert_eq!(
+			std::path::Path::new(&root),
+			dir.canonicalize().unwrap().as_path()
+		);
+	}
+
+	cpkg(&dir, &["generate", "make"]);
+
+	let has_make = std::process::Command::new("make")
+		.arg("--version")
+		.output()
+		.is_ok_and(|o| o.status.success());
+
+	if !has_make {
+		eprintln!("make isn't installed, not building with the generated Makefile");
+		return;
+	}
+
+	std::fs::remove_dir_all(dir.join("target")).unwrap();
+
+	for goal in ["all", "test"] {
+		let make = std::process::Command::new("make")
+			.arg(goal)
+			.current_dir(&dir)
+			.output()
+			.unwrap();
+
+		assert!(
+			make.status.success(),
+			"make {goal}:\n{}",
+			String::from_utf8_lossy(&make.stderr)
+		);
+	}
+
+	let binary = std::fs::read_dir(dir.join("target"))
+		.unwrap()
+		.flatten()
+		.map(|e| e.path())
+		.find(|p| p.file_stem().is_some_and(|s| s == "hw1"))
+		.expect("make built the binary");
+
+	let output = std::process::Command::new(binary).output().unwrap();
+	assert_eq!(
+		String::from_utf8_lossy(&output.stdout),
+		"Hello from ünï code!\n"
+	);
+}